@@ -0,0 +1,3 @@
+-- Your SQL goes here
+ALTER TABLE allsongs
+ADD COLUMN mtime DOUBLE;
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX playlist_bridge_position_idx;
+
+ALTER TABLE playlist_bridge
+DROP COLUMN position;
@@ -0,0 +1,14 @@
+-- Your SQL goes here
+ALTER TABLE playlist_bridge
+ADD COLUMN position INTEGER;
+
+-- Keep the order songs were added in for existing playlists
+UPDATE playlist_bridge
+SET position = (
+    SELECT COUNT(*)
+    FROM playlist_bridge AS p
+    WHERE p.playlist = playlist_bridge.playlist
+      AND p.id < playlist_bridge.id
+);
+
+CREATE INDEX playlist_bridge_position_idx ON playlist_bridge(playlist, position);
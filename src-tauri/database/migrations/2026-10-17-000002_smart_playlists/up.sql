@@ -0,0 +1,3 @@
+-- Your SQL goes here
+ALTER TABLE playlists
+ADD COLUMN smart_rules TEXT;
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX play_history_song_idx;
+DROP INDEX play_history_started_at_idx;
+
+DROP TABLE play_history;
@@ -0,0 +1,13 @@
+-- Your SQL goes here
+CREATE TABLE play_history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    song_id TEXT NOT NULL,
+    started_at BIGINT NOT NULL,
+    duration DOUBLE NOT NULL,
+    completed BOOLEAN NOT NULL,
+    provider TEXT,
+    player TEXT
+);
+
+CREATE INDEX play_history_started_at_idx ON play_history(started_at);
+CREATE INDEX play_history_song_idx ON play_history(song_id);
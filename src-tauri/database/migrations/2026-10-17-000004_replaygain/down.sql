@@ -0,0 +1,9 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE allsongs
+DROP COLUMN track_gain;
+ALTER TABLE allsongs
+DROP COLUMN track_peak;
+ALTER TABLE allsongs
+DROP COLUMN album_gain;
+ALTER TABLE allsongs
+DROP COLUMN album_peak;
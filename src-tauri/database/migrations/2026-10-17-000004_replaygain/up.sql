@@ -0,0 +1,9 @@
+-- Your SQL goes here
+ALTER TABLE allsongs
+ADD COLUMN track_gain DOUBLE;
+ALTER TABLE allsongs
+ADD COLUMN track_peak DOUBLE;
+ALTER TABLE allsongs
+ADD COLUMN album_gain DOUBLE;
+ALTER TABLE allsongs
+ADD COLUMN album_peak DOUBLE;
@@ -0,0 +1,13 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX album_identity_uq;
+CREATE UNIQUE INDEX album_name_uq ON albums(album_name);
+ALTER TABLE allsongs
+DROP COLUMN disc_no;
+ALTER TABLE allsongs
+DROP COLUMN disc_total;
+ALTER TABLE allsongs
+DROP COLUMN compilation;
+ALTER TABLE albums
+DROP COLUMN disc_total;
+ALTER TABLE albums
+DROP COLUMN compilation;
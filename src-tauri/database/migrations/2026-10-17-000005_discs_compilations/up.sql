@@ -0,0 +1,15 @@
+-- Your SQL goes here
+ALTER TABLE allsongs
+ADD COLUMN disc_no DOUBLE;
+ALTER TABLE allsongs
+ADD COLUMN disc_total DOUBLE;
+ALTER TABLE allsongs
+ADD COLUMN compilation BOOLEAN;
+ALTER TABLE albums
+ADD COLUMN disc_total DOUBLE;
+ALTER TABLE albums
+ADD COLUMN compilation BOOLEAN;
+
+-- Albums are identified by their name, album artist and year instead of just the name
+DROP INDEX album_name_uq;
+CREATE UNIQUE INDEX album_identity_uq ON albums(album_name, IFNULL(album_artist, ''), IFNULL(year, ''));
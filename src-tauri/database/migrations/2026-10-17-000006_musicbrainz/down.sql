@@ -0,0 +1,21 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX artist_mbid_idx;
+DROP INDEX album_mbid_idx;
+DROP INDEX album_identity_uq;
+CREATE UNIQUE INDEX album_identity_uq ON albums(album_name, IFNULL(album_artist, ''), IFNULL(year, ''));
+ALTER TABLE allsongs
+DROP COLUMN recording_mbid;
+ALTER TABLE allsongs
+DROP COLUMN isrc;
+ALTER TABLE allsongs
+DROP COLUMN original_date;
+ALTER TABLE allsongs
+DROP COLUMN label;
+ALTER TABLE allsongs
+DROP COLUMN catalog_number;
+ALTER TABLE allsongs
+DROP COLUMN composer;
+ALTER TABLE allsongs
+DROP COLUMN bpm;
+ALTER TABLE albums
+DROP COLUMN album_mbid;
@@ -0,0 +1,23 @@
+-- Your SQL goes here
+ALTER TABLE allsongs
+ADD COLUMN recording_mbid TEXT;
+ALTER TABLE allsongs
+ADD COLUMN isrc TEXT;
+ALTER TABLE allsongs
+ADD COLUMN original_date TEXT;
+ALTER TABLE allsongs
+ADD COLUMN label TEXT;
+ALTER TABLE allsongs
+ADD COLUMN catalog_number TEXT;
+ALTER TABLE allsongs
+ADD COLUMN composer TEXT;
+ALTER TABLE allsongs
+ADD COLUMN bpm DOUBLE;
+ALTER TABLE albums
+ADD COLUMN album_mbid TEXT;
+
+-- Different releases of an album may share its name, artist and year
+DROP INDEX album_identity_uq;
+CREATE UNIQUE INDEX album_identity_uq ON albums(album_name, IFNULL(album_artist, ''), IFNULL(year, ''), IFNULL(album_mbid, ''));
+CREATE INDEX album_mbid_idx ON albums(album_mbid);
+CREATE INDEX artist_mbid_idx ON artists(artist_mbid);
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX tag_journal_batch_idx;
+
+DROP TABLE tag_journal;
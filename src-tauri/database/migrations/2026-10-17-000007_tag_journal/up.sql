@@ -0,0 +1,11 @@
+-- Your SQL goes here
+CREATE TABLE tag_journal (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    batch_id TEXT NOT NULL,
+    song_id TEXT NOT NULL,
+    path TEXT NOT NULL,
+    changes TEXT NOT NULL,
+    written_at BIGINT NOT NULL
+);
+
+CREATE INDEX tag_journal_batch_idx ON tag_journal(batch_id);
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE allsongs
+DROP COLUMN start_offset;
+ALTER TABLE allsongs
+DROP COLUMN end_offset;
@@ -0,0 +1,5 @@
+-- Your SQL goes here
+ALTER TABLE allsongs
+ADD COLUMN start_offset DOUBLE;
+ALTER TABLE allsongs
+ADD COLUMN end_offset DOUBLE;
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX merged_duplicates_kept_idx;
+
+DROP TABLE merged_duplicates;
@@ -0,0 +1,8 @@
+-- Your SQL goes here
+CREATE TABLE merged_duplicates (
+    path TEXT PRIMARY KEY NOT NULL,
+    hash TEXT,
+    kept_id TEXT NOT NULL
+);
+
+CREATE INDEX merged_duplicates_kept_idx ON merged_duplicates(kept_id);
@@ -15,16 +15,18 @@
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::cmp::min;
+use std::collections::{HashMap, HashSet};
 
-use std::fmt::Write;
 use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
 use std::{path::PathBuf, vec};
 
 use diesel::{
     connection::SimpleConnection,
     delete, insert_into,
     r2d2::{self, ConnectionManager, Pool, PooledConnection},
-    update, Connection, ExpressionMethods, QueryDsl, RunQueryDsl, SqliteConnection,
+    update, Connection, ExpressionMethods, OptionalExtension, QueryDsl, QueryResult,
+    RunQueryDsl, SqliteConnection,
 };
 use diesel::{BoolExpressionMethods, Insertable, TextExpressionMethods};
 use diesel_logger::LoggingConnection;
@@ -34,11 +36,18 @@ use tracing::{debug, info, trace, warn};
 use uuid::Uuid;
 
 use types::common::{BridgeUtils, SearchByTerm};
-use types::entities::{Analytics, EntityInfo, PlaylistBridge, SearchResult};
+use types::entities::{
+    Analytics, EntityInfo, PlayDetails, PlayHistory, PlayHistoryItem, PlaylistBridge, SearchResult,
+};
 use types::errors::{Result, error_helpers};
 use types::schema::analytics::dsl::analytics;
+use types::schema::merged_duplicates::dsl::merged_duplicates;
 use types::schema::playlists::dsl::playlists;
+use types::schema::tag_journal::dsl::tag_journal;
+use types::smart_playlist::{SmartPlaylistField, SmartPlaylistRules};
 use types::songs::{AllAnalytics, SearchableSong};
+use types::stats::ListeningStats;
+use types::tags::TagJournalEntry;
 use types::{
     schema::{
         self,
@@ -49,6 +58,7 @@ use types::{
         artists::{artist_id, dsl::artists},
         genre_bridge::dsl::genre_bridge,
         genres::{dsl::genres, genre_id},
+        play_history::dsl::play_history,
         playlist_bridge::dsl::playlist_bridge,
     },
     {
@@ -56,11 +66,13 @@ use types::{
             AlbumBridge, ArtistBridge, GenreBridge, GetEntityOptions, QueryableAlbum,
             QueryableArtist, QueryableGenre, QueryablePlaylist,
         },
-        songs::{GetSongOptions, QueryableSong, Song},
+        songs::{GetSongOptions, QueryableSong, Song, SongType},
     },
 };
 
 use super::migrations::run_migrations;
+use super::smart_query::build_smart_query;
+use super::stats::{build_listening_stats, load_song_relations, QUERY_CHUNK};
 
 #[derive(Debug, Clone)]
 pub struct Database {
@@ -113,6 +125,117 @@ impl Database {
         Ok(_album.album_id.as_ref().unwrap().clone())
     }
 
+    // Albums are identified by their MusicBrainz release ID when tagged with one. Otherwise
+    // by their name, album artist and year so that albums sharing a name (like "Greatest Hits")
+    // by different artists stay separate
+    #[tracing::instrument(level = "debug", skip(self, conn))]
+    fn find_album(
+        &self,
+        conn: &mut PooledConnection<ConnectionManager<LoggingConnection<SqliteConnection>>>,
+        _album: &QueryableAlbum,
+    ) -> Result<Option<QueryableAlbum>> {
+        if let Some(mbid) = &_album.album_mbid {
+            let fetched: Option<QueryableAlbum> =
+                QueryDsl::filter(albums, schema::albums::album_mbid.eq(mbid.clone()))
+                    .first(conn)
+                    .optional()
+                    .map_err(error_helpers::to_database_error)?;
+            if fetched.is_some() {
+                return Ok(fetched);
+            }
+        }
+
+        let mut predicate = schema::albums::table.into_boxed();
+        predicate = match &_album.album_name {
+            Some(name) => predicate.filter(schema::albums::album_name.eq(name.clone())),
+            None => predicate.filter(schema::albums::album_name.is_null()),
+        };
+        predicate = match &_album.album_artist {
+            Some(artist) => predicate.filter(schema::albums::album_artist.eq(artist.clone())),
+            None => predicate.filter(schema::albums::album_artist.is_null()),
+        };
+        predicate = match &_album.year {
+            Some(year) => predicate.filter(schema::albums::year.eq(year.clone())),
+            None => predicate.filter(schema::albums::year.is_null()),
+        };
+        if _album.album_mbid.is_some() {
+            // Albums matched to another release are a different edition
+            predicate = predicate.filter(schema::albums::album_mbid.is_null());
+        }
+
+        let fetched: Option<QueryableAlbum> = predicate
+            .first(conn)
+            .optional()
+            .map_err(error_helpers::to_database_error)?;
+        Ok(fetched)
+    }
+
+    // Fills in the disc count, compilation flag and release ID of an existing album from a
+    // newly scanned song
+    #[tracing::instrument(level = "debug", skip(self, conn))]
+    fn merge_album_details(
+        &self,
+        conn: &mut PooledConnection<ConnectionManager<LoggingConnection<SqliteConnection>>>,
+        existing: &QueryableAlbum,
+        _album: &QueryableAlbum,
+    ) -> Result<()> {
+        let disc_total = match (existing.disc_total, _album.disc_total) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (a, b) => a.or(b),
+        };
+        let compilation =
+            existing.compilation.unwrap_or_default() || _album.compilation.unwrap_or_default();
+
+        let album_mbid = existing.album_mbid.clone().or(_album.album_mbid.clone());
+
+        if disc_total != existing.disc_total
+            || compilation != existing.compilation.unwrap_or_default()
+            || album_mbid != existing.album_mbid
+        {
+            update(albums.filter(album_id.eq(existing.album_id.clone())))
+                .set((
+                    schema::albums::disc_total.eq(disc_total),
+                    schema::albums::compilation.eq(Some(compilation)),
+                    schema::albums::album_mbid.eq(album_mbid),
+                ))
+                .execute(conn)
+                .map_err(error_helpers::to_database_error)?;
+        }
+        Ok(())
+    }
+
+    // Artists tagged with a MusicBrainz ID are matched by it, so that different artists
+    // sharing a name stay separate. Untagged artists are matched by name
+    #[tracing::instrument(level = "debug", skip(self, conn))]
+    fn find_artist(
+        &self,
+        conn: &mut PooledConnection<ConnectionManager<LoggingConnection<SqliteConnection>>>,
+        _artist: &QueryableArtist,
+    ) -> Result<Option<QueryableArtist>> {
+        if let Some(mbid) = &_artist.artist_mbid {
+            let fetched = self.get_artists(
+                QueryableArtist {
+                    artist_mbid: Some(mbid.clone()),
+                    ..Default::default()
+                },
+                false,
+                conn,
+            )?;
+            if let Some(fetched) = fetched.into_iter().next() {
+                return Ok(Some(fetched));
+            }
+        }
+
+        let fetched = self.get_artists(
+            QueryableArtist::search_by_term(_artist.artist_name.clone()),
+            false,
+            conn,
+        )?;
+        Ok(fetched
+            .into_iter()
+            .find(|a| _artist.artist_mbid.is_none() || a.artist_mbid.is_none()))
+    }
+
     #[tracing::instrument(level = "debug", skip(self, conn))]
     fn insert_artist(
         &self,
@@ -169,6 +292,10 @@ impl Database {
             playlist.playlist_name = "New playlist".to_string();
         }
 
+        if let Some(rules) = playlist.smart_rules.as_ref() {
+            build_smart_query(rules)?;
+        }
+
         if playlist.playlist_path.is_some() {
             let fetched = self.get_playlists(
                 QueryablePlaylist {
@@ -183,15 +310,128 @@ impl Database {
             }
         }
 
-        self.insert_playlist(&mut conn, &playlist)
+        let id = self.insert_playlist(&mut conn, &playlist)?;
+        if playlist.smart_rules.is_some() {
+            self.refresh_smart_playlist(&mut conn, &playlist)?;
+        }
+        Ok(id)
+    }
+
+    fn get_smart_playlist_songs(
+        &self,
+        conn: &mut PooledConnection<ConnectionManager<LoggingConnection<SqliteConnection>>>,
+        rules: &SmartPlaylistRules,
+    ) -> Result<Vec<QueryableSong>> {
+        trace!("Evaluating smart playlist rules");
+        let (query, sorted) = build_smart_query(rules)?;
+        let mut songs: Vec<QueryableSong> =
+            query.load(conn).map_err(error_helpers::to_database_error)?;
+
+        if !sorted {
+            if let Some(field @ (SmartPlaylistField::PlayCount | SmartPlaylistField::PlayTime)) =
+                rules.sort_by
+            {
+                let stats: HashMap<String, (i32, f64)> = QueryDsl::select(
+                    analytics,
+                    (
+                        schema::analytics::song_id,
+                        schema::analytics::play_count,
+                        schema::analytics::play_time,
+                    ),
+                )
+                .load::<(Option<String>, Option<i32>, Option<f64>)>(conn)
+                .map_err(error_helpers::to_database_error)?
+                .into_iter()
+                .filter_map(|(id, count, time)| {
+                    Some((id?, (count.unwrap_or_default(), time.unwrap_or_default())))
+                })
+                .collect();
+
+                let stat = |s: &QueryableSong| {
+                    let (count, time) = s
+                        ._id
+                        .as_ref()
+                        .and_then(|id| stats.get(id))
+                        .cloned()
+                        .unwrap_or_default();
+                    if field == SmartPlaylistField::PlayCount {
+                        count as f64
+                    } else {
+                        time
+                    }
+                };
+                songs.sort_by(|a, b| stat(a).total_cmp(&stat(b)));
+                if rules.sort_desc {
+                    songs.reverse();
+                }
+            }
+
+            if let Some(limit) = rules.limit {
+                songs.truncate(limit.max(0) as usize);
+            }
+        }
+
+        Ok(songs)
+    }
+
+    // Smart playlists don't have songs in the playlist bridge, so their song count
+    // is updated whenever their songs may have changed
+    fn refresh_smart_playlist(
+        &self,
+        conn: &mut PooledConnection<ConnectionManager<LoggingConnection<SqliteConnection>>>,
+        playlist: &QueryablePlaylist,
+    ) -> Result<()> {
+        let Some(rules) = playlist.smart_rules.as_ref() else {
+            return Ok(());
+        };
+
+        let count = self.get_smart_playlist_songs(conn, rules)?.len();
+        update(playlists)
+            .filter(schema::playlists::playlist_id.eq(playlist.playlist_id.clone()))
+            .set(schema::playlists::playlist_song_count.eq(count as f64))
+            .execute(conn).map_err(error_helpers::to_database_error)?;
+        Ok(())
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn refresh_smart_playlists(&self) -> Result<()> {
+        let mut conn = self.pool.get().unwrap();
+        let smart_playlists: Vec<QueryablePlaylist> =
+            QueryDsl::filter(playlists, schema::playlists::smart_rules.is_not_null())
+                .load(&mut conn).map_err(error_helpers::to_database_error)?;
+
+        for playlist in smart_playlists.iter() {
+            if let Err(e) = self.refresh_smart_playlist(&mut conn, playlist) {
+                warn!("Failed to refresh smart playlist {:?}: {:?}", playlist.playlist_id, e);
+            }
+        }
+        info!("Refreshed {} smart playlists", smart_playlists.len());
+        Ok(())
+    }
+
+    fn is_smart_playlist(
+        &self,
+        conn: &mut PooledConnection<ConnectionManager<LoggingConnection<SqliteConnection>>>,
+        playlist_id: &str,
+    ) -> Result<bool> {
+        let rules: Option<Option<SmartPlaylistRules>> =
+            QueryDsl::select(playlists, schema::playlists::smart_rules)
+                .filter(schema::playlists::playlist_id.eq(playlist_id))
+                .first(conn)
+                .optional().map_err(error_helpers::to_database_error)?;
+        Ok(rules.flatten().is_some())
     }
 
     #[tracing::instrument(level = "debug", skip(self))]
     pub fn add_to_playlist_bridge(&self, playlist_id: String, song_id: String) -> Result<()> {
         let mut conn = self.pool.get().unwrap();
         trace!("Inserting song in playlist bridge");
+        let position = next_playlist_position(&mut conn, &playlist_id).map_err(error_helpers::to_database_error)?;
         insert_into(playlist_bridge)
-            .values(PlaylistBridge::insert_value(playlist_id, song_id))
+            .values(PlaylistBridge {
+                position: Some(position),
+                ..PlaylistBridge::insert_value(playlist_id, song_id)
+            })
             .execute(&mut conn).map_err(error_helpers::to_database_error)?;
 
         trace!("Inserted song in playlist bridge");
@@ -200,11 +440,61 @@ impl Database {
     }
 
     #[tracing::instrument(level = "debug", skip(self))]
-    pub fn insert_songs(&self, mut songs: Vec<Song>) -> Result<Vec<Song>> {
+    pub fn insert_songs(&self, songs: Vec<Song>) -> Result<Vec<Song>> {
+        let mut songs = self.skip_merged_duplicates(songs)?;
         self.insert_songs_by_ref(songs.as_mut_slice())?;
         Ok(songs)
     }
 
+    // Copies removed by merging duplicates stay out of the library when they are scanned again.
+    // A different file at the same path is added, and its record dropped
+    #[tracing::instrument(level = "debug", skip(self, songs))]
+    fn skip_merged_duplicates(&self, songs: Vec<Song>) -> Result<Vec<Song>> {
+        let mut conn = self.pool.get().unwrap();
+        let merged: HashMap<String, Option<String>> = QueryDsl::select(
+            merged_duplicates,
+            (
+                schema::merged_duplicates::path,
+                schema::merged_duplicates::hash,
+            ),
+        )
+        .load::<(String, Option<String>)>(&mut conn)
+        .map_err(error_helpers::to_database_error)?
+        .into_iter()
+        .collect();
+        if merged.is_empty() {
+            return Ok(songs);
+        }
+
+        let mut replaced = vec![];
+        let songs = songs
+            .into_iter()
+            .filter(|s| {
+                let Some(path) = s.song.path.as_ref() else {
+                    return true;
+                };
+                match merged.get(path) {
+                    Some(hash) if *hash == s.song.hash => false,
+                    Some(_) => {
+                        replaced.push(path.clone());
+                        true
+                    }
+                    None => true,
+                }
+            })
+            .collect();
+
+        if !replaced.is_empty() {
+            delete(QueryDsl::filter(
+                merged_duplicates,
+                schema::merged_duplicates::path.eq_any(replaced),
+            ))
+            .execute(&mut conn)
+            .map_err(error_helpers::to_database_error)?;
+        }
+        Ok(songs)
+    }
+
     pub fn insert_songs_by_ref(&self, songs: &mut [Song]) -> Result<()> {
         let mut conn = self.pool.get().unwrap();
         trace!("Inserting songs");
@@ -225,15 +515,13 @@ impl Database {
             }
 
             if let Some(_album) = &mut song.album {
-                let album_id_ = self
-                    .get_albums(
-                        QueryableAlbum::search_by_term(_album.album_name.clone()),
-                        false,
-                        &mut conn,
-                    )?
-                    .first()
-                    .map(|v| v.album_id.clone().unwrap())
-                    .unwrap_or_else(|| self.insert_album(&mut conn, _album).unwrap());
+                let album_id_ = match self.find_album(&mut conn, _album)? {
+                    Some(existing) => {
+                        self.merge_album_details(&mut conn, &existing, _album)?;
+                        existing.album_id.unwrap()
+                    }
+                    None => self.insert_album(&mut conn, _album)?,
+                };
 
                 AlbumBridge::insert_value(album_id_.clone(), song.song._id.clone().unwrap())
                     .insert_into(album_bridge)
@@ -245,15 +533,19 @@ impl Database {
 
             if let Some(_artists) = &mut song.artists {
                 for mut _artist in _artists {
-                    let artist_id_ = self
-                        .get_artists(
-                            QueryableArtist::search_by_term(_artist.artist_name.clone()),
-                            false,
-                            &mut conn,
-                        )?
-                        .first()
-                        .map(|v| v.artist_id.clone().unwrap())
-                        .unwrap_or_else(|| self.insert_artist(&mut conn, _artist).unwrap());
+                    let artist_id_ = match self.find_artist(&mut conn, _artist)? {
+                        Some(existing) => {
+                            let mbid = _artist.artist_mbid.clone();
+                            if existing.artist_mbid.is_none() && mbid.is_some() {
+                                update(artists.filter(artist_id.eq(existing.artist_id.clone())))
+                                    .set(schema::artists::artist_mbid.eq(mbid))
+                                    .execute(&mut conn)
+                                    .map_err(error_helpers::to_database_error)?;
+                            }
+                            existing.artist_id.unwrap()
+                        }
+                        None => self.insert_artist(&mut conn, _artist)?,
+                    };
 
                     ArtistBridge::insert_value(artist_id_.clone(), song.song._id.clone().unwrap())
                         .insert_into(artist_bridge)
@@ -306,6 +598,11 @@ impl Database {
                         schema::analytics::song_id.eq(id.clone()),
                     ))
                     .execute(conn)?;
+                    delete(QueryDsl::filter(
+                        play_history,
+                        schema::play_history::song_id.eq(id.clone()),
+                    ))
+                    .execute(conn)?;
 
                     // Then delete bridge references
                     delete(QueryDsl::filter(
@@ -329,6 +626,13 @@ impl Database {
                     ))
                     .execute(conn)?;
 
+                    // Copies merged into this song can be added again once it's gone
+                    delete(QueryDsl::filter(
+                        merged_duplicates,
+                        schema::merged_duplicates::kept_id.eq(id.clone()),
+                    ))
+                    .execute(conn)?;
+
                     // Finally delete the song itself
                     delete(QueryDsl::filter(allsongs, _id.eq(id.clone()))).execute(conn)?;
                 }
@@ -655,20 +959,27 @@ impl Database {
         }
 
         let playlist = playlist.unwrap();
-        let playlist_data: Vec<AlbumBridge> = QueryDsl::filter(
-            playlist_bridge,
-            schema::playlist_bridge::playlist.eq(playlist.playlist_id.clone()),
-        )
-        .load(conn).map_err(error_helpers::to_database_error)?;
+        if let Some(rules) = playlist.smart_rules.as_ref() {
+            return self.get_smart_playlist_songs(conn, rules);
+        }
 
-        let songs: Vec<QueryableSong> = QueryDsl::filter(
+        let playlist_data = load_playlist_order(conn, playlist.playlist_id.as_deref().unwrap_or_default())
+            .map_err(error_helpers::to_database_error)?;
+
+        let mut songs: HashMap<String, QueryableSong> = QueryDsl::filter(
             allsongs,
-            _id.eq_any(playlist_data.into_iter().map(|v| v.song)),
+            _id.eq_any(playlist_data.iter().map(|(_, song)| song)),
         )
-        .load(conn).map_err(error_helpers::to_database_error)?;
+        .load::<QueryableSong>(conn).map_err(error_helpers::to_database_error)?
+        .into_iter()
+        .filter_map(|s| s._id.clone().map(|id| (id, s)))
+        .collect();
         info!("Fetched playlist songs");
 
-        Ok(songs)
+        Ok(playlist_data
+            .into_iter()
+            .filter_map(|(_, song)| songs.remove(&song))
+            .collect())
     }
 
     fn get_song_from_queryable(
@@ -821,6 +1132,9 @@ impl Database {
                 year: None,
                 album_coverpath_low: None,
                 album_extra_info: None,
+                disc_total: None,
+                compilation: None,
+                album_mbid: None,
             },
             false,
             &mut conn,
@@ -861,6 +1175,7 @@ impl Database {
                 extension: None,
                 icon: None,
                 library_item: None,
+                smart_rules: None,
             },
             false,
             &mut conn,
@@ -917,9 +1232,270 @@ impl Database {
         Ok(ret)
     }
 
+    // Returns the song at `path`, or all songs inside it if `path` is a directory
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn get_songs_by_path(&self, path: PathBuf) -> Result<Vec<QueryableSong>> {
+        let mut conn = self.pool.get().unwrap();
+        let path_str = path.to_string_lossy().to_string();
+        let dir_prefix = format!("{}{}%", path_str, std::path::MAIN_SEPARATOR);
+
+        let songs: Vec<QueryableSong> = QueryDsl::filter(
+            allsongs,
+            song_path.eq(path_str).or(song_path.like(dir_prefix)),
+        )
+        .load(&mut conn).map_err(error_helpers::to_database_error)?;
+
+        // LIKE treats '_' and '%' in the path as wildcards
+        Ok(songs
+            .into_iter()
+            .filter(|s| {
+                s.path
+                    .as_ref()
+                    .is_some_and(|p| PathBuf::from(p).starts_with(&path))
+            })
+            .collect())
+    }
+
+    // Updates the path of the song at `from` or of all songs inside it if `from` is a directory
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn move_songs(&self, from: PathBuf, to: PathBuf) -> Result<usize> {
+        let songs = self.get_songs_by_path(from.clone())?;
+        let len = songs.len();
+
+        self.pool
+            .get()
+            .unwrap()
+            .transaction::<(), diesel::result::Error, _>(|conn| {
+                for song in songs {
+                    let (Some(id), Some(old_path)) = (song._id, song.path) else {
+                        continue;
+                    };
+                    let Ok(relative) = PathBuf::from(old_path).strip_prefix(&from).map(|p| p.to_path_buf()) else {
+                        continue;
+                    };
+                    let new_path = if relative.as_os_str().is_empty() {
+                        to.clone()
+                    } else {
+                        to.join(relative)
+                    };
+
+                    update(allsongs.filter(_id.eq(id)))
+                        .set(song_path.eq(new_path.to_string_lossy().to_string()))
+                        .execute(conn)?;
+                }
+                Ok(())
+            }).map_err(error_helpers::to_database_error)?;
+
+        info!("Moved {} songs", len);
+        Ok(len)
+    }
+
+    // Local songs without ReplayGain tags which haven't been analysed yet
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn get_songs_without_gain(&self) -> Result<Vec<QueryableSong>> {
+        let mut conn = self.pool.get().unwrap();
+        QueryDsl::filter(
+            allsongs,
+            schema::allsongs::type_
+                .eq(SongType::LOCAL)
+                .and(schema::allsongs::track_gain.is_null())
+                .and(song_path.is_not_null())
+                .and(schema::allsongs::start_offset.is_null()),
+        )
+        .load(&mut conn).map_err(error_helpers::to_database_error)
+    }
+
+    // Stores the track gain measured for a song which had no ReplayGain tags
     #[tracing::instrument(level = "debug", skip(self))]
-    pub fn add_to_playlist(&self, id: String, mut songs: Vec<Song>) -> Result<()> {
+    pub fn set_song_gain(&self, id: String, gain: f64, peak: f64) -> Result<()> {
+        let mut conn = self.pool.get().unwrap();
+        update(allsongs.filter(_id.eq(id)))
+            .set((
+                schema::allsongs::track_gain.eq(gain),
+                schema::allsongs::track_peak.eq(peak),
+            ))
+            .execute(&mut conn).map_err(error_helpers::to_database_error)?;
+        Ok(())
+    }
+
+    // Groups local songs which are copies of each other. Songs with the same audio hash
+    // are always copies, others (like a FLAC and an MP3 of the same track) are matched
+    // by their title, artists and duration
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn get_duplicate_songs(&self) -> Result<Vec<Vec<Song>>> {
+        let mut conn = self.pool.get().unwrap();
+        let local_songs: Vec<QueryableSong> =
+            QueryDsl::filter(allsongs, schema::allsongs::type_.eq(SongType::LOCAL))
+                .load(&mut conn).map_err(error_helpers::to_database_error)?;
+
+        // Only songs sharing a hash or a title can be copies, so artists
+        // don't need to be fetched for the whole library
+        let mut candidates: HashMap<String, usize> = HashMap::new();
+        for s in local_songs.iter() {
+            if let Some(hash) = &s.hash {
+                *candidates.entry(format!("hash:{}", hash)).or_default() += 1;
+            }
+            if let Some(title) = s.title.as_deref().map(normalize_title) {
+                *candidates.entry(format!("title:{}", title)).or_default() += 1;
+            }
+        }
+
+        let mut songs = vec![];
+        for s in local_songs {
+            let is_candidate = s
+                .hash
+                .as_ref()
+                .is_some_and(|h| candidates[&format!("hash:{}", h)] > 1)
+                || s.title
+                    .as_deref()
+                    .is_some_and(|t| candidates[&format!("title:{}", normalize_title(t))] > 1);
+            if is_candidate {
+                songs.push(self.get_song_from_queryable(&mut conn, s)?);
+            }
+        }
+
+        let mut groups: Vec<Vec<Song>> = group_duplicates(&songs)
+            .into_iter()
+            .map(|group| group.into_iter().map(|i| songs[i].clone()).collect())
+            .collect();
+        groups.sort_by_key(|g| g[0].song.title.clone().unwrap_or_default().to_lowercase());
+
+        info!("Found {} groups of duplicate songs", groups.len());
+        Ok(groups)
+    }
+
+    // Moves play counts, play time, play history and playlist memberships of `duplicates`
+    // into `keep` and removes the duplicates. Returns the paths of the removed songs.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn merge_duplicates(&self, keep: String, duplicates: Vec<String>) -> Result<Vec<String>> {
+        let duplicates: Vec<String> = duplicates.into_iter().filter(|d| *d != keep).collect();
+        if duplicates.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let mut conn = self.pool.get().unwrap();
+        let removed: Vec<(Option<String>, Option<String>)> =
+            QueryDsl::select(allsongs, (song_path, schema::allsongs::hash))
+                .filter(_id.eq_any(&duplicates))
+                .load(&mut conn).map_err(error_helpers::to_database_error)?;
+        let removed_paths: Vec<String> = removed.iter().filter_map(|(p, _)| p.clone()).collect();
+
+        conn.transaction::<(), diesel::result::Error, _>(|conn| {
+            // Remembered so the next scan doesn't add the removed copies back. Copies merged
+            // into the duplicates earlier now belong to the kept song
+            update(merged_duplicates)
+                .filter(schema::merged_duplicates::kept_id.eq_any(&duplicates))
+                .set(schema::merged_duplicates::kept_id.eq(keep.clone()))
+                .execute(conn)?;
+            for (path, hash) in removed {
+                let Some(path) = path else {
+                    continue;
+                };
+                insert_into(merged_duplicates)
+                    .values((
+                        schema::merged_duplicates::path.eq(path),
+                        schema::merged_duplicates::hash.eq(hash.clone()),
+                        schema::merged_duplicates::kept_id.eq(keep.clone()),
+                    ))
+                    .on_conflict(schema::merged_duplicates::path)
+                    .do_update()
+                    .set((
+                        schema::merged_duplicates::hash.eq(hash),
+                        schema::merged_duplicates::kept_id.eq(keep.clone()),
+                    ))
+                    .execute(conn)?;
+            }
+
+            let merged: Vec<(Option<i32>, Option<f64>)> = QueryDsl::select(
+                analytics,
+                (schema::analytics::play_count, schema::analytics::play_time),
+            )
+            .filter(schema::analytics::song_id.eq_any(&duplicates))
+            .load(conn)?;
+
+            if !merged.is_empty() {
+                let play_count: i32 = merged.iter().map(|(c, _)| c.unwrap_or_default()).sum();
+                let play_time: f64 = merged.iter().map(|(_, t)| t.unwrap_or_default()).sum();
+
+                let existing = QueryDsl::select(analytics, schema::analytics::id)
+                    .filter(schema::analytics::song_id.eq(keep.clone()))
+                    .first::<Option<String>>(conn)
+                    .optional()?;
+                if existing.is_some() {
+                    update(analytics)
+                        .filter(schema::analytics::song_id.eq(keep.clone()))
+                        .set((
+                            schema::analytics::play_count
+                                .eq(schema::analytics::play_count + play_count),
+                            schema::analytics::play_time
+                                .eq(schema::analytics::play_time + play_time),
+                        ))
+                        .execute(conn)?;
+                } else {
+                    insert_into(analytics)
+                        .values(Analytics {
+                            id: Some(Uuid::new_v4().to_string()),
+                            song_id: Some(keep.clone()),
+                            play_count: Some(play_count),
+                            play_time: Some(play_time),
+                        })
+                        .execute(conn)?;
+                }
+            }
+
+            let mut kept_playlists: Vec<Option<String>> =
+                QueryDsl::select(playlist_bridge, schema::playlist_bridge::playlist)
+                    .filter(schema::playlist_bridge::song.eq(keep.clone()))
+                    .load(conn)?;
+            let bridges: Vec<(Option<i32>, Option<String>)> = QueryDsl::select(
+                playlist_bridge,
+                (schema::playlist_bridge::id, schema::playlist_bridge::playlist),
+            )
+            .filter(schema::playlist_bridge::song.eq_any(&duplicates))
+            .load(conn)?;
+
+            for (bridge_id, playlist) in bridges {
+                if kept_playlists.contains(&playlist) {
+                    delete(playlist_bridge)
+                        .filter(schema::playlist_bridge::id.eq(bridge_id))
+                        .execute(conn)?;
+                } else {
+                    // Updating the row in place keeps the song where it was in the playlist
+                    update(playlist_bridge)
+                        .filter(schema::playlist_bridge::id.eq(bridge_id))
+                        .set(schema::playlist_bridge::song.eq(keep.clone()))
+                        .execute(conn)?;
+                    kept_playlists.push(playlist);
+                }
+            }
+
+            update(play_history)
+                .filter(schema::play_history::song_id.eq_any(&duplicates))
+                .set(schema::play_history::song_id.eq(keep.clone()))
+                .execute(conn)?;
+            Ok(())
+        }).map_err(error_helpers::to_database_error)?;
+        drop(conn);
+
+        self.remove_songs(duplicates)?;
+
+        info!("Merged duplicates into {}", keep);
+        Ok(removed_paths)
+    }
+
+    // Appends songs to the playlist, or inserts them starting at `position`.
+    // Songs already in the playlist are skipped
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn add_to_playlist(
+        &self,
+        id: String,
+        mut songs: Vec<Song>,
+        position: Option<usize>,
+    ) -> Result<()> {
         trace!("Adding to playlist");
+        if self.is_smart_playlist(&mut self.pool.get().unwrap(), &id)? {
+            return Err("Songs can't be added to a smart playlist".into());
+        }
         songs.iter_mut().for_each(|v| {
             v.song.show_in_library = Some(false);
         });
@@ -933,21 +1509,108 @@ impl Database {
         }
 
         let mut conn = self.pool.get().unwrap();
+        let mut existing: HashSet<String> = load_playlist_order(&mut conn, &id)
+            .map_err(error_helpers::to_database_error)?
+            .into_iter()
+            .map(|(_, song)| song)
+            .collect();
+        let mut next_position =
+            next_playlist_position(&mut conn, &id).map_err(error_helpers::to_database_error)?;
+
+        let mut added = 0;
         for s in songs {
+            let Some(song_id) = s.song._id.clone() else {
+                continue;
+            };
+            if existing.contains(&song_id) {
+                continue;
+            }
+
             if let Err(e) = insert_into(playlist_bridge)
                 .values((
                     schema::playlist_bridge::playlist.eq(id.clone()),
-                    schema::playlist_bridge::song.eq(s.song._id.clone()),
+                    schema::playlist_bridge::song.eq(song_id.clone()),
+                    schema::playlist_bridge::position.eq(next_position),
                 ))
                 .execute(&mut conn)
             {
                 warn!("Failed to add {:?} to playlist: {:?}", s, e);
+                continue;
+            }
+            existing.insert(song_id);
+            next_position += 1;
+            added += 1;
+        }
+
+        if let Some(position) = position {
+            if added > 0 {
+                conn.transaction::<(), diesel::result::Error, _>(|conn| {
+                    let mut order: Vec<i32> = load_playlist_order(conn, &id)?
+                        .into_iter()
+                        .map(|(bridge_id, _)| bridge_id)
+                        .collect();
+                    // Newly added songs are at the end
+                    let new = order.split_off(order.len() - added);
+                    let position = min(position, order.len());
+                    order.splice(position..position, new);
+                    write_playlist_order(conn, &order)
+                })
+                .map_err(error_helpers::to_database_error)?;
             }
         }
         info!("Added to playlist");
         Ok(())
     }
 
+    // Sets the order of the playlist to `songs`. Songs of the playlist missing
+    // from `songs` keep their relative order after the given ones
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn reorder_playlist(&self, id: String, songs: Vec<String>) -> Result<()> {
+        trace!("Reordering playlist");
+        let mut conn = self.pool.get().unwrap();
+        if self.is_smart_playlist(&mut conn, &id)? {
+            return Err("Smart playlists can't be reordered".into());
+        }
+        conn.transaction::<(), diesel::result::Error, _>(|conn| {
+            let mut remaining = load_playlist_order(conn, &id)?;
+            let mut order = Vec::with_capacity(remaining.len());
+            for song in songs {
+                if let Some(index) = remaining.iter().position(|(_, s)| *s == song) {
+                    order.push(remaining.remove(index).0);
+                }
+            }
+            order.extend(remaining.into_iter().map(|(bridge_id, _)| bridge_id));
+            write_playlist_order(conn, &order)
+        })
+        .map_err(error_helpers::to_database_error)?;
+
+        info!("Reordered playlist");
+        Ok(())
+    }
+
+    // Moves a single song of the playlist to index `to`
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn move_in_playlist(&self, id: String, song: String, to: usize) -> Result<()> {
+        trace!("Moving song in playlist");
+        let mut conn = self.pool.get().unwrap();
+        if self.is_smart_playlist(&mut conn, &id)? {
+            return Err("Smart playlists can't be reordered".into());
+        }
+        let mut order = load_playlist_order(&mut conn, &id).map_err(error_helpers::to_database_error)?;
+        let Some(from) = order.iter().position(|(_, s)| *s == song) else {
+            return Err("Song not found in playlist".into());
+        };
+
+        let moved = order.remove(from);
+        order.insert(min(to, order.len()), moved);
+        let order: Vec<i32> = order.into_iter().map(|(bridge_id, _)| bridge_id).collect();
+        conn.transaction::<(), diesel::result::Error, _>(|conn| write_playlist_order(conn, &order))
+            .map_err(error_helpers::to_database_error)?;
+
+        info!("Moved song in playlist");
+        Ok(())
+    }
+
     #[tracing::instrument(level = "debug", skip(self))]
     pub fn remove_from_playlist(&self, id: String, songs: Vec<String>) -> Result<()> {
         trace!("Removing from playlist");
@@ -1062,11 +1725,16 @@ impl Database {
     #[tracing::instrument(level = "debug", skip(self))]
     pub fn update_playlist(&self, playlist: QueryablePlaylist) -> Result<()> {
         trace!("Updating playlist");
+        if let Some(rules) = playlist.smart_rules.as_ref() {
+            build_smart_query(rules)?;
+        }
+
         let mut conn = self.pool.get().unwrap();
         update(playlists)
             .filter(schema::playlists::playlist_id.eq(playlist.playlist_id.clone()))
-            .set(playlist)
+            .set(&playlist)
             .execute(&mut conn).map_err(error_helpers::to_database_error)?;
+        self.refresh_smart_playlist(&mut conn, &playlist)?;
         info!("Updated playlist");
         Ok(())
     }
@@ -1136,10 +1804,38 @@ impl Database {
         Ok(())
     }
 
+    // Play time is also added to the play history if details about the play are known
     #[tracing::instrument(level = "debug", skip(self))]
-    pub fn increment_play_time(&self, id: String, duration: f64) -> Result<()> {
+    pub fn increment_play_time(
+        &self,
+        id: String,
+        duration: f64,
+        details: Option<PlayDetails>,
+    ) -> Result<()> {
         trace!("Incrementing play time");
         let mut conn = self.pool.get().unwrap();
+
+        if let Some(details) = details {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as i64)
+                .unwrap_or_default();
+            insert_into(play_history)
+                .values(PlayHistory {
+                    id: None,
+                    song_id: id.clone(),
+                    started_at: details
+                        .started_at
+                        .unwrap_or(now - (duration * 1000f64) as i64),
+                    duration,
+                    completed: details.completed,
+                    provider: details.provider,
+                    player: details.player,
+                })
+                .execute(&mut conn).map_err(error_helpers::to_database_error)?;
+            info!("Added play to history");
+        }
+
         let play_time = QueryDsl::select(analytics, schema::analytics::play_time)
             .filter(schema::analytics::song_id.eq(id.clone()))
             .first::<Option<f64>>(&mut conn);
@@ -1187,8 +1883,229 @@ impl Database {
         })
     }
 
+    // Plays which started between `from` and `to` (unix timestamps in milliseconds),
+    // most recent first. Plays of songs which aren't in the database are skipped
     #[tracing::instrument(level = "debug", skip(self))]
-    pub fn export_playlist(&self, playlist_id: String) -> Result<String> {
+    pub fn get_play_history(
+        &self,
+        from: Option<i64>,
+        to: Option<i64>,
+        limit: Option<i64>,
+    ) -> Result<Vec<PlayHistoryItem>> {
+        trace!("Fetching play history");
+        let mut conn = self.pool.get().unwrap();
+
+        let mut query = play_history.into_boxed();
+        if let Some(from) = from {
+            query = query.filter(schema::play_history::started_at.ge(from));
+        }
+        if let Some(to) = to {
+            query = query.filter(schema::play_history::started_at.lt(to));
+        }
+        if let Some(limit) = limit {
+            query = query.limit(limit);
+        }
+
+        let plays: Vec<PlayHistory> = query
+            .order((schema::play_history::started_at.desc(), schema::play_history::id.desc()))
+            .load(&mut conn).map_err(error_helpers::to_database_error)?;
+
+        let song_ids: HashSet<&String> = plays.iter().map(|p| &p.song_id).collect();
+        let mut songs: HashMap<String, Song> = HashMap::new();
+        for s in QueryDsl::filter(allsongs, _id.eq_any(song_ids))
+            .load::<QueryableSong>(&mut conn).map_err(error_helpers::to_database_error)?
+        {
+            if let Some(id) = s._id.clone() {
+                songs.insert(id, self.get_song_from_queryable(&mut conn, s)?);
+            }
+        }
+
+        info!("Fetched play history");
+        Ok(plays
+            .into_iter()
+            .filter_map(|play| {
+                let song = songs.get(&play.song_id)?.clone();
+                Some(PlayHistoryItem { play, song })
+            })
+            .collect())
+    }
+
+    // Stats over plays which started between `from` and `to`. Plays are split into days,
+    // weeks and months in the local time given by `utc_offset` (in minutes)
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn get_listening_stats(
+        &self,
+        from: Option<i64>,
+        to: Option<i64>,
+        utc_offset: i32,
+    ) -> Result<ListeningStats> {
+        trace!("Computing listening stats");
+        let mut conn = self.pool.get().unwrap();
+
+        let mut query = play_history.into_boxed();
+        if let Some(from) = from {
+            query = query.filter(schema::play_history::started_at.ge(from));
+        }
+        if let Some(to) = to {
+            query = query.filter(schema::play_history::started_at.lt(to));
+        }
+        let plays: Vec<PlayHistory> = query
+            .order(schema::play_history::started_at.asc())
+            .load(&mut conn).map_err(error_helpers::to_database_error)?;
+
+        let song_ids: Vec<String> = plays
+            .iter()
+            .map(|p| p.song_id.clone())
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        let relations = load_song_relations(&mut conn, &song_ids).map_err(error_helpers::to_database_error)?;
+
+        let mut first_plays: HashMap<String, i64> = HashMap::new();
+        for chunk in song_ids.chunks(QUERY_CHUNK) {
+            first_plays.extend(
+                play_history
+                    .filter(schema::play_history::song_id.eq_any(chunk))
+                    .group_by(schema::play_history::song_id)
+                    .select((
+                        schema::play_history::song_id,
+                        diesel::dsl::min(schema::play_history::started_at),
+                    ))
+                    .load::<(String, Option<i64>)>(&mut conn)
+                    .map_err(error_helpers::to_database_error)?
+                    .into_iter()
+                    .filter_map(|(id, first)| Some((id, first?))),
+            );
+        }
+
+        info!("Computed listening stats over {} plays", plays.len());
+        Ok(build_listening_stats(
+            &plays,
+            &relations,
+            &first_plays,
+            from,
+            to,
+            utc_offset,
+        ))
+    }
+
+    // Removes plays which started before `before`, or the whole history if it isn't set
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn clear_play_history(&self, before: Option<i64>) -> Result<()> {
+        let mut conn = self.pool.get().unwrap();
+        let mut query = delete(play_history).into_boxed();
+        if let Some(before) = before {
+            query = query.filter(schema::play_history::started_at.lt(before));
+        }
+        let removed = query.execute(&mut conn).map_err(error_helpers::to_database_error)?;
+        info!("Removed {} plays from history", removed);
+        Ok(())
+    }
+
+    // Replaces the album, artists and genres of a song with those of a rescanned copy,
+    // like after its tags were written back to the file
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn refresh_song(&self, mut song: Song) -> Result<Song> {
+        let Some(id) = song.song._id.clone() else {
+            return Err("Song does not have an ID".into());
+        };
+
+        {
+            let mut conn = self.pool.get().unwrap();
+            conn.transaction::<(), diesel::result::Error, _>(|conn| {
+                delete(QueryDsl::filter(
+                    album_bridge,
+                    schema::album_bridge::song.eq(&id),
+                ))
+                .execute(conn)?;
+                delete(QueryDsl::filter(
+                    artist_bridge,
+                    schema::artist_bridge::song.eq(&id),
+                ))
+                .execute(conn)?;
+                delete(QueryDsl::filter(
+                    genre_bridge,
+                    schema::genre_bridge::song.eq(&id),
+                ))
+                .execute(conn)?;
+                Ok(())
+            })
+            .map_err(error_helpers::to_database_error)?;
+        }
+
+        self.insert_songs_by_ref(std::slice::from_mut(&mut song))?;
+        info!("Refreshed song {}", id);
+        Ok(song)
+    }
+
+    #[tracing::instrument(level = "debug", skip(self, entries))]
+    pub fn add_tag_journal_entries(&self, entries: Vec<TagJournalEntry>) -> Result<()> {
+        let mut conn = self.pool.get().unwrap();
+        insert_into(tag_journal)
+            .values(&entries)
+            .execute(&mut conn)
+            .map_err(error_helpers::to_database_error)?;
+        info!("Added {} tag journal entries", entries.len());
+        Ok(())
+    }
+
+    // Tag edits written to files, most recent first
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn get_tag_journal(&self, limit: Option<i64>) -> Result<Vec<TagJournalEntry>> {
+        let mut conn = self.pool.get().unwrap();
+        let mut query = tag_journal.into_boxed();
+        if let Some(limit) = limit {
+            query = query.limit(limit);
+        }
+        query
+            .order((
+                schema::tag_journal::written_at.desc(),
+                schema::tag_journal::id.desc(),
+            ))
+            .load(&mut conn)
+            .map_err(error_helpers::to_database_error)
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn get_tag_batch(&self, batch_id: String) -> Result<Vec<TagJournalEntry>> {
+        let mut conn = self.pool.get().unwrap();
+        QueryDsl::filter(tag_journal, schema::tag_journal::batch_id.eq(batch_id))
+            .order(schema::tag_journal::id.asc())
+            .load(&mut conn)
+            .map_err(error_helpers::to_database_error)
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn remove_tag_batch(&self, batch_id: String) -> Result<()> {
+        let mut conn = self.pool.get().unwrap();
+        delete(QueryDsl::filter(
+            tag_journal,
+            schema::tag_journal::batch_id.eq(batch_id),
+        ))
+        .execute(&mut conn)
+        .map_err(error_helpers::to_database_error)?;
+        Ok(())
+    }
+
+    /// Removes single entries of a batch, like the ones which were undone while others failed
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn remove_tag_journal_entries(&self, ids: Vec<i32>) -> Result<()> {
+        let mut conn = self.pool.get().unwrap();
+        delete(QueryDsl::filter(
+            tag_journal,
+            schema::tag_journal::id.eq_any(ids),
+        ))
+        .execute(&mut conn)
+        .map_err(error_helpers::to_database_error)?;
+        Ok(())
+    }
+
+    /// A playlist along with its songs in playlist order, for writing it to a file
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn get_playlist_with_songs(
+        &self,
+        playlist_id: String,
+    ) -> Result<(QueryablePlaylist, Vec<Song>)> {
         let mut conn = self.pool.get().unwrap();
 
         let binding = self.get_playlists(
@@ -1199,13 +2116,9 @@ impl Database {
             true,
             &mut conn,
         )?;
-        let playlist = binding.first();
-
-        if playlist.is_none() {
+        let Some(playlist) = binding.into_iter().next() else {
             return Err("Playlist not found".into());
-        }
-
-        let playlist = playlist.unwrap();
+        };
 
         let playlist_songs = self.get_songs_by_options(GetSongOptions {
             playlist: Some(QueryablePlaylist {
@@ -1215,80 +2128,7 @@ impl Database {
             ..Default::default()
         })?;
 
-        let mut ret = format!("#EXTM3U\n#PLAYLIST:{}\n", playlist.playlist_name);
-
-        for s in playlist_songs {
-            if let Some(path) = &s.song.path {
-                let duration = s.song.duration.unwrap_or(0f64);
-                let title = s.song.title.unwrap_or_default();
-                let album_info = s.album.as_ref().map_or(String::new(), |album| {
-                    format!("#EXTALB:{}", album.album_name.clone().unwrap_or_default())
-                });
-                let genre_info = if let Some(genre) = &s.genre {
-                    if !genre.is_empty() {
-                        format!(
-                            "#EXTGENRE:{}",
-                            genre
-                                .iter()
-                                .filter_map(|g| g.genre_name.clone())
-                                .collect::<Vec<String>>()
-                                .join(",")
-                        )
-                    } else {
-                        String::new()
-                    }
-                } else {
-                    String::new()
-                };
-                let cover_path = match s.song.song_cover_path_high {
-                    Some(cover) => format!("#EXTIMG:{}", cover),
-                    None => String::new(),
-                };
-                let song_info = format!("#MOOSINF:{}", s.song.type_);
-                let file_path = format!("file://{}", path);
-
-                write!(
-                    ret,
-                    "#EXTINF:{},{}\n{}\n{}\n{}\n{}\n{}\n",
-                    duration, title, album_info, genre_info, cover_path, song_info, file_path
-                )?;
-            } else if let Some(url) = &s.song.url {
-                let duration = s.song.duration.unwrap_or(0f64);
-                let title = s.song.title.unwrap_or_default();
-                let album_info = s.album.as_ref().map_or(String::new(), |album| {
-                    format!("#EXTALB:{}", album.album_name.clone().unwrap_or_default())
-                });
-                let genre_info = if let Some(genre) = &s.genre {
-                    if !genre.is_empty() {
-                        format!(
-                            "#EXTGENRE:{}",
-                            genre
-                                .iter()
-                                .filter_map(|g| g.genre_name.clone())
-                                .collect::<Vec<String>>()
-                                .join(",")
-                        )
-                    } else {
-                        String::new()
-                    }
-                } else {
-                    String::new()
-                };
-                let cover_path = match s.song.song_cover_path_high {
-                    Some(cover) => format!("#EXTIMG:{}", cover),
-                    None => String::new(),
-                };
-                let song_info = format!("#MOOSINF:{}", s.song.type_);
-
-                write!(
-                    ret,
-                    "#EXTINF:{},{}\n{}\n{}\n{}\n{}\n{}\n",
-                    duration, title, album_info, genre_info, cover_path, song_info, url
-                )?;
-            }
-        }
-
-        Ok(ret.replace("\n\n", "\n"))
+        Ok((playlist, playlist_songs))
     }
 }
 
@@ -1311,4 +2151,133 @@ fn merge(a: &mut Value, b: Value) {
     *a = b;
 }
 
+fn normalize_title(title: &str) -> String {
+    title
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(|c| c.to_lowercase())
+        .collect()
+}
+
+// Copies of a track encoded differently can be a little longer or shorter
+const DUPLICATE_DURATION_TOLERANCE: f64 = 2f64;
+
+// Returns groups of indices into `songs` which are copies of each other
+#[tracing::instrument(level = "debug", skip(songs))]
+pub(crate) fn group_duplicates(songs: &[Song]) -> Vec<Vec<usize>> {
+    let mut parents: Vec<usize> = (0..songs.len()).collect();
+    fn find(parents: &mut [usize], i: usize) -> usize {
+        let mut root = i;
+        while parents[root] != root {
+            root = parents[root];
+        }
+        parents[i] = root;
+        root
+    }
+    fn union(parents: &mut [usize], a: usize, b: usize) {
+        let (a, b) = (find(parents, a), find(parents, b));
+        if a != b {
+            parents[b] = a;
+        }
+    }
+
+    let mut by_hash: HashMap<&str, usize> = HashMap::new();
+    let mut by_metadata: HashMap<String, Vec<(f64, usize)>> = HashMap::new();
+    for (i, s) in songs.iter().enumerate() {
+        if let Some(hash) = s.song.hash.as_deref() {
+            match by_hash.get(hash) {
+                Some(first) => union(&mut parents, *first, i),
+                None => {
+                    by_hash.insert(hash, i);
+                }
+            }
+        }
+
+        let title = s.song.title.as_deref().map(normalize_title).unwrap_or_default();
+        let duration = s.song.duration.unwrap_or_default();
+        if title.is_empty() || duration <= 0f64 {
+            continue;
+        }
+        let mut artists: Vec<String> = s
+            .artists
+            .iter()
+            .flatten()
+            .filter_map(|a| a.artist_name.as_deref().map(normalize_title))
+            .collect();
+        artists.sort();
+        by_metadata
+            .entry(format!("{}|{}", title, artists.join(",")))
+            .or_default()
+            .push((duration, i));
+    }
+
+    for mut candidates in by_metadata.into_values() {
+        candidates.sort_by(|a, b| a.0.total_cmp(&b.0));
+        for pair in candidates.windows(2) {
+            if pair[1].0 - pair[0].0 <= DUPLICATE_DURATION_TOLERANCE {
+                union(&mut parents, pair[0].1, pair[1].1);
+            }
+        }
+    }
+
+    let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
+    for i in 0..songs.len() {
+        let root = find(&mut parents, i);
+        groups.entry(root).or_default().push(i);
+    }
+
+    let mut groups: Vec<Vec<usize>> = groups.into_values().filter(|g| g.len() > 1).collect();
+    groups.sort();
+    groups
+}
+
+// Bridge ids and songs of a playlist in playlist order
+#[tracing::instrument(level = "debug", skip(conn))]
+fn load_playlist_order(
+    conn: &mut LoggingConnection<SqliteConnection>,
+    playlist_id: &str,
+) -> QueryResult<Vec<(i32, String)>> {
+    let rows: Vec<(Option<i32>, Option<String>)> = QueryDsl::select(
+        playlist_bridge,
+        (schema::playlist_bridge::id, schema::playlist_bridge::song),
+    )
+    .filter(schema::playlist_bridge::playlist.eq(playlist_id))
+    .order((
+        schema::playlist_bridge::position.asc(),
+        schema::playlist_bridge::id.asc(),
+    ))
+    .load(conn)?;
+
+    Ok(rows
+        .into_iter()
+        .filter_map(|(bridge_id, song)| Some((bridge_id?, song?)))
+        .collect())
+}
 
+#[tracing::instrument(level = "debug", skip(conn, order))]
+fn write_playlist_order(
+    conn: &mut LoggingConnection<SqliteConnection>,
+    order: &[i32],
+) -> QueryResult<()> {
+    for (position, bridge_id) in order.iter().enumerate() {
+        update(playlist_bridge)
+            .filter(schema::playlist_bridge::id.eq(bridge_id))
+            .set(schema::playlist_bridge::position.eq(position as i32))
+            .execute(conn)?;
+    }
+    Ok(())
+}
+
+#[tracing::instrument(level = "debug", skip(conn))]
+fn next_playlist_position(
+    conn: &mut LoggingConnection<SqliteConnection>,
+    playlist_id: &str,
+) -> QueryResult<i32> {
+    let last: Option<i32> = QueryDsl::select(
+        playlist_bridge,
+        diesel::dsl::max(schema::playlist_bridge::position),
+    )
+    .filter(schema::playlist_bridge::playlist.eq(playlist_id))
+    .first(conn)?;
+    Ok(last.map_or(0, |p| p + 1))
+}
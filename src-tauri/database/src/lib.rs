@@ -17,6 +17,8 @@
 pub mod cache;
 pub mod database;
 pub mod migrations;
+mod smart_query;
+mod stats;
 
 #[cfg(test)]
 mod test;
@@ -0,0 +1,360 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use diesel::{
+    dsl::{sql, IntoBoxed, Select},
+    expression::SqlLiteral,
+    sql_types::{Integer, Nullable},
+    sqlite::Sqlite,
+    BoolExpressionMethods, EscapeExpressionMethods, ExpressionMethods, QueryDsl,
+    TextExpressionMethods,
+};
+use macros::filter_field_op;
+use types::{
+    errors::Result,
+    schema::{self, allsongs::_id},
+    smart_playlist::{
+        SmartPlaylistField, SmartPlaylistMatch, SmartPlaylistOperator, SmartPlaylistRule,
+        SmartPlaylistRules,
+    },
+};
+
+/// Ids of all songs matching the rules of a smart playlist
+pub(crate) type RuleQuery<'a> = IntoBoxed<'a, Select<schema::allsongs::table, _id>, Sqlite>;
+
+/// Library songs, sorted and limited as defined by the smart playlist
+pub(crate) type SongQuery<'a> = schema::allsongs::BoxedQuery<'a, Sqlite>;
+
+#[tracing::instrument(level = "debug", skip(value))]
+fn parse_number<T: std::str::FromStr>(field: SmartPlaylistField, value: &str) -> Result<T> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| format!("Invalid value {:?} for {:?}", value, field).into())
+}
+
+/// Matches `value` anywhere in the text, with `\\` as the escape character
+fn like_pattern(value: &str) -> String {
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{}%", escaped)
+}
+
+/// Years are stored as text, so "999" would otherwise be greater than "2020"
+fn year_as_integer() -> SqlLiteral<Nullable<Integer>> {
+    sql("CAST(allsongs.year AS INTEGER)")
+}
+
+macro_rules! filter_like {
+    ($query:expr, $column:expr, $op:ident, $value:expr, $inclusive:expr) => {{
+        let predicate = $column.$op(like_pattern(&$value)).escape('\\');
+        if $inclusive {
+            QueryDsl::filter($query, predicate)
+        } else {
+            QueryDsl::or_filter($query, predicate)
+        }
+    }};
+}
+
+macro_rules! compare_text {
+    ($query:expr, $column:expr, $rule:expr, $inclusive:expr) => {{
+        let value = $rule.value.clone();
+        match $rule.operator {
+            SmartPlaylistOperator::Equals => {
+                filter_field_op!($query, Some(value), $column, eq, $inclusive)
+            }
+            SmartPlaylistOperator::NotEquals => {
+                filter_field_op!($query, Some(value), $column, ne, $inclusive)
+            }
+            SmartPlaylistOperator::Contains => {
+                filter_like!($query, $column, like, value, $inclusive)
+            }
+            SmartPlaylistOperator::NotContains => {
+                filter_like!($query, $column, not_like, value, $inclusive)
+            }
+            SmartPlaylistOperator::GreaterThan => {
+                filter_field_op!($query, Some(value), $column, gt, $inclusive)
+            }
+            SmartPlaylistOperator::LessThan => {
+                filter_field_op!($query, Some(value), $column, lt, $inclusive)
+            }
+        }
+    }};
+}
+
+macro_rules! compare_number {
+    ($query:expr, $column:expr, $rule:expr, $inclusive:expr, $ty:ty) => {{
+        let value: $ty = parse_number($rule.field, &$rule.value)?;
+        match $rule.operator {
+            SmartPlaylistOperator::Equals => {
+                filter_field_op!($query, Some(value), $column, eq, $inclusive)
+            }
+            SmartPlaylistOperator::NotEquals => {
+                filter_field_op!($query, Some(value), $column, ne, $inclusive)
+            }
+            SmartPlaylistOperator::GreaterThan => {
+                filter_field_op!($query, Some(value), $column, gt, $inclusive)
+            }
+            SmartPlaylistOperator::LessThan => {
+                filter_field_op!($query, Some(value), $column, lt, $inclusive)
+            }
+            SmartPlaylistOperator::Contains | SmartPlaylistOperator::NotContains => {
+                return Err(
+                    format!("{:?} can't be used with {:?}", $rule.operator, $rule.field).into(),
+                );
+            }
+        }
+    }};
+}
+
+// Artists, albums and genres are matched by name through their bridge tables
+macro_rules! compare_related {
+    ($query:expr, $bridge:ident, $bridge_entity:ident, $entity:ident, $entity_id:ident, $entity_name:ident, $rule:expr, $inclusive:expr) => {{
+        let value = $rule.value.clone();
+        let names = match $rule.operator {
+            SmartPlaylistOperator::Equals | SmartPlaylistOperator::NotEquals => {
+                QueryDsl::select(schema::$entity::table, schema::$entity::$entity_id)
+                    .filter(schema::$entity::$entity_name.eq(value))
+                    .into_boxed()
+            }
+            SmartPlaylistOperator::Contains | SmartPlaylistOperator::NotContains => {
+                QueryDsl::select(schema::$entity::table, schema::$entity::$entity_id)
+                    .filter(
+                        schema::$entity::$entity_name
+                            .like(like_pattern(&value))
+                            .escape('\\'),
+                    )
+                    .into_boxed()
+            }
+            SmartPlaylistOperator::GreaterThan | SmartPlaylistOperator::LessThan => {
+                return Err(
+                    format!("{:?} can't be used with {:?}", $rule.operator, $rule.field).into(),
+                );
+            }
+        };
+        let songs = QueryDsl::select(schema::$bridge::table, schema::$bridge::song)
+            .filter(schema::$bridge::song.is_not_null())
+            .filter(schema::$bridge::$bridge_entity.eq_any(names));
+
+        match $rule.operator {
+            SmartPlaylistOperator::Equals | SmartPlaylistOperator::Contains => {
+                filter_field_op!($query, Some(songs), _id, eq_any, $inclusive)
+            }
+            _ => filter_field_op!($query, Some(songs), _id, ne_all, $inclusive),
+        }
+    }};
+}
+
+// Songs which were never played don't have analytics, they are treated as having 0 plays
+macro_rules! compare_analytics {
+    ($query:expr, $column:ident, $rule:expr, $inclusive:expr, $ty:ty) => {{
+        let value: $ty = parse_number($rule.field, &$rule.value)?;
+        let analytics = QueryDsl::select(schema::analytics::table, schema::analytics::song_id)
+            .filter(schema::analytics::song_id.is_not_null());
+        let zero: $ty = Default::default();
+        match $rule.operator {
+            SmartPlaylistOperator::GreaterThan => filter_field_op!(
+                $query,
+                Some(analytics.filter(schema::analytics::$column.gt(value))),
+                _id,
+                eq_any,
+                $inclusive
+            ),
+            SmartPlaylistOperator::LessThan => filter_field_op!(
+                $query,
+                Some(analytics.filter(schema::analytics::$column.ge(value))),
+                _id,
+                ne_all,
+                $inclusive
+            ),
+            SmartPlaylistOperator::Equals if value == zero => filter_field_op!(
+                $query,
+                Some(analytics.filter(schema::analytics::$column.ne(zero))),
+                _id,
+                ne_all,
+                $inclusive
+            ),
+            SmartPlaylistOperator::Equals => filter_field_op!(
+                $query,
+                Some(analytics.filter(schema::analytics::$column.eq(value))),
+                _id,
+                eq_any,
+                $inclusive
+            ),
+            SmartPlaylistOperator::NotEquals if value == zero => filter_field_op!(
+                $query,
+                Some(analytics.filter(schema::analytics::$column.ne(zero))),
+                _id,
+                eq_any,
+                $inclusive
+            ),
+            SmartPlaylistOperator::NotEquals => filter_field_op!(
+                $query,
+                Some(analytics.filter(schema::analytics::$column.eq(value))),
+                _id,
+                ne_all,
+                $inclusive
+            ),
+            SmartPlaylistOperator::Contains | SmartPlaylistOperator::NotContains => {
+                return Err(
+                    format!("{:?} can't be used with {:?}", $rule.operator, $rule.field).into(),
+                );
+            }
+        }
+    }};
+}
+
+#[tracing::instrument(level = "debug", skip(query, rule, inclusive))]
+fn apply_rule<'a>(
+    query: RuleQuery<'a>,
+    rule: &SmartPlaylistRule,
+    inclusive: bool,
+) -> Result<RuleQuery<'a>> {
+    Ok(match rule.field {
+        SmartPlaylistField::Title => {
+            compare_text!(query, schema::allsongs::title, rule, inclusive)
+        }
+        SmartPlaylistField::Year => match rule.operator {
+            SmartPlaylistOperator::Contains | SmartPlaylistOperator::NotContains => {
+                compare_text!(query, schema::allsongs::year, rule, inclusive)
+            }
+            _ => compare_number!(query, year_as_integer(), rule, inclusive, i32),
+        },
+        SmartPlaylistField::Codec => {
+            compare_text!(query, schema::allsongs::codec, rule, inclusive)
+        }
+        SmartPlaylistField::Path => compare_text!(query, schema::allsongs::path, rule, inclusive),
+        SmartPlaylistField::Duration => {
+            compare_number!(query, schema::allsongs::duration, rule, inclusive, f64)
+        }
+        SmartPlaylistField::Bitrate => {
+            compare_number!(query, schema::allsongs::bitrate, rule, inclusive, f64)
+        }
+        SmartPlaylistField::DateAdded => {
+            compare_number!(query, schema::allsongs::date_added, rule, inclusive, i64)
+        }
+        SmartPlaylistField::Artist => compare_related!(
+            query,
+            artist_bridge,
+            artist,
+            artists,
+            artist_id,
+            artist_name,
+            rule,
+            inclusive
+        ),
+        SmartPlaylistField::Album => compare_related!(
+            query,
+            album_bridge,
+            album,
+            albums,
+            album_id,
+            album_name,
+            rule,
+            inclusive
+        ),
+        SmartPlaylistField::Genre => compare_related!(
+            query,
+            genre_bridge,
+            genre,
+            genres,
+            genre_id,
+            genre_name,
+            rule,
+            inclusive
+        ),
+        SmartPlaylistField::PlayCount => {
+            compare_analytics!(query, play_count, rule, inclusive, i32)
+        }
+        SmartPlaylistField::PlayTime => {
+            compare_analytics!(query, play_time, rule, inclusive, f64)
+        }
+    })
+}
+
+/// Builds the query for songs of a smart playlist. Returns false along with the query if
+/// sorting (and the limit that follows it) has to be done by the caller, which is the case
+/// for fields which aren't columns of `allsongs`
+#[tracing::instrument(level = "debug", skip(rules))]
+pub(crate) fn build_smart_query<'a>(rules: &SmartPlaylistRules) -> Result<(SongQuery<'a>, bool)> {
+    let inclusive = rules.match_type == SmartPlaylistMatch::All;
+
+    let mut query = schema::allsongs::table
+        .filter(
+            schema::allsongs::show_in_library
+                .is_null()
+                .or(schema::allsongs::show_in_library.eq(true)),
+        )
+        .into_boxed();
+
+    if !rules.rules.is_empty() {
+        let mut matching: RuleQuery<'a> =
+            QueryDsl::select(schema::allsongs::table, _id).into_boxed();
+        for rule in rules.rules.iter() {
+            matching = apply_rule(matching, rule, inclusive)?;
+        }
+        query = query.filter(_id.eq_any(matching));
+    }
+
+    let sorted_in_query = match rules.sort_by {
+        Some(field) => {
+            let (sorted, query_sorted) = sort_query(query, field, rules.sort_desc);
+            query = query_sorted;
+            sorted
+        }
+        None => true,
+    };
+
+    if sorted_in_query {
+        if let Some(limit) = rules.limit {
+            query = query.limit(limit);
+        }
+    }
+
+    Ok((query, sorted_in_query))
+}
+
+macro_rules! order_by {
+    ($query:expr, $column:expr, $desc:expr) => {
+        if $desc {
+            $query.order_by($column.desc())
+        } else {
+            $query.order_by($column.asc())
+        }
+    };
+}
+
+/// Returns false if the query couldn't be sorted by `field`
+#[tracing::instrument(level = "debug", skip(query))]
+fn sort_query(
+    query: SongQuery<'_>,
+    field: SmartPlaylistField,
+    desc: bool,
+) -> (bool, SongQuery<'_>) {
+    let query = match field {
+        SmartPlaylistField::Title => order_by!(query, schema::allsongs::title, desc),
+        SmartPlaylistField::Year => order_by!(query, year_as_integer(), desc),
+        SmartPlaylistField::Duration => order_by!(query, schema::allsongs::duration, desc),
+        SmartPlaylistField::Bitrate => order_by!(query, schema::allsongs::bitrate, desc),
+        SmartPlaylistField::Codec => order_by!(query, schema::allsongs::codec, desc),
+        SmartPlaylistField::Path => order_by!(query, schema::allsongs::path, desc),
+        SmartPlaylistField::DateAdded => order_by!(query, schema::allsongs::date_added, desc),
+        _ => return (false, query),
+    };
+    (true, query)
+}
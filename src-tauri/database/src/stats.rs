@@ -0,0 +1,329 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use diesel::{ExpressionMethods, QueryDsl, QueryResult, RunQueryDsl, SqliteConnection};
+use diesel_logger::LoggingConnection;
+use types::{
+    entities::PlayHistory,
+    schema,
+    stats::{ListeningStats, ListeningStreak, StatsEntry, StatsPeriod},
+};
+
+/// Number of songs, artists, albums and genres listed in the stats
+const TOP_STATS: usize = 10;
+
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+
+/// Ids bound per query, well below SQLite's limit on bound variables
+pub(crate) const QUERY_CHUNK: usize = 500;
+
+type Related = HashMap<String, Vec<(String, String)>>;
+
+/// Titles of the played songs along with the ids and names of their artists, albums and genres
+#[derive(Default)]
+pub(crate) struct SongRelations {
+    pub titles: HashMap<String, String>,
+    pub artists: Related,
+    pub albums: Related,
+    pub genres: Related,
+}
+
+macro_rules! load_related {
+    ($conn:expr, $ids:expr, $bridge:ident, $bridge_entity:ident, $entity:ident, $entity_id:ident, $entity_name:ident) => {{
+        let bridges: Vec<(Option<String>, Option<String>)> = QueryDsl::select(
+            schema::$bridge::table,
+            (schema::$bridge::song, schema::$bridge::$bridge_entity),
+        )
+        .filter(schema::$bridge::song.eq_any($ids))
+        .load($conn)?;
+
+        let entity_ids: Vec<String> = bridges
+            .iter()
+            .filter_map(|(_, e)| e.clone())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        let mut names: HashMap<String, String> = HashMap::new();
+        for chunk in entity_ids.chunks(QUERY_CHUNK) {
+            names.extend(
+                QueryDsl::select(
+                    schema::$entity::table,
+                    (schema::$entity::$entity_id, schema::$entity::$entity_name),
+                )
+                .filter(schema::$entity::$entity_id.eq_any(chunk))
+                .load::<(Option<String>, Option<String>)>($conn)?
+                .into_iter()
+                .filter_map(|(id, name)| Some((id?, name?))),
+            );
+        }
+
+        let mut related = Related::new();
+        for (song, entity) in bridges {
+            if let (Some(song), Some(entity)) = (song, entity) {
+                if let Some(name) = names.get(&entity) {
+                    related
+                        .entry(song)
+                        .or_default()
+                        .push((entity, name.clone()));
+                }
+            }
+        }
+        related
+    }};
+}
+
+#[tracing::instrument(level = "debug", skip(conn, song_ids))]
+pub(crate) fn load_song_relations(
+    conn: &mut LoggingConnection<SqliteConnection>,
+    song_ids: &[String],
+) -> QueryResult<SongRelations> {
+    let mut relations = SongRelations::default();
+    for chunk in song_ids.chunks(QUERY_CHUNK) {
+        relations.titles.extend(
+            QueryDsl::select(
+                schema::allsongs::table,
+                (schema::allsongs::_id, schema::allsongs::title),
+            )
+            .filter(schema::allsongs::_id.eq_any(chunk))
+            .load::<(Option<String>, Option<String>)>(conn)?
+            .into_iter()
+            .filter_map(|(id, title)| Some((id?, title?))),
+        );
+        relations.artists.extend(load_related!(
+            conn,
+            chunk,
+            artist_bridge,
+            artist,
+            artists,
+            artist_id,
+            artist_name
+        ));
+        relations.albums.extend(load_related!(
+            conn,
+            chunk,
+            album_bridge,
+            album,
+            albums,
+            album_id,
+            album_name
+        ));
+        relations.genres.extend(load_related!(
+            conn,
+            chunk,
+            genre_bridge,
+            genre,
+            genres,
+            genre_id,
+            genre_name
+        ));
+    }
+    Ok(relations)
+}
+
+/// Converts days since the unix epoch to a (year, month, day) date
+#[tracing::instrument(level = "debug", skip())]
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let doe = days.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[tracing::instrument(level = "debug", skip())]
+fn format_day(days: i64) -> String {
+    let (year, month, day) = civil_from_days(days);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+#[tracing::instrument(level = "debug", skip())]
+fn format_month(days: i64) -> String {
+    let (year, month, _) = civil_from_days(days);
+    format!("{:04}-{:02}", year, month)
+}
+
+/// Days since the unix epoch in the local time of `utc_offset` (minutes)
+#[tracing::instrument(level = "debug", skip())]
+fn local_day(timestamp: i64, utc_offset: i32) -> i64 {
+    (timestamp + utc_offset as i64 * 60 * 1000).div_euclid(DAY_MS)
+}
+
+/// Day the week of `day` starts on. 1970-01-01 was a thursday
+#[tracing::instrument(level = "debug", skip())]
+fn week_start(day: i64) -> i64 {
+    day - (day + 3).rem_euclid(7)
+}
+
+#[tracing::instrument(level = "debug", skip(plays))]
+fn into_entries(
+    plays: HashMap<String, (i64, f64)>,
+    names: impl Fn(&str) -> Option<String>,
+) -> Vec<StatsEntry> {
+    let mut entries: Vec<StatsEntry> = plays
+        .into_iter()
+        .filter_map(|(id, (play_count, play_time))| {
+            Some(StatsEntry {
+                name: names(&id)?,
+                id,
+                play_count,
+                play_time,
+            })
+        })
+        .collect();
+    entries.sort_by(|a, b| {
+        b.play_time
+            .total_cmp(&a.play_time)
+            .then(b.play_count.cmp(&a.play_count))
+            .then(a.name.cmp(&b.name))
+    });
+    entries.truncate(TOP_STATS);
+    entries
+}
+
+#[tracing::instrument(level = "debug", skip(periods))]
+fn into_periods(periods: BTreeMap<String, (i64, f64)>) -> Vec<StatsPeriod> {
+    periods
+        .into_iter()
+        .map(|(period, (play_count, play_time))| StatsPeriod {
+            period,
+            play_count,
+            play_time,
+        })
+        .collect()
+}
+
+#[tracing::instrument(level = "debug", skip(days))]
+fn longest_streak(days: &BTreeSet<i64>) -> Option<ListeningStreak> {
+    let mut longest: Option<(i64, i64)> = None;
+    let mut current: Option<(i64, i64)> = None;
+    for day in days.iter().copied() {
+        current = match current {
+            Some((start, end)) if end + 1 == day => Some((start, day)),
+            _ => Some((day, day)),
+        };
+        let (start, end) = current.unwrap();
+        if longest.is_none_or(|(s, e)| end - start > e - s) {
+            longest = Some((start, end));
+        }
+    }
+
+    longest.map(|(start, end)| ListeningStreak {
+        start: format_day(start),
+        end: format_day(end),
+        days: end - start + 1,
+    })
+}
+
+/// `first_plays` holds the time each song was first played, over the whole history
+#[tracing::instrument(level = "debug", skip(plays, relations, first_plays))]
+pub(crate) fn build_listening_stats(
+    plays: &[PlayHistory],
+    relations: &SongRelations,
+    first_plays: &HashMap<String, i64>,
+    from: Option<i64>,
+    to: Option<i64>,
+    utc_offset: i32,
+) -> ListeningStats {
+    let mut songs: HashMap<String, (i64, f64)> = HashMap::new();
+    let mut artists: HashMap<String, (i64, f64)> = HashMap::new();
+    let mut albums: HashMap<String, (i64, f64)> = HashMap::new();
+    let mut genres: HashMap<String, (i64, f64)> = HashMap::new();
+    let mut days: BTreeMap<String, (i64, f64)> = BTreeMap::new();
+    let mut weeks: BTreeMap<String, (i64, f64)> = BTreeMap::new();
+    let mut months: BTreeMap<String, (i64, f64)> = BTreeMap::new();
+    let mut played_days = BTreeSet::new();
+
+    let add = |map: &mut HashMap<String, (i64, f64)>, key: &str, duration: f64| {
+        let entry = map.entry(key.to_string()).or_default();
+        entry.0 += 1;
+        entry.1 += duration;
+    };
+    let add_period = |map: &mut BTreeMap<String, (i64, f64)>, key: String, duration: f64| {
+        let entry = map.entry(key).or_default();
+        entry.0 += 1;
+        entry.1 += duration;
+    };
+
+    for play in plays {
+        add(&mut songs, &play.song_id, play.duration);
+        for (related, map) in [
+            (&relations.artists, &mut artists),
+            (&relations.albums, &mut albums),
+            (&relations.genres, &mut genres),
+        ] {
+            for (id, _) in related.get(&play.song_id).into_iter().flatten() {
+                add(map, id, play.duration);
+            }
+        }
+
+        let day = local_day(play.started_at, utc_offset);
+        played_days.insert(day);
+        add_period(&mut days, format_day(day), play.duration);
+        add_period(&mut weeks, format_day(week_start(day)), play.duration);
+        add_period(&mut months, format_month(day), play.duration);
+    }
+
+    let names = |related: &Related| {
+        let mut names = HashMap::new();
+        for (id, name) in related.values().flatten() {
+            names.insert(id.clone(), name.clone());
+        }
+        names
+    };
+    let artist_names = names(&relations.artists);
+    let album_names = names(&relations.albums);
+    let genre_names = names(&relations.genres);
+
+    let in_range = |time: i64| from.is_none_or(|f| time >= f) && to.is_none_or(|t| time < t);
+    let new_songs: HashMap<String, (i64, f64)> = songs
+        .iter()
+        .filter(|(id, _)| first_plays.get(*id).is_some_and(|t| in_range(*t)))
+        .map(|(id, v)| (id.clone(), *v))
+        .collect();
+
+    let play_count = plays.len() as i64;
+    let skip_count = plays.iter().filter(|p| !p.completed).count() as i64;
+    let song_name = |id: &str| relations.titles.get(id).cloned();
+
+    ListeningStats {
+        from,
+        to,
+        play_count,
+        play_time: plays.iter().map(|p| p.duration).sum(),
+        skip_count,
+        skip_rate: if play_count > 0 {
+            skip_count as f64 / play_count as f64
+        } else {
+            0f64
+        },
+        top_songs: into_entries(songs, song_name),
+        top_artists: into_entries(artists, |id| artist_names.get(id).cloned()),
+        top_albums: into_entries(albums, |id| album_names.get(id).cloned()),
+        top_genres: into_entries(genres, |id| genre_names.get(id).cloned()),
+        new_songs: into_entries(new_songs, song_name),
+        days: into_periods(days),
+        weeks: into_periods(weeks),
+        months: into_periods(months),
+        longest_streak: longest_streak(&played_days),
+    }
+}
@@ -19,9 +19,16 @@ use std::{env::temp_dir, fs, path::PathBuf};
 use crate::database::Database;
 use types::{
     entities::{
-        GetEntityOptions, QueryableAlbum, QueryableArtist, QueryableGenre, QueryablePlaylist,
+        GetEntityOptions, PlayDetails, QueryableAlbum, QueryableArtist, QueryableGenre,
+        QueryablePlaylist,
+    },
+    smart_playlist::{
+        SmartPlaylistField, SmartPlaylistMatch, SmartPlaylistOperator, SmartPlaylistRule,
+        SmartPlaylistRules,
     },
     songs::{GetSongOptions, QueryableSong, SearchableSong, Song, SongType},
+    stats::{ListeningStreak, StatsEntry, StatsPeriod},
+    tags::{TagChange, TagChanges, TagField, TagJournalEntry},
 };
 use uuid::Uuid;
 
@@ -239,8 +246,9 @@ fn test_remove_songs() {
     // Add analytics data to both songs
     db.increment_play_count(keep_id.clone()).unwrap();
     db.increment_play_count(remove_id.clone()).unwrap();
-    db.increment_play_time(keep_id.clone(), 60.0).unwrap();
-    db.increment_play_time(remove_id.clone(), 120.0).unwrap();
+    db.increment_play_time(keep_id.clone(), 60.0, None).unwrap();
+    db.increment_play_time(remove_id.clone(), 120.0, None)
+        .unwrap();
 
     // Remove one song
     db.remove_songs(vec![remove_id.clone()]).unwrap();
@@ -320,7 +328,7 @@ fn test_playlist_operations() {
         .unwrap();
 
     // Add songs to playlist
-    db.add_to_playlist(playlist_id.clone(), songs.clone())
+    db.add_to_playlist(playlist_id.clone(), songs.clone(), None)
         .unwrap();
 
     // Get playlist songs
@@ -371,6 +379,112 @@ fn test_playlist_operations() {
     cleanup(&db_path);
 }
 
+// Test that playlists keep their order across inserts and moves
+#[test]
+fn test_playlist_order() {
+    let db_path = get_test_db_path();
+    let db = Database::new(db_path.clone());
+
+    let playlist_id = db
+        .create_playlist(QueryablePlaylist {
+            playlist_name: "Ordered".to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+
+    let songs = db
+        .insert_songs(
+            ["a", "b", "c", "d", "e"]
+                .iter()
+                .map(|t| create_test_song(t, &format!("/path/to/order_{}.mp3", t)))
+                .collect(),
+        )
+        .unwrap();
+
+    let get_order = || {
+        db.get_songs_by_options(GetSongOptions {
+            playlist: Some(QueryablePlaylist {
+                playlist_id: Some(playlist_id.clone()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        })
+        .unwrap()
+        .into_iter()
+        .map(|s| s.song.title.unwrap())
+        .collect::<Vec<_>>()
+    };
+
+    db.add_to_playlist(
+        playlist_id.clone(),
+        vec![songs[2].clone(), songs[0].clone()],
+        None,
+    )
+    .unwrap();
+    assert_eq!(get_order(), vec!["c", "a"]);
+
+    // Inserting at a position, songs already in the playlist are skipped
+    db.add_to_playlist(
+        playlist_id.clone(),
+        vec![songs[1].clone(), songs[0].clone(), songs[3].clone()],
+        Some(1),
+    )
+    .unwrap();
+    assert_eq!(get_order(), vec!["c", "b", "d", "a"]);
+
+    db.add_to_playlist_bridge(playlist_id.clone(), songs[4].song._id.clone().unwrap())
+        .unwrap();
+    assert_eq!(get_order(), vec!["c", "b", "d", "a", "e"]);
+
+    db.move_in_playlist(playlist_id.clone(), songs[4].song._id.clone().unwrap(), 0)
+        .unwrap();
+    assert_eq!(get_order(), vec!["e", "c", "b", "d", "a"]);
+
+    db.move_in_playlist(playlist_id.clone(), songs[2].song._id.clone().unwrap(), 10)
+        .unwrap();
+    assert_eq!(get_order(), vec!["e", "b", "d", "a", "c"]);
+
+    db.remove_from_playlist(
+        playlist_id.clone(),
+        vec![songs[3].song._id.clone().unwrap()],
+    )
+    .unwrap();
+    db.reorder_playlist(
+        playlist_id.clone(),
+        vec![
+            songs[0].song._id.clone().unwrap(),
+            songs[1].song._id.clone().unwrap(),
+        ],
+    )
+    .unwrap();
+    assert_eq!(get_order(), vec!["a", "b", "e", "c"]);
+
+    // Appending after a removal still goes to the end
+    db.add_to_playlist(playlist_id.clone(), vec![songs[3].clone()], None)
+        .unwrap();
+    assert_eq!(get_order(), vec!["a", "b", "e", "c", "d"]);
+
+    // Playlists are exported in playlist order
+    let (_, exported) = db.get_playlist_with_songs(playlist_id.clone()).unwrap();
+    let exported_paths: Vec<String> = exported.into_iter().filter_map(|s| s.song.path).collect();
+    assert_eq!(
+        exported_paths,
+        vec![
+            "/path/to/order_a.mp3",
+            "/path/to/order_b.mp3",
+            "/path/to/order_e.mp3",
+            "/path/to/order_c.mp3",
+            "/path/to/order_d.mp3",
+        ]
+    );
+
+    assert!(db
+        .move_in_playlist(playlist_id, "missing".to_string(), 0)
+        .is_err());
+
+    cleanup(&db_path);
+}
+
 // Test album operations
 #[test]
 fn test_album_operations() {
@@ -637,8 +751,10 @@ fn test_analytics() {
     }
 
     // Add some play time
-    db.increment_play_time(song_id.clone(), 120.0).unwrap();
-    db.increment_play_time(song_id.clone(), 180.0).unwrap();
+    db.increment_play_time(song_id.clone(), 120.0, None)
+        .unwrap();
+    db.increment_play_time(song_id.clone(), 180.0, None)
+        .unwrap();
 
     // Get top listened songs
     let analytics = db.get_top_listened_songs().unwrap();
@@ -663,3 +779,852 @@ fn test_analytics() {
 
     cleanup(&db_path);
 }
+
+// Test recording and querying the play history
+#[test]
+fn test_play_history() {
+    let db_path = get_test_db_path();
+    let db = Database::new(db_path.clone());
+
+    let songs = db
+        .insert_songs(vec![
+            create_test_song("First", "/music/first.mp3"),
+            create_test_song("Second", "/music/second.mp3"),
+        ])
+        .unwrap();
+    let first_id = songs[0].song._id.clone().unwrap();
+    let second_id = songs[1].song._id.clone().unwrap();
+
+    db.increment_play_time(
+        first_id.clone(),
+        200.0,
+        Some(PlayDetails {
+            completed: true,
+            provider: None,
+            player: Some("LOCAL".to_string()),
+            ..Default::default()
+        }),
+    )
+    .unwrap();
+    db.increment_play_time(
+        second_id.clone(),
+        10.0,
+        Some(PlayDetails {
+            completed: false,
+            provider: Some("extension".to_string()),
+            player: Some("RODIO".to_string()),
+            ..Default::default()
+        }),
+    )
+    .unwrap();
+
+    // Play time without details isn't part of the history
+    db.increment_play_time(first_id.clone(), 50.0, None)
+        .unwrap();
+
+    let history = db.get_play_history(None, None, None).unwrap();
+    assert_eq!(history.len(), 2);
+
+    // The second song started later since it was played for less time
+    assert_eq!(history[0].song.song._id, Some(second_id.clone()));
+    assert!(!history[0].play.completed);
+    assert_eq!(history[0].play.provider.as_deref(), Some("extension"));
+    assert_eq!(history[0].play.player.as_deref(), Some("RODIO"));
+    assert_eq!(history[1].song.song._id, Some(first_id.clone()));
+    assert!(history[1].play.completed);
+    assert_eq!(history[1].play.duration, 200.0);
+    assert!(history[1].play.started_at < history[0].play.started_at);
+
+    let limited = db.get_play_history(None, None, Some(1)).unwrap();
+    assert_eq!(limited.len(), 1);
+    assert_eq!(limited[0].song.song._id, Some(second_id.clone()));
+
+    let started_at = history[0].play.started_at;
+    let range = db
+        .get_play_history(Some(started_at), Some(started_at + 1), None)
+        .unwrap();
+    assert_eq!(range.len(), 1);
+    assert_eq!(range[0].song.song._id, Some(second_id.clone()));
+
+    db.clear_play_history(Some(started_at)).unwrap();
+    let history = db.get_play_history(None, None, None).unwrap();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].song.song._id, Some(second_id.clone()));
+
+    // Removing songs removes their plays
+    db.remove_songs(vec![second_id]).unwrap();
+    assert!(db.get_play_history(None, None, None).unwrap().is_empty());
+
+    cleanup(&db_path);
+}
+
+// Test listening stats computed from the play history
+#[test]
+fn test_listening_stats() {
+    let db_path = get_test_db_path();
+    let db = Database::new(db_path.clone());
+
+    let stats_song = |title: &str, artist: &str, genre: &str| {
+        let mut song = create_test_song(title, &format!("/path/to/stats_{}.mp3", title));
+        song.artists = Some(vec![QueryableArtist {
+            artist_name: Some(artist.to_string()),
+            ..Default::default()
+        }]);
+        song.genre = Some(vec![QueryableGenre {
+            genre_name: Some(genre.to_string()),
+            ..Default::default()
+        }]);
+        song
+    };
+
+    let songs = db
+        .insert_songs(vec![
+            stats_song("Alpha", "First Artist", "Rock"),
+            stats_song("Beta", "Second Artist", "Jazz"),
+            stats_song("Gamma", "First Artist", "Rock"),
+        ])
+        .unwrap();
+    let ids: Vec<String> = songs.iter().map(|s| s.song._id.clone().unwrap()).collect();
+
+    // 2024-10-04, a friday
+    const DAY: i64 = 24 * 60 * 60 * 1000;
+    const HOUR: i64 = 60 * 60 * 1000;
+    let start = 20000 * DAY;
+
+    let play = |song: usize, started_at: i64, duration: f64, completed: bool| {
+        db.increment_play_time(
+            ids[song].clone(),
+            duration,
+            Some(PlayDetails {
+                started_at: Some(started_at),
+                completed,
+                ..Default::default()
+            }),
+        )
+        .unwrap();
+    };
+
+    play(0, start - 10 * DAY, 100.0, true);
+    play(0, start + HOUR, 200.0, true);
+    play(1, start + 2 * HOUR, 30.0, false);
+    play(0, start + DAY + HOUR, 200.0, true);
+    play(2, start + 2 * DAY, 150.0, true);
+    play(1, start + 5 * DAY, 300.0, true);
+
+    let stats = db
+        .get_listening_stats(Some(start), Some(start + 10 * DAY), 0)
+        .unwrap();
+
+    assert_eq!(stats.play_count, 5);
+    assert_eq!(stats.play_time, 880.0);
+    assert_eq!(stats.skip_count, 1);
+    assert_eq!(stats.skip_rate, 0.2);
+
+    let names = |entries: &Vec<StatsEntry>| {
+        entries
+            .iter()
+            .map(|e| (e.name.clone(), e.play_count, e.play_time))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        names(&stats.top_songs),
+        vec![
+            ("Alpha".to_string(), 2, 400.0),
+            ("Beta".to_string(), 2, 330.0),
+            ("Gamma".to_string(), 1, 150.0),
+        ]
+    );
+    assert_eq!(
+        names(&stats.top_artists),
+        vec![
+            ("First Artist".to_string(), 3, 550.0),
+            ("Second Artist".to_string(), 2, 330.0),
+        ]
+    );
+    assert_eq!(
+        names(&stats.top_genres),
+        vec![
+            ("Rock".to_string(), 3, 550.0),
+            ("Jazz".to_string(), 2, 330.0)
+        ]
+    );
+    assert_eq!(
+        names(&stats.top_albums),
+        vec![("Test Album".to_string(), 5, 880.0)]
+    );
+
+    // Alpha was first played before the range
+    assert_eq!(
+        names(&stats.new_songs),
+        vec![
+            ("Beta".to_string(), 2, 330.0),
+            ("Gamma".to_string(), 1, 150.0)
+        ]
+    );
+
+    let periods = |periods: &Vec<StatsPeriod>| {
+        periods
+            .iter()
+            .map(|p| (p.period.clone(), p.play_count))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        periods(&stats.days),
+        vec![
+            ("2024-10-04".to_string(), 2),
+            ("2024-10-05".to_string(), 1),
+            ("2024-10-06".to_string(), 1),
+            ("2024-10-09".to_string(), 1),
+        ]
+    );
+    assert_eq!(
+        periods(&stats.weeks),
+        vec![("2024-09-30".to_string(), 4), ("2024-10-07".to_string(), 1)]
+    );
+    assert_eq!(periods(&stats.months), vec![("2024-10".to_string(), 5)]);
+    assert_eq!(
+        stats.longest_streak,
+        Some(ListeningStreak {
+            start: "2024-10-04".to_string(),
+            end: "2024-10-06".to_string(),
+            days: 3,
+        })
+    );
+
+    // Days are split in local time
+    let stats = db
+        .get_listening_stats(Some(start), Some(start + DAY), -2 * 60)
+        .unwrap();
+    assert_eq!(
+        periods(&stats.days),
+        vec![("2024-10-03".to_string(), 1), ("2024-10-04".to_string(), 1)]
+    );
+
+    let csv = stats.to_csv();
+    assert!(csv.starts_with("section,id,name,play_count,play_time\n"));
+    assert!(csv.contains(&format!("top_song,{},Alpha,1,200\n", ids[0])));
+    assert!(csv.contains("day,2024-10-03,,1,200\n"));
+
+    cleanup(&db_path);
+}
+
+// Stats over more songs than fit in a single query
+#[test]
+fn test_listening_stats_many_songs() {
+    let db_path = get_test_db_path();
+    let db = Database::new(db_path.clone());
+
+    let songs = db
+        .insert_songs(
+            (0..1100)
+                .map(|i| {
+                    create_test_song(&format!("Song {}", i), &format!("/path/to/many_{}.mp3", i))
+                })
+                .collect(),
+        )
+        .unwrap();
+
+    let play = |song: &Song, started_at: i64| {
+        db.increment_play_time(
+            song.song._id.clone().unwrap(),
+            100.0,
+            Some(PlayDetails {
+                started_at: Some(started_at),
+                completed: true,
+                ..Default::default()
+            }),
+        )
+        .unwrap();
+    };
+    for (i, song) in songs.iter().enumerate() {
+        play(song, i as i64 * 1000);
+    }
+    play(&songs[1050], 2_000_000);
+    play(&songs[1050], 3_000_000);
+
+    let stats = db.get_listening_stats(None, None, 0).unwrap();
+    assert_eq!(stats.play_count, 1102);
+    assert_eq!(stats.top_songs[0].name, "Song 1050");
+    assert_eq!(stats.top_songs[0].play_count, 3);
+    assert_eq!(stats.top_artists[0].name, "Test Artist");
+    assert_eq!(stats.top_artists[0].play_count, 1102);
+
+    cleanup(&db_path);
+}
+
+// Test looking up and moving songs by path
+#[test]
+fn test_get_and_move_songs_by_path() {
+    let db_path = get_test_db_path();
+    let db = Database::new(db_path.clone());
+
+    db.insert_songs(vec![
+        create_test_song("Song 1", "/music/album/song1.mp3"),
+        create_test_song("Song 2", "/music/album/song2.mp3"),
+        create_test_song("Song 3", "/music/album_2/song3.mp3"),
+        create_test_song("Song 4", "/music/song4.mp3"),
+    ])
+    .unwrap();
+
+    // Directory lookups should not match siblings sharing the same prefix
+    let songs = db.get_songs_by_path(PathBuf::from("/music/album")).unwrap();
+    assert_eq!(songs.len(), 2);
+
+    let songs = db
+        .get_songs_by_path(PathBuf::from("/music/song4.mp3"))
+        .unwrap();
+    assert_eq!(songs.len(), 1);
+    let song_id = songs[0]._id.clone();
+
+    // Moving a single file keeps its id
+    let moved = db
+        .move_songs(
+            PathBuf::from("/music/song4.mp3"),
+            PathBuf::from("/music/renamed.mp3"),
+        )
+        .unwrap();
+    assert_eq!(moved, 1);
+    let songs = db
+        .get_songs_by_path(PathBuf::from("/music/renamed.mp3"))
+        .unwrap();
+    assert_eq!(songs.len(), 1);
+    assert_eq!(songs[0]._id, song_id);
+
+    // Moving a directory moves everything inside it
+    let moved = db
+        .move_songs(PathBuf::from("/music/album"), PathBuf::from("/other/album"))
+        .unwrap();
+    assert_eq!(moved, 2);
+    assert!(db
+        .get_songs_by_path(PathBuf::from("/music/album"))
+        .unwrap()
+        .is_empty());
+    let songs = db.get_songs_by_path(PathBuf::from("/other/album")).unwrap();
+    assert_eq!(songs.len(), 2);
+    assert!(songs
+        .iter()
+        .any(|s| s.path.as_deref() == Some("/other/album/song1.mp3")));
+
+    cleanup(&db_path);
+}
+
+// Test storing analysed ReplayGain values
+#[test]
+fn test_song_gain() {
+    let db_path = get_test_db_path();
+    let db = Database::new(db_path.clone());
+
+    let mut tagged = create_test_song("Tagged", "/music/tagged.flac");
+    tagged.song.track_gain = Some(-6.5);
+    tagged.song.track_peak = Some(0.98);
+    let inserted = db
+        .insert_songs(vec![
+            tagged,
+            create_test_song("Untagged", "/music/untagged.mp3"),
+        ])
+        .unwrap();
+
+    let songs = db.get_songs_without_gain().unwrap();
+    assert_eq!(songs.len(), 1);
+    assert_eq!(songs[0].title.as_deref(), Some("Untagged"));
+
+    let id = songs[0]._id.clone().unwrap();
+    db.set_song_gain(id, -3.2, 0.75).unwrap();
+    assert!(db.get_songs_without_gain().unwrap().is_empty());
+
+    let songs = db
+        .get_songs_by_path(PathBuf::from("/music/untagged.mp3"))
+        .unwrap();
+    assert_eq!(songs[0].track_gain, Some(-3.2));
+    assert_eq!(songs[0].track_peak, Some(0.75));
+
+    // Tags read by the scanner are kept as they are
+    let songs = db
+        .get_songs_by_path(PathBuf::from("/music/tagged.flac"))
+        .unwrap();
+    assert_eq!(songs[0]._id, inserted[0].song._id);
+    assert_eq!(songs[0].track_gain, Some(-6.5));
+
+    cleanup(&db_path);
+}
+
+// Test that albums are identified by their name, album artist and year
+#[test]
+fn test_album_identity() {
+    let db_path = get_test_db_path();
+    let db = Database::new(db_path.clone());
+
+    let album_song = |title: &str, path: &str, artist: &str, disc: f64| {
+        let mut song = create_test_song(title, path);
+        song.song.disc_no = Some(disc);
+        song.song.disc_total = Some(disc);
+        song.album = Some(QueryableAlbum {
+            album_name: Some("Greatest Hits".to_string()),
+            album_artist: Some(artist.to_string()),
+            year: Some("1999".to_string()),
+            disc_total: Some(disc),
+            ..Default::default()
+        });
+        song
+    };
+
+    let inserted = db
+        .insert_songs(vec![
+            album_song("One", "/music/a/1-01.mp3", "Artist A", 1f64),
+            album_song("Two", "/music/a/2-01.mp3", "Artist A", 2f64),
+            album_song("Three", "/music/b/01.mp3", "Artist B", 1f64),
+        ])
+        .unwrap();
+
+    // Both discs of the same album share it, albums by other artists don't
+    let first = inserted[0].album.as_ref().unwrap().album_id.clone();
+    assert_eq!(inserted[1].album.as_ref().unwrap().album_id, first);
+    assert_ne!(inserted[2].album.as_ref().unwrap().album_id, first);
+
+    let result = db
+        .get_entity_by_options(GetEntityOptions {
+            album: Some(QueryableAlbum {
+                album_name: Some("Greatest Hits".to_string()),
+                ..Default::default()
+            }),
+            inclusive: Some(false),
+            ..Default::default()
+        })
+        .unwrap();
+    let albums: Vec<QueryableAlbum> = serde_json::from_value(result).unwrap();
+    assert_eq!(albums.len(), 2);
+
+    // The disc count grows as later discs are scanned
+    let album = albums.iter().find(|a| a.album_id == first).unwrap();
+    assert_eq!(album.disc_total, Some(2f64));
+    assert_eq!(album.album_song_count, 2f64);
+
+    let songs = db
+        .get_songs_by_path(PathBuf::from("/music/a/2-01.mp3"))
+        .unwrap();
+    assert_eq!(songs[0].disc_no, Some(2f64));
+
+    cleanup(&db_path);
+}
+
+// Test that MusicBrainz IDs take precedence over names when matching artists and albums
+#[test]
+fn test_musicbrainz_identity() {
+    let db_path = get_test_db_path();
+    let db = Database::new(db_path.clone());
+
+    let tagged_song = |title: &str, path: &str, artist_mbid: Option<&str>, album_mbid: &str| {
+        let mut song = create_test_song(title, path);
+        song.artists.as_mut().unwrap()[0].artist_mbid = artist_mbid.map(|s| s.to_string());
+        song.album.as_mut().unwrap().album_mbid = Some(album_mbid.to_string());
+        song
+    };
+
+    let inserted = db
+        .insert_songs(vec![
+            create_test_song("Untagged", "/music/untagged.mp3"),
+            tagged_song("First", "/music/first.mp3", Some("artist-1"), "release-1"),
+            tagged_song("Second", "/music/second.mp3", Some("artist-2"), "release-2"),
+            tagged_song("Third", "/music/third.mp3", None, "release-1"),
+        ])
+        .unwrap();
+
+    let artist_id = |i: usize| inserted[i].artists.as_ref().unwrap()[0].artist_id.clone();
+    let album_id = |i: usize| inserted[i].album.as_ref().unwrap().album_id.clone();
+
+    // The first tagged artist and release are merged into the untagged ones
+    assert_eq!(artist_id(1), artist_id(0));
+    assert_eq!(album_id(1), album_id(0));
+
+    // Different IDs with the same names stay separate
+    assert_ne!(artist_id(2), artist_id(0));
+    assert_ne!(album_id(2), album_id(0));
+
+    // Songs without an artist ID are still matched by name, and releases by their ID
+    assert_eq!(artist_id(3), artist_id(0));
+    assert_eq!(album_id(3), album_id(0));
+
+    cleanup(&db_path);
+}
+
+// Test finding duplicate songs and merging them
+#[test]
+fn test_duplicates() {
+    let db_path = get_test_db_path();
+    let db = Database::new(db_path.clone());
+
+    let mut flac = create_test_song("Song", "/music/a/song.flac");
+    flac.song.hash = Some("hash_1".to_string());
+    // Same track in another format, with slightly different tags
+    let mut mp3 = create_test_song("song!", "/music/b/song.mp3");
+    mp3.song.hash = Some("hash_2".to_string());
+    mp3.song.duration = Some(301.2);
+    // Same title but clearly a different recording
+    let mut live = create_test_song("Song", "/music/c/song_live.mp3");
+    live.song.hash = Some("hash_3".to_string());
+    live.song.duration = Some(420.0);
+    // Identical audio with different tags
+    let mut other_1 = create_test_song("Other", "/music/a/other.mp3");
+    other_1.song.hash = Some("hash_4".to_string());
+    let mut other_2 = create_test_song("Untitled", "/music/b/other.mp3");
+    other_2.song.hash = Some("hash_4".to_string());
+
+    let songs = db
+        .insert_songs(vec![flac, mp3, live, other_1, other_2])
+        .unwrap();
+    let flac_id = songs[0].song._id.clone().unwrap();
+    let mp3_id = songs[1].song._id.clone().unwrap();
+
+    let groups = db.get_duplicate_songs().unwrap();
+    assert_eq!(groups.len(), 2);
+    let mut paths: Vec<Vec<String>> = groups
+        .iter()
+        .map(|g| {
+            let mut paths: Vec<String> = g.iter().filter_map(|s| s.song.path.clone()).collect();
+            paths.sort();
+            paths
+        })
+        .collect();
+    paths.sort();
+    assert_eq!(
+        paths,
+        vec![
+            vec!["/music/a/other.mp3", "/music/b/other.mp3"],
+            vec!["/music/a/song.flac", "/music/b/song.mp3"],
+        ]
+    );
+
+    let playlist_id = db
+        .create_playlist(QueryablePlaylist {
+            playlist_name: "Duplicates".to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+    db.add_to_playlist_bridge(playlist_id.clone(), mp3_id.clone())
+        .unwrap();
+    db.increment_play_time(flac_id.clone(), 100.0, None)
+        .unwrap();
+    db.increment_play_time(mp3_id.clone(), 50.0, None).unwrap();
+
+    let removed = db
+        .merge_duplicates(flac_id.clone(), vec![mp3_id.clone()])
+        .unwrap();
+    assert_eq!(removed, vec!["/music/b/song.mp3".to_string()]);
+
+    // Play time and playlists now belong to the kept song
+    let analytics = db.get_top_listened_songs().unwrap();
+    let (_, play_time) = analytics
+        .songs
+        .iter()
+        .find(|(id, _)| *id == flac_id)
+        .unwrap();
+    assert_eq!(*play_time, 150.0);
+    assert!(!analytics.songs.iter().any(|(id, _)| *id == mp3_id));
+
+    let playlist_songs = db
+        .get_songs_by_options(GetSongOptions {
+            playlist: Some(QueryablePlaylist {
+                playlist_id: Some(playlist_id),
+                ..Default::default()
+            }),
+            ..Default::default()
+        })
+        .unwrap();
+    assert_eq!(playlist_songs.len(), 1);
+    assert_eq!(playlist_songs[0].song._id, Some(flac_id));
+
+    assert_eq!(db.get_duplicate_songs().unwrap().len(), 1);
+
+    // Rescanning the removed copy doesn't add it back
+    let mut rescanned = create_test_song("song!", "/music/b/song.mp3");
+    rescanned.song.hash = Some("hash_2".to_string());
+    assert!(db.insert_songs(vec![rescanned.clone()]).unwrap().is_empty());
+
+    // A different file at the same path is added
+    rescanned.song.hash = Some("hash_5".to_string());
+    assert_eq!(db.insert_songs(vec![rescanned]).unwrap().len(), 1);
+
+    cleanup(&db_path);
+}
+
+// Test evaluating smart playlist rules
+#[test]
+fn test_smart_playlists() {
+    let db_path = get_test_db_path();
+    let db = Database::new(db_path.clone());
+
+    let smart_song = |title: &str, genre: &str, year: &str, date_added: i64| {
+        let mut song = create_test_song(title, &format!("/path/to/smart_{}.mp3", title));
+        song.song.year = Some(year.to_string());
+        song.song.date_added = Some(date_added);
+        song.genre = Some(vec![QueryableGenre {
+            genre_name: Some(genre.to_string()),
+            ..Default::default()
+        }]);
+        song
+    };
+
+    let songs = db
+        .insert_songs(vec![
+            smart_song("Blue", "Jazz", "1959", 1),
+            smart_song("Green", "Jazz", "1965", 3),
+            smart_song("Red", "Jazz", "1975", 4),
+            smart_song("Gold", "Rock", "1960", 5),
+            smart_song("Grey", "Jazz", "1961", 2),
+            smart_song("Silent", "Jazz", "1950", 6),
+            smart_song("50% Off_", "Folk", "999", 7),
+        ])
+        .unwrap();
+
+    for (song, plays) in songs.iter().zip([5, 4, 10, 6, 1, 0, 2]) {
+        for _ in 0..plays {
+            db.increment_play_count(song.song._id.clone().unwrap())
+                .unwrap();
+        }
+    }
+
+    let rule = |field, operator, value: &str| SmartPlaylistRule {
+        field,
+        operator,
+        value: value.to_string(),
+    };
+
+    let create_smart_playlist = |rules: SmartPlaylistRules| {
+        db.create_playlist(QueryablePlaylist {
+            playlist_name: "Smart".to_string(),
+            smart_rules: Some(rules),
+            ..Default::default()
+        })
+        .unwrap()
+    };
+
+    let get_titles = |playlist_id: &str| {
+        db.get_songs_by_options(GetSongOptions {
+            playlist: Some(QueryablePlaylist {
+                playlist_id: Some(playlist_id.to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        })
+        .unwrap()
+        .into_iter()
+        .map(|s| s.song.title.unwrap())
+        .collect::<Vec<_>>()
+    };
+
+    let mut rules = SmartPlaylistRules {
+        match_type: SmartPlaylistMatch::All,
+        rules: vec![
+            rule(
+                SmartPlaylistField::Genre,
+                SmartPlaylistOperator::Equals,
+                "Jazz",
+            ),
+            rule(
+                SmartPlaylistField::Year,
+                SmartPlaylistOperator::LessThan,
+                "1970",
+            ),
+            rule(
+                SmartPlaylistField::PlayCount,
+                SmartPlaylistOperator::GreaterThan,
+                "3",
+            ),
+        ],
+        sort_by: Some(SmartPlaylistField::DateAdded),
+        sort_desc: true,
+        limit: None,
+    };
+    let jazz = create_smart_playlist(rules.clone());
+    assert_eq!(get_titles(&jazz), vec!["Green", "Blue"]);
+
+    rules.limit = Some(1);
+    let jazz_limited = create_smart_playlist(rules);
+    assert_eq!(get_titles(&jazz_limited), vec!["Green"]);
+
+    let any = create_smart_playlist(SmartPlaylistRules {
+        match_type: SmartPlaylistMatch::Any,
+        rules: vec![
+            rule(
+                SmartPlaylistField::Title,
+                SmartPlaylistOperator::Equals,
+                "Blue",
+            ),
+            rule(
+                SmartPlaylistField::Genre,
+                SmartPlaylistOperator::Equals,
+                "Rock",
+            ),
+        ],
+        sort_by: Some(SmartPlaylistField::Title),
+        ..Default::default()
+    });
+    assert_eq!(get_titles(&any), vec!["Blue", "Gold"]);
+
+    let most_played = create_smart_playlist(SmartPlaylistRules {
+        sort_by: Some(SmartPlaylistField::PlayCount),
+        sort_desc: true,
+        limit: Some(2),
+        ..Default::default()
+    });
+    assert_eq!(get_titles(&most_played), vec!["Red", "Gold"]);
+
+    let never_played = create_smart_playlist(SmartPlaylistRules {
+        rules: vec![rule(
+            SmartPlaylistField::PlayCount,
+            SmartPlaylistOperator::Equals,
+            "0",
+        )],
+        ..Default::default()
+    });
+    assert_eq!(get_titles(&never_played), vec!["Silent"]);
+
+    // Years are compared as numbers, not as text
+    let old = create_smart_playlist(SmartPlaylistRules {
+        rules: vec![rule(
+            SmartPlaylistField::Year,
+            SmartPlaylistOperator::LessThan,
+            "1000",
+        )],
+        ..Default::default()
+    });
+    assert_eq!(get_titles(&old), vec!["50% Off_"]);
+
+    // LIKE wildcards in values are matched literally
+    let wildcards = create_smart_playlist(SmartPlaylistRules {
+        match_type: SmartPlaylistMatch::All,
+        rules: vec![
+            rule(
+                SmartPlaylistField::Title,
+                SmartPlaylistOperator::Contains,
+                "%",
+            ),
+            rule(
+                SmartPlaylistField::Title,
+                SmartPlaylistOperator::Contains,
+                "_",
+            ),
+        ],
+        ..Default::default()
+    });
+    assert_eq!(get_titles(&wildcards), vec!["50% Off_"]);
+
+    // Song counts are kept up to date for the playlist list
+    let playlists = db
+        .get_entity_by_options(GetEntityOptions {
+            playlist: Some(QueryablePlaylist {
+                playlist_id: Some(jazz.clone()),
+                ..Default::default()
+            }),
+            inclusive: Some(true),
+            ..Default::default()
+        })
+        .unwrap();
+    assert_eq!(playlists[0]["playlist_song_count"].as_f64(), Some(2f64));
+
+    let (_, exported) = db.get_playlist_with_songs(jazz.clone()).unwrap();
+    let exported_paths: Vec<String> = exported.into_iter().filter_map(|s| s.song.path).collect();
+    assert!(exported_paths.contains(&"/path/to/smart_Green.mp3".to_string()));
+    assert!(!exported_paths.contains(&"/path/to/smart_Red.mp3".to_string()));
+
+    // Songs can't be added to smart playlists by hand
+    assert!(db
+        .add_to_playlist(jazz.clone(), vec![songs[2].clone()], None)
+        .is_err());
+
+    // Invalid rules are rejected instead of silently matching nothing
+    assert!(db
+        .create_playlist(QueryablePlaylist {
+            playlist_name: "Invalid".to_string(),
+            smart_rules: Some(SmartPlaylistRules {
+                rules: vec![rule(
+                    SmartPlaylistField::Duration,
+                    SmartPlaylistOperator::Contains,
+                    "10",
+                )],
+                ..Default::default()
+            }),
+            ..Default::default()
+        })
+        .is_err());
+
+    cleanup(&db_path);
+}
+
+// Test rescanned songs replacing their old details, and the tag journal
+#[test]
+fn test_tag_edits() {
+    let db_path = get_test_db_path();
+    let db = Database::new(db_path.clone());
+
+    let inserted = db
+        .insert_songs(vec![create_test_song("Song", "/music/song.mp3")])
+        .unwrap();
+    let id = inserted[0].song._id.clone().unwrap();
+
+    let mut rescanned = create_test_song("Edited", "/music/song.mp3");
+    rescanned.song._id = Some(id.clone());
+    rescanned.album.as_mut().unwrap().album_name = Some("Other Album".to_string());
+    rescanned.artists.as_mut().unwrap()[0].artist_name = Some("Other Artist".to_string());
+    db.refresh_song(rescanned).unwrap();
+
+    let songs = db
+        .get_songs_by_options(GetSongOptions {
+            song: Some(SearchableSong {
+                _id: Some(id.clone()),
+                ..Default::default()
+            }),
+            inclusive: Some(true),
+            ..Default::default()
+        })
+        .unwrap();
+    assert_eq!(songs.len(), 1);
+    assert_eq!(songs[0].song.title.as_deref(), Some("Edited"));
+    assert_eq!(
+        songs[0].album.as_ref().unwrap().album_name.as_deref(),
+        Some("Other Album")
+    );
+    let artists = songs[0].artists.clone().unwrap();
+    assert_eq!(artists.len(), 1);
+    assert_eq!(artists[0].artist_name.as_deref(), Some("Other Artist"));
+
+    let entry = |batch_id: &str, written_at: i64| TagJournalEntry {
+        id: None,
+        batch_id: batch_id.to_string(),
+        song_id: id.clone(),
+        path: "/music/song.mp3".to_string(),
+        changes: TagChanges(vec![TagChange {
+            field: TagField::Title,
+            old: Some("Song".to_string()),
+            new: Some("Edited".to_string()),
+        }]),
+        written_at,
+    };
+    db.add_tag_journal_entries(vec![entry("first", 1), entry("first", 1)])
+        .unwrap();
+    db.add_tag_journal_entries(vec![entry("second", 2)])
+        .unwrap();
+
+    // Newest edits come first
+    let journal = db.get_tag_journal(Some(2)).unwrap();
+    assert_eq!(journal.len(), 2);
+    assert_eq!(journal[0].batch_id, "second");
+    assert_eq!(journal[0].changes, entry("second", 2).changes);
+
+    assert_eq!(db.get_tag_batch("first".to_string()).unwrap().len(), 2);
+    db.remove_tag_batch("first".to_string()).unwrap();
+    assert!(db.get_tag_batch("first".to_string()).unwrap().is_empty());
+    assert_eq!(db.get_tag_journal(None).unwrap().len(), 1);
+
+    // Single entries of a batch can be removed while the rest stays
+    db.add_tag_journal_entries(vec![entry("third", 3), entry("third", 3)])
+        .unwrap();
+    let batch = db.get_tag_batch("third".to_string()).unwrap();
+    db.remove_tag_journal_entries(vec![batch[0].id.unwrap()])
+        .unwrap();
+    let remaining = db.get_tag_batch("third".to_string()).unwrap();
+    assert_eq!(remaining.len(), 1);
+    assert_eq!(remaining[0].id, batch[1].id);
+
+    cleanup(&db_path);
+}
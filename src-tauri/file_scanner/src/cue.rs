@@ -0,0 +1,402 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use types::{
+    entities::{QueryableAlbum, QueryableArtist, QueryableGenre},
+    errors::Result,
+    songs::{Song, CUE_TRACK_SEPARATOR},
+};
+use uuid::Uuid;
+
+use crate::utils::{decode_text, file_mtime, is_song_file, parse_replaygain, scan_song};
+
+/// CD frames per second, the last part of timestamps in CUE sheets
+const FRAMES_PER_SECOND: f64 = 75f64;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CueTrack {
+    pub number: u32,
+    pub title: Option<String>,
+    pub performer: Option<String>,
+    pub songwriter: Option<String>,
+    pub isrc: Option<String>,
+    /// Start of the track (INDEX 01) in seconds
+    pub start: f64,
+    pub track_gain: Option<f64>,
+    pub track_peak: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CueFile {
+    /// File name as written in the sheet, usually relative to the sheet
+    pub name: String,
+    pub tracks: Vec<CueTrack>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CueSheet {
+    pub title: Option<String>,
+    pub performer: Option<String>,
+    pub genre: Option<String>,
+    pub date: Option<String>,
+    pub disc_no: Option<f64>,
+    pub disc_total: Option<f64>,
+    pub album_gain: Option<f64>,
+    pub album_peak: Option<f64>,
+    pub files: Vec<CueFile>,
+}
+
+/// Parses a timestamp written as minutes, seconds and frames, like "03:25:45"
+#[tracing::instrument(level = "debug", skip(value))]
+pub fn parse_cue_timestamp(value: &str) -> Option<f64> {
+    let mut parts = value.trim().split(':').map(|p| p.parse::<u32>().ok());
+    let (minutes, seconds, frames) = (parts.next()??, parts.next()??, parts.next()??);
+    if parts.next().is_some() || seconds >= 60 || frames >= FRAMES_PER_SECOND as u32 {
+        return None;
+    }
+    Some(minutes as f64 * 60f64 + seconds as f64 + frames as f64 / FRAMES_PER_SECOND)
+}
+
+/// Splits the first argument of a command from the rest. Arguments may be quoted
+#[tracing::instrument(level = "debug", skip(args))]
+fn split_arg(args: &str) -> (String, &str) {
+    let args = args.trim();
+    if let Some(quoted) = args.strip_prefix('"') {
+        if let Some((arg, rest)) = quoted.split_once('"') {
+            return (arg.to_string(), rest.trim());
+        }
+        return (quoted.to_string(), "");
+    }
+    match args.split_once(char::is_whitespace) {
+        Some((arg, rest)) => (arg.to_string(), rest.trim()),
+        None => (args.to_string(), ""),
+    }
+}
+
+/// Value of a command with a single, optionally quoted, argument
+#[tracing::instrument(level = "debug", skip(args))]
+fn text_arg(args: &str) -> Option<String> {
+    let args = args.trim();
+    let value = if args.starts_with('"') {
+        split_arg(args).0
+    } else {
+        args.to_string()
+    };
+    Some(value).filter(|v| !v.is_empty())
+}
+
+/// Adds a finished track to the file it belongs to. Tracks without an INDEX 01 can't be played
+#[tracing::instrument(level = "debug", skip(sheet, track, has_start))]
+fn finish_track(sheet: &mut CueSheet, track: Option<CueTrack>, has_start: bool) {
+    if let (Some(track), true) = (track, has_start) {
+        if let Some(file) = sheet.files.last_mut() {
+            file.tracks.push(track);
+        }
+    }
+}
+
+#[tracing::instrument(level = "debug", skip(content))]
+pub fn parse_cue(content: &str) -> CueSheet {
+    let mut sheet = CueSheet::default();
+    // Only set for audio tracks. Data tracks are skipped along with their details
+    let mut track: Option<CueTrack> = None;
+    let mut in_track = false;
+    let mut has_start = false;
+
+    for line in content.lines() {
+        let line = line.trim();
+        let (command, args) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+
+        match command.to_uppercase().as_str() {
+            "FILE" => {
+                finish_track(&mut sheet, track.take(), has_start);
+                in_track = false;
+                // Unquoted names may contain spaces, the file type is always the last word
+                let name = if args.trim_start().starts_with('"') {
+                    split_arg(args).0
+                } else {
+                    args.trim()
+                        .rsplit_once(char::is_whitespace)
+                        .map(|(name, _)| name.trim().to_string())
+                        .unwrap_or_else(|| args.trim().to_string())
+                };
+                sheet.files.push(CueFile {
+                    name,
+                    tracks: vec![],
+                });
+            }
+            "TRACK" => {
+                finish_track(&mut sheet, track.take(), has_start);
+                in_track = true;
+                has_start = false;
+                let (number, kind) = split_arg(args);
+                if kind.eq_ignore_ascii_case("AUDIO") {
+                    track = number.parse().ok().map(|number| CueTrack {
+                        number,
+                        ..Default::default()
+                    });
+                }
+            }
+            "INDEX" => {
+                let (number, timestamp) = split_arg(args);
+                if let (Some(track), Ok(1)) = (track.as_mut(), number.parse::<u32>()) {
+                    if let Some(start) = parse_cue_timestamp(timestamp) {
+                        track.start = start;
+                        has_start = true;
+                    }
+                }
+            }
+            "TITLE" => match (in_track, track.as_mut()) {
+                (true, Some(track)) => track.title = text_arg(args),
+                (true, None) => {}
+                (false, _) => sheet.title = text_arg(args),
+            },
+            "PERFORMER" => match (in_track, track.as_mut()) {
+                (true, Some(track)) => track.performer = text_arg(args),
+                (true, None) => {}
+                (false, _) => sheet.performer = text_arg(args),
+            },
+            "SONGWRITER" => {
+                if let Some(track) = track.as_mut() {
+                    track.songwriter = text_arg(args);
+                }
+            }
+            "ISRC" => {
+                if let Some(track) = track.as_mut() {
+                    track.isrc = text_arg(args);
+                }
+            }
+            "REM" => {
+                let (key, value) = split_arg(args);
+                let value = text_arg(value);
+                match (key.to_uppercase().as_str(), track.as_mut()) {
+                    ("GENRE", _) => sheet.genre = value,
+                    ("DATE", _) => sheet.date = value,
+                    ("DISCNUMBER", _) => sheet.disc_no = value.and_then(|v| v.parse().ok()),
+                    ("TOTALDISCS", _) => sheet.disc_total = value.and_then(|v| v.parse().ok()),
+                    ("REPLAYGAIN_ALBUM_GAIN", _) => {
+                        sheet.album_gain = value.as_deref().and_then(parse_replaygain)
+                    }
+                    ("REPLAYGAIN_ALBUM_PEAK", _) => {
+                        sheet.album_peak = value.as_deref().and_then(parse_replaygain)
+                    }
+                    ("REPLAYGAIN_TRACK_GAIN", Some(track)) => {
+                        track.track_gain = value.as_deref().and_then(parse_replaygain)
+                    }
+                    ("REPLAYGAIN_TRACK_PEAK", Some(track)) => {
+                        track.track_peak = value.as_deref().and_then(parse_replaygain)
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+    finish_track(&mut sheet, track.take(), has_start);
+
+    sheet.files.retain(|f| !f.tracks.is_empty());
+    sheet
+}
+
+#[tracing::instrument(level = "debug", skip(path))]
+pub fn read_cue(path: &Path) -> Result<CueSheet> {
+    Ok(parse_cue(&decode_text(&fs::read(path)?)))
+}
+
+/// Finds the audio file a sheet refers to. Sheets often still name the file they were
+/// ripped to, like a WAV which was converted to FLAC later, so a song file with the
+/// same name and any other extension is used as well
+#[tracing::instrument(level = "debug", skip(cue_path, name))]
+pub fn resolve_cue_file(cue_path: &Path, name: &str) -> Option<PathBuf> {
+    let dir = cue_path.parent()?;
+    let path = dir.join(name);
+    if path.is_file() {
+        return Some(path);
+    }
+
+    let stem = Path::new(name).file_stem()?;
+    fs::read_dir(dir)
+        .ok()?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .find(|p| p.file_stem() == Some(stem) && is_song_file(p))
+}
+
+/// Audio files of a sheet along with the library paths of their tracks
+#[tracing::instrument(level = "debug", skip(cue_path, sheet))]
+pub fn cue_track_paths(cue_path: &Path, sheet: &CueSheet) -> Vec<(PathBuf, Vec<String>)> {
+    sheet
+        .files
+        .iter()
+        .filter_map(|file| {
+            let audio_path = resolve_cue_file(cue_path, &file.name)?;
+            let canonical = dunce::canonicalize(&audio_path).ok()?;
+            let paths = file
+                .tracks
+                .iter()
+                .map(|t| cue_track_path(&canonical, t.number))
+                .collect();
+            Some((audio_path, paths))
+        })
+        .collect()
+}
+
+/// Audio files which the CUE sheets in `dir` split into tracks
+#[tracing::instrument(level = "debug", skip(dir))]
+pub fn cue_split_files(dir: &Path) -> HashSet<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return HashSet::new();
+    };
+    entries
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|e| e.eq_ignore_ascii_case("cue")))
+        .filter_map(|cue_path| Some((read_cue(&cue_path).ok()?, cue_path)))
+        .flat_map(|(sheet, cue_path)| {
+            cue_track_paths(&cue_path, &sheet)
+                .into_iter()
+                .map(|(audio_path, _)| audio_path)
+        })
+        .collect()
+}
+
+#[tracing::instrument(level = "debug", skip(audio_path, number))]
+fn cue_track_path(audio_path: &Path, number: u32) -> String {
+    format!(
+        "{}{}{}",
+        audio_path.to_string_lossy(),
+        CUE_TRACK_SEPARATOR,
+        number
+    )
+}
+
+/// Tracks change whenever either the sheet or the audio file is modified
+#[tracing::instrument(level = "debug", skip(cue_path, audio_path))]
+pub fn cue_mtime(cue_path: &Path, audio_path: &Path) -> Option<f64> {
+    let cue = file_mtime(cue_path)?;
+    let audio = file_mtime(audio_path)?;
+    Some(cue.max(audio))
+}
+
+/// Splits the audio files of a sheet into songs, one for each track. Details missing
+/// from the sheet are taken from the tags of the audio file
+#[tracing::instrument(level = "debug", skip(cue_path, sheet, thumbnail_dir, artist_split))]
+pub fn scan_cue(
+    cue_path: &Path,
+    sheet: &CueSheet,
+    thumbnail_dir: &Path,
+    artist_split: &str,
+) -> Result<Vec<Song>> {
+    let mut ret = vec![];
+    for file in &sheet.files {
+        let Some(audio_path) = resolve_cue_file(cue_path, &file.name) else {
+            tracing::warn!("{} not found for {}", file.name, cue_path.display());
+            continue;
+        };
+
+        let base = scan_song(&audio_path, thumbnail_dir, artist_split)?;
+        let Some(file_path) = base.song.path.clone() else {
+            continue;
+        };
+        let file_duration = base.song.duration.unwrap_or_default();
+        let mtime = cue_mtime(cue_path, &audio_path);
+
+        for (i, track) in file.tracks.iter().enumerate() {
+            let end = file.tracks.get(i + 1).map(|t| t.start);
+            let mut song = base.clone();
+            song.song._id = Some(Uuid::new_v4().to_string());
+            song.song.path = Some(cue_track_path(Path::new(&file_path), track.number));
+            song.song.title = track
+                .title
+                .clone()
+                .or_else(|| Some(format!("Track {}", track.number)));
+            song.song.track_no = Some(track.number as f64);
+            song.song.start_offset = Some(track.start);
+            song.song.end_offset = end;
+            song.song.duration = Some((end.unwrap_or(file_duration) - track.start).max(0f64));
+            song.song.mtime = mtime;
+            // The file is shared by all tracks, so it can't identify any of them
+            song.song.hash = None;
+            song.song.inode = None;
+            song.song.deviceno = None;
+            song.song.lyrics = None;
+            song.song.recording_mbid = None;
+            song.song.isrc = track.isrc.clone();
+            song.song.composer = track.songwriter.clone().or(song.song.composer);
+            song.song.track_gain = track.track_gain.or(sheet.album_gain);
+            song.song.track_peak = track.track_peak.or(sheet.album_peak);
+            song.song.album_gain = sheet.album_gain.or(song.song.album_gain);
+            song.song.album_peak = sheet.album_peak.or(song.song.album_peak);
+            if sheet.date.is_some() {
+                song.song.year.clone_from(&sheet.date);
+            }
+            if sheet.disc_no.is_some() {
+                song.song.disc_no = sheet.disc_no;
+                song.song.disc_total = sheet.disc_total.or(song.song.disc_total);
+            }
+
+            let performer = track.performer.as_ref().or(sheet.performer.as_ref());
+            if let Some(performer) = performer {
+                song.artists = Some(
+                    performer
+                        .split(artist_split)
+                        .map(|a| QueryableArtist {
+                            artist_id: Some(Uuid::new_v4().to_string()),
+                            artist_name: Some(a.trim().to_string()),
+                            ..Default::default()
+                        })
+                        .collect(),
+                );
+            }
+
+            if let Some(album) = song.album.as_mut() {
+                album.album_name = sheet.title.clone().or(album.album_name.take());
+                album.album_artist = sheet.performer.clone().or(album.album_artist.take());
+                album.year.clone_from(&song.song.year);
+                album.disc_total = song.song.disc_total;
+            } else if let Some(title) = sheet.title.clone() {
+                song.album = Some(QueryableAlbum {
+                    album_id: Some(Uuid::new_v4().to_string()),
+                    album_name: Some(title),
+                    album_artist: sheet.performer.clone(),
+                    album_coverpath_high: song.song.song_cover_path_high.clone(),
+                    album_coverpath_low: song.song.song_cover_path_low.clone(),
+                    year: song.song.year.clone(),
+                    disc_total: song.song.disc_total,
+                    ..Default::default()
+                });
+            }
+
+            if let Some(genre) = sheet.genre.clone() {
+                song.genre = Some(vec![QueryableGenre {
+                    genre_name: Some(genre),
+                    ..Default::default()
+                }]);
+            }
+
+            ret.push(song);
+        }
+    }
+
+    if ret.is_empty() {
+        return Err(format!("No playable tracks in {}", cue_path.display()).into());
+    }
+    Ok(ret)
+}
@@ -14,16 +14,34 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+mod cue;
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+pub use cue::cue_split_files;
+mod playlist_formats;
+pub use playlist_formats::{playlist_format, read_playlist, M3u, PlaylistFormat};
 #[cfg(not(any(target_os = "android", target_os = "ios")))]
 mod playlist_scanner;
 #[cfg(not(any(target_os = "android", target_os = "ios")))]
+mod progress;
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
 mod scanner;
 #[cfg(not(any(target_os = "android", target_os = "ios")))]
-pub use scanner::{ScanState, ScannerHolder};
+pub use scanner::ScannerHolder;
 #[cfg(not(any(target_os = "android", target_os = "ios")))]
 mod song_scanner;
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+mod tag_writer;
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+pub use tag_writer::{cover_backups, diff_tags, write_tags, EMBEDDED_COVER};
 mod types;
 mod utils;
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+mod watcher;
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+pub use utils::{file_identity, get_files_recursively, is_playlist_or_cue_file, scan_song};
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+pub use watcher::{MusicWatcher, WatchEvent};
 
 #[cfg(test)]
 mod tests;
@@ -31,4 +49,5 @@ mod tests;
 #[cfg(target_os = "android")]
 mod scanner_android;
 #[cfg(target_os = "android")]
-pub use scanner_android::{ScanState, ScannerHolder};
+pub use scanner_android::ScannerHolder;
+pub use ::types::scanner::{ScanProgress, ScanState};
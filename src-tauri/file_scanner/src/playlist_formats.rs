@@ -0,0 +1,612 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use roxmltree::{Document, Node};
+use types::{
+    entities::{ImportedPlaylist, QueryableArtist, QueryablePlaylist},
+    errors::{error_helpers, MoosyncError, Result},
+    songs::{QueryableSong, Song, SongType},
+};
+use uuid::Uuid;
+
+use crate::utils::decode_text;
+
+/// An entry of a playlist file, before it is resolved into a song
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PlaylistEntry {
+    /// Path, file URI or URL of the song
+    pub location: String,
+    pub title: Option<String>,
+    /// Artists separated by ';'
+    pub artists: Option<String>,
+    /// Duration in seconds
+    pub duration: Option<f64>,
+    pub song_type: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PlaylistFile {
+    pub title: Option<String>,
+    pub entries: Vec<PlaylistEntry>,
+}
+
+/// Reads and writes one playlist file format
+pub trait PlaylistFormat: Sync {
+    /// Lowercase extensions of files in this format
+    fn extensions(&self) -> &'static [&'static str];
+    fn read(&self, content: &str) -> Result<PlaylistFile>;
+    fn write(&self, name: &str, songs: &[Song]) -> Result<String>;
+}
+
+/// Extended M3U, with Moosync's own #MOOSINF and #PLAYLIST lines
+pub struct M3u;
+pub struct Pls;
+pub struct Xspf;
+/// Windows Media Player playlists
+pub struct Wpl;
+
+static FORMATS: [&dyn PlaylistFormat; 4] = [&M3u, &Pls, &Xspf, &Wpl];
+
+/// Format of a playlist file, going by its extension
+#[tracing::instrument(level = "debug", skip(path))]
+pub fn playlist_format(path: &Path) -> Option<&'static dyn PlaylistFormat> {
+    let extension = path.extension()?.to_str()?.to_lowercase();
+    FORMATS
+        .iter()
+        .find(|f| f.extensions().contains(&extension.as_str()))
+        .copied()
+}
+
+#[tracing::instrument(level = "debug", skip(song))]
+fn song_location(song: &QueryableSong) -> Option<&str> {
+    song.path
+        .as_deref()
+        .or(song.url.as_deref())
+        .or(song.playback_url.as_deref())
+}
+
+#[tracing::instrument(level = "debug", skip(song))]
+fn song_artists(song: &Song) -> Option<String> {
+    let artists = song
+        .artists
+        .iter()
+        .flatten()
+        .filter_map(|a| a.artist_name.clone())
+        .collect::<Vec<_>>()
+        .join(";");
+    Some(artists).filter(|a| !a.is_empty())
+}
+
+#[tracing::instrument(level = "debug", skip(song))]
+fn song_album(song: &Song) -> Option<String> {
+    song.album.as_ref().and_then(|a| a.album_name.clone())
+}
+
+/// Windows drive letters in file URIs come after an extra slash, as in "file:///C:/Music"
+#[tracing::instrument(level = "debug", skip(path))]
+fn strip_drive_slash(path: &str) -> &str {
+    let bytes = path.as_bytes();
+    if bytes.len() > 2 && bytes[0] == b'/' && bytes[1].is_ascii_alphabetic() && bytes[2] == b':' {
+        return &path[1..];
+    }
+    path
+}
+
+/// Encodes a local path as a file URI. Other locations are kept as they are
+#[tracing::instrument(level = "debug", skip(location))]
+fn to_file_uri(location: &str) -> String {
+    if location.contains("://") {
+        return location.to_string();
+    }
+
+    let path = location.replace('\\', "/");
+    let mut ret = if path.starts_with('/') {
+        "file://".to_string()
+    } else {
+        "file:///".to_string()
+    };
+    for b in path.bytes() {
+        if b.is_ascii_alphanumeric() || b"-._~/:".contains(&b) {
+            ret.push(b as char);
+        } else {
+            let _ = write!(ret, "%{:02X}", b);
+        }
+    }
+    ret
+}
+
+/// Decodes the percent encoded path of a file URI
+#[tracing::instrument(level = "debug", skip(location))]
+fn from_file_uri(location: &str) -> String {
+    let Some(path) = location.strip_prefix("file://") else {
+        return location.to_string();
+    };
+
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            if let Some(b) = path
+                .get(i + 1..i + 3)
+                .and_then(|h| u8::from_str_radix(h, 16).ok())
+            {
+                decoded.push(b);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    format!("file://{}", String::from_utf8_lossy(&decoded))
+}
+
+#[tracing::instrument(level = "debug", skip(value))]
+fn escape_xml(value: &str) -> String {
+    let mut ret = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => ret.push_str("&amp;"),
+            '<' => ret.push_str("&lt;"),
+            '>' => ret.push_str("&gt;"),
+            '"' => ret.push_str("&quot;"),
+            '\'' => ret.push_str("&apos;"),
+            _ => ret.push(c),
+        }
+    }
+    ret
+}
+
+#[tracing::instrument(level = "debug", skip(node, name))]
+fn child_text(node: Node<'_, '_>, name: &str) -> Option<String> {
+    node.children()
+        .find(|n| n.has_tag_name(name))
+        .and_then(|n| n.text())
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+}
+
+impl PlaylistFormat for M3u {
+    fn extensions(&self) -> &'static [&'static str] {
+        &["m3u", "m3u8"]
+    }
+
+    #[tracing::instrument(level = "debug", skip(self, content))]
+    fn read(&self, content: &str) -> Result<PlaylistFile> {
+        let mut playlist = PlaylistFile::default();
+        let mut entry = PlaylistEntry::default();
+
+        for line in content.lines() {
+            let line = line.trim();
+            if let Some(metadata) = line.strip_prefix("#EXTINF:") {
+                let (duration, non_duration) = metadata.split_once(',').unwrap_or((metadata, ""));
+                entry.duration = Some(
+                    duration
+                        .trim()
+                        .parse::<f64>()
+                        .map_err(error_helpers::to_parse_error)?,
+                );
+
+                // Titles are written as "Artists - Title"
+                let (artists, title) = match non_duration
+                    .split_once(" - ")
+                    .or_else(|| non_duration.split_once('-'))
+                {
+                    Some((artists, title)) => (artists.trim(), title.trim()),
+                    None => ("", non_duration.trim()),
+                };
+                entry.artists = Some(artists.to_string()).filter(|a| !a.is_empty());
+                entry.title = Some(title.to_string());
+                continue;
+            }
+
+            if let Some(song_type) = line.strip_prefix("#MOOSINF:") {
+                entry.song_type = Some(song_type.to_string());
+                continue;
+            }
+
+            if let Some(title) = line.strip_prefix("#PLAYLIST:") {
+                playlist.title = Some(title.to_string());
+                continue;
+            }
+
+            if line.starts_with('#') || line.is_empty() {
+                continue;
+            }
+
+            entry.location = line.to_string();
+            playlist.entries.push(std::mem::take(&mut entry));
+        }
+
+        Ok(playlist)
+    }
+
+    #[tracing::instrument(level = "debug", skip(self, name, songs))]
+    fn write(&self, name: &str, songs: &[Song]) -> Result<String> {
+        let mut ret = format!("#EXTM3U\n#PLAYLIST:{}\n", name);
+
+        for s in songs {
+            let Some(location) = song_location(&s.song) else {
+                continue;
+            };
+
+            let title = s.song.title.clone().unwrap_or_default();
+            let title = match song_artists(s) {
+                Some(artists) => format!("{} - {}", artists, title),
+                None => title,
+            };
+            writeln!(ret, "#EXTINF:{},{}", s.song.duration.unwrap_or(0f64), title)?;
+
+            if let Some(album) = song_album(s) {
+                writeln!(ret, "#EXTALB:{}", album)?;
+            }
+            let genres = s
+                .genre
+                .iter()
+                .flatten()
+                .filter_map(|g| g.genre_name.clone())
+                .collect::<Vec<String>>();
+            if !genres.is_empty() {
+                writeln!(ret, "#EXTGENRE:{}", genres.join(","))?;
+            }
+            if let Some(cover) = &s.song.song_cover_path_high {
+                writeln!(ret, "#EXTIMG:{}", cover)?;
+            }
+            writeln!(ret, "#MOOSINF:{}", s.song.type_)?;
+
+            if s.song.path.is_some() {
+                writeln!(ret, "file://{}", location)?;
+            } else {
+                writeln!(ret, "{}", location)?;
+            }
+        }
+
+        Ok(ret)
+    }
+}
+
+impl PlaylistFormat for Pls {
+    fn extensions(&self) -> &'static [&'static str] {
+        &["pls"]
+    }
+
+    #[tracing::instrument(level = "debug", skip(self, content))]
+    fn read(&self, content: &str) -> Result<PlaylistFile> {
+        // Entries are numbered keys like File1, Title1 and Length1, in no particular order
+        let mut entries: BTreeMap<usize, PlaylistEntry> = BTreeMap::new();
+        for line in content.lines() {
+            let Some((key, value)) = line.trim().split_once('=') else {
+                continue;
+            };
+            let key = key.trim().to_lowercase();
+            let Some(split) = key.find(|c: char| c.is_ascii_digit()) else {
+                continue;
+            };
+            let (field, index) = key.split_at(split);
+            let Ok(index) = index.parse::<usize>() else {
+                continue;
+            };
+
+            let value = value.trim();
+            let entry = entries.entry(index).or_default();
+            match field {
+                "file" => entry.location = value.to_string(),
+                "title" => entry.title = Some(value.to_string()).filter(|t| !t.is_empty()),
+                // Streams have a length of -1
+                "length" => entry.duration = value.parse::<f64>().ok().filter(|d| *d >= 0f64),
+                _ => {}
+            }
+        }
+
+        Ok(PlaylistFile {
+            title: None,
+            entries: entries
+                .into_values()
+                .filter(|e| !e.location.is_empty())
+                .collect(),
+        })
+    }
+
+    #[tracing::instrument(level = "debug", skip(self, _name, songs))]
+    fn write(&self, _name: &str, songs: &[Song]) -> Result<String> {
+        let mut ret = "[playlist]\n".to_string();
+
+        let mut count = 0;
+        for s in songs {
+            let Some(location) = song_location(&s.song) else {
+                continue;
+            };
+            count += 1;
+
+            writeln!(ret, "File{}={}", count, location)?;
+            if let Some(title) = &s.song.title {
+                writeln!(ret, "Title{}={}", count, title)?;
+            }
+            writeln!(
+                ret,
+                "Length{}={}",
+                count,
+                s.song.duration.map_or(-1, |d| d.round() as i64)
+            )?;
+        }
+
+        writeln!(ret, "NumberOfEntries={}", count)?;
+        writeln!(ret, "Version=2")?;
+        Ok(ret)
+    }
+}
+
+impl PlaylistFormat for Xspf {
+    fn extensions(&self) -> &'static [&'static str] {
+        &["xspf"]
+    }
+
+    #[tracing::instrument(level = "debug", skip(self, content))]
+    fn read(&self, content: &str) -> Result<PlaylistFile> {
+        let document = Document::parse(content).map_err(error_helpers::to_parse_error)?;
+        let root = document.root_element();
+
+        let entries = root
+            .children()
+            .filter(|n| n.has_tag_name("trackList"))
+            .flat_map(|n| n.children())
+            .filter(|n| n.has_tag_name("track"))
+            .filter_map(|track| {
+                Some(PlaylistEntry {
+                    location: from_file_uri(&child_text(track, "location")?),
+                    title: child_text(track, "title"),
+                    artists: child_text(track, "creator"),
+                    // Durations are in milliseconds
+                    duration: child_text(track, "duration")
+                        .and_then(|d| d.parse::<f64>().ok())
+                        .map(|d| d / 1000f64),
+                    song_type: None,
+                })
+            })
+            .collect();
+
+        Ok(PlaylistFile {
+            title: child_text(root, "title"),
+            entries,
+        })
+    }
+
+    #[tracing::instrument(level = "debug", skip(self, name, songs))]
+    fn write(&self, name: &str, songs: &[Song]) -> Result<String> {
+        let mut ret = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n".to_string();
+        writeln!(
+            ret,
+            "<playlist version=\"1\" xmlns=\"http://xspf.org/ns/0/\">"
+        )?;
+        writeln!(ret, "  <title>{}</title>", escape_xml(name))?;
+        writeln!(ret, "  <trackList>")?;
+
+        for s in songs {
+            let Some(location) = song_location(&s.song) else {
+                continue;
+            };
+
+            // Remote songs keep their original url
+            let location = if s.song.path.is_some() {
+                to_file_uri(location)
+            } else {
+                location.to_string()
+            };
+
+            writeln!(ret, "    <track>")?;
+            writeln!(ret, "      <location>{}</location>", escape_xml(&location))?;
+            if let Some(title) = &s.song.title {
+                writeln!(ret, "      <title>{}</title>", escape_xml(title))?;
+            }
+            if let Some(artists) = song_artists(s) {
+                writeln!(ret, "      <creator>{}</creator>", escape_xml(&artists))?;
+            }
+            if let Some(cover) = &s.song.song_cover_path_high {
+                writeln!(
+                    ret,
+                    "      <image>{}</image>",
+                    escape_xml(&to_file_uri(cover))
+                )?;
+            }
+            if let Some(album) = song_album(s) {
+                writeln!(ret, "      <album>{}</album>", escape_xml(&album))?;
+            }
+            if let Some(duration) = s.song.duration {
+                writeln!(
+                    ret,
+                    "      <duration>{}</duration>",
+                    (duration * 1000f64).round() as i64
+                )?;
+            }
+            writeln!(ret, "    </track>")?;
+        }
+
+        writeln!(ret, "  </trackList>")?;
+        writeln!(ret, "</playlist>")?;
+        Ok(ret)
+    }
+}
+
+impl PlaylistFormat for Wpl {
+    fn extensions(&self) -> &'static [&'static str] {
+        &["wpl"]
+    }
+
+    #[tracing::instrument(level = "debug", skip(self, content))]
+    fn read(&self, content: &str) -> Result<PlaylistFile> {
+        let document = Document::parse(content).map_err(error_helpers::to_parse_error)?;
+        let root = document.root_element();
+
+        let entries = root
+            .descendants()
+            .filter(|n| n.has_tag_name("media"))
+            .filter_map(|media| {
+                Some(PlaylistEntry {
+                    location: media.attribute("src")?.to_string(),
+                    title: media.attribute("trackTitle").map(|t| t.to_string()),
+                    artists: media.attribute("trackArtist").map(|a| a.to_string()),
+                    ..Default::default()
+                })
+            })
+            .collect();
+
+        Ok(PlaylistFile {
+            title: root
+                .children()
+                .find(|n| n.has_tag_name("head"))
+                .and_then(|head| child_text(head, "title")),
+            entries,
+        })
+    }
+
+    #[tracing::instrument(level = "debug", skip(self, name, songs))]
+    fn write(&self, name: &str, songs: &[Song]) -> Result<String> {
+        let songs = songs
+            .iter()
+            .filter_map(|s| Some((song_location(&s.song)?, s)))
+            .collect::<Vec<_>>();
+
+        let mut ret = "<?wpl version=\"1.0\"?>\n<smil>\n  <head>\n".to_string();
+        writeln!(ret, "    <meta name=\"Generator\" content=\"Moosync\"/>")?;
+        writeln!(
+            ret,
+            "    <meta name=\"ItemCount\" content=\"{}\"/>",
+            songs.len()
+        )?;
+        writeln!(ret, "    <title>{}</title>", escape_xml(name))?;
+        writeln!(ret, "  </head>\n  <body>\n    <seq>")?;
+
+        for (location, s) in songs {
+            write!(ret, "      <media src=\"{}\"", escape_xml(location))?;
+            if let Some(title) = &s.song.title {
+                write!(ret, " trackTitle=\"{}\"", escape_xml(title))?;
+            }
+            if let Some(artists) = song_artists(s) {
+                write!(ret, " trackArtist=\"{}\"", escape_xml(&artists))?;
+            }
+            if let Some(album) = song_album(s) {
+                write!(ret, " albumTitle=\"{}\"", escape_xml(&album))?;
+            }
+            writeln!(ret, "/>")?;
+        }
+
+        writeln!(ret, "    </seq>\n  </body>\n</smil>")?;
+        Ok(ret)
+    }
+}
+
+#[tracing::instrument(level = "debug", skip(artists))]
+fn parse_artists(artists: Option<String>) -> Vec<QueryableArtist> {
+    artists
+        .iter()
+        .flat_map(|a| a.split(';'))
+        .filter(|a| !a.trim().is_empty())
+        .map(|artist| QueryableArtist {
+            artist_id: Some(Uuid::new_v4().to_string()),
+            artist_name: Some(artist.trim().to_string()),
+            ..Default::default()
+        })
+        .collect()
+}
+
+/// Turns an entry into a song. Local files that don't exist are skipped
+#[tracing::instrument(level = "debug", skip(base_dir, entry))]
+fn resolve_entry(base_dir: &Path, entry: PlaylistEntry) -> Result<Option<Song>> {
+    let mut location = entry.location;
+    let mut song_type = entry.song_type;
+    if let Some(path) = location.strip_prefix("file://") {
+        location = strip_drive_slash(path).to_string();
+    } else if location.starts_with("http") {
+        location = location.replace("http://", "").replace("https://", "");
+        song_type = Some("URL".to_string());
+    }
+
+    let mut song = QueryableSong {
+        _id: Some(Uuid::new_v4().to_string()),
+        type_: SongType::from_str(song_type.as_deref().unwrap_or("LOCAL"))?,
+        title: entry.title,
+        duration: entry.duration,
+        ..Default::default()
+    };
+
+    if song.type_ == SongType::LOCAL {
+        // Playlists written on Windows separate directories with backslashes
+        #[cfg(not(windows))]
+        let location = location.replace('\\', "/");
+        let mut path = PathBuf::from(&location);
+        if path.is_relative() {
+            path = base_dir.join(path);
+        }
+        let Ok(path) = dunce::canonicalize(&path) else {
+            return Ok(None);
+        };
+        song.size = fs::metadata(&path).ok().map(|m| m.len() as f64);
+        song.path = Some(path.to_string_lossy().to_string());
+    } else {
+        song.playback_url = Some(location);
+    }
+
+    Ok(Some(Song {
+        song,
+        album: None,
+        artists: Some(parse_artists(entry.artists)),
+        genre: Some(vec![]),
+    }))
+}
+
+/// Reads a playlist file in any supported format. Playlists without a title are named after
+/// their file
+#[tracing::instrument(level = "debug", skip(path))]
+pub fn read_playlist(path: &Path) -> Result<ImportedPlaylist> {
+    let format = playlist_format(path).ok_or_else(|| {
+        MoosyncError::String(format!("Unsupported playlist format: {}", path.display()))
+    })?;
+    let file = format.read(&decode_text(&fs::read(path)?))?;
+
+    let base_dir = path.parent().unwrap_or(Path::new(""));
+    let mut songs = vec![];
+    for entry in file.entries {
+        if let Some(song) = resolve_entry(base_dir, entry)? {
+            songs.push(song);
+        }
+    }
+
+    let playlist_name = file.title.filter(|t| !t.is_empty()).unwrap_or_else(|| {
+        path.file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string()
+    });
+
+    Ok(ImportedPlaylist {
+        playlist: QueryablePlaylist {
+            playlist_id: Some(Uuid::new_v4().to_string()),
+            playlist_name,
+            playlist_path: Some(path.to_string_lossy().to_string()),
+            ..Default::default()
+        },
+        songs,
+    })
+}
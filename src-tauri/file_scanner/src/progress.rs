@@ -0,0 +1,147 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    path::Path,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc::Sender,
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use types::{
+    errors::{MoosyncError, Result},
+    scanner::ScanProgress,
+};
+
+const SEND_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Shared between the scanner and the thread pool to count files and
+/// check whether the scan was cancelled
+pub struct ProgressTracker {
+    progress: Arc<Mutex<ScanProgress>>,
+    tx: Option<Sender<ScanProgress>>,
+    started: Instant,
+    last_sent: Mutex<Option<Instant>>,
+    cancel_generation: Arc<AtomicU64>,
+    generation: u64,
+}
+
+impl Default for ProgressTracker {
+    #[tracing::instrument(level = "debug", skip())]
+    fn default() -> Self {
+        Self::new(Default::default(), None, Default::default(), 0)
+    }
+}
+
+impl ProgressTracker {
+    #[tracing::instrument(level = "debug", skip(progress, tx, cancel_generation, generation))]
+    pub fn new(
+        progress: Arc<Mutex<ScanProgress>>,
+        tx: Option<Sender<ScanProgress>>,
+        cancel_generation: Arc<AtomicU64>,
+        generation: u64,
+    ) -> Self {
+        Self {
+            progress,
+            tx,
+            started: Instant::now(),
+            last_sent: Mutex::new(None),
+            cancel_generation,
+            generation,
+        }
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel_generation.load(Ordering::Acquire) != self.generation
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn check_cancelled(&self) -> Result<()> {
+        if self.is_cancelled() {
+            return Err(MoosyncError::String("Scan cancelled".to_string()));
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(level = "debug", skip(self, count))]
+    pub fn discovered(&self, count: usize) {
+        self.progress.lock().unwrap().discovered += count;
+        self.send(false);
+    }
+
+    #[tracing::instrument(level = "debug", skip(self, path))]
+    pub fn processing(&self, path: &Path) {
+        self.progress.lock().unwrap().current_path = Some(path.to_string_lossy().to_string());
+        self.send(false);
+    }
+
+    #[tracing::instrument(level = "debug", skip(self, success))]
+    pub fn processed(&self, success: bool) {
+        {
+            let mut progress = self.progress.lock().unwrap();
+            progress.processed += 1;
+            if !success {
+                progress.errors += 1;
+            }
+            progress.eta_ms = estimate_eta(
+                self.started.elapsed(),
+                progress.processed,
+                progress.discovered,
+            );
+        }
+        self.send(false);
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn error(&self) {
+        self.progress.lock().unwrap().errors += 1;
+        self.send(false);
+    }
+
+    /// Sends the current progress, at most once every [`SEND_INTERVAL`] unless forced
+    #[tracing::instrument(level = "debug", skip(self, force))]
+    pub fn send(&self, force: bool) {
+        let Some(tx) = self.tx.as_ref() else {
+            return;
+        };
+
+        {
+            let mut last_sent = self.last_sent.lock().unwrap();
+            if !force && last_sent.is_some_and(|l| l.elapsed() < SEND_INTERVAL) {
+                return;
+            }
+            *last_sent = Some(Instant::now());
+        }
+
+        let progress = self.progress.lock().unwrap().clone();
+        let _ = tx.send(progress);
+    }
+}
+
+/// Remaining time assuming the files left take as long as the ones already processed
+#[tracing::instrument(level = "debug", skip(elapsed, processed, discovered))]
+pub(crate) fn estimate_eta(elapsed: Duration, processed: usize, discovered: usize) -> Option<u64> {
+    if processed == 0 {
+        return None;
+    }
+
+    let remaining = discovered.saturating_sub(processed) as u128;
+    Some((elapsed.as_millis() * remaining / processed as u128) as u64)
+}
@@ -18,42 +18,121 @@ use std::{
     path::PathBuf,
     str::FromStr,
     sync::{
+        atomic::{AtomicU64, Ordering},
         mpsc::{self, Sender},
-        Mutex,
+        Arc, Condvar, Mutex,
     },
 };
 
 use threadpool::ThreadPool;
-use types::errors::Result;
-use types::{entities::QueryablePlaylist, songs::Song};
-
-use crate::{playlist_scanner::PlaylistScanner, song_scanner::SongScanner};
+use types::errors::{MoosyncError, Result};
+use types::{
+    entities::QueryablePlaylist,
+    scanner::{ScanProgress, ScanReport, ScanState},
+    songs::{QueryableSong, Song},
+};
 
-#[derive(Debug, PartialEq, Eq)]
-pub enum ScanState {
-    UNDEFINED,
-    SCANNING,
-    QUEUED,
-}
+use crate::{
+    playlist_scanner::PlaylistScanner, progress::ProgressTracker, song_scanner::SongScanner,
+};
 
 #[derive(Debug)]
 pub struct ScannerHolder {
-    state: Mutex<ScanState>,
-    progress: Mutex<u8>,
+    progress: Arc<Mutex<ScanProgress>>,
+    scan_finished: Condvar,
+    cancel_generation: Arc<AtomicU64>,
+}
+
+/// Marks the running scan as finished when dropped, so that errors
+/// don't leave the scanner stuck and queued scans get to run
+struct RunningScan<'a> {
+    holder: &'a ScannerHolder,
+    progress_tx: Sender<ScanProgress>,
+}
+
+impl Drop for RunningScan<'_> {
+    #[tracing::instrument(level = "debug", skip(self))]
+    fn drop(&mut self) {
+        let mut progress = self.holder.progress.lock().unwrap();
+        progress.state = ScanState::UNDEFINED;
+        progress.current_path = None;
+        progress.eta_ms = None;
+        let _ = self.progress_tx.send(progress.clone());
+        self.holder.scan_finished.notify_all();
+    }
 }
 
 impl ScannerHolder {
     #[tracing::instrument(level = "debug", skip())]
     pub fn new() -> Self {
         Self {
-            state: Mutex::new(ScanState::UNDEFINED),
-            progress: Mutex::new(0),
+            progress: Default::default(),
+            scan_finished: Condvar::new(),
+            cancel_generation: Default::default(),
         }
     }
 
     #[tracing::instrument(level = "debug", skip(self))]
-    pub fn get_progress(&self) -> u8 {
-        *self.progress.lock().unwrap()
+    pub fn get_progress(&self) -> ScanProgress {
+        self.progress.lock().unwrap().clone()
+    }
+
+    /// Cancels the running scan along with all scans waiting for it
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn cancel_scan(&self) {
+        self.cancel_generation.fetch_add(1, Ordering::AcqRel);
+        // Wake up queued scans so they can give up
+        let _progress = self.progress.lock().unwrap();
+        self.scan_finished.notify_all();
+    }
+
+    /// Waits for the running scan to finish, if any, and marks a new one as running
+    #[tracing::instrument(level = "debug", skip(self, generation, progress_tx))]
+    fn begin_scan(
+        &self,
+        generation: u64,
+        progress_tx: Sender<ScanProgress>,
+    ) -> Result<RunningScan<'_>> {
+        let is_cancelled = || self.cancel_generation.load(Ordering::Acquire) != generation;
+
+        let mut progress = self.progress.lock().unwrap();
+        if progress.state != ScanState::UNDEFINED {
+            progress.queued += 1;
+            progress.state = ScanState::QUEUED;
+            let _ = progress_tx.send(progress.clone());
+
+            progress = self
+                .scan_finished
+                .wait_while(progress, |p| {
+                    p.state != ScanState::UNDEFINED && !is_cancelled()
+                })
+                .unwrap();
+            progress.queued -= 1;
+        }
+
+        if is_cancelled() {
+            if progress.state == ScanState::QUEUED && progress.queued == 0 {
+                progress.state = ScanState::SCANNING;
+            }
+            let _ = progress_tx.send(progress.clone());
+            return Err(MoosyncError::String("Scan cancelled".to_string()));
+        }
+
+        *progress = ScanProgress {
+            state: if progress.queued > 0 {
+                ScanState::QUEUED
+            } else {
+                ScanState::SCANNING
+            },
+            queued: progress.queued,
+            ..Default::default()
+        };
+        let _ = progress_tx.send(progress.clone());
+
+        Ok(RunningScan {
+            holder: self,
+            progress_tx,
+        })
     }
 
     #[tracing::instrument(
@@ -64,27 +143,35 @@ impl ScannerHolder {
             thumbnail_dir,
             artist_split,
             scan_threads,
+            exclude_paths,
+            known_songs,
             song_tx,
-            playlist_tx
+            playlist_tx,
+            progress_tx
         )
     )]
+    #[allow(clippy::too_many_arguments)]
     pub fn start_scan(
         &self,
         dir: String,
         thumbnail_dir: String,
         artist_split: String,
         scan_threads: f64,
+        exclude_paths: Vec<String>,
+        known_songs: Vec<QueryableSong>,
         song_tx: Sender<(Option<String>, Vec<Song>)>,
         playlist_tx: Sender<Vec<QueryablePlaylist>>,
-    ) -> Result<()> {
-        let mut state = self.state.lock().unwrap();
-        if *state != ScanState::UNDEFINED {
-            *state = ScanState::QUEUED;
-            return Ok(());
-        }
-
-        *state = ScanState::SCANNING;
-        let (_progress_tx, _progress_rx) = mpsc::channel::<u8>();
+        progress_tx: Sender<ScanProgress>,
+    ) -> Result<ScanReport> {
+        let generation = self.cancel_generation.load(Ordering::Acquire);
+        let _running = self.begin_scan(generation, progress_tx.clone())?;
+
+        let progress = Arc::new(ProgressTracker::new(
+            self.progress.clone(),
+            Some(progress_tx),
+            self.cancel_generation.clone(),
+            generation,
+        ));
 
         let threads = scan_threads;
 
@@ -105,12 +192,18 @@ impl ScannerHolder {
             &mut song_pool,
             thumbnail_dir.clone(),
             artist_split,
+            exclude_paths.into_iter().map(PathBuf::from).collect(),
+            known_songs
+                .into_iter()
+                .filter_map(|s| Some((s.path.clone()?, s)))
+                .collect(),
+            progress.clone(),
         );
 
         let (tx_song, rx_song) = mpsc::channel::<(Option<String>, Result<Song>)>();
         let (tx_playlist, rx_playlist) = mpsc::channel::<Result<QueryablePlaylist>>();
 
-        song_scanner.start(tx_song.clone())?;
+        let report = song_scanner.start(tx_song.clone())?;
         let playlist_scanner = PlaylistScanner::new(dir, thumbnail_dir, song_scanner);
         playlist_scanner.start(tx_song, tx_playlist)?;
 
@@ -125,6 +218,7 @@ impl ScannerHolder {
         }
 
         for item in rx_song {
+            progress.check_cancelled()?;
             match item.1 {
                 Ok(song) => {
                     tracing::info!("Scanned song {:?}", song);
@@ -143,8 +237,7 @@ impl ScannerHolder {
             }
         }
 
-        *state = ScanState::UNDEFINED;
-
-        Ok(())
+        progress.check_cancelled()?;
+        Ok(report)
     }
 }
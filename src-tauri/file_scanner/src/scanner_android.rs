@@ -17,14 +17,11 @@
 use std::sync::{mpsc::Sender, Mutex};
 
 use types::errors::Result;
-use types::{entities::QueryablePlaylist, songs::Song};
-
-#[derive(Debug, PartialEq, Eq)]
-pub enum ScanState {
-    UNDEFINED,
-    SCANNING,
-    QUEUED,
-}
+use types::{
+    entities::QueryablePlaylist,
+    scanner::{ScanProgress, ScanReport},
+    songs::{QueryableSong, Song},
+};
 
 #[derive(Debug)]
 pub struct ScannerHolder {}
@@ -43,10 +40,13 @@ impl ScannerHolder {
     }
 
     #[tracing::instrument(level = "debug", skip(self))]
-    pub fn get_progress(&self) -> u8 {
-        0
+    pub fn get_progress(&self) -> ScanProgress {
+        ScanProgress::default()
     }
 
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn cancel_scan(&self) {}
+
     #[tracing::instrument(
         level = "trace",
         skip(
@@ -55,19 +55,26 @@ impl ScannerHolder {
             thumbnail_dir,
             artist_split,
             scan_threads,
+            exclude_paths,
+            known_songs,
             song_tx,
-            playlist_tx
+            playlist_tx,
+            progress_tx
         )
     )]
+    #[allow(clippy::too_many_arguments)]
     pub fn start_scan(
         &self,
         dir: String,
         thumbnail_dir: String,
         artist_split: String,
         scan_threads: f64,
+        exclude_paths: Vec<String>,
+        known_songs: Vec<QueryableSong>,
         song_tx: Sender<(Option<String>, Vec<Song>)>,
         playlist_tx: Sender<Vec<QueryablePlaylist>>,
-    ) -> Result<()> {
-        Ok(())
+        progress_tx: Sender<ScanProgress>,
+    ) -> Result<ScanReport> {
+        Ok(ScanReport::default())
     }
 }
@@ -14,36 +14,73 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::{path::PathBuf, sync::mpsc::Sender};
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::{mpsc::Sender, Arc},
+};
 
-use crate::utils::{check_directory, get_files_recursively, scan_file};
+use crate::cue::{cue_mtime, cue_track_paths, read_cue, scan_cue, CueSheet};
+use crate::progress::ProgressTracker;
+use crate::utils::{
+    check_directory, get_file_status, get_files_recursively, scan_song, FileStatus,
+};
 use threadpool::ThreadPool;
 use types::errors::Result;
-use types::songs::Song;
+use types::scanner::ScanReport;
+use types::songs::{QueryableSong, Song};
 
 pub struct SongScanner<'a> {
     dir: PathBuf,
     pool: &'a mut ThreadPool,
     thumbnail_dir: PathBuf,
     artist_split: String,
+    exclude_paths: Vec<PathBuf>,
+    known_songs: HashMap<String, QueryableSong>,
+    progress: Arc<ProgressTracker>,
 }
 
 impl<'a> SongScanner<'a> {
-    #[tracing::instrument(level = "debug", skip(dir, pool, thumbnail_dir, artist_split))]
+    #[tracing::instrument(
+        level = "debug",
+        skip(
+            dir,
+            pool,
+            thumbnail_dir,
+            artist_split,
+            exclude_paths,
+            known_songs,
+            progress
+        )
+    )]
     pub fn new(
         dir: PathBuf,
         pool: &'a mut ThreadPool,
         thumbnail_dir: PathBuf,
         artist_split: String,
+        exclude_paths: Vec<PathBuf>,
+        known_songs: HashMap<String, QueryableSong>,
+        progress: Arc<ProgressTracker>,
     ) -> Self {
         Self {
             dir,
             pool,
             thumbnail_dir,
             artist_split,
+            exclude_paths,
+            known_songs,
+            progress,
         }
     }
 
+    pub fn exclude_paths(&self) -> &[PathBuf] {
+        &self.exclude_paths
+    }
+
+    pub fn progress(&self) -> &ProgressTracker {
+        &self.progress
+    }
+
     #[tracing::instrument(level = "debug", skip(self))]
     fn check_dirs(&self) -> Result<()> {
         check_directory(self.thumbnail_dir.clone())?;
@@ -51,44 +88,225 @@ impl<'a> SongScanner<'a> {
         Ok(())
     }
 
+    #[tracing::instrument(level = "debug", skip(self, path))]
+    fn known_song(&self, path: &Path) -> Option<&QueryableSong> {
+        self.known_songs
+            .get(path.to_string_lossy().as_ref())
+            .or_else(|| {
+                let path = dunce::canonicalize(path).ok()?;
+                self.known_songs.get(path.to_string_lossy().as_ref())
+            })
+    }
+
+    /// Scans the file at `path` unless it is unchanged since the last scan.
+    /// Unchanged files are only sent if they belong to a playlist.
     #[tracing::instrument(level = "debug", skip(self, tx, size, path, playlist_id))]
-    pub fn scan_in_pool(
+    pub(crate) fn scan_in_pool(
         &self,
         tx: Sender<(Option<String>, Result<Song>)>,
         size: f64,
         path: PathBuf,
         playlist_id: Option<String>,
-    ) {
+    ) -> FileStatus {
+        let known = self.known_song(&path);
+        let status = get_file_status(&path, size, known);
+
+        match (&status, known) {
+            (FileStatus::Unchanged, Some(known)) => {
+                if playlist_id.is_some() {
+                    // Only the columns set here get updated
+                    let song = Song {
+                        song: QueryableSong {
+                            _id: known._id.clone(),
+                            path: known.path.clone(),
+                            type_: known.type_,
+                            ..Default::default()
+                        },
+                        album: None,
+                        artists: None,
+                        genre: None,
+                    };
+                    tx.send((playlist_id, Ok(song)))
+                        .expect("channel will be there waiting for the pool");
+                }
+                self.progress.processed(true);
+            }
+            _ => {
+                // Keep the id of modified songs so playlists and analytics still point to them
+                let known_id = known.and_then(|k| k._id.clone());
+                let thumbnail_dir = self.thumbnail_dir.clone();
+                let artist_split = self.artist_split.clone();
+                let progress = self.progress.clone();
+                self.pool.execute(move || {
+                    // Files still waiting in the pool are skipped once the scan is cancelled
+                    if progress.is_cancelled() {
+                        return;
+                    }
+
+                    progress.processing(&path);
+                    let mut metadata = scan_song(&path, &thumbnail_dir, &artist_split);
+                    if let Ok(song) = metadata.as_mut() {
+                        if known_id.is_some() {
+                            song.song._id = known_id;
+                        }
+                    }
+                    progress.processed(metadata.is_ok());
+
+                    // The receiver is gone if the scan was cancelled meanwhile
+                    let _ = tx.send((playlist_id, metadata));
+                });
+            }
+        }
+
+        status
+    }
+
+    /// Scans the tracks of a CUE sheet unless neither the sheet nor its audio files
+    /// changed since the last scan. Returns the library paths of the tracks with their status
+    #[tracing::instrument(level = "debug", skip(self, tx, cue_path, sheet))]
+    fn scan_cue_in_pool(
+        &self,
+        tx: Sender<(Option<String>, Result<Song>)>,
+        cue_path: PathBuf,
+        sheet: CueSheet,
+    ) -> Vec<(String, FileStatus)> {
+        let mut statuses = vec![];
+        let mut known_ids = HashMap::new();
+        for (audio_path, paths) in cue_track_paths(&cue_path, &sheet) {
+            let mtime = cue_mtime(&cue_path, &audio_path);
+            for path in paths {
+                let known = self.known_songs.get(&path);
+                let status = match known {
+                    None => FileStatus::New,
+                    Some(known) if mtime.is_some() && known.mtime == mtime => FileStatus::Unchanged,
+                    Some(_) => FileStatus::Modified,
+                };
+                if let Some(id) = known.and_then(|k| k._id.clone()) {
+                    known_ids.insert(path.clone(), id);
+                }
+                statuses.push((path, status));
+            }
+        }
+
+        if statuses
+            .iter()
+            .all(|(_, s)| matches!(s, FileStatus::Unchanged))
+        {
+            self.progress.processed(true);
+            return statuses;
+        }
+
         let thumbnail_dir = self.thumbnail_dir.clone();
         let artist_split = self.artist_split.clone();
+        let progress = self.progress.clone();
         self.pool.execute(move || {
-            let mut metadata = scan_file(&path, &thumbnail_dir, size, false, &artist_split);
-            if metadata.is_err() {
-                metadata = scan_file(&path, &thumbnail_dir, size, true, &artist_split);
+            if progress.is_cancelled() {
+                return;
             }
 
-            tx.send((playlist_id, metadata))
-                .expect("channel will be there waiting for the pool");
+            progress.processing(&cue_path);
+            match scan_cue(&cue_path, &sheet, &thumbnail_dir, &artist_split) {
+                Ok(songs) => {
+                    progress.processed(true);
+                    for mut song in songs {
+                        // Tracks keep their ids, same as modified files
+                        if let Some(id) = song.song.path.as_ref().and_then(|p| known_ids.get(p)) {
+                            song.song._id = Some(id.clone());
+                        }
+                        let _ = tx.send((None, Ok(song)));
+                    }
+                }
+                Err(e) => {
+                    progress.processed(false);
+                    let _ = tx.send((None, Err(e)));
+                }
+            }
         });
+
+        statuses
     }
 
     #[tracing::instrument(level = "debug", skip(self, tx_song))]
-    pub fn start(&self, tx_song: Sender<(Option<String>, Result<Song>)>) -> Result<usize> {
+    pub fn start(&self, tx_song: Sender<(Option<String>, Result<Song>)>) -> Result<ScanReport> {
         tracing::debug!("Satrting scan");
         self.check_dirs()?;
 
-        let file_list = get_files_recursively(self.dir.clone())?;
+        let file_list = get_files_recursively(self.dir.clone(), &self.exclude_paths)?;
+
+        let mut cue_sheets = vec![];
+        for cue_path in file_list.cue_list {
+            match read_cue(&cue_path) {
+                Ok(sheet) => cue_sheets.push((cue_path, sheet)),
+                Err(e) => tracing::warn!("Failed to read {}: {:?}", cue_path.display(), e),
+            }
+        }
+
+        // Files split into tracks by a CUE sheet aren't songs on their own
+        let cue_files: HashSet<PathBuf> = cue_sheets
+            .iter()
+            .flat_map(|(cue_path, sheet)| cue_track_paths(cue_path, sheet))
+            .map(|(audio_path, _)| audio_path)
+            .collect();
+        let song_list: Vec<_> = file_list
+            .file_list
+            .into_iter()
+            .filter(|(path, _)| !cue_files.contains(path))
+            .collect();
+        self.progress.discovered(song_list.len() + cue_sheets.len());
 
-        let song_list = file_list.file_list;
+        let mut report = ScanReport::default();
+        let mut seen = HashSet::new();
 
-        let len = song_list.len();
+        for (cue_path, sheet) in cue_sheets {
+            self.progress.check_cancelled()?;
+
+            for (path, status) in self.scan_cue_in_pool(tx_song.clone(), cue_path, sheet) {
+                seen.insert(path.clone());
+                match status {
+                    FileStatus::New => report.added.push(path),
+                    FileStatus::Modified => report.updated.push(path),
+                    FileStatus::Unchanged => report.unchanged += 1,
+                }
+            }
+        }
 
         for (file_path, size) in song_list {
-            self.scan_in_pool(tx_song.clone(), size, file_path, None);
+            self.progress.check_cancelled()?;
+
+            let path_str = self
+                .known_song(&file_path)
+                .and_then(|k| k.path.clone())
+                .unwrap_or_else(|| file_path.to_string_lossy().to_string());
+            seen.insert(path_str.clone());
+
+            match self.scan_in_pool(tx_song.clone(), size, file_path, None) {
+                FileStatus::New => report.added.push(path_str),
+                FileStatus::Modified => report.updated.push(path_str),
+                FileStatus::Unchanged => report.unchanged += 1,
+            }
+        }
+
+        // An unreachable directory (like an unmounted drive) doesn't mean its songs are gone
+        if self.dir.exists() {
+            report.removed = self
+                .known_songs
+                .keys()
+                .filter(|p| !seen.contains(*p))
+                .cloned()
+                .collect();
         }
 
         drop(tx_song);
 
-        Ok(len)
+        tracing::info!(
+            "Scanned {}: {} added, {} updated, {} removed, {} unchanged",
+            self.dir.display(),
+            report.added.len(),
+            report.updated.len(),
+            report.removed.len(),
+            report.unchanged
+        );
+
+        Ok(report)
     }
 }
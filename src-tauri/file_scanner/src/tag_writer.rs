@@ -0,0 +1,226 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    fs::{self, File},
+    path::{Path, PathBuf},
+};
+
+use lofty::{
+    config::WriteOptions,
+    file::TaggedFile,
+    picture::{Picture, PictureType},
+    prelude::{Accessor, AudioFile, ItemKey, TaggedFileExt},
+    probe::Probe,
+    tag::Tag,
+};
+use types::{
+    errors::{error_helpers, Result},
+    tags::{TagChange, TagEdit, TagField},
+};
+use uuid::Uuid;
+
+/// Value shown for cover art which is already embedded in a file
+pub const EMBEDDED_COVER: &str = "embedded";
+
+#[tracing::instrument(level = "debug", skip(path))]
+fn read_file(path: &Path) -> Result<TaggedFile> {
+    Probe::open(path)
+        .map_err(error_helpers::to_media_error)?
+        .guess_file_type()
+        .map_err(error_helpers::to_media_error)?
+        .read()
+        .map_err(error_helpers::to_media_error)
+}
+
+/// The tag edits go into, which is the same one the scanner reads from
+#[tracing::instrument(level = "debug", skip(file))]
+fn editable_tag(file: &TaggedFile) -> Option<&Tag> {
+    file.primary_tag().or(file.first_tag())
+}
+
+#[tracing::instrument(level = "debug", skip(value))]
+fn parse_number(field: TagField, value: &str) -> Result<u32> {
+    // Positions may be written along with their total, like "3/12"
+    value
+        .split('/')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .parse()
+        .map_err(|_| format!("Invalid value for {:?}: {}", field, value).into())
+}
+
+/// Checks that a new value can be written before touching any file. Numbers are
+/// returned the way they will be read back from the tag
+#[tracing::instrument(level = "debug", skip(value))]
+fn normalize(field: TagField, value: String) -> Result<String> {
+    match field {
+        TagField::Year | TagField::TrackNo | TagField::DiscNo => {
+            Ok(parse_number(field, &value)?.to_string())
+        }
+        TagField::Cover => {
+            if !Path::new(&value).is_file() {
+                return Err(format!("Cover image not found: {}", value).into());
+            }
+            Ok(value)
+        }
+        _ => Ok(value),
+    }
+}
+
+#[tracing::instrument(level = "debug", skip(tag))]
+fn current_value(tag: Option<&Tag>, field: TagField) -> Option<String> {
+    let tag = tag?;
+    match field {
+        TagField::Title => tag.title().map(|v| v.to_string()),
+        TagField::Artists => tag.artist().map(|v| v.to_string()),
+        TagField::Album => tag.album().map(|v| v.to_string()),
+        TagField::AlbumArtist => tag.get_string(&ItemKey::AlbumArtist).map(str::to_string),
+        TagField::Year => tag.year().map(|v| v.to_string()),
+        TagField::Genre => tag.genre().map(|v| v.to_string()),
+        TagField::TrackNo => tag.track().map(|v| v.to_string()),
+        TagField::DiscNo => tag.disk().map(|v| v.to_string()),
+        TagField::Lyrics => tag.get_string(&ItemKey::Lyrics).map(str::to_string),
+        TagField::Cover => tag
+            .pictures()
+            .iter()
+            .any(|p| p.pic_type() == PictureType::CoverFront)
+            .then(|| EMBEDDED_COVER.to_string()),
+    }
+}
+
+/// Compares an edit with the tags in a file, without writing anything. Only the
+/// fields which would change are returned
+#[tracing::instrument(level = "debug", skip(path, edit, artist_split))]
+pub fn diff_tags(path: &Path, edit: &TagEdit, artist_split: &str) -> Result<Vec<TagChange>> {
+    let file = read_file(path)?;
+    let tag = editable_tag(&file);
+
+    let mut changes = vec![];
+    for (field, new) in edit.values(artist_split) {
+        let new = new.map(|new| normalize(field, new)).transpose()?;
+        let old = current_value(tag, field);
+        if old != new {
+            changes.push(TagChange { field, old, new });
+        }
+    }
+    Ok(changes)
+}
+
+/// Saves the current front cover of a tag so replacing it can be undone
+#[tracing::instrument(level = "debug", skip(tag))]
+fn backup_cover(tag: &Tag, backup_dir: &Path) -> Result<Option<String>> {
+    let Some(picture) = tag
+        .pictures()
+        .iter()
+        .find(|p| p.pic_type() == PictureType::CoverFront)
+    else {
+        return Ok(None);
+    };
+
+    fs::create_dir_all(backup_dir)?;
+    let backup_path = backup_dir.join(format!("{}.cover", Uuid::new_v4()));
+    fs::write(&backup_path, picture.data())?;
+    Ok(Some(backup_path.to_string_lossy().to_string()))
+}
+
+#[tracing::instrument(level = "debug", skip(tag, change))]
+fn apply_change(tag: &mut Tag, change: &mut TagChange, backup_dir: Option<&Path>) -> Result<()> {
+    let field = change.field;
+    match (field, change.new.clone()) {
+        (TagField::Title, Some(v)) => tag.set_title(v),
+        (TagField::Title, None) => tag.remove_title(),
+        (TagField::Artists, Some(v)) => tag.set_artist(v),
+        (TagField::Artists, None) => tag.remove_artist(),
+        (TagField::Album, Some(v)) => tag.set_album(v),
+        (TagField::Album, None) => tag.remove_album(),
+        (TagField::AlbumArtist, Some(v)) => {
+            tag.insert_text(ItemKey::AlbumArtist, v);
+        }
+        (TagField::AlbumArtist, None) => tag.remove_key(&ItemKey::AlbumArtist),
+        (TagField::Year, Some(v)) => tag.set_year(parse_number(field, &v)?),
+        (TagField::Year, None) => tag.remove_year(),
+        (TagField::Genre, Some(v)) => tag.set_genre(v),
+        (TagField::Genre, None) => tag.remove_genre(),
+        (TagField::TrackNo, Some(v)) => tag.set_track(parse_number(field, &v)?),
+        (TagField::TrackNo, None) => tag.remove_track(),
+        (TagField::DiscNo, Some(v)) => tag.set_disk(parse_number(field, &v)?),
+        (TagField::DiscNo, None) => tag.remove_disk(),
+        (TagField::Lyrics, Some(v)) => {
+            tag.insert_text(ItemKey::Lyrics, v);
+        }
+        (TagField::Lyrics, None) => tag.remove_key(&ItemKey::Lyrics),
+        (TagField::Cover, new) => {
+            if let Some(backup_dir) = backup_dir {
+                change.old = backup_cover(tag, backup_dir)?;
+            }
+
+            tag.remove_picture_type(PictureType::CoverFront);
+            if let Some(new) = new {
+                let mut picture = Picture::from_reader(&mut File::open(new)?)
+                    .map_err(error_helpers::to_media_error)?;
+                picture.set_pic_type(PictureType::CoverFront);
+                tag.push_picture(picture);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Writes changes into a file. When `backup_dir` is set, cover art being replaced is
+/// saved there and the returned changes point to it, so that they can be undone
+#[tracing::instrument(level = "debug", skip(path, changes))]
+pub fn write_tags(
+    path: &Path,
+    changes: &[TagChange],
+    backup_dir: Option<&Path>,
+) -> Result<Vec<TagChange>> {
+    let mut file = read_file(path)?;
+    let tag_type = match editable_tag(&file) {
+        Some(tag) => tag.tag_type(),
+        None => {
+            let tag_type = file.primary_tag_type();
+            file.insert_tag(Tag::new(tag_type));
+            tag_type
+        }
+    };
+    let Some(tag) = file.tag_mut(tag_type) else {
+        return Err("File does not support tags".into());
+    };
+
+    let mut written = vec![];
+    for change in changes {
+        let mut change = change.clone();
+        apply_change(tag, &mut change, backup_dir)?;
+        written.push(change);
+    }
+
+    file.save_to_path(path, WriteOptions::default())
+        .map_err(error_helpers::to_media_error)?;
+    Ok(written)
+}
+
+/// Cover backups referenced by written changes, which can be removed once they are undone
+#[tracing::instrument(level = "debug", skip(changes))]
+pub fn cover_backups(changes: &[TagChange], backup_dir: &Path) -> Vec<PathBuf> {
+    changes
+        .iter()
+        .filter(|c| c.field == TagField::Cover)
+        .filter_map(|c| c.old.as_ref().map(PathBuf::from))
+        .filter(|p| p.starts_with(backup_dir))
+        .collect()
+}
@@ -2,12 +2,41 @@ use std::{
     env,
     fs::{self, File},
     io::Write,
-    sync::mpsc,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use notify::{
+    event::{CreateKind, DataChange, ModifyKind, RemoveKind, RenameMode},
+    Event, EventKind,
 };
 
 use threadpool::ThreadPool;
+use types::{
+    entities::{QueryableAlbum, QueryableArtist, QueryableGenre, VARIOUS_ARTISTS},
+    scanner::ScanProgress,
+    songs::{QueryableSong, Song, SongType},
+    tags::{TagEdit, TagField},
+};
 
-use crate::{playlist_scanner::PlaylistScanner, song_scanner::SongScanner};
+use crate::{
+    cue::{cue_split_files, cue_track_paths, parse_cue, parse_cue_timestamp, read_cue},
+    playlist_formats::{playlist_format, read_playlist, M3u, PlaylistFormat, Xspf},
+    playlist_scanner::PlaylistScanner,
+    progress::{estimate_eta, ProgressTracker},
+    song_scanner::SongScanner,
+    tag_writer::{diff_tags, write_tags},
+    utils::{
+        assign_artist_mbids, file_mtime, get_file_status, get_files_recursively, hash_audio,
+        is_playlist_or_cue_file, parse_flag, parse_r128_gain, parse_replaygain, parse_sylt,
+        resolve_album_artist, scan_lrc, FileStatus, IGNORE_FILE_NAME,
+    },
+    watcher::{Debouncer, WatchEvent},
+};
 
 #[test]
 fn test_playlist_scan() {
@@ -36,6 +65,9 @@ https://chiru.no/stream.flac"#;
         &mut pool,
         test_out_dir.clone(),
         "".to_string(),
+        vec![],
+        Default::default(),
+        Default::default(),
     );
     let playlist_scanner =
         PlaylistScanner::new(test_in_dir.clone(), test_out_dir.clone(), song_scanner);
@@ -91,6 +123,9 @@ https://example.com/track2"#;
         &mut pool,
         test_out_dir.clone(),
         "".to_string(),
+        vec![],
+        Default::default(),
+        Default::default(),
     );
     let playlist_scanner =
         PlaylistScanner::new(test_in_dir.clone(), test_out_dir.clone(), song_scanner);
@@ -136,6 +171,9 @@ https://example.com/lonely_track"#;
         &mut pool,
         test_out_dir.clone(),
         "".to_string(),
+        vec![],
+        Default::default(),
+        Default::default(),
     );
     let playlist_scanner =
         PlaylistScanner::new(test_in_dir.clone(), test_out_dir.clone(), song_scanner);
@@ -160,3 +198,704 @@ https://example.com/lonely_track"#;
     fs::remove_dir_all(test_in_dir).unwrap();
     fs::remove_dir_all(test_out_dir).unwrap();
 }
+
+#[test]
+fn test_lrc_scan_keeps_timestamps() {
+    let lrc_contents = r#"[ar:Artist]
+[ti:Title]
+[00:12.00]First line
+[00:15.50]<00:15.50>Second <00:16.20>line<00:17.00>
+[00:20.00][00:40.00]Chorus"#;
+
+    let test_in_dir = env::temp_dir().join("moosync-test-in-lrc");
+    fs::create_dir_all(test_in_dir.clone()).unwrap();
+
+    let mut input = File::create(test_in_dir.join("song.lrc")).unwrap();
+    input.write_all(lrc_contents.as_bytes()).unwrap();
+
+    let lyrics = scan_lrc(test_in_dir.join("song.mp3")).unwrap();
+    assert!(lyrics.is_synced());
+    assert_eq!(lyrics.lines.len(), 4);
+
+    assert_eq!(lyrics.lines[0].start_ms, 12000);
+    assert_eq!(lyrics.lines[0].end_ms, Some(15500));
+    assert_eq!(lyrics.lines[0].text, "First line");
+
+    assert_eq!(lyrics.lines[1].text, "Second line");
+    assert_eq!(lyrics.lines[1].words.len(), 2);
+    assert_eq!(lyrics.lines[1].words[1].start_ms, 16200);
+    assert_eq!(lyrics.lines[1].words[1].end_ms, Some(17000));
+
+    assert_eq!(lyrics.lines[3].start_ms, 40000);
+    assert_eq!(lyrics.line_at(21000), Some(2));
+
+    fs::remove_dir_all(test_in_dir).unwrap();
+}
+
+#[test]
+fn test_sylt_parse() {
+    // UTF-8, "eng", millisecond timestamps, lyrics content type, empty descriptor
+    let mut data = vec![3u8, b'e', b'n', b'g', 2, 1, 0];
+    for (time, text) in [
+        (1000u32, "Hello "),
+        (1500, "world"),
+        (3000, "\nNext "),
+        (3500, "line"),
+    ] {
+        data.extend_from_slice(text.as_bytes());
+        data.push(0);
+        data.extend_from_slice(&time.to_be_bytes());
+    }
+
+    let lyrics = parse_sylt(&data).unwrap();
+    assert_eq!(lyrics.lines.len(), 2);
+    assert_eq!(lyrics.lines[0].text, "Hello world");
+    assert_eq!(lyrics.lines[0].end_ms, Some(3000));
+    assert_eq!(lyrics.lines[1].start_ms, 3000);
+    assert_eq!(lyrics.lines[1].words[1].start_ms, 3500);
+    assert_eq!(lyrics.plain, "Hello world\nNext line");
+}
+
+#[test]
+fn test_exclude_paths_and_ignore_file() {
+    let test_in_dir = env::temp_dir().join("moosync-test-in-ignore");
+    let _ = fs::remove_dir_all(&test_in_dir);
+
+    for dir in ["album", "album/stems", "excluded", "samples"] {
+        fs::create_dir_all(test_in_dir.join(dir)).unwrap();
+    }
+    for file in [
+        "song.mp3",
+        "album/track.flac",
+        "album/intro.flac",
+        "album/stems/vocals.wav",
+        "excluded/song.mp3",
+        "samples/kick.wav",
+        "samples/keep.wav",
+    ] {
+        File::create(test_in_dir.join(file)).unwrap();
+    }
+
+    fs::write(
+        test_in_dir.join(IGNORE_FILE_NAME),
+        "samples/*\n!samples/keep.wav\n",
+    )
+    .unwrap();
+    fs::write(
+        test_in_dir.join("album").join(IGNORE_FILE_NAME),
+        "stems/\nintro.*\n",
+    )
+    .unwrap();
+
+    let file_list =
+        get_files_recursively(test_in_dir.clone(), &[test_in_dir.join("excluded")]).unwrap();
+
+    let root = dunce::canonicalize(&test_in_dir).unwrap();
+    let mut files = file_list
+        .file_list
+        .into_iter()
+        .map(|(path, _)| {
+            path.strip_prefix(&root)
+                .unwrap()
+                .to_string_lossy()
+                .replace('\\', "/")
+        })
+        .collect::<Vec<_>>();
+    files.sort();
+
+    assert_eq!(
+        files,
+        vec!["album/track.flac", "samples/keep.wav", "song.mp3"]
+    );
+
+    // Excluding the scanned directory itself yields nothing
+    let file_list = get_files_recursively(test_in_dir.clone(), &[test_in_dir.clone()]).unwrap();
+    assert!(file_list.file_list.is_empty());
+
+    fs::remove_dir_all(test_in_dir).unwrap();
+}
+
+#[test]
+fn test_watch_events_debounce() {
+    let event = |kind: EventKind, paths: &[&str]| {
+        paths
+            .iter()
+            .fold(Event::new(kind), |e, p| e.add_path(PathBuf::from(p)))
+    };
+
+    let mut debouncer = Debouncer::default();
+    // Written in multiple chunks
+    debouncer.push(event(EventKind::Create(CreateKind::File), &["/m/new.mp3"]));
+    debouncer.push(event(
+        EventKind::Modify(ModifyKind::Data(DataChange::Any)),
+        &["/m/new.mp3"],
+    ));
+    // Temporary file that never made it
+    debouncer.push(event(EventKind::Create(CreateKind::File), &["/m/tmp.mp3"]));
+    debouncer.push(event(EventKind::Remove(RemoveKind::File), &["/m/tmp.mp3"]));
+    // Replaced by a new copy
+    debouncer.push(event(
+        EventKind::Remove(RemoveKind::File),
+        &["/m/replaced.mp3"],
+    ));
+    debouncer.push(event(
+        EventKind::Create(CreateKind::File),
+        &["/m/replaced.mp3"],
+    ));
+    // Renamed twice
+    debouncer.push(event(
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)),
+        &["/m/a.mp3", "/m/b.mp3"],
+    ));
+    debouncer.push(event(
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)),
+        &["/m/b.mp3", "/m/c.mp3"],
+    ));
+    debouncer.push(event(
+        EventKind::Remove(RemoveKind::File),
+        &["/m/removed.mp3"],
+    ));
+
+    assert_eq!(
+        debouncer.flush(),
+        vec![
+            WatchEvent::Renamed(PathBuf::from("/m/a.mp3"), PathBuf::from("/m/c.mp3")),
+            WatchEvent::Removed(PathBuf::from("/m/removed.mp3")),
+            WatchEvent::Created(PathBuf::from("/m/new.mp3")),
+            WatchEvent::Modified(PathBuf::from("/m/replaced.mp3")),
+        ]
+    );
+    assert!(debouncer.is_empty());
+}
+
+#[test]
+fn test_file_status() {
+    let test_dir = env::temp_dir().join("moosync-test-file-status");
+    fs::create_dir_all(&test_dir).unwrap();
+    let path = test_dir.join("song.mp3");
+    fs::write(&path, b"some audio").unwrap();
+
+    let size = fs::metadata(&path).unwrap().len() as f64;
+    let mut known = QueryableSong {
+        path: Some(path.to_string_lossy().to_string()),
+        size: Some(size),
+        mtime: file_mtime(&path),
+        hash: hash_audio(&path).ok(),
+        ..Default::default()
+    };
+
+    assert_eq!(get_file_status(&path, size, None), FileStatus::New);
+    assert_eq!(
+        get_file_status(&path, size, Some(&known)),
+        FileStatus::Unchanged
+    );
+
+    // Different modification time, tags might have been edited in place
+    known.mtime = Some(0f64);
+    assert_eq!(
+        get_file_status(&path, size, Some(&known)),
+        FileStatus::Modified
+    );
+
+    // Audio that couldn't be hashed
+    known.mtime = file_mtime(&path);
+    known.hash = None;
+    assert_eq!(
+        get_file_status(&path, size, Some(&known)),
+        FileStatus::Unchanged
+    );
+
+    // Different size
+    known.hash = Some("hash".to_string());
+    assert_eq!(
+        get_file_status(&path, size + 1f64, Some(&known)),
+        FileStatus::Modified
+    );
+
+    fs::remove_dir_all(&test_dir).unwrap();
+}
+
+#[test]
+fn test_scan_progress() {
+    assert_eq!(estimate_eta(Duration::from_secs(2), 0, 10), None);
+    assert_eq!(estimate_eta(Duration::from_secs(2), 4, 10), Some(3000));
+    assert_eq!(estimate_eta(Duration::from_secs(2), 10, 10), Some(0));
+
+    let progress = Arc::new(Mutex::new(ScanProgress::default()));
+    let cancel_generation = Arc::new(AtomicU64::new(0));
+    let (tx, rx) = mpsc::channel();
+    let tracker = ProgressTracker::new(progress.clone(), Some(tx), cancel_generation.clone(), 0);
+
+    tracker.discovered(3);
+    tracker.processing(&PathBuf::from("/m/a.mp3"));
+    tracker.processed(true);
+    tracker.processed(false);
+    tracker.send(true);
+
+    let sent: Vec<ScanProgress> = rx.try_iter().collect();
+    assert_eq!(sent.first().unwrap().discovered, 3);
+    let last = sent.last().unwrap();
+    assert_eq!(last.processed, 2);
+    assert_eq!(last.errors, 1);
+    assert_eq!(last.current_path, Some("/m/a.mp3".to_string()));
+    assert_eq!(*progress.lock().unwrap(), *last);
+
+    assert!(tracker.check_cancelled().is_ok());
+    cancel_generation.fetch_add(1, Ordering::AcqRel);
+    assert!(tracker.check_cancelled().is_err());
+}
+
+#[test]
+fn test_hash_audio_ignores_tags() {
+    let test_dir = env::temp_dir().join("moosync-test-hash-audio");
+    fs::create_dir_all(&test_dir).unwrap();
+
+    let audio = vec![0xffu8, 0xfb, 0x90, 0x64, 1, 2, 3, 4, 5, 6, 7, 8];
+
+    let untagged = test_dir.join("untagged.mp3");
+    fs::write(&untagged, &audio).unwrap();
+
+    // ID3v2 tag in front, ID3v1 tag at the end
+    let mut tagged_data = b"ID3\x04\x00\x00\x00\x00\x00\x05".to_vec();
+    tagged_data.extend_from_slice(&[0u8; 5]);
+    tagged_data.extend_from_slice(&audio);
+    let mut id3v1 = b"TAG".to_vec();
+    id3v1.resize(128, b'x');
+    tagged_data.extend_from_slice(&id3v1);
+    let tagged = test_dir.join("tagged.mp3");
+    fs::write(&tagged, &tagged_data).unwrap();
+
+    // FLAC metadata blocks, the last one flagged
+    let mut flac_data = b"fLaC".to_vec();
+    flac_data.extend_from_slice(&[0x00, 0x00, 0x00, 0x02, 9, 9]);
+    flac_data.extend_from_slice(&[0x84, 0x00, 0x00, 0x01, 9]);
+    flac_data.extend_from_slice(&audio);
+    let flac = test_dir.join("song.flac");
+    fs::write(&flac, &flac_data).unwrap();
+
+    let hash = hash_audio(&untagged).unwrap();
+    assert_eq!(hash_audio(&tagged).unwrap(), hash);
+    assert_eq!(hash_audio(&flac).unwrap(), hash);
+
+    let different = test_dir.join("different.mp3");
+    fs::write(&different, &audio[..8]).unwrap();
+    assert_ne!(hash_audio(&different).unwrap(), hash);
+
+    fs::remove_dir_all(&test_dir).unwrap();
+}
+
+#[test]
+fn test_parse_replaygain() {
+    assert_eq!(parse_replaygain("-6.54 dB"), Some(-6.54));
+    assert_eq!(parse_replaygain("+2.10 dB"), Some(2.1));
+    assert_eq!(parse_replaygain("0.988159"), Some(0.988159));
+    assert_eq!(parse_replaygain("loud"), None);
+
+    // R128 gains are relative to -23 LUFS, 5 dB below the ReplayGain reference
+    assert_eq!(parse_r128_gain("0"), Some(5f64));
+    assert_eq!(parse_r128_gain("-2560"), Some(-5f64));
+    assert_eq!(parse_r128_gain("99999"), None);
+}
+
+#[test]
+fn test_resolve_album_artist() {
+    let artists = vec![QueryableArtist {
+        artist_name: Some("Track Artist".to_string()),
+        ..Default::default()
+    }];
+
+    assert_eq!(
+        resolve_album_artist(Some("Album Artist"), false, &artists),
+        (Some("Album Artist".to_string()), false)
+    );
+    assert_eq!(
+        resolve_album_artist(None, false, &artists),
+        (Some("Track Artist".to_string()), false)
+    );
+
+    // Flagged compilations and albums tagged "Various Artists" are grouped together
+    let various = (Some(VARIOUS_ARTISTS.to_string()), true);
+    assert_eq!(
+        resolve_album_artist(Some("DJ Mix"), true, &artists),
+        various
+    );
+    assert_eq!(
+        resolve_album_artist(Some("various artists"), false, &[]),
+        various
+    );
+
+    assert!(parse_flag("1"));
+    assert!(parse_flag("TRUE"));
+    assert!(!parse_flag("0"));
+}
+
+#[test]
+fn test_assign_artist_mbids() {
+    let artist = |name: &str| QueryableArtist {
+        artist_name: Some(name.to_string()),
+        ..Default::default()
+    };
+    let ids = vec!["id-a".to_string(), "id-b".to_string()];
+
+    let mut artists = vec![artist("A"), artist("B")];
+    assign_artist_mbids(&mut artists, &ids);
+    assert_eq!(artists[0].artist_mbid.as_deref(), Some("id-a"));
+    assert_eq!(artists[1].artist_mbid.as_deref(), Some("id-b"));
+
+    // IDs which can't be matched up with the artists are ignored
+    let mut artists = vec![artist("A")];
+    assign_artist_mbids(&mut artists, &ids);
+    assert_eq!(artists[0].artist_mbid, None);
+}
+
+#[test]
+fn test_tag_edit_round_trip() {
+    let test_dir = env::temp_dir().join("moosync-test-tag-edit");
+    fs::create_dir_all(&test_dir).unwrap();
+
+    // A few silent MPEG-1 Layer III frames at 128 kbps / 44.1 kHz
+    let mut frame = vec![0xffu8, 0xfb, 0x90, 0x64];
+    frame.resize(417, 0);
+    let path = test_dir.join("song.mp3");
+    fs::write(&path, frame.repeat(10)).unwrap();
+
+    let edit = TagEdit {
+        title: Some("New title".to_string()),
+        artists: Some(vec!["A".to_string(), " B ".to_string(), "".to_string()]),
+        track_no: Some("3/12".to_string()),
+        genre: Some(" ".to_string()),
+        ..Default::default()
+    };
+
+    // Removing a tag which isn't there is not a change
+    let changes = diff_tags(&path, &edit, ";").unwrap();
+    let fields: Vec<TagField> = changes.iter().map(|c| c.field).collect();
+    assert_eq!(
+        fields,
+        vec![TagField::Title, TagField::Artists, TagField::TrackNo]
+    );
+    assert!(changes.iter().all(|c| c.old.is_none()));
+    assert_eq!(changes[1].new.as_deref(), Some("A;B"));
+    assert_eq!(changes[2].new.as_deref(), Some("3"));
+
+    let written = write_tags(&path, &changes, None).unwrap();
+    assert_eq!(written, changes);
+    assert!(diff_tags(&path, &edit, ";").unwrap().is_empty());
+
+    // Writing the inverse changes restores the file
+    let inverse: Vec<_> = written.iter().map(|c| c.inverse()).collect();
+    write_tags(&path, &inverse, None).unwrap();
+    assert_eq!(diff_tags(&path, &edit, ";").unwrap(), changes);
+
+    let invalid = TagEdit {
+        year: Some("soon".to_string()),
+        ..Default::default()
+    };
+    assert!(diff_tags(&path, &invalid, ";").is_err());
+
+    fs::remove_dir_all(&test_dir).unwrap();
+}
+
+#[test]
+fn test_parse_cue() {
+    // Frames are 1/75 of a second
+    assert!((parse_cue_timestamp("03:25:45").unwrap() - 205.6).abs() < 1e-9);
+    assert_eq!(parse_cue_timestamp("00:00:75"), None);
+    assert_eq!(parse_cue_timestamp("1:2"), None);
+
+    let sheet = parse_cue(
+        r#"REM GENRE Rock
+REM DATE 1999
+REM REPLAYGAIN_ALBUM_GAIN -7.50 dB
+PERFORMER "The Band"
+TITLE "Live Album"
+FILE "Live Album.wav" WAVE
+  TRACK 01 AUDIO
+    TITLE "Intro"
+    INDEX 01 00:00:00
+  TRACK 02 MODE1/2352
+    TITLE "Data"
+    INDEX 01 01:00:00
+  TRACK 03 AUDIO
+    TITLE "Song"
+    PERFORMER "Guest; The Band"
+    REM REPLAYGAIN_TRACK_GAIN -6.54 dB
+    INDEX 00 02:00:00
+    INDEX 01 02:01:15
+"#,
+    );
+
+    assert_eq!(sheet.title.as_deref(), Some("Live Album"));
+    assert_eq!(sheet.performer.as_deref(), Some("The Band"));
+    assert_eq!(sheet.genre.as_deref(), Some("Rock"));
+    assert_eq!(sheet.date.as_deref(), Some("1999"));
+    assert_eq!(sheet.album_gain, Some(-7.5));
+    assert_eq!(sheet.files.len(), 1);
+
+    // Data tracks are skipped without their details leaking into the sheet
+    let tracks = &sheet.files[0].tracks;
+    assert_eq!(tracks.len(), 2);
+    assert_eq!(tracks[0].title.as_deref(), Some("Intro"));
+    assert_eq!(tracks[1].number, 3);
+    assert_eq!(tracks[1].performer.as_deref(), Some("Guest; The Band"));
+    assert!((tracks[1].start - 121.2).abs() < 1e-9);
+    assert_eq!(tracks[1].track_gain, Some(-6.54));
+
+    // Sheets which still name the WAV they were ripped to find the converted file
+    let test_dir = env::temp_dir().join("moosync-test-cue");
+    fs::create_dir_all(&test_dir).unwrap();
+    let cue_path = test_dir.join("Live Album.cue");
+    let mut cue = b"TITLE \"Caf\xe9\"\nFILE \"Live Album.wav\" WAVE\n".to_vec();
+    cue.extend_from_slice(b"  TRACK 01 AUDIO\n    INDEX 01 00:00:00\n");
+    fs::write(&cue_path, cue).unwrap();
+    fs::write(test_dir.join("Live Album.flac"), b"").unwrap();
+
+    let sheet = read_cue(&cue_path).unwrap();
+    assert_eq!(sheet.title.as_deref(), Some("Café"));
+    let paths = cue_track_paths(&cue_path, &sheet);
+    assert_eq!(paths.len(), 1);
+    assert_eq!(paths[0].0, test_dir.join("Live Album.flac"));
+    assert!(paths[0].1[0].ends_with("Live Album.flac#1"));
+    assert!(cue_split_files(&test_dir).contains(&test_dir.join("Live Album.flac")));
+
+    let file_list = get_files_recursively(test_dir.clone(), &[]).unwrap();
+    assert_eq!(file_list.cue_list.len(), 1);
+
+    fs::remove_dir_all(&test_dir).unwrap();
+}
+
+#[test]
+fn test_playlist_formats() {
+    let test_dir = env::temp_dir().join("moosync-test-playlist-formats");
+    fs::create_dir_all(&test_dir).unwrap();
+    fs::write(test_dir.join("My Song & Co.mp3"), b"").unwrap();
+    fs::create_dir_all(test_dir.join("sub")).unwrap();
+    fs::write(test_dir.join("sub").join("other.mp3"), b"").unwrap();
+    let song_path = dunce::canonicalize(test_dir.join("My Song & Co.mp3"))
+        .unwrap()
+        .to_string_lossy()
+        .to_string();
+
+    let songs = vec![
+        Song {
+            song: QueryableSong {
+                path: Some(song_path.clone()),
+                title: Some("Title <1>".to_string()),
+                duration: Some(125.4),
+                ..Default::default()
+            },
+            album: Some(QueryableAlbum {
+                album_name: Some("Album".to_string()),
+                ..Default::default()
+            }),
+            artists: Some(vec![
+                QueryableArtist {
+                    artist_name: Some("A".to_string()),
+                    ..Default::default()
+                },
+                QueryableArtist {
+                    artist_name: Some("B".to_string()),
+                    ..Default::default()
+                },
+            ]),
+            genre: None,
+        },
+        Song {
+            song: QueryableSong {
+                type_: SongType::URL,
+                url: Some("https://example.com/stream".to_string()),
+                title: Some("Stream".to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        // Songs without a location can't be written
+        Song::default(),
+    ];
+
+    // Whatever is written can be read back, in every format
+    for extension in ["m3u", "pls", "xspf", "wpl"] {
+        let path = test_dir.join(format!("playlist.{}", extension));
+        let format = playlist_format(&path).unwrap();
+        fs::write(&path, format.write("Mix & Match", &songs).unwrap()).unwrap();
+
+        let imported = read_playlist(&path).unwrap();
+        assert_eq!(imported.songs.len(), 2, "{}", extension);
+
+        let local = &imported.songs[0];
+        assert_eq!(local.song.type_, SongType::LOCAL);
+        assert_eq!(local.song.path.as_deref(), Some(song_path.as_str()));
+        assert_eq!(local.song.title.as_deref(), Some("Title <1>"));
+
+        let stream = &imported.songs[1];
+        assert_eq!(stream.song.type_, SongType::URL);
+        assert_eq!(stream.song.title.as_deref(), Some("Stream"));
+
+        // PLS has no playlist title or artists
+        let artists = local.artists.as_ref().unwrap().len();
+        if extension == "pls" {
+            assert_eq!(imported.playlist.playlist_name, "playlist");
+            assert_eq!(artists, 0);
+            assert_eq!(local.song.duration, Some(125f64));
+        } else {
+            assert_eq!(imported.playlist.playlist_name, "Mix & Match");
+            assert_eq!(artists, 2, "{}", extension);
+        }
+    }
+
+    let file_list = get_files_recursively(test_dir.clone(), &[]).unwrap();
+    assert_eq!(file_list.playlist_list.len(), 4);
+    // Changing any of these in a watched folder rescans the folder
+    assert!(is_playlist_or_cue_file(&test_dir.join("playlist.xspf")));
+    assert!(is_playlist_or_cue_file(Path::new("album.CUE")));
+    assert!(!is_playlist_or_cue_file(&test_dir.join("My Song & Co.mp3")));
+
+    // Files written by other players, with relative paths and missing songs
+    let pls_path = test_dir.join("other.PLS");
+    fs::write(
+        &pls_path,
+        r#"[playlist]
+Title2=Radio
+File2=http://radio.example.com/live
+file1=My Song & Co.mp3
+Length2=-1
+File3=missing.mp3
+NumberOfEntries=3
+"#,
+    )
+    .unwrap();
+    let imported = read_playlist(&pls_path).unwrap();
+    assert_eq!(imported.songs.len(), 2);
+    assert_eq!(
+        imported.songs[0].song.path.as_deref(),
+        Some(song_path.as_str())
+    );
+    assert_eq!(imported.songs[1].song.title.as_deref(), Some("Radio"));
+    assert_eq!(imported.songs[1].song.duration, None);
+
+    let wpl_path = test_dir.join("other.wpl");
+    fs::write(
+        &wpl_path,
+        r#"<?wpl version="1.0"?>
+<smil>
+    <head>
+        <title>Road Trip</title>
+    </head>
+    <body>
+        <seq>
+            <media src="My Song &amp; Co.mp3" trackTitle="Song"/>
+            <media src="missing.mp3"/>
+            <media src="..\moosync-test-playlist-formats\sub\other.mp3"/>
+        </seq>
+    </body>
+</smil>"#,
+    )
+    .unwrap();
+    let imported = read_playlist(&wpl_path).unwrap();
+    assert_eq!(imported.playlist.playlist_name, "Road Trip");
+    assert_eq!(imported.songs.len(), 2);
+    assert_eq!(imported.songs[0].song.title.as_deref(), Some("Song"));
+    // Relative paths with backslashes resolve on every platform
+    let other_path = dunce::canonicalize(test_dir.join("sub").join("other.mp3")).unwrap();
+    assert_eq!(
+        imported.songs[1].song.path.as_deref(),
+        Some(other_path.to_string_lossy().as_ref())
+    );
+
+    fs::remove_dir_all(&test_dir).unwrap();
+}
+
+#[test]
+fn test_m3u_write() {
+    let local = |name: &str| Song {
+        song: QueryableSong {
+            path: Some(format!("/path/to/{}.mp3", name)),
+            title: Some(name.to_string()),
+            duration: Some(60f64),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut songs = vec![local("b"), local("a")];
+    songs[0].album = Some(QueryableAlbum {
+        album_name: Some("Album".to_string()),
+        ..Default::default()
+    });
+    songs[0].genre = Some(vec![
+        QueryableGenre {
+            genre_name: Some("Jazz".to_string()),
+            ..Default::default()
+        },
+        QueryableGenre {
+            genre_name: Some("Blues".to_string()),
+            ..Default::default()
+        },
+    ]);
+    songs.push(Song {
+        song: QueryableSong {
+            type_: SongType::URL,
+            url: Some("https://example.com/stream".to_string()),
+            title: Some("Stream".to_string()),
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+
+    let written = M3u.write("Mix", &songs).unwrap();
+    assert!(written.starts_with("#EXTM3U\n#PLAYLIST:Mix\n"));
+    assert!(written.contains("#EXTINF:60,b\n#EXTALB:Album\n#EXTGENRE:Jazz,Blues\n"));
+    assert!(!written.contains("\n\n"));
+
+    // Local songs are written as file urls, in the order they are given
+    let locations: Vec<&str> = written.lines().filter(|l| !l.starts_with('#')).collect();
+    assert_eq!(
+        locations,
+        vec![
+            "file:///path/to/b.mp3",
+            "file:///path/to/a.mp3",
+            "https://example.com/stream",
+        ]
+    );
+}
+
+#[test]
+fn test_xspf_write() {
+    let remote = |url: &str| Song {
+        song: QueryableSong {
+            type_: SongType::URL,
+            url: Some(url.to_string()),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let songs = vec![
+        Song {
+            song: QueryableSong {
+                path: Some("/path/to/a b.mp3".to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        remote("https://example.com/stream?a=1&b=2"),
+        remote("spotify:track:abc"),
+    ];
+
+    // Only local songs are written as file urls
+    let written = Xspf.write("Mix", &songs).unwrap();
+    let locations: Vec<&str> = written
+        .lines()
+        .filter_map(|l| {
+            l.trim()
+                .strip_prefix("<location>")?
+                .strip_suffix("</location>")
+        })
+        .collect();
+    assert_eq!(
+        locations,
+        vec![
+            "file:///path/to/a%20b.mp3",
+            "https://example.com/stream?a=1&amp;b=2",
+            "spotify:track:abc",
+        ]
+    );
+}
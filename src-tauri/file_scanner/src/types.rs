@@ -16,8 +16,9 @@
 
 use std::path::PathBuf;
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct FileList {
     pub file_list: Vec<(PathBuf, f64)>,
     pub playlist_list: Vec<PathBuf>,
+    pub cue_list: Vec<PathBuf>,
 }
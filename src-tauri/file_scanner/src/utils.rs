@@ -16,27 +16,43 @@
 
 use lazy_static::lazy_static;
 use lofty::{
-    picture::Picture, prelude::Accessor, prelude::AudioFile, prelude::TaggedFileExt, probe::Probe,
+    config::ParseOptions,
+    file::{FileType, TaggedFile},
+    id3::v2::{Frame, FrameId},
+    mpeg::MpegFile,
+    picture::Picture,
+    prelude::Accessor,
+    prelude::AudioFile,
+    prelude::ItemKey,
+    prelude::TaggedFileExt,
+    probe::Probe,
     read_from_path,
 };
 use regex::Regex;
 use std::{
-    f64, fs,
+    borrow::Cow,
+    f64,
+    fs::{self, File},
+    io::{Read, Seek, SeekFrom},
     num::NonZeroU32,
     path::{Path, PathBuf},
+    time::UNIX_EPOCH,
 };
 use types::{
-    entities::{QueryableAlbum, QueryableArtist, QueryableGenre},
+    entities::{QueryableAlbum, QueryableArtist, QueryableGenre, VARIOUS_ARTISTS},
+    lyrics::{Lyrics, LyricsLine, LyricsWord},
     songs::{QueryableSong, Song, SongType},
 };
 use uuid::Uuid;
 
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
 use image::ColorType;
 use types::errors::Result;
 
 use fast_image_resize::{self as fr, ResizeOptions};
 
-use crate::types::FileList;
+use crate::{playlist_formats::playlist_format, types::FileList};
 
 use types::errors::error_helpers;
 
@@ -49,61 +65,366 @@ pub fn check_directory(dir: PathBuf) -> Result<()> {
     Ok(())
 }
 
+pub(crate) const IGNORE_FILE_NAME: &str = ".moosyncignore";
+
+lazy_static! {
+    static ref SONG_RE: Regex = Regex::new("flac|mp3|ogg|m4a|webm|wav|wv|aac|opus").unwrap();
+    static ref CUE_RE: Regex = Regex::new("(?i)^cue$").unwrap();
+}
+
+#[tracing::instrument(level = "debug", skip(path))]
+fn normalize_path(path: &Path) -> PathBuf {
+    dunce::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
 #[tracing::instrument(level = "debug", skip(dir))]
-pub fn get_files_recursively(dir: PathBuf) -> Result<FileList> {
-    let mut file_list: Vec<(PathBuf, f64)> = vec![];
-    let mut playlist_list: Vec<PathBuf> = vec![];
+fn load_ignore_file(dir: &Path) -> Option<Gitignore> {
+    let ignore_file = dir.join(IGNORE_FILE_NAME);
+    if !ignore_file.is_file() {
+        return None;
+    }
 
-    lazy_static! {
-        static ref SONG_RE: Regex = Regex::new("flac|mp3|ogg|m4a|webm|wav|wv|aac|opus").unwrap();
-        static ref PLAYLIST_RE: Regex = Regex::new("m3u|m3u8").unwrap();
+    let mut builder = GitignoreBuilder::new(dir);
+    if let Some(e) = builder.add(&ignore_file) {
+        tracing::warn!("Failed to parse {}: {:?}", ignore_file.display(), e);
     }
 
-    if !dir.exists() {
-        return Ok(FileList {
-            file_list,
-            playlist_list,
-        });
+    match builder.build() {
+        Ok(gitignore) => Some(gitignore),
+        Err(e) => {
+            tracing::warn!("Failed to parse {}: {:?}", ignore_file.display(), e);
+            None
+        }
     }
+}
 
-    if dir.is_file() {
-        if let Ok(metadata) = fs::metadata(&dir) {
-            let extension = dir
-                .extension()
-                .unwrap_or_default()
-                .to_str()
-                .unwrap_or_default();
-            if !extension.is_empty() {
-                if SONG_RE.is_match(extension) {
-                    file_list.push((dir.clone(), metadata.len() as f64));
-                }
+// Rules from deeper ignore files take precedence, same as git
+#[tracing::instrument(level = "debug", skip(ignores, path, is_dir))]
+fn is_ignored(ignores: &[Gitignore], path: &Path, is_dir: bool) -> bool {
+    for gitignore in ignores.iter().rev() {
+        match gitignore.matched(path, is_dir) {
+            Match::Ignore(_) => return true,
+            Match::Whitelist(_) => return false,
+            Match::None => {}
+        }
+    }
+    false
+}
 
-                if PLAYLIST_RE.is_match(extension) {
-                    playlist_list.push(dir);
-                }
-            }
-            return Ok(FileList {
-                file_list,
-                playlist_list,
-            });
+#[tracing::instrument(level = "debug", skip(path, exclude_paths))]
+pub fn is_excluded(path: &Path, exclude_paths: &[PathBuf]) -> bool {
+    exclude_paths.iter().any(|e| path.starts_with(e))
+}
+
+#[tracing::instrument(level = "debug", skip(path, size, files))]
+fn push_file(path: PathBuf, size: u64, files: &mut FileList) {
+    let extension = path
+        .extension()
+        .unwrap_or_default()
+        .to_str()
+        .unwrap_or_default();
+    if !extension.is_empty() {
+        if SONG_RE.is_match(extension) {
+            files.file_list.push((path.clone(), size as f64));
+        }
+
+        if playlist_format(&path).is_some() {
+            files.playlist_list.push(path.clone());
+        }
+
+        if CUE_RE.is_match(extension) {
+            files.cue_list.push(path);
         }
     }
+}
 
-    let dir_entries = fs::read_dir(dir)?;
+#[tracing::instrument(level = "debug", skip(dir, exclude_paths, ignores, files))]
+fn walk_dir(
+    dir: &Path,
+    exclude_paths: &[PathBuf],
+    ignores: &mut Vec<Gitignore>,
+    files: &mut FileList,
+) -> Result<()> {
+    let ignore_file = load_ignore_file(dir);
+    let has_ignore_file = ignore_file.is_some();
+    if let Some(ignore_file) = ignore_file {
+        ignores.push(ignore_file);
+    }
 
+    let dir_entries = fs::read_dir(dir)?;
     for entry in dir_entries {
         let Ok(entry) = entry else { continue };
         let path = entry.path();
+        let Ok(metadata) = fs::metadata(&path) else {
+            continue;
+        };
+
+        if is_excluded(&path, exclude_paths) || is_ignored(ignores, &path, metadata.is_dir()) {
+            tracing::debug!("Skipping {}", path.display());
+            continue;
+        }
 
-        let res = get_files_recursively(path)?;
-        file_list.extend_from_slice(&res.file_list);
-        playlist_list.extend_from_slice(&res.playlist_list);
+        if metadata.is_dir() {
+            walk_dir(&path, exclude_paths, ignores, files)?;
+        } else {
+            push_file(path, metadata.len(), files);
+        }
     }
 
-    Ok(FileList {
-        file_list,
-        playlist_list,
-    })
+    if has_ignore_file {
+        ignores.pop();
+    }
+
+    Ok(())
+}
+
+/// Checks if `path` (somewhere inside `root`) is matched by any `.moosyncignore`
+/// between `root` and the directory containing `path`
+#[tracing::instrument(level = "debug", skip(root, path, is_dir))]
+pub fn is_path_ignored(root: &Path, path: &Path, is_dir: bool) -> bool {
+    let Ok(relative) = path.strip_prefix(root) else {
+        return false;
+    };
+
+    let mut ignores = vec![];
+    let mut current = root.to_path_buf();
+    if let Some(ignore_file) = load_ignore_file(&current) {
+        ignores.push(ignore_file);
+    }
+
+    let mut components = relative.components().peekable();
+    while let Some(component) = components.next() {
+        current.push(component);
+        let is_last = components.peek().is_none();
+        if is_ignored(&ignores, &current, !is_last || is_dir) {
+            return true;
+        }
+
+        if !is_last {
+            if let Some(ignore_file) = load_ignore_file(&current) {
+                ignores.push(ignore_file);
+            }
+        }
+    }
+
+    false
+}
+
+#[tracing::instrument(level = "debug", skip(path))]
+pub fn is_song_file(path: &Path) -> bool {
+    let extension = path
+        .extension()
+        .unwrap_or_default()
+        .to_str()
+        .unwrap_or_default();
+    !extension.is_empty() && SONG_RE.is_match(extension)
+}
+
+/// Playlists and CUE sheets, which are imported by scanning the directory they are in
+#[tracing::instrument(level = "debug", skip(path))]
+pub fn is_playlist_or_cue_file(path: &Path) -> bool {
+    playlist_format(path).is_some()
+        || path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| CUE_RE.is_match(e))
+}
+
+/// Inode and device number of a file, used to follow files across moves
+#[cfg(unix)]
+#[tracing::instrument(level = "debug", skip(path))]
+pub fn file_identity(path: &Path) -> (Option<String>, Option<String>) {
+    use std::os::unix::fs::MetadataExt;
+    match fs::metadata(path) {
+        Ok(metadata) => (
+            Some(metadata.ino().to_string()),
+            Some(metadata.dev().to_string()),
+        ),
+        Err(_) => (None, None),
+    }
+}
+
+#[cfg(not(unix))]
+#[tracing::instrument(level = "debug", skip(_path))]
+pub fn file_identity(_path: &Path) -> (Option<String>, Option<String>) {
+    (None, None)
+}
+
+/// Text files written by older tools, like CUE sheets and playlists, are often not UTF-8.
+/// Those are read as Latin-1
+#[tracing::instrument(level = "debug", skip(data))]
+pub(crate) fn decode_text(data: &[u8]) -> String {
+    let data = data.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(data);
+    match std::str::from_utf8(data) {
+        Ok(content) => content.to_string(),
+        Err(_) => data.iter().map(|b| *b as char).collect(),
+    }
+}
+
+#[tracing::instrument(level = "debug", skip(path))]
+pub fn file_mtime(path: &Path) -> Option<f64> {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs_f64())
+}
+
+#[tracing::instrument(level = "debug", skip(data))]
+fn syncsafe_u32(data: &[u8]) -> u64 {
+    data.iter().fold(0u64, |acc, b| (acc << 7) | (*b as u64 & 0x7f))
+}
+
+/// Byte range of `file` holding the audio stream. ID3v2 tags and FLAC metadata blocks
+/// at the start as well as ID3v1 and APEv2 tags at the end are left out, so editing
+/// tags doesn't change the range's contents.
+#[tracing::instrument(level = "debug", skip(file, len))]
+pub(crate) fn get_audio_range(file: &mut File, len: u64) -> Result<(u64, u64)> {
+    let mut start = 0u64;
+    let mut end = len;
+
+    let mut header = [0u8; 10];
+    loop {
+        file.seek(SeekFrom::Start(start))?;
+        if start + 10 > end || file.read_exact(&mut header).is_err() {
+            break;
+        }
+
+        if &header[..3] == b"ID3" {
+            let footer = if header[5] & 0x10 != 0 { 10 } else { 0 };
+            start += 10 + footer + syncsafe_u32(&header[6..10]);
+            continue;
+        }
+
+        if &header[..4] == b"fLaC" {
+            start += 4;
+            let mut block_header = [0u8; 4];
+            loop {
+                file.seek(SeekFrom::Start(start))?;
+                if start + 4 > end || file.read_exact(&mut block_header).is_err() {
+                    break;
+                }
+                let block_len =
+                    u32::from_be_bytes([0, block_header[1], block_header[2], block_header[3]]);
+                start += 4 + block_len as u64;
+                if block_header[0] & 0x80 != 0 {
+                    break;
+                }
+            }
+        }
+        break;
+    }
+
+    let mut trailer = [0u8; 32];
+    loop {
+        if end >= start + 128 {
+            file.seek(SeekFrom::Start(end - 128))?;
+            file.read_exact(&mut trailer[..3])?;
+            if &trailer[..3] == b"TAG" {
+                end -= 128;
+                continue;
+            }
+        }
+
+        if end >= start + 32 {
+            file.seek(SeekFrom::Start(end - 32))?;
+            file.read_exact(&mut trailer)?;
+            if &trailer[..8] == b"APETAGEX" {
+                let size =
+                    u32::from_le_bytes([trailer[12], trailer[13], trailer[14], trailer[15]]) as u64;
+                let flags =
+                    u32::from_le_bytes([trailer[20], trailer[21], trailer[22], trailer[23]]);
+                let header = if flags & 0x8000_0000 != 0 { 32 } else { 0 };
+                end = end.saturating_sub(size + header).max(start);
+                continue;
+            }
+        }
+        break;
+    }
+
+    Ok((start.min(end), end))
+}
+
+/// Hash of the audio stream of a file, ignoring its tags. Copies of a song
+/// with different tags still get the same hash.
+#[tracing::instrument(level = "debug", skip(path))]
+pub fn hash_audio(path: &Path) -> Result<String> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    let (start, end) = get_audio_range(&mut file, len)?;
+
+    file.seek(SeekFrom::Start(start))?;
+    let mut reader = file.take(end - start);
+    let mut hasher = blake3::Hasher::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let read = reader.read(&mut buf)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buf[..read]);
+    }
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum FileStatus {
+    New,
+    Unchanged,
+    Modified,
+}
+
+/// Compares a file on disk with the state stored for it in the library
+#[tracing::instrument(level = "debug", skip(path, size, known))]
+pub(crate) fn get_file_status(path: &Path, size: f64, known: Option<&QueryableSong>) -> FileStatus {
+    let Some(known) = known else {
+        return FileStatus::New;
+    };
+
+    // Files without a hash (CUE tracks, unreadable audio) are compared by size and mtime alone,
+    // so they aren't rescanned every time
+    if known.size != Some(size) {
+        return FileStatus::Modified;
+    }
+
+    let mtime = file_mtime(path);
+    if mtime.is_some() && known.mtime == mtime {
+        return FileStatus::Unchanged;
+    }
+
+    FileStatus::Modified
+}
+
+/// Lists all songs, playlists and CUE sheets under `dir`. Anything inside `exclude_paths`
+/// or matched by a `.moosyncignore` file (gitignore syntax) is skipped.
+#[tracing::instrument(level = "debug", skip(dir, exclude_paths))]
+pub fn get_files_recursively(dir: PathBuf, exclude_paths: &[PathBuf]) -> Result<FileList> {
+    let mut files = FileList::default();
+
+    if !dir.exists() {
+        return Ok(files);
+    }
+
+    let dir = normalize_path(&dir);
+    let exclude_paths = exclude_paths
+        .iter()
+        .map(|p| normalize_path(p))
+        .collect::<Vec<_>>();
+
+    if is_excluded(&dir, &exclude_paths) {
+        return Ok(files);
+    }
+
+    if dir.is_file() {
+        if let Ok(metadata) = fs::metadata(&dir) {
+            push_file(dir, metadata.len(), &mut files);
+        }
+        return Ok(files);
+    }
+
+    walk_dir(&dir, &exclude_paths, &mut vec![], &mut files)?;
+
+    Ok(files)
 }
 
 #[tracing::instrument(level = "debug", skip(data, path, dimensions))]
@@ -175,33 +496,314 @@ fn store_picture(thumbnail_dir: &Path, picture: &Picture) -> Result<(PathBuf, Pa
 }
 
 #[tracing::instrument(level = "debug", skip(path))]
-fn scan_lrc(mut path: PathBuf) -> Option<String> {
+pub(crate) fn scan_lrc(mut path: PathBuf) -> Option<Lyrics> {
     path.set_extension("lrc");
     if path.exists() {
-        lazy_static! {
-            static ref LRC_REGEX: Regex = Regex::new(r"\[\d{2}:\d{2}.\d{2}\]").unwrap();
-        }
-
         let data = fs::read(path);
         if data.is_err() {
             return None;
         }
 
-        let mut parsed_lyrics = "".to_string();
-        let parsed = String::from_utf8_lossy(&data.unwrap()).to_string();
-        for line in parsed.split('\n') {
-            if LRC_REGEX.is_match(line) {
-                parsed_lyrics.push_str(&LRC_REGEX.replace_all(line, ""));
-                parsed_lyrics.push('\n');
+        let parsed = Lyrics::parse(&String::from_utf8_lossy(&data.unwrap()));
+        if !parsed.is_empty() {
+            return Some(parsed);
+        }
+    }
+
+    None
+}
+
+#[tracing::instrument(level = "debug", skip(data, encoding))]
+fn read_sylt_string<'a>(data: &'a [u8], encoding: u8) -> Option<(String, &'a [u8])> {
+    if encoding == 1 || encoding == 2 {
+        let end = data.chunks_exact(2).position(|c| c == [0, 0])? * 2;
+        let mut units = data[..end]
+            .chunks_exact(2)
+            .map(|c| [c[0], c[1]])
+            .collect::<Vec<_>>();
+
+        // Encoding 1 carries a BOM per string, encoding 2 is always big endian
+        let mut little_endian = false;
+        if let Some(bom) = units.first() {
+            if *bom == [0xFF, 0xFE] || *bom == [0xFE, 0xFF] {
+                little_endian = *bom == [0xFF, 0xFE];
+                units.remove(0);
+            }
+        }
+
+        let units = units
+            .into_iter()
+            .map(|c| {
+                if little_endian {
+                    u16::from_le_bytes(c)
+                } else {
+                    u16::from_be_bytes(c)
+                }
+            })
+            .collect::<Vec<_>>();
+        return Some((String::from_utf16_lossy(&units), &data[end + 2..]));
+    }
+
+    let end = data.iter().position(|b| *b == 0)?;
+    let text = if encoding == 3 {
+        String::from_utf8_lossy(&data[..end]).to_string()
+    } else {
+        data[..end].iter().map(|b| *b as char).collect()
+    };
+    Some((text, &data[end + 1..]))
+}
+
+/// Parses the body of an ID3v2 SYLT frame. Only millisecond timestamps are supported.
+#[tracing::instrument(level = "debug", skip(data))]
+pub(crate) fn parse_sylt(data: &[u8]) -> Option<Lyrics> {
+    if data.len() < 6 {
+        return None;
+    }
+
+    let encoding = data[0];
+    let timestamp_format = data[4];
+    if timestamp_format != 2 {
+        tracing::debug!("Unsupported SYLT timestamp format {}", timestamp_format);
+        return None;
+    }
+
+    // Skip the content descriptor
+    let (_, mut rest) = read_sylt_string(&data[6..], encoding)?;
+
+    let mut entries = vec![];
+    while !rest.is_empty() {
+        let Some((text, remaining)) = read_sylt_string(rest, encoding) else {
+            break;
+        };
+        if remaining.len() < 4 {
+            break;
+        }
+        let time = u32::from_be_bytes([remaining[0], remaining[1], remaining[2], remaining[3]]);
+        entries.push((time as u64, text));
+        rest = &remaining[4..];
+    }
+
+    if entries.is_empty() {
+        return None;
+    }
+
+    // Some taggers store one entry per syllable and mark new lines with a leading line break
+    let has_line_breaks = entries
+        .iter()
+        .skip(1)
+        .any(|(_, text)| text.starts_with(['\n', '\r']));
+
+    let mut lines: Vec<LyricsLine> = vec![];
+    for (time, text) in entries {
+        let starts_line = text.starts_with(['\n', '\r']);
+        let text = text.trim_start_matches(['\n', '\r']).to_string();
+        if has_line_breaks && !starts_line {
+            if let Some(line) = lines.last_mut() {
+                line.text.push_str(&text);
+                line.words.push(LyricsWord {
+                    start_ms: time,
+                    end_ms: None,
+                    text,
+                });
+                continue;
             }
         }
 
-        return Some(parsed_lyrics);
+        lines.push(LyricsLine {
+            start_ms: time,
+            end_ms: None,
+            text: text.clone(),
+            words: if has_line_breaks {
+                vec![LyricsWord {
+                    start_ms: time,
+                    end_ms: None,
+                    text,
+                }]
+            } else {
+                vec![]
+            },
+        });
+    }
+
+    for line in lines.iter_mut() {
+        let starts = line.words.iter().skip(1).map(|w| w.start_ms).collect::<Vec<_>>();
+        for (word, next_start) in line.words.iter_mut().zip(starts) {
+            word.end_ms = Some(next_start);
+        }
+        if line.words.len() <= 1 {
+            line.words.clear();
+        }
+    }
+
+    Some(Lyrics::from_lines(lines))
+}
+
+#[tracing::instrument(level = "debug", skip(path))]
+fn scan_sylt(path: &Path) -> Option<Lyrics> {
+    let mut file = File::open(path).ok()?;
+    let mpeg_file = MpegFile::read_from(&mut file, ParseOptions::new()).ok()?;
+    let frame = mpeg_file
+        .id3v2()?
+        .get(&FrameId::Valid(Cow::Borrowed("SYLT")))?;
+
+    if let Frame::Binary(binary_frame) = frame {
+        return parse_sylt(&binary_frame.data);
     }
 
     None
 }
 
+/// Parses ReplayGain gains like "-6.54 dB" and peaks like "0.988159"
+#[tracing::instrument(level = "debug", skip())]
+pub fn parse_replaygain(value: &str) -> Option<f64> {
+    let value = value.trim();
+    let value = value
+        .strip_suffix("dB")
+        .or_else(|| value.strip_suffix("db"))
+        .unwrap_or(value);
+    value.trim().parse::<f64>().ok().filter(|v| v.is_finite())
+}
+
+/// Converts an R128 gain, stored by Opus files as Q7.8 fixed point dB relative to
+/// -23 LUFS, to a ReplayGain gain relative to -18 LUFS
+#[tracing::instrument(level = "debug", skip())]
+pub fn parse_r128_gain(value: &str) -> Option<f64> {
+    let value: i16 = value.trim().parse().ok()?;
+    Some(value as f64 / 256f64 + 5f64)
+}
+
+/// Reads ReplayGain values from any tag of the file, since some formats
+/// keep them outside the primary tag (like APE tags in MP3 files)
+#[tracing::instrument(level = "debug", skip(file, song))]
+fn read_replaygain(file: &TaggedFile, song: &mut QueryableSong) {
+    let find = |key: ItemKey| {
+        file.tags()
+            .iter()
+            .find_map(|t| t.get_string(&key).and_then(parse_replaygain))
+    };
+    let find_r128 = |key: &str| {
+        file.tags().iter().find_map(|t| {
+            t.get_string(&ItemKey::Unknown(key.to_string()))
+                .and_then(parse_r128_gain)
+        })
+    };
+
+    song.track_gain = find(ItemKey::ReplayGainTrackGain).or_else(|| find_r128("R128_TRACK_GAIN"));
+    song.track_peak = find(ItemKey::ReplayGainTrackPeak);
+    song.album_gain = find(ItemKey::ReplayGainAlbumGain).or_else(|| find_r128("R128_ALBUM_GAIN"));
+    song.album_peak = find(ItemKey::ReplayGainAlbumPeak);
+}
+
+/// Keys used for the MusicBrainz release type by the different tag formats
+const RELEASE_TYPE_KEYS: [&str; 3] = [
+    "RELEASETYPE",
+    "MUSICBRAINZ_ALBUMTYPE",
+    "MusicBrainz Album Type",
+];
+
+/// Finds the first non-empty value of `key` in any tag of the file
+#[tracing::instrument(level = "debug", skip(file, key))]
+fn find_tag(file: &TaggedFile, key: &ItemKey) -> Option<String> {
+    file.tags().iter().find_map(|t| {
+        t.get_string(key)
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(str::to_string)
+    })
+}
+
+/// Reads MusicBrainz artist IDs, which may be stored as separate values or joined in one
+#[tracing::instrument(level = "debug", skip(file))]
+fn find_artist_mbids(file: &TaggedFile) -> Vec<String> {
+    file.tags()
+        .iter()
+        .map(|t| {
+            t.get_strings(&ItemKey::MusicBrainzArtistId)
+                .flat_map(|v| v.split(['/', ';', '\0']))
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        })
+        .find(|ids| !ids.is_empty())
+        .unwrap_or_default()
+}
+
+/// Pairs MusicBrainz artist IDs with the track artists they were tagged for. IDs are only
+/// assigned when there is one for every artist, since they can't be matched up otherwise
+#[tracing::instrument(level = "debug", skip(artists, ids))]
+pub fn assign_artist_mbids(artists: &mut [QueryableArtist], ids: &[String]) {
+    if ids.len() != artists.len() {
+        return;
+    }
+
+    for (artist, id) in artists.iter_mut().zip(ids) {
+        artist.artist_mbid = Some(id.clone());
+    }
+}
+
+/// Reads MusicBrainz IDs and other extended tags from any tag of the file
+#[tracing::instrument(level = "debug", skip(file, song))]
+fn read_extended_tags(file: &TaggedFile, song: &mut QueryableSong) {
+    song.recording_mbid = find_tag(file, &ItemKey::MusicBrainzRecordingId);
+    song.isrc = find_tag(file, &ItemKey::Isrc);
+    song.original_date = find_tag(file, &ItemKey::OriginalReleaseDate);
+    song.label = find_tag(file, &ItemKey::Label);
+    song.catalog_number = find_tag(file, &ItemKey::CatalogNumber);
+    song.composer = find_tag(file, &ItemKey::Composer);
+    song.bpm = find_tag(file, &ItemKey::Bpm)
+        .or_else(|| find_tag(file, &ItemKey::IntegerBpm))
+        .and_then(|v| v.parse().ok());
+    song.release_type = RELEASE_TYPE_KEYS
+        .iter()
+        .find_map(|key| find_tag(file, &ItemKey::Unknown(key.to_string())));
+}
+
+/// Reads a boolean tag like the compilation flag, which may be stored as "1" or "true"
+#[tracing::instrument(level = "debug", skip())]
+pub fn parse_flag(value: &str) -> bool {
+    let value = value.trim();
+    value == "1" || value.eq_ignore_ascii_case("true")
+}
+
+/// Picks the artist an album is filed under, and whether it is a compilation.
+/// Compilations are grouped under "Various Artists" while other albums without an
+/// album artist tag fall back to their first track artist
+#[tracing::instrument(level = "debug", skip(artists))]
+pub fn resolve_album_artist(
+    album_artist: Option<&str>,
+    compilation: bool,
+    artists: &[QueryableArtist],
+) -> (Option<String>, bool) {
+    let album_artist = album_artist.map(str::trim).filter(|a| !a.is_empty());
+    let compilation =
+        compilation || album_artist.is_some_and(|a| a.eq_ignore_ascii_case(VARIOUS_ARTISTS));
+
+    if compilation {
+        return (Some(VARIOUS_ARTISTS.to_string()), true);
+    }
+
+    let album_artist = album_artist
+        .map(|a| a.to_string())
+        .or_else(|| artists.first().and_then(|a| a.artist_name.clone()));
+    (album_artist, false)
+}
+
+/// Scans a single file, retrying with a guessed file type if probing fails
+#[tracing::instrument(level = "debug", skip(path, thumbnail_dir, artist_split))]
+pub fn scan_song(path: &PathBuf, thumbnail_dir: &Path, artist_split: &str) -> Result<Song> {
+    let size = fs::metadata(path)?.len() as f64;
+    let mut metadata = scan_file(path, thumbnail_dir, size, false, artist_split);
+    if metadata.is_err() {
+        metadata = scan_file(path, thumbnail_dir, size, true, artist_split);
+    }
+
+    if let Ok(song) = metadata.as_mut() {
+        song.song.hash = hash_audio(path).ok();
+    }
+    metadata
+}
+
 #[tracing::instrument(level = "debug", skip(path, thumbnail_dir, size, guess, artist_split))]
 pub fn scan_file(
     path: &PathBuf,
@@ -222,6 +824,8 @@ pub fn scan_file(
     song.song.size = Some(size);
     song.song.duration = Some(0f64);
     song.song.type_ = SongType::LOCAL;
+    (song.song.inode, song.song.deviceno) = file_identity(path);
+    song.song.mtime = file_mtime(path);
 
     let file = if guess {
         read_from_path(path.clone())
@@ -247,6 +851,8 @@ pub fn scan_file(
     song.song.bitrate = Some((properties.audio_bitrate().unwrap_or_default() * 1000) as f64);
     song.song.sample_rate = properties.sample_rate().map(|v| v as f64);
     song.song.duration = Some(properties.duration().as_secs() as f64);
+    read_replaygain(&file, &mut song.song);
+    read_extended_tags(&file, &mut song.song);
 
     if tags.is_some() {
         let metadata = tags.unwrap();
@@ -285,12 +891,19 @@ pub fn scan_file(
             }
         }
 
+        // Prefer time-coded lyrics, whether they come from the tags, a SYLT frame or a sidecar .lrc
         let mut lyrics = metadata
             .get_string(&lofty::prelude::ItemKey::Lyrics)
-            .map(str::to_string);
-
-        if lyrics.is_none() {
-            lyrics = scan_lrc(path.clone());
+            .map(Lyrics::parse)
+            .filter(|l| !l.is_empty());
+
+        if !lyrics.as_ref().is_some_and(|l| l.is_synced()) {
+            let synced = if file.file_type() == FileType::Mpeg {
+                scan_sylt(path)
+            } else {
+                None
+            };
+            lyrics = synced.or_else(|| scan_lrc(path.clone())).or(lyrics);
         }
 
         song.song.title = metadata
@@ -298,7 +911,7 @@ pub fn scan_file(
             .map(|s| s.to_string())
             .or(path.file_name().map(|s| s.to_string_lossy().to_string()));
         // song.album = metadata.album().map(|s| s.to_string());
-        let artists: Option<Vec<QueryableArtist>> = metadata.artist().map(|s| {
+        let mut artists: Option<Vec<QueryableArtist>> = metadata.artist().map(|s| {
             s.split(artist_split)
                 .map(|s| QueryableArtist {
                     artist_id: Some(Uuid::new_v4().to_string()),
@@ -307,6 +920,13 @@ pub fn scan_file(
                 })
                 .collect()
         });
+        if let Some(artists) = artists.as_mut() {
+            assign_artist_mbids(artists, &find_artist_mbids(&file));
+        }
+
+        song.song.year = metadata.year().map(|s| s.to_string());
+        song.song.disc_no = metadata.disk().map(|v| v as f64);
+        song.song.disc_total = metadata.disk_total().map(|v| v as f64);
 
         let album = metadata.album();
         if album.is_some() {
@@ -314,28 +934,38 @@ pub fn scan_file(
                 .get_string(&lofty::prelude::ItemKey::TrackNumber)
                 .map(|s| s.parse().unwrap_or_default());
 
+            let (album_artist, compilation) = resolve_album_artist(
+                metadata.get_string(&lofty::prelude::ItemKey::AlbumArtist),
+                metadata
+                    .get_string(&lofty::prelude::ItemKey::FlagCompilation)
+                    .is_some_and(parse_flag),
+                artists.as_deref().unwrap_or_default(),
+            );
+            song.song.compilation = Some(compilation);
+
             song.album = Some(QueryableAlbum {
                 album_id: Some(Uuid::new_v4().to_string()),
                 album_name: album.map(|v| v.to_string()),
                 album_coverpath_high: song.song.song_cover_path_high.clone(),
                 album_coverpath_low: song.song.song_cover_path_low.clone(),
-                album_artist: metadata
-                    .get_string(&lofty::prelude::ItemKey::AlbumArtist)
-                    .map(|s| s.to_owned()),
+                album_artist,
+                year: song.song.year.clone(),
+                disc_total: song.song.disc_total,
+                compilation: Some(compilation),
+                album_mbid: find_tag(&file, &ItemKey::MusicBrainzReleaseId),
                 ..Default::default()
             })
         }
 
         song.artists = artists;
 
-        song.song.year = metadata.year().map(|s| s.to_string());
         song.genre = metadata.genre().map(|s| {
             vec![QueryableGenre {
                 genre_name: Some(s.to_string()),
                 ..Default::default()
             }]
         });
-        song.song.lyrics = lyrics;
+        song.song.lyrics = lyrics.map(|l| l.to_lrc());
     }
 
     Ok(song)
@@ -0,0 +1,306 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, RecvTimeoutError, Sender},
+    thread,
+    time::Duration,
+};
+
+use notify::{
+    event::{ModifyKind, RenameMode},
+    Event, EventKind, RecursiveMode, Watcher,
+};
+use types::errors::{error_helpers, Result};
+
+use crate::utils::{is_excluded, is_path_ignored, is_playlist_or_cue_file, is_song_file};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WatchEvent {
+    Created(PathBuf),
+    Modified(PathBuf),
+    Removed(PathBuf),
+    Renamed(PathBuf, PathBuf),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PendingChange {
+    Created,
+    Modified,
+    Removed,
+}
+
+/// Collects raw filesystem events and coalesces them per path, so that
+/// an editor saving a file or a copy in progress results in a single change.
+#[derive(Debug, Default)]
+pub struct Debouncer {
+    renames: Vec<(PathBuf, PathBuf)>,
+    changes: HashMap<PathBuf, PendingChange>,
+    order: Vec<PathBuf>,
+}
+
+impl Debouncer {
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn is_empty(&self) -> bool {
+        self.renames.is_empty() && self.changes.is_empty()
+    }
+
+    #[tracing::instrument(level = "debug", skip(self, path, change))]
+    fn push_change(&mut self, path: PathBuf, change: PendingChange) {
+        let merged = match (self.changes.get(&path).copied(), change) {
+            (None, change) => Some(change),
+            (Some(PendingChange::Created), PendingChange::Modified) => Some(PendingChange::Created),
+            (Some(PendingChange::Created), PendingChange::Removed) => None,
+            (Some(PendingChange::Removed), PendingChange::Created) => Some(PendingChange::Modified),
+            (Some(_), change) => Some(change),
+        };
+
+        match merged {
+            Some(merged) => {
+                if self.changes.insert(path.clone(), merged).is_none() {
+                    self.order.push(path);
+                }
+            }
+            None => {
+                self.changes.remove(&path);
+                self.order.retain(|p| p != &path);
+            }
+        }
+    }
+
+    #[tracing::instrument(level = "debug", skip(self, from, to))]
+    fn push_rename(&mut self, from: PathBuf, to: PathBuf) {
+        // A file created and moved within the same window is simply a new file
+        if self.changes.get(&from) == Some(&PendingChange::Created) {
+            self.push_change(from, PendingChange::Removed);
+            self.push_change(to, PendingChange::Created);
+            return;
+        }
+
+        // Follow chained renames (a -> b -> c)
+        if let Some(rename) = self.renames.iter_mut().find(|(_, t)| t == &from) {
+            rename.1 = to;
+            return;
+        }
+
+        self.renames.push((from, to));
+    }
+
+    #[tracing::instrument(level = "debug", skip(self, event))]
+    pub fn push(&mut self, event: Event) {
+        let mut paths = event.paths.into_iter();
+        match event.kind {
+            EventKind::Create(_) => paths.for_each(|p| self.push_change(p, PendingChange::Created)),
+            EventKind::Remove(_) => paths.for_each(|p| self.push_change(p, PendingChange::Removed)),
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => {
+                if let (Some(from), Some(to)) = (paths.next(), paths.next()) {
+                    self.push_rename(from, to);
+                }
+            }
+            EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+                paths.for_each(|p| self.push_change(p, PendingChange::Removed))
+            }
+            EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
+                paths.for_each(|p| self.push_change(p, PendingChange::Created))
+            }
+            // Backends which can't pair renames only tell us that something happened to the path
+            EventKind::Modify(ModifyKind::Name(_)) => paths.for_each(|p| {
+                let change = if p.exists() {
+                    PendingChange::Created
+                } else {
+                    PendingChange::Removed
+                };
+                self.push_change(p, change)
+            }),
+            EventKind::Modify(_) => {
+                paths.for_each(|p| self.push_change(p, PendingChange::Modified))
+            }
+            EventKind::Access(_) | EventKind::Any | EventKind::Other => {}
+        }
+    }
+
+    /// Drains all pending changes. Renames come first, followed by removals
+    /// so that moved files can be matched against the songs that disappeared.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn flush(&mut self) -> Vec<WatchEvent> {
+        let mut ret: Vec<WatchEvent> = self
+            .renames
+            .drain(..)
+            .map(|(from, to)| WatchEvent::Renamed(from, to))
+            .collect();
+
+        let order = std::mem::take(&mut self.order);
+        let mut changes = std::mem::take(&mut self.changes);
+        for change in [
+            PendingChange::Removed,
+            PendingChange::Created,
+            PendingChange::Modified,
+        ] {
+            for path in order.iter() {
+                if changes.get(path) == Some(&change) {
+                    let path = changes.remove_entry(path).unwrap().0;
+                    ret.push(match change {
+                        PendingChange::Created => WatchEvent::Created(path),
+                        PendingChange::Modified => WatchEvent::Modified(path),
+                        PendingChange::Removed => WatchEvent::Removed(path),
+                    });
+                }
+            }
+        }
+
+        ret
+    }
+}
+
+pub struct MusicWatcher {
+    _watcher: Box<dyn Watcher + Send>,
+    unwatched_paths: Vec<PathBuf>,
+}
+
+impl MusicWatcher {
+    /// Watches `paths` recursively and sends debounced batches of changes to `tx`.
+    /// Paths which could not be watched are available through [`MusicWatcher::unwatched_paths`]
+    /// and should be covered by the periodic scan instead.
+    #[tracing::instrument(level = "debug", skip(paths, exclude_paths, debounce, tx))]
+    pub fn new(
+        paths: Vec<PathBuf>,
+        exclude_paths: Vec<PathBuf>,
+        debounce: Duration,
+        tx: Sender<Vec<WatchEvent>>,
+    ) -> Result<Self> {
+        let (raw_tx, raw_rx) = mpsc::channel::<Event>();
+        let mut watcher = notify::recommended_watcher(move |ev: notify::Result<Event>| match ev {
+            Ok(ev) => {
+                let _ = raw_tx.send(ev);
+            }
+            Err(e) => tracing::error!("Music watcher error: {:?}", e),
+        })
+        .map_err(error_helpers::to_file_system_error)?;
+
+        let paths = paths
+            .into_iter()
+            .map(|p| dunce::canonicalize(&p).unwrap_or(p))
+            .collect::<Vec<_>>();
+        let exclude_paths = exclude_paths
+            .into_iter()
+            .map(|p| dunce::canonicalize(&p).unwrap_or(p))
+            .collect::<Vec<_>>();
+
+        let mut unwatched_paths = vec![];
+        for path in paths.iter() {
+            if let Err(e) = watcher.watch(path, RecursiveMode::Recursive) {
+                tracing::warn!(
+                    "Failed to watch {:?}, falling back to periodic scans: {:?}",
+                    path,
+                    e
+                );
+                unwatched_paths.push(path.clone());
+            } else {
+                tracing::info!("Watching {:?} for changes", path);
+            }
+        }
+
+        let roots = paths;
+        thread::spawn(move || {
+            let mut debouncer = Debouncer::default();
+            loop {
+                match raw_rx.recv_timeout(debounce) {
+                    Ok(ev) => debouncer.push(ev),
+                    Err(RecvTimeoutError::Timeout) => {
+                        if debouncer.is_empty() {
+                            continue;
+                        }
+                        let events = filter_events(debouncer.flush(), &roots, &exclude_paths);
+                        if !events.is_empty() && tx.send(events).is_err() {
+                            break;
+                        }
+                    }
+                    // The watcher was dropped
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+            }
+            tracing::info!("Stopped music watcher");
+        });
+
+        Ok(Self {
+            _watcher: Box::new(watcher),
+            unwatched_paths,
+        })
+    }
+
+    pub fn unwatched_paths(&self) -> &[PathBuf] {
+        &self.unwatched_paths
+    }
+}
+
+#[tracing::instrument(level = "debug", skip(roots, exclude_paths, path))]
+fn is_watched_path(roots: &[PathBuf], exclude_paths: &[PathBuf], path: &Path) -> bool {
+    if is_excluded(path, exclude_paths) {
+        return false;
+    }
+
+    let Some(root) = roots.iter().find(|r| path.starts_with(r)) else {
+        return false;
+    };
+
+    let is_dir = path.is_dir();
+    if is_path_ignored(root, path, is_dir) {
+        return false;
+    }
+
+    // Removed paths can't be checked for being a directory anymore
+    if !path.exists() {
+        return is_song_file(path) || is_playlist_or_cue_file(path) || path.extension().is_none();
+    }
+
+    is_dir || is_song_file(path) || is_playlist_or_cue_file(path)
+}
+
+#[tracing::instrument(level = "debug", skip(events, roots, exclude_paths))]
+fn filter_events(
+    events: Vec<WatchEvent>,
+    roots: &[PathBuf],
+    exclude_paths: &[PathBuf],
+) -> Vec<WatchEvent> {
+    events
+        .into_iter()
+        .filter_map(|ev| match ev {
+            // A file moved out of the library is gone, one moved in is new
+            WatchEvent::Renamed(from, to) => {
+                match (
+                    is_watched_path(roots, exclude_paths, &from),
+                    is_watched_path(roots, exclude_paths, &to),
+                ) {
+                    (true, true) => Some(WatchEvent::Renamed(from, to)),
+                    (true, false) => Some(WatchEvent::Removed(from)),
+                    (false, true) => Some(WatchEvent::Created(to)),
+                    (false, false) => None,
+                }
+            }
+            WatchEvent::Created(p) => {
+                is_watched_path(roots, exclude_paths, &p).then_some(WatchEvent::Created(p))
+            }
+            WatchEvent::Modified(p) => {
+                is_watched_path(roots, exclude_paths, &p).then_some(WatchEvent::Modified(p))
+            }
+            WatchEvent::Removed(p) => {
+                is_watched_path(roots, exclude_paths, &p).then_some(WatchEvent::Removed(p))
+            }
+        })
+        .collect()
+}
@@ -19,6 +19,7 @@ use regex::Regex;
 
 use serde_json::Value;
 use types::errors::{Result, error_helpers};
+use types::lyrics::{Lyrics, LyricsLine};
 
 #[cfg(test)]
 mod test;
@@ -203,25 +204,49 @@ impl LyricsFetcher {
         Ok(String::new())
     }
 
-    #[tracing::instrument(level = "debug", skip(self, librespot, uri))]
-    fn get_spotify_lyrics(&self, librespot: &LibrespotHolder, uri: String) -> Result<String> {
-        let res = librespot.get_lyrics(format!("spotify:track:{}", uri))?;
-        let parsed: Value = serde_json::from_str(&res).map_err(error_helpers::to_parse_error)?;
+    #[tracing::instrument(level = "debug", skip(self, res))]
+    fn parse_spotify_lyrics(&self, res: &str) -> Result<Lyrics> {
+        let parsed: Value = serde_json::from_str(res).map_err(error_helpers::to_parse_error)?;
         if let Some(lyrics_obj) = parsed.get("lyrics") {
+            let synced = lyrics_obj.get("syncType").and_then(|s| s.as_str()) != Some("UNSYNCED");
             if let Some(lines) = lyrics_obj.get("lines") {
                 if let Some(lines_arr) = lines.as_array() {
-                    let mut res = String::new();
+                    // Spotify sends timestamps as strings
+                    let get_time = |line: &Value, key: &str| {
+                        line.get(key).and_then(|t| {
+                            t.as_u64()
+                                .or_else(|| t.as_str().and_then(|t| t.parse().ok()))
+                        })
+                    };
+
+                    let mut res = vec![];
                     for line in lines_arr {
-                        if let Some(words) = line.get("words") {
-                            res.push_str(words.as_str().unwrap())
+                        if let Some(words) = line.get("words").and_then(|w| w.as_str()) {
+                            res.push(LyricsLine {
+                                start_ms: get_time(line, "startTimeMs").unwrap_or_default(),
+                                end_ms: get_time(line, "endTimeMs").filter(|t| *t > 0),
+                                text: words.to_string(),
+                                ..Default::default()
+                            });
                         }
                     }
-                    return Ok(res);
+
+                    let lyrics = Lyrics::from_lines(res);
+                    if !synced {
+                        return Ok(Lyrics::plain(lyrics.plain));
+                    }
+                    return Ok(lyrics);
                 }
             }
         }
 
-        Ok(String::new())
+        Ok(Lyrics::default())
+    }
+
+    #[tracing::instrument(level = "debug", skip(self, librespot, uri))]
+    fn get_spotify_lyrics(&self, librespot: &LibrespotHolder, uri: String) -> Result<Lyrics> {
+        let res = librespot.get_lyrics(format!("spotify:track:{}", uri))?;
+        self.parse_spotify_lyrics(&res)
     }
 
     #[tracing::instrument(level = "debug", skip(self, librespot, id, url, artists, title))]
@@ -232,7 +257,7 @@ impl LyricsFetcher {
         url: String,
         artists: Vec<String>,
         title: String,
-    ) -> Result<String> {
+    ) -> Result<Lyrics> {
         // self.get_google_lyrics(artists, title).await
         if id.starts_with("spotify:") {
             let res = self.get_spotify_lyrics(librespot, format!("spotify:track:{}", url));
@@ -242,11 +267,11 @@ impl LyricsFetcher {
         }
 
         let res = self.get_az_lyrics(&artists, &title).await;
-        if res.is_ok() {
-            return res;
+        if let Ok(res) = res {
+            return Ok(Lyrics::parse(&res));
         }
 
-        self.get_genius_lyrics(&artists, &title).await
+        Ok(Lyrics::parse(&self.get_genius_lyrics(&artists, &title).await?))
     }
 }
 
@@ -105,3 +105,28 @@ fn test_lyrics_error_handling() {
         "Should handle empty title gracefully"
     );
 }
+
+// Test parsing of the lyrics returned by spotify
+#[test]
+fn test_parse_spotify_lyrics() {
+    let lyrics_fetcher = LyricsFetcher::new();
+
+    let res = r#"{"lyrics":{"syncType":"LINE_SYNCED","lines":[
+        {"startTimeMs":"960","words":"First line","syllables":[],"endTimeMs":"0"},
+        {"startTimeMs":"4200","words":"Second line","syllables":[],"endTimeMs":"0"}
+    ]}}"#;
+    let lyrics = lyrics_fetcher.parse_spotify_lyrics(res).unwrap();
+    assert!(lyrics.is_synced(), "Should keep line timings");
+    assert_eq!(lyrics.lines[0].start_ms, 960);
+    assert_eq!(lyrics.lines[0].end_ms, Some(4200));
+    assert_eq!(lyrics.lines[1].text, "Second line");
+    assert_eq!(lyrics.plain, "First line\nSecond line");
+
+    let res = r#"{"lyrics":{"syncType":"UNSYNCED","lines":[
+        {"startTimeMs":"0","words":"First line","syllables":[],"endTimeMs":"0"},
+        {"startTimeMs":"0","words":"Second line","syllables":[],"endTimeMs":"0"}
+    ]}}"#;
+    let lyrics = lyrics_fetcher.parse_spotify_lyrics(res).unwrap();
+    assert!(!lyrics.is_synced(), "Unsynced lyrics should not have lines");
+    assert_eq!(lyrics.plain, "First line\nSecond line");
+}
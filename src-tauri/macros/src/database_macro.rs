@@ -43,3 +43,18 @@ macro_rules! filter_field_like {
         }
     };
 }
+
+#[macro_export]
+macro_rules! filter_field_op {
+    ($predicate:expr, $field:expr, $column:expr, $op:ident, $inclusive:expr) => {
+        if let Some(val) = $field {
+            if $inclusive {
+                QueryDsl::filter($predicate, $column.$op(val))
+            } else {
+                QueryDsl::or_filter($predicate, $column.$op(val))
+            }
+        } else {
+            $predicate
+        }
+    };
+}
@@ -0,0 +1,339 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    collections::HashMap,
+    fs,
+    hash::{DefaultHasher, Hash, Hasher},
+    io,
+    path::PathBuf,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::{debug, error, info};
+use types::{
+    cache::AudioCacheStats,
+    errors::{error_helpers, Result},
+};
+
+use crate::RodioPlayer;
+
+/// File listing the cached tracks, kept next to them
+const INDEX_FILE: &str = "index.json";
+
+/// Order in which cached tracks are dropped once the cache is full
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum EvictionPolicy {
+    #[default]
+    LeastRecentlyUsed,
+    LeastFrequentlyUsed,
+    OldestFirst,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct AudioCacheSettings {
+    /// Bytes tracks which aren't available offline may take up. 0 disables caching them
+    pub max_size: u64,
+    pub policy: EvictionPolicy,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    file: String,
+    size: u64,
+    /// Milliseconds since the epoch
+    added: u64,
+    last_used: u64,
+    uses: u64,
+    /// Made available offline explicitly, never evicted
+    offline: bool,
+}
+
+/// Streamed tracks downloaded to disk, keyed by song id, so playing them again
+/// doesn't need the network
+#[derive(Debug)]
+pub struct AudioCache {
+    dir: PathBuf,
+    entries: Mutex<HashMap<String, CacheEntry>>,
+    settings: Mutex<AudioCacheSettings>,
+    /// Ids being downloaded, and whether they should be kept offline once done
+    downloading: Mutex<HashMap<String, bool>>,
+    /// Id and src of the playing song, cached once it stops playing so it isn't
+    /// downloaded while it streams
+    deferred: Mutex<Option<(String, String)>>,
+}
+
+#[tracing::instrument(level = "debug", skip())]
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+impl AudioCache {
+    /// Loads the cache in `dir`. Files the index doesn't know about, such as
+    /// interrupted downloads, are removed
+    #[tracing::instrument(level = "debug", skip())]
+    pub fn new(dir: PathBuf, settings: AudioCacheSettings) -> Self {
+        if !dir.exists() {
+            fs::create_dir_all(dir.clone()).unwrap();
+        }
+
+        let mut entries: HashMap<String, CacheEntry> = fs::read(dir.join(INDEX_FILE))
+            .ok()
+            .and_then(|data| serde_json::from_slice(&data).ok())
+            .unwrap_or_default();
+        entries.retain(|_, e| dir.join(&e.file).exists());
+
+        if let Ok(files) = fs::read_dir(&dir) {
+            for file in files.flatten() {
+                let name = file.file_name().to_string_lossy().to_string();
+                if name != INDEX_FILE && !entries.values().any(|e| e.file == name) {
+                    debug!("Removing stray cache file {}", name);
+                    let _ = fs::remove_file(file.path());
+                }
+            }
+        }
+
+        let cache = Self {
+            dir,
+            entries: Mutex::new(entries),
+            settings: Mutex::new(settings),
+            downloading: Mutex::new(HashMap::new()),
+            deferred: Mutex::new(None),
+        };
+        cache.evict();
+        cache
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn set_settings(&self, settings: AudioCacheSettings) {
+        *self.settings.lock().unwrap() = settings;
+        self.evict();
+    }
+
+    /// Whether tracks are cached when they are played
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn is_enabled(&self) -> bool {
+        self.settings.lock().unwrap().max_size > 0
+    }
+
+    /// Replaces the song to cache once it stops playing, returning the previous one
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn replace_deferred(&self, next: Option<(String, String)>) -> Option<(String, String)> {
+        std::mem::replace(&mut *self.deferred.lock().unwrap(), next)
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn contains(&self, id: &str) -> bool {
+        self.entries.lock().unwrap().contains_key(id)
+    }
+
+    /// Path of the cached copy of the song, counted as a use of it
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn get(&self, id: &str) -> Option<PathBuf> {
+        let mut entries = self.entries.lock().unwrap();
+        let entry = entries.get_mut(id)?;
+        entry.last_used = now_millis();
+        entry.uses += 1;
+        let path = self.dir.join(&entry.file);
+        self.save_index(&entries);
+        Some(path)
+    }
+
+    /// Downloads `src` into the cache as the song `id`. Songs kept `offline` aren't
+    /// evicted, and are downloaded even if caching is disabled
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn store(&self, id: String, src: String, offline: bool) -> Result<()> {
+        {
+            let mut entries = self.entries.lock().unwrap();
+            if let Some(entry) = entries.get_mut(&id) {
+                if offline && !entry.offline {
+                    entry.offline = true;
+                    self.save_index(&entries);
+                }
+                return Ok(());
+            }
+        }
+
+        if !offline && !self.is_enabled() {
+            return Ok(());
+        }
+
+        {
+            let mut downloading = self.downloading.lock().unwrap();
+            if let Some(keep) = downloading.get_mut(&id) {
+                *keep |= offline;
+                return Ok(());
+            }
+            downloading.insert(id.clone(), offline);
+        }
+
+        info!("Caching {} from {}", id, src);
+        let res = self.download(&id, &src).await;
+        let offline = self
+            .downloading
+            .lock()
+            .unwrap()
+            .remove(&id)
+            .unwrap_or(offline);
+        let (file, size) = res?;
+
+        let now = now_millis();
+        {
+            let mut entries = self.entries.lock().unwrap();
+            entries.insert(
+                id,
+                CacheEntry {
+                    file,
+                    size,
+                    added: now,
+                    last_used: now,
+                    uses: 0,
+                    offline,
+                },
+            );
+            self.save_index(&entries);
+        }
+        self.evict();
+        Ok(())
+    }
+
+    /// Downloads `src` to a new file in the cache, returning its name and size
+    async fn download(&self, id: &str, src: &str) -> Result<(String, u64)> {
+        let stream = RodioPlayer::open_remote(self.dir.clone(), src).await?;
+
+        let mut hasher = DefaultHasher::new();
+        id.hash(&mut hasher);
+        let file = format!("{:016x}{:x}", hasher.finish(), now_millis());
+        let part = self.dir.join(format!("{}.part", file));
+        let path = self.dir.join(&file);
+
+        let size = tokio::task::spawn_blocking(move || -> Result<u64> {
+            let mut reader = stream.reader;
+            let res = fs::File::create(&part)
+                .and_then(|mut out| io::copy(&mut reader, &mut out))
+                .and_then(|size| fs::rename(&part, &path).map(|_| size));
+            if res.is_err() {
+                let _ = fs::remove_file(&part);
+            }
+            Ok(res?)
+        })
+        .await
+        .map_err(error_helpers::to_playback_error)??;
+
+        Ok((file, size))
+    }
+
+    /// Lets songs made available offline be evicted again like any other cached song
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn remove_offline(&self, ids: &[String]) {
+        {
+            let mut entries = self.entries.lock().unwrap();
+            for id in ids {
+                if let Some(entry) = entries.get_mut(id) {
+                    entry.offline = false;
+                }
+            }
+            self.save_index(&entries);
+        }
+        self.evict();
+    }
+
+    /// Removes every cached song, including those available offline if `include_offline` is set
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn clear(&self, include_offline: bool) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, e| {
+            if e.offline && !include_offline {
+                return true;
+            }
+            self.remove_file(e);
+            false
+        });
+        self.save_index(&entries);
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn stats(&self) -> AudioCacheStats {
+        let entries = self.entries.lock().unwrap();
+        let offline = entries.values().filter(|e| e.offline);
+        AudioCacheStats {
+            size: entries.values().map(|e| e.size).sum(),
+            max_size: self.settings.lock().unwrap().max_size,
+            tracks: entries.len(),
+            offline_tracks: offline.clone().count(),
+            offline_size: offline.map(|e| e.size).sum(),
+        }
+    }
+
+    /// Drops songs, in the order of the eviction policy, until those which
+    /// aren't available offline fit in the cache
+    #[tracing::instrument(level = "debug", skip(self))]
+    fn evict(&self) {
+        let settings = *self.settings.lock().unwrap();
+        let mut entries = self.entries.lock().unwrap();
+
+        let mut candidates: Vec<(String, CacheEntry)> = entries
+            .iter()
+            .filter(|(_, e)| !e.offline)
+            .map(|(id, e)| (id.clone(), e.clone()))
+            .collect();
+        let mut size: u64 = candidates.iter().map(|(_, e)| e.size).sum();
+        if size <= settings.max_size {
+            return;
+        }
+
+        candidates.sort_by_key(|(_, e)| match settings.policy {
+            EvictionPolicy::LeastRecentlyUsed => (e.last_used, 0),
+            EvictionPolicy::LeastFrequentlyUsed => (e.uses, e.last_used),
+            EvictionPolicy::OldestFirst => (e.added, 0),
+        });
+        for (id, entry) in candidates {
+            if size <= settings.max_size {
+                break;
+            }
+            debug!("Evicting {} from the audio cache", id);
+            self.remove_file(&entry);
+            entries.remove(&id);
+            size -= entry.size;
+        }
+        self.save_index(&entries);
+    }
+
+    fn remove_file(&self, entry: &CacheEntry) {
+        if let Err(e) = fs::remove_file(self.dir.join(&entry.file)) {
+            error!("Failed to remove cached file {}: {:?}", entry.file, e);
+        }
+    }
+
+    /// Writes the index to a temporary file first so a crash can't leave it half written
+    fn save_index(&self, entries: &HashMap<String, CacheEntry>) {
+        let res = serde_json::to_vec(entries)
+            .map_err(io::Error::other)
+            .and_then(|data| {
+                let tmp = self.dir.join(format!("{}.part", INDEX_FILE));
+                fs::write(&tmp, data)?;
+                fs::rename(tmp, self.dir.join(INDEX_FILE))
+            });
+        if let Err(e) = res {
+            error!("Failed to save the audio cache index: {:?}", e);
+        }
+    }
+}
@@ -0,0 +1,134 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    f32::consts::FRAC_PI_2,
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use rodio::Sink;
+
+/// Interval between volume changes while fading
+const FADE_STEP: Duration = Duration::from_millis(20);
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum CrossfadeCurve {
+    #[default]
+    Linear,
+    /// Keeps the perceived loudness constant through the fade
+    EqualPower,
+    /// Eases in and out of the fade
+    SCurve,
+}
+
+impl CrossfadeCurve {
+    /// Gains of the outgoing and incoming track at `progress` (from 0 to 1) through the fade
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn gains(&self, progress: f32) -> (f32, f32) {
+        let t = progress.clamp(0f32, 1f32);
+        match self {
+            Self::Linear => (1f32 - t, t),
+            Self::EqualPower => ((t * FRAC_PI_2).cos(), (t * FRAC_PI_2).sin()),
+            Self::SCurve => {
+                let s = t * t * (3f32 - 2f32 * t);
+                (1f32 - s, s)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CrossfadeSettings {
+    pub duration: Duration,
+    pub curve: CrossfadeCurve,
+}
+
+/// Fades `from` out and `to` in over `duration`. The fade stops early, leaving only `to`
+/// audible, once `is_current` returns false. Pausing `to` also pauses the fade
+pub(crate) fn fade(
+    from: Arc<Sink>,
+    to: Arc<Sink>,
+    duration: Duration,
+    curve: CrossfadeCurve,
+    volume: Arc<Mutex<f32>>,
+    is_current: impl Fn() -> bool + Send + 'static,
+) {
+    thread::spawn(move || {
+        let steps = (duration.as_millis() / FADE_STEP.as_millis()).max(1) as u32;
+        let mut step = 0;
+        while step <= steps && is_current() {
+            if !to.is_paused() {
+                let (fade_out, fade_in) = curve.gains(step as f32 / steps as f32);
+                let volume = *volume.lock().unwrap();
+                from.set_volume(volume * fade_out);
+                to.set_volume(volume * fade_in);
+                step += 1;
+            }
+            thread::sleep(FADE_STEP);
+        }
+
+        from.stop();
+        to.set_volume(*volume.lock().unwrap());
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_gains(curve: CrossfadeCurve, progress: f32, expected: (f32, f32)) {
+        let (fade_out, fade_in) = curve.gains(progress);
+        assert!(
+            (fade_out - expected.0).abs() < 1e-4,
+            "{:?} at {}",
+            curve,
+            progress
+        );
+        assert!(
+            (fade_in - expected.1).abs() < 1e-4,
+            "{:?} at {}",
+            curve,
+            progress
+        );
+    }
+
+    #[test]
+    fn test_crossfade_gains() {
+        for curve in [
+            CrossfadeCurve::Linear,
+            CrossfadeCurve::EqualPower,
+            CrossfadeCurve::SCurve,
+        ] {
+            assert_gains(curve, 0f32, (1f32, 0f32));
+            assert_gains(curve, 1f32, (0f32, 1f32));
+            // Progress outside of the fade is clamped
+            assert_gains(curve, -1f32, (1f32, 0f32));
+            assert_gains(curve, 2f32, (0f32, 1f32));
+        }
+
+        assert_gains(CrossfadeCurve::Linear, 0.25, (0.75, 0.25));
+        assert_gains(CrossfadeCurve::SCurve, 0.5, (0.5, 0.5));
+        assert_gains(CrossfadeCurve::SCurve, 0.25, (0.84375, 0.15625));
+
+        // Equal power keeps the summed power of both tracks constant
+        for progress in [0.1, 0.25, 0.5, 0.75, 0.9] {
+            let (fade_out, fade_in) = CrossfadeCurve::EqualPower.gains(progress);
+            assert!((fade_out * fade_out + fade_in * fade_in - 1f32).abs() < 1e-4);
+        }
+    }
+}
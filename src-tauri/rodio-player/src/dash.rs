@@ -0,0 +1,499 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::time::Duration;
+
+use dash_mpd::{AdaptationSet, BaseURL, Period, Representation, SegmentTemplate, MPD};
+use reqwest::{header::CONTENT_TYPE, Url};
+use rodio::{source::SeekError, Source};
+use tokio::io::{AsyncWriteExt, DuplexStream};
+use tracing::{debug, error, info};
+use types::errors::{error_helpers, Result};
+
+/// Size of the buffer between the segment downloader and the decoder
+const PIPE_CAPACITY: usize = 256 * 1024;
+
+/// Codecs symphonia can decode, in order of preference
+const SUPPORTED_CODECS: &[&str] = &["flac", "opus", "mp4a", "mp3", "vorbis"];
+
+/// Audio of a DASH manifest, as a list of urls which played back to back form one file
+#[derive(Debug, Clone, Default)]
+pub(crate) struct DashPlaylist {
+    pub(crate) segments: Vec<Url>,
+    pub(crate) duration: Option<Duration>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TimelineEntry {
+    t: Option<u64>,
+    d: u64,
+    r: Option<i64>,
+}
+
+/// Whether a Content-Type header is the one of a DASH manifest
+#[tracing::instrument(level = "trace", skip())]
+fn is_manifest_type(content_type: &str) -> bool {
+    content_type.to_lowercase().contains("dash+xml")
+}
+
+/// Whether `src` is a DASH manifest, which isn't always apparent from its url.
+/// Only the headers are requested, so other streams aren't downloaded twice
+#[tracing::instrument(level = "debug", skip())]
+pub(crate) async fn is_manifest(src: &str) -> bool {
+    let response = reqwest::Client::new().head(src).send().await;
+    match response.and_then(|r| r.error_for_status()) {
+        Ok(response) => response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|c| c.to_str().ok())
+            .is_some_and(is_manifest_type),
+        Err(err) => {
+            debug!("Failed to check if {} is a DASH manifest: {:?}", src, err);
+            false
+        }
+    }
+}
+
+/// Fetches the manifest at `src` and lists the segments of its best supported audio representation.
+/// Only static (on demand) manifests are supported
+#[tracing::instrument(level = "debug", skip())]
+pub(crate) async fn load_playlist(src: &str) -> Result<DashPlaylist> {
+    let url = Url::parse(src).map_err(error_helpers::to_playback_error)?;
+    let manifest = reqwest::get(url.clone())
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(error_helpers::to_playback_error)?
+        .text()
+        .await
+        .map_err(error_helpers::to_playback_error)?;
+    let mpd = dash_mpd::parse(&manifest).map_err(error_helpers::to_playback_error)?;
+
+    if mpd.mpdtype.as_deref() == Some("dynamic") {
+        return Err("Live DASH streams are not supported".into());
+    }
+
+    let period = mpd.periods.first().ok_or("DASH manifest has no periods")?;
+    let (adaptation, representation) =
+        select_representation(period).ok_or("DASH manifest has no supported audio")?;
+    info!(
+        "Selected DASH representation {:?} ({:?}, {:?} bps)",
+        representation.id, representation.codecs, representation.bandwidth
+    );
+
+    let base = [
+        &mpd.BaseURL,
+        &period.BaseURL,
+        &adaptation.BaseURL,
+        &representation.BaseURL,
+    ]
+    .into_iter()
+    .try_fold(url, |base, urls| resolve(&base, urls))?;
+
+    let duration = period.duration.or(mpd.mediaPresentationDuration);
+    let segments = segment_urls(&base, adaptation, representation, duration)?;
+    debug!("DASH stream has {} segments", segments.len());
+
+    Ok(DashPlaylist { segments, duration })
+}
+
+/// Audio representation with the highest bandwidth, preferring codecs which can be decoded
+#[tracing::instrument(level = "debug", skip(period))]
+fn select_representation(period: &Period) -> Option<(&AdaptationSet, &Representation)> {
+    let is_audio = |mime: Option<&String>| mime.is_some_and(|m| m.starts_with("audio/"));
+    let codec_rank = |codecs: Option<&String>| {
+        codecs.and_then(|codecs| {
+            let codecs = codecs.to_lowercase();
+            SUPPORTED_CODECS.iter().position(|c| codecs.starts_with(c))
+        })
+    };
+
+    let candidates = period
+        .adaptations
+        .iter()
+        .filter(|a| {
+            a.contentType.as_deref() == Some("audio")
+                || is_audio(a.mimeType.as_ref())
+                || a.representations
+                    .iter()
+                    .any(|r| is_audio(r.mimeType.as_ref()))
+        })
+        .flat_map(|a| a.representations.iter().map(move |r| (a, r)));
+
+    // Unknown codecs rank last but are still tried if nothing else is available
+    candidates.min_by_key(|(a, r)| {
+        let rank =
+            codec_rank(r.codecs.as_ref().or(a.codecs.as_ref())).unwrap_or(SUPPORTED_CODECS.len());
+        (rank, std::cmp::Reverse(r.bandwidth.unwrap_or_default()))
+    })
+}
+
+/// Resolves the first of `urls` against `base`, DASH only uses the others as fallbacks
+#[tracing::instrument(level = "debug", skip(base, urls))]
+fn resolve(base: &Url, urls: &[BaseURL]) -> Result<Url> {
+    match urls.first() {
+        Some(url) => base
+            .join(url.base.trim())
+            .map_err(error_helpers::to_playback_error),
+        None => Ok(base.clone()),
+    }
+}
+
+#[tracing::instrument(level = "debug", skip(base, adaptation, representation))]
+fn segment_urls(
+    base: &Url,
+    adaptation: &AdaptationSet,
+    representation: &Representation,
+    duration: Option<Duration>,
+) -> Result<Vec<Url>> {
+    let join = |path: &str| base.join(path).map_err(error_helpers::to_playback_error);
+
+    if let Some(template) = representation
+        .SegmentTemplate
+        .as_ref()
+        .or(adaptation.SegmentTemplate.as_ref())
+    {
+        return template_urls(base, template, representation, duration);
+    }
+
+    if let Some(list) = representation.SegmentList.as_ref() {
+        let mut urls = vec![];
+        if let Some(init) = list
+            .Initialization
+            .as_ref()
+            .and_then(|i| i.sourceURL.as_ref())
+        {
+            urls.push(join(init)?);
+        }
+        for segment in &list.segment_urls {
+            if let Some(media) = segment.media.as_ref() {
+                urls.push(join(media)?);
+            }
+        }
+        return Ok(urls);
+    }
+
+    // Representations with only a BaseURL (or SegmentBase) are a single file
+    Ok(vec![base.clone()])
+}
+
+#[tracing::instrument(level = "debug", skip(base, template, representation))]
+fn template_urls(
+    base: &Url,
+    template: &SegmentTemplate,
+    representation: &Representation,
+    duration: Option<Duration>,
+) -> Result<Vec<Url>> {
+    let id = representation.id.clone().unwrap_or_default();
+    let bandwidth = representation.bandwidth.unwrap_or_default();
+    let start_number = template.startNumber.unwrap_or(1);
+    let timescale = template.timescale.unwrap_or(1).max(1);
+    let end_time = duration.map(|d| (d.as_secs_f64() * timescale as f64).round() as u64);
+
+    let segments = if let Some(timeline) = template.SegmentTimeline.as_ref() {
+        let entries: Vec<TimelineEntry> = timeline
+            .segments
+            .iter()
+            .map(|s| TimelineEntry {
+                t: s.t,
+                d: s.d,
+                r: s.r,
+            })
+            .collect();
+        expand_timeline(&entries, start_number, end_time)
+    } else {
+        let segment_duration = template.duration.unwrap_or_default();
+        let Some(end_time) = end_time.filter(|_| segment_duration > 0f64) else {
+            return Err("DASH segment count can't be determined".into());
+        };
+        let count = (end_time as f64 / segment_duration).ceil() as u64;
+        (0..count)
+            .map(|i| (start_number + i, (i as f64 * segment_duration) as u64))
+            .collect()
+    };
+
+    let mut paths = vec![];
+    if let Some(init) = template.initialization.as_ref() {
+        paths.push(expand_template(init, &id, bandwidth, start_number, 0));
+    }
+    let media = template
+        .media
+        .as_ref()
+        .ok_or("DASH segment template has no media")?;
+    paths.extend(
+        segments
+            .into_iter()
+            .map(|(number, time)| expand_template(media, &id, bandwidth, number, time)),
+    );
+
+    paths
+        .iter()
+        .map(|p| base.join(p).map_err(error_helpers::to_playback_error))
+        .collect()
+}
+
+/// Segment numbers and start times of a SegmentTimeline. A negative repeat count
+/// repeats the segment until the next entry, or the end of the period
+#[tracing::instrument(level = "debug", skip(entries))]
+fn expand_timeline(
+    entries: &[TimelineEntry],
+    start_number: u64,
+    end_time: Option<u64>,
+) -> Vec<(u64, u64)> {
+    let mut ret = vec![];
+    let mut time = 0u64;
+    let mut number = start_number;
+    for (i, entry) in entries.iter().enumerate() {
+        if let Some(t) = entry.t {
+            time = t;
+        }
+        if entry.d == 0 {
+            continue;
+        }
+        let repeat = match entry.r {
+            Some(r) if r >= 0 => r as u64,
+            Some(_) => {
+                let until = entries
+                    .get(i + 1)
+                    .and_then(|e| e.t)
+                    .or(end_time)
+                    .unwrap_or(time);
+                until
+                    .saturating_sub(time)
+                    .div_ceil(entry.d)
+                    .saturating_sub(1)
+            }
+            None => 0,
+        };
+        for _ in 0..=repeat {
+            ret.push((number, time));
+            number += 1;
+            time += entry.d;
+        }
+    }
+    ret
+}
+
+/// Fills the `$Identifier$` and `$Identifier%0Nd$` placeholders of a segment template
+#[tracing::instrument(level = "debug", skip())]
+fn expand_template(template: &str, id: &str, bandwidth: u64, number: u64, time: u64) -> String {
+    let mut ret = String::with_capacity(template.len());
+    let mut parts = template.split('$');
+    if let Some(first) = parts.next() {
+        ret.push_str(first);
+    }
+    // Placeholders are every other part, `$$` is an escaped `$`
+    while let Some(placeholder) = parts.next() {
+        let (name, width) = match placeholder.split_once("%0") {
+            Some((name, format)) => (
+                name,
+                format.trim_end_matches('d').parse::<usize>().unwrap_or(0),
+            ),
+            None => (placeholder, 0),
+        };
+        match name {
+            "" => ret.push('$'),
+            "RepresentationID" => ret.push_str(id),
+            "Bandwidth" => ret.push_str(&format!("{:0width$}", bandwidth)),
+            "Number" => ret.push_str(&format!("{:0width$}", number)),
+            "Time" => ret.push_str(&format!("{:0width$}", time)),
+            _ => {
+                ret.push('$');
+                ret.push_str(placeholder);
+                ret.push('$');
+            }
+        }
+        if let Some(literal) = parts.next() {
+            ret.push_str(literal);
+        }
+    }
+    ret
+}
+
+/// Downloads `segments` one after another into a pipe read by the decoder.
+/// Downloading stops once the reading end is dropped
+#[tracing::instrument(level = "debug", skip(segments))]
+pub(crate) fn segment_reader(segments: Vec<Url>) -> DuplexStream {
+    let (mut writer, reader) = tokio::io::duplex(PIPE_CAPACITY);
+    tokio::spawn(async move {
+        let client = reqwest::Client::new();
+        for url in segments {
+            let mut res = match client.get(url.clone()).send().await {
+                Ok(res) if res.status().is_success() => res,
+                Ok(res) => {
+                    error!("Failed to fetch DASH segment {}: {}", url, res.status());
+                    return;
+                }
+                Err(e) => {
+                    error!("Failed to fetch DASH segment {}: {:?}", url, e);
+                    return;
+                }
+            };
+
+            loop {
+                match res.chunk().await {
+                    Ok(Some(chunk)) => {
+                        if writer.write_all(&chunk).await.is_err() {
+                            debug!("DASH stream closed");
+                            return;
+                        }
+                    }
+                    Ok(None) => break,
+                    Err(e) => {
+                        error!("Failed to read DASH segment {}: {:?}", url, e);
+                        return;
+                    }
+                }
+            }
+        }
+        let _ = writer.shutdown().await;
+    });
+    reader
+}
+
+/// Source with a known total duration. Segmented streams have no content length
+/// for the decoder to work it out, but the manifest knows it
+pub(crate) struct WithDuration<S> {
+    inner: S,
+    duration: Option<Duration>,
+}
+
+impl<S: Source> WithDuration<S> {
+    pub(crate) fn new(inner: S, duration: Option<Duration>) -> Self {
+        Self { inner, duration }
+    }
+}
+
+impl<S: Source> Iterator for WithDuration<S> {
+    type Item = S::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<S: Source> Source for WithDuration<S> {
+    fn current_span_len(&self) -> Option<usize> {
+        self.inner.current_span_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration().or(self.duration)
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> std::result::Result<(), SeekError> {
+        self.inner.try_seek(pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(t: Option<u64>, d: u64, r: Option<i64>) -> TimelineEntry {
+        TimelineEntry { t, d, r }
+    }
+
+    #[test]
+    fn test_expand_timeline() {
+        // Repeats are added to the segment itself
+        assert_eq!(
+            expand_timeline(
+                &[entry(Some(0), 10, Some(2)), entry(None, 5, None)],
+                1,
+                None
+            ),
+            vec![(1, 0), (2, 10), (3, 20), (4, 30)]
+        );
+
+        // An explicit start time skips ahead
+        assert_eq!(
+            expand_timeline(
+                &[entry(Some(100), 10, None), entry(Some(150), 10, None)],
+                5,
+                None
+            ),
+            vec![(5, 100), (6, 150)]
+        );
+
+        // A negative repeat runs until the next entry
+        assert_eq!(
+            expand_timeline(
+                &[entry(Some(0), 10, Some(-1)), entry(Some(25), 5, None)],
+                0,
+                None
+            ),
+            vec![(0, 0), (1, 10), (2, 20), (3, 25)]
+        );
+
+        // or the end of the period
+        assert_eq!(
+            expand_timeline(&[entry(None, 10, Some(-1))], 1, Some(35)),
+            vec![(1, 0), (2, 10), (3, 20), (4, 30)]
+        );
+
+        // Segments without a duration are skipped
+        assert_eq!(
+            expand_timeline(&[entry(None, 0, Some(3)), entry(None, 10, None)], 1, None),
+            vec![(1, 0)]
+        );
+    }
+
+    #[test]
+    fn test_is_manifest_type() {
+        assert!(is_manifest_type("application/dash+xml"));
+        assert!(is_manifest_type("Application/DASH+XML; charset=utf-8"));
+        assert!(!is_manifest_type("application/xml"));
+        assert!(!is_manifest_type("audio/mpeg"));
+    }
+
+    #[test]
+    fn test_expand_template() {
+        assert_eq!(
+            expand_template("$RepresentationID$/seg-$Number$.m4s", "audio", 128000, 7, 0),
+            "audio/seg-7.m4s"
+        );
+        assert_eq!(
+            expand_template(
+                "$Bandwidth$/$Time$-$Number%05d$.m4s",
+                "a",
+                128000,
+                42,
+                96000
+            ),
+            "128000/96000-00042.m4s"
+        );
+        // `$$` is an escaped `$`, unknown placeholders are left as they are
+        assert_eq!(
+            expand_template("init$$$Unknown$.mp4", "a", 0, 1, 0),
+            "init$$Unknown$.mp4"
+        );
+        assert_eq!(expand_template("init.mp4", "a", 0, 1, 0), "init.mp4");
+    }
+}
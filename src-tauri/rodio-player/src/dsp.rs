@@ -0,0 +1,342 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    f32::consts::PI,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use rodio::{source::SeekError, Source};
+use types::dsp::{DspSettings, EqBand, EqFilterType, MAX_EQ_GAIN};
+
+/// Peak level the limiter keeps the output under, about -1 dBFS
+const LIMITER_THRESHOLD: f32 = 0.89;
+
+/// Seconds the limiter takes to recover after a peak
+const LIMITER_RELEASE_S: f32 = 0.2;
+
+#[tracing::instrument(level = "trace", skip())]
+fn db_to_gain(db: f32) -> f32 {
+    10f32.powf(db / 20f32)
+}
+
+/// Biquad coefficients from the RBJ audio EQ cookbook, normalized by a0
+#[derive(Debug, Clone, Copy)]
+struct Biquad {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+}
+
+impl Biquad {
+    #[tracing::instrument(level = "trace", skip(band))]
+    fn new(band: &EqBand, sample_rate: u32) -> Self {
+        let a = 10f32.powf(band.gain.clamp(-MAX_EQ_GAIN, MAX_EQ_GAIN) / 40f32);
+        let frequency = band.frequency.clamp(10f32, sample_rate as f32 * 0.49);
+        let w0 = 2f32 * PI * frequency / sample_rate as f32;
+        let (sin, cos) = w0.sin_cos();
+        let alpha = sin / (2f32 * band.q.max(0.1));
+        let shelf = 2f32 * a.sqrt() * alpha;
+
+        let (b0, b1, b2, a0, a1, a2) = match band.filter {
+            EqFilterType::Peak => (
+                1f32 + alpha * a,
+                -2f32 * cos,
+                1f32 - alpha * a,
+                1f32 + alpha / a,
+                -2f32 * cos,
+                1f32 - alpha / a,
+            ),
+            EqFilterType::LowShelf => (
+                a * ((a + 1f32) - (a - 1f32) * cos + shelf),
+                2f32 * a * ((a - 1f32) - (a + 1f32) * cos),
+                a * ((a + 1f32) - (a - 1f32) * cos - shelf),
+                (a + 1f32) + (a - 1f32) * cos + shelf,
+                -2f32 * ((a - 1f32) + (a + 1f32) * cos),
+                (a + 1f32) + (a - 1f32) * cos - shelf,
+            ),
+            EqFilterType::HighShelf => (
+                a * ((a + 1f32) + (a - 1f32) * cos + shelf),
+                -2f32 * a * ((a - 1f32) + (a + 1f32) * cos),
+                a * ((a + 1f32) + (a - 1f32) * cos - shelf),
+                (a + 1f32) - (a - 1f32) * cos + shelf,
+                2f32 * ((a - 1f32) - (a + 1f32) * cos),
+                (a + 1f32) - (a - 1f32) * cos - shelf,
+            ),
+        };
+
+        Self {
+            b0: b0 / a0,
+            b1: b1 / a0,
+            b2: b2 / a0,
+            a1: a1 / a0,
+            a2: a2 / a0,
+        }
+    }
+}
+
+/// Filter memory of a single channel, transposed direct form II
+#[derive(Debug, Default, Clone, Copy)]
+struct BiquadState {
+    z1: f32,
+    z2: f32,
+}
+
+impl BiquadState {
+    #[inline]
+    fn process(&mut self, c: &Biquad, x: f32) -> f32 {
+        let y = c.b0 * x + self.z1;
+        self.z1 = c.b1 * x - c.a1 * y + self.z2;
+        self.z2 = c.b2 * x - c.a2 * y;
+        y
+    }
+}
+
+/// Settings shared by the player and all sources it plays, so changes apply live
+#[derive(Debug, Default)]
+pub(crate) struct DspControl {
+    settings: Mutex<DspSettings>,
+    version: AtomicU64,
+}
+
+impl DspControl {
+    #[tracing::instrument(level = "debug", skip(self, settings))]
+    pub(crate) fn set(&self, settings: DspSettings) {
+        *self.settings.lock().unwrap() = settings;
+        self.version.fetch_add(1, Ordering::Release);
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub(crate) fn get(&self) -> DspSettings {
+        self.settings.lock().unwrap().clone()
+    }
+}
+
+/// Processing state of the chain for one stream format
+pub(crate) struct DspChain {
+    settings: DspSettings,
+    channels: u16,
+    sample_rate: u32,
+    preamp: f32,
+    filters: Vec<Biquad>,
+    /// Filter memory per channel, then per filter
+    states: Vec<Vec<BiquadState>>,
+    limiter_gain: f32,
+    limiter_release: f32,
+}
+
+impl DspChain {
+    #[tracing::instrument(level = "debug", skip(settings))]
+    pub(crate) fn new(settings: DspSettings, channels: u16, sample_rate: u32) -> Self {
+        let mut chain = Self {
+            settings,
+            channels,
+            sample_rate,
+            preamp: 1f32,
+            filters: vec![],
+            states: vec![],
+            limiter_gain: 1f32,
+            limiter_release: 0f32,
+        };
+        chain.configure();
+        chain
+    }
+
+    /// Recomputes coefficients, keeping the filter memory if the filters still line up
+    #[tracing::instrument(level = "debug", skip(self))]
+    fn configure(&mut self) {
+        let sample_rate = self.sample_rate.max(1);
+        // Settings may come from presets or clients which don't respect the limits of the UI
+        self.preamp = db_to_gain(self.settings.preamp.clamp(-MAX_EQ_GAIN, MAX_EQ_GAIN));
+        // Bands without gain don't change the signal
+        self.filters = self
+            .settings
+            .bands
+            .iter()
+            .filter(|b| b.gain.abs() > 0.01)
+            .map(|b| Biquad::new(b, sample_rate))
+            .collect();
+        if self.states.len() != self.channels as usize
+            || self.states.iter().any(|s| s.len() != self.filters.len())
+        {
+            self.states =
+                vec![vec![BiquadState::default(); self.filters.len()]; self.channels as usize];
+        }
+        self.limiter_release = 1f32 - (-1f32 / (LIMITER_RELEASE_S * sample_rate as f32)).exp();
+    }
+
+    #[tracing::instrument(level = "debug", skip(self, settings))]
+    pub(crate) fn set_settings(&mut self, settings: DspSettings) {
+        self.settings = settings;
+        self.configure();
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub(crate) fn set_format(&mut self, channels: u16, sample_rate: u32) {
+        self.channels = channels;
+        self.sample_rate = sample_rate;
+        self.reset();
+        self.configure();
+    }
+
+    /// Clears filter memory, for example after seeking
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub(crate) fn reset(&mut self) {
+        self.states.clear();
+        self.limiter_gain = 1f32;
+        self.configure();
+    }
+
+    /// Processes one interleaved frame in place
+    pub(crate) fn process(&mut self, frame: &mut [f32]) {
+        if !self.settings.enabled {
+            return;
+        }
+
+        for (sample, states) in frame.iter_mut().zip(self.states.iter_mut()) {
+            let mut x = *sample * self.preamp;
+            for (filter, state) in self.filters.iter().zip(states.iter_mut()) {
+                x = state.process(filter, x);
+            }
+            *sample = x;
+        }
+
+        if frame.len() >= 2 && self.settings.balance != 0f32 {
+            let balance = self.settings.balance.clamp(-1f32, 1f32);
+            frame[0] *= (1f32 - balance).min(1f32);
+            frame[1] *= (1f32 + balance).min(1f32);
+        }
+
+        if self.settings.mono && frame.len() >= 2 {
+            let mixed = frame.iter().sum::<f32>() / frame.len() as f32;
+            frame.iter_mut().for_each(|s| *s = mixed);
+        }
+
+        if self.settings.limiter {
+            // Instant attack so peaks never pass, slow release to avoid pumping
+            let peak = frame.iter().fold(0f32, |p, s| p.max(s.abs()));
+            let target = if peak > LIMITER_THRESHOLD {
+                LIMITER_THRESHOLD / peak
+            } else {
+                1f32
+            };
+            if target < self.limiter_gain {
+                self.limiter_gain = target;
+            } else {
+                self.limiter_gain += (target - self.limiter_gain) * self.limiter_release;
+            }
+            frame.iter_mut().for_each(|s| *s *= self.limiter_gain);
+        }
+    }
+}
+
+/// Runs the samples of a source through the DSP chain, one frame at a time
+pub(crate) struct DspSource<S: Source> {
+    inner: S,
+    control: Arc<DspControl>,
+    version: u64,
+    chain: DspChain,
+    frame: Vec<f32>,
+    position: usize,
+}
+
+impl<S: Source> DspSource<S> {
+    #[tracing::instrument(level = "debug", skip(inner, control))]
+    pub(crate) fn new(inner: S, control: Arc<DspControl>) -> Self {
+        let version = control.version.load(Ordering::Acquire);
+        let chain = DspChain::new(control.get(), inner.channels(), inner.sample_rate());
+        Self {
+            inner,
+            control,
+            version,
+            chain,
+            frame: vec![],
+            position: 0,
+        }
+    }
+
+    fn next_frame(&mut self) -> bool {
+        let version = self.control.version.load(Ordering::Acquire);
+        if version != self.version {
+            self.version = version;
+            self.chain.set_settings(self.control.get());
+        }
+
+        let (channels, sample_rate) = (self.inner.channels(), self.inner.sample_rate());
+        if channels != self.chain.channels || sample_rate != self.chain.sample_rate {
+            self.chain.set_format(channels, sample_rate);
+        }
+
+        self.frame.clear();
+        self.frame
+            .extend(self.inner.by_ref().take(channels.max(1) as usize));
+        if self.frame.is_empty() {
+            return false;
+        }
+
+        self.chain.process(&mut self.frame);
+        self.position = 0;
+        true
+    }
+}
+
+impl<S: Source> Iterator for DspSource<S> {
+    type Item = f32;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.position >= self.frame.len() && !self.next_frame() {
+            return None;
+        }
+        let sample = self.frame[self.position];
+        self.position += 1;
+        Some(sample)
+    }
+}
+
+impl<S: Source> Source for DspSource<S> {
+    fn current_span_len(&self) -> Option<usize> {
+        // Samples of the buffered frame were already taken from the inner source
+        self.inner
+            .current_span_len()
+            .map(|len| len + self.frame.len() - self.position)
+    }
+
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.frame.clear();
+        self.position = 0;
+        self.chain.reset();
+        self.inner.try_seek(pos)
+    }
+}
@@ -16,9 +16,11 @@
 
 use std::{
     fs,
+    io::{Read, Seek},
     path::PathBuf,
     str::FromStr,
     sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
         mpsc::{channel, Receiver, Sender},
         Arc, Mutex,
     },
@@ -27,59 +29,205 @@ use std::{
 };
 
 use hls_client::{config::ConfigBuilder, stream::HLSStream};
-use rodio::Sink;
-use stream_download::{storage::temp::TempStorageProvider, Settings, StreamDownload};
-use tracing::{debug, error, info, trace};
+use rodio::{cpal::StreamError, source::EmptyCallback, OutputStream, Sink, Source};
+use stream_download::{
+    async_read::AsyncReadStreamParams, storage::temp::TempStorageProvider, Settings, StreamDownload,
+};
+use tracing::{debug, error, info, trace, warn};
 use types::{
+    dsp::DspSettings,
     errors::Result,
-    ui::player_details::PlayerEvents,
+    ui::player_details::{OutputDevice, PlayerEvents},
 };
 use types::errors::error_helpers;
 
+mod cache;
+mod crossfade;
+mod dash;
+mod dsp;
+mod loudness;
+mod output;
+mod range;
+mod replaygain;
+pub use cache::{AudioCache, AudioCacheSettings, EvictionPolicy};
+pub use crossfade::{CrossfadeCurve, CrossfadeSettings};
+pub use loudness::{analyse_loudness, Loudness};
+pub use replaygain::{ReplayGainMode, ReplayGainSettings, TrackGain};
+use dsp::{DspControl, DspSource};
+use range::{split_time_range, RangeSource};
+
+/// Boxed decoder of any supported source, ready to be appended to the sink
+type RodioSource = Box<dyn Source + Send>;
+
+/// Reader over a remote src, which blocks until the bytes being read are downloaded
+pub(crate) trait RemoteReader: Read + Seek + Send + Sync {}
+
+impl<T: Read + Seek + Send + Sync> RemoteReader for T {}
+
+/// Remote src opened for reading
+pub(crate) struct RemoteStream {
+    reader: Box<dyn RemoteReader>,
+    content_length: Option<u64>,
+    /// Duration listed by the manifest of the stream, if it has one
+    duration: Option<Duration>,
+}
 
+/// Default interval between position updates while playing
+const DEFAULT_POSITION_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Default)]
+struct PlaybackState {
+    /// Bumped every time the sink is cleared so callbacks of dropped sources are ignored
+    generation: u64,
+    current: Option<String>,
+    current_duration: Option<Duration>,
+    /// ReplayGain volume of the current src, kept to reload it at the same level
+    current_gain: f32,
+    preloaded: Option<String>,
+    preloaded_duration: Option<Duration>,
+    preloaded_gain: f32,
+    /// Stops the preloaded source if it is cancelled before it starts playing
+    preload_cancelled: Option<Arc<AtomicBool>>,
+    /// Preloaded src which is already playing but hasn't been loaded by the UI yet
+    gapless: Option<String>,
+}
 
 pub struct RodioPlayer {
     tx: Sender<RodioCommand>,
     events_rx: Arc<Mutex<Receiver<PlayerEvents>>>,
+    dsp: Arc<DspControl>,
+    /// Interval between position updates in milliseconds
+    position_interval: Arc<AtomicU64>,
 }
 
 enum RodioCommand {
-    SetSrc(String),
+    SetSrc(String, f32),
+    Preload(String, bool, f32),
+    /// A preloaded src finished opening, along with where its crossfade starts
+    PreloadReady(
+        u64,
+        String,
+        f32,
+        Result<RodioSource>,
+        Option<(CrossfadeSettings, Duration)>,
+    ),
+    CancelPreload,
+    StartCrossfade(u64),
+    SetCrossfade(Option<CrossfadeSettings>),
     Play,
     Pause,
     Stop,
     SetVolume(f32),
-    Seek(u64),
+    Seek(Duration),
+    ReportPosition,
+    SetDevice(Option<String>),
+    /// The device of the stream with this id went away
+    DeviceLost(u64),
 }
 
 impl RodioPlayer {
+    /// Plays to the output device called `device`, or the default device if None
     #[tracing::instrument(level = "debug", skip())]
-    pub fn new(cache_dir: PathBuf) -> Self {
+    pub fn new(cache_dir: PathBuf, device: Option<String>) -> Self {
         let (events_tx, events_rx) = channel::<PlayerEvents>();
         let cache_dir = cache_dir.join("rodio");
         if !cache_dir.exists() {
             fs::create_dir(cache_dir.clone()).unwrap();
         }
-        let tx = Self::initialize(events_tx, cache_dir);
+        let dsp = Arc::new(DspControl::default());
+        let position_interval =
+            Arc::new(AtomicU64::new(DEFAULT_POSITION_INTERVAL.as_millis() as u64));
+        let tx = Self::initialize(events_tx, cache_dir, dsp.clone(), device);
+        Self::report_position(tx.clone(), position_interval.clone());
         Self {
             tx,
             events_rx: Arc::new(Mutex::new(events_rx)),
+            dsp,
+            position_interval,
+        }
+    }
+
+    /// Asks the player thread for its position every `interval` milliseconds.
+    /// The interval is read again before every update so changes apply right away
+    fn report_position(tx: Sender<RodioCommand>, interval: Arc<AtomicU64>) {
+        thread::spawn(move || loop {
+            thread::sleep(Duration::from_millis(interval.load(Ordering::Relaxed)));
+            if tx.send(RodioCommand::ReportPosition).is_err() {
+                break;
+            }
+        });
+    }
+
+    /// Decodes a remote stream. Knowing the byte length lets the decoder work out the
+    /// duration of streams whose tags don't have it, and seek within them
+    fn decode_stream<R>(reader: R, byte_len: Option<u64>) -> Result<RodioSource>
+    where
+        R: Read + Seek + Send + Sync + 'static,
+    {
+        let mut builder = rodio::Decoder::builder()
+            .with_data(reader)
+            .with_seekable(true);
+        if let Some(byte_len) = byte_len {
+            builder = builder.with_byte_len(byte_len);
+        }
+        let decoder = builder.build().map_err(error_helpers::to_playback_error)?;
+        trace!("Decoder created, duration {:?}", decoder.total_duration());
+
+        Ok(Box::new(decoder))
+    }
+
+    /// Opens a src played at `gain` times its volume, as set by ReplayGain
+    async fn open_src_with_gain(cache_dir: PathBuf, src: &str, gain: f32) -> Result<RodioSource> {
+        let source = Self::open_src(cache_dir, src).await?;
+        if gain == 1f32 {
+            return Ok(source);
         }
+        Ok(Box::new(source.amplify(gain)))
     }
 
-    async fn set_src(cache_dir: PathBuf, src: String, sink: &Arc<Sink>) -> Result<()> {
+    /// Local srcs may end with a media fragment like `#t=12.5,245` to play only that part
+    /// of the file, which is how tracks of CUE sheets are played
+    async fn open_src(cache_dir: PathBuf, src: &str) -> Result<RodioSource> {
+        if !src.starts_with("http") {
+            if let Some((file, range)) = split_time_range(src) {
+                let source = Self::handle_local_file(file).await?;
+                let source =
+                    RangeSource::new(source, range).map_err(error_helpers::to_playback_error)?;
+                return Ok(Box::new(source));
+            }
+        }
+
+        if src.contains(".m3u8") || src.starts_with("http") {
+            let stream = Self::open_remote(cache_dir, src).await?;
+            let source = Self::decode_stream(stream.reader, stream.content_length)?;
+            if stream.duration.is_some() {
+                return Ok(Box::new(dash::WithDuration::new(source, stream.duration)));
+            }
+            Ok(source)
+        } else {
+            Self::handle_local_file(src).await
+        }
+    }
+
+    /// Opens a remote src for reading, downloading it into `cache_dir` as it is read
+    pub(crate) async fn open_remote(cache_dir: PathBuf, src: &str) -> Result<RemoteStream> {
         if src.ends_with(".m3u8") || src.contains(".m3u8") {
-            Self::handle_hls_stream(cache_dir.clone(), &src, sink).await?;
+            Self::handle_hls_stream(cache_dir, src).await
+        } else if src.starts_with("http") && src.contains(".mpd") {
+            Self::handle_dash_stream(cache_dir, src).await
         } else if src.starts_with("http") {
-            Self::handle_http_stream(cache_dir.clone(), &src, sink).await?;
+            // Providers also serve DASH manifests from urls without the extension
+            if dash::is_manifest(src).await {
+                Self::handle_dash_stream(cache_dir, src).await
+            } else {
+                Self::handle_http_stream(cache_dir, src).await
+            }
         } else {
-            Self::handle_local_file(&src, sink).await?;
+            Err(format!("{} is not a remote src", src).into())
         }
-
-        Ok(())
     }
 
-    async fn handle_hls_stream(cache_dir: PathBuf, src: &str, sink: &Arc<Sink>) -> Result<()> {
+    async fn handle_hls_stream(cache_dir: PathBuf, src: &str) -> Result<RemoteStream> {
         let reader = StreamDownload::new::<HLSStream>(
             ConfigBuilder::new().url(src).map_err(error_helpers::to_playback_error)?.build().map_err(error_helpers::to_playback_error)?,
             TempStorageProvider::new_in(cache_dir.clone()),
@@ -88,18 +236,45 @@ impl RodioPlayer {
         .await
         .map_err(error_helpers::to_playback_error)?;
 
-        info!("HLS Stream content length {:?}", reader.content_length());
+        let content_length = reader.content_length();
+        info!("HLS Stream content length {:?}", content_length);
         trace!("Stream created");
 
-        let decoder = rodio::Decoder::new(reader).map_err(error_helpers::to_playback_error)?;
-        trace!("Decoder created");
-        sink.append(decoder);
-        trace!("Decoder appended");
+        Ok(RemoteStream {
+            reader: Box::new(reader),
+            content_length,
+            duration: None,
+        })
+    }
 
-        Ok(())
+    async fn handle_dash_stream(cache_dir: PathBuf, src: &str) -> Result<RemoteStream> {
+        let playlist = dash::load_playlist(src).await?;
+
+        // Representations without segments are a single file
+        let mut stream = if let [url] = playlist.segments.as_slice() {
+            Self::handle_http_stream(cache_dir, url.as_str()).await?
+        } else {
+            let reader = StreamDownload::new_async_read(
+                AsyncReadStreamParams::new(dash::segment_reader(playlist.segments)),
+                TempStorageProvider::new_in(cache_dir.clone()),
+                Settings::default(),
+            )
+            .await
+            .map_err(error_helpers::to_playback_error)?;
+            trace!("DASH stream created");
+
+            RemoteStream {
+                reader: Box::new(reader),
+                content_length: None,
+                duration: None,
+            }
+        };
+
+        stream.duration = playlist.duration;
+        Ok(stream)
     }
 
-    async fn handle_http_stream(cache_dir: PathBuf, src: &str, sink: &Arc<Sink>) -> Result<()> {
+    async fn handle_http_stream(cache_dir: PathBuf, src: &str) -> Result<RemoteStream> {
         trace!("Creating HTTP stream");
 
         match StreamDownload::new_http(
@@ -116,32 +291,99 @@ impl RodioPlayer {
             Ok(reader) => {
                 trace!("Stream created");
 
-                let decoder = rodio::Decoder::new(reader).map_err(error_helpers::to_playback_error)?;
-                trace!("Decoder created");
-                sink.append(decoder);
-                trace!("Decoder appended");
-
-                Ok(())
+                let content_length = reader.content_length();
+                Ok(RemoteStream {
+                    reader: Box::new(reader),
+                    content_length,
+                    duration: None,
+                })
             }
             Err(e) => Err(e.to_string().into()),
         }
     }
 
-    async fn handle_local_file(src: &str, sink: &Arc<Sink>) -> Result<()> {
+    async fn handle_local_file(src: &str) -> Result<RodioSource> {
         let path = PathBuf::from_str(src).unwrap();
         if path.exists() {
             let file = fs::File::open(path)?;
             let decoder = rodio::Decoder::try_from(file).map_err(error_helpers::to_playback_error)?;
-            sink.append(decoder);
 
-            trace!("Local file {} appended", src);
+            trace!("Local file {} decoded", src);
 
-            return Ok(());
+            return Ok(Box::new(decoder));
         }
 
         Err("Failed to read local file".into())
     }
 
+    /// Appends a source, run through the DSP chain, followed by a callback which runs
+    /// exactly when the source finishes playing. If another source was preloaded behind
+    /// it, it becomes the current source and keeps playing without a gap.
+    /// A source appended with `cancelled` is skipped, without ending, once the flag is set
+    fn append_src(
+        sink: &Sink,
+        source: RodioSource,
+        generation: u64,
+        cancelled: Option<Arc<AtomicBool>>,
+        state: Arc<Mutex<PlaybackState>>,
+        events_tx: Sender<PlayerEvents>,
+        dsp: Arc<DspControl>,
+    ) {
+        let source: RodioSource = match cancelled.clone() {
+            Some(cancelled) => Box::new(source.stoppable().periodic_access(
+                Duration::from_millis(50),
+                move |source| {
+                    if cancelled.load(Ordering::Relaxed) {
+                        source.stop();
+                    }
+                },
+            )),
+            None => source,
+        };
+        sink.append(DspSource::new(source, dsp));
+        sink.append(EmptyCallback::new(Box::new(move || {
+            {
+                let mut state = state.lock().unwrap();
+                if state.generation != generation
+                    || cancelled
+                        .as_ref()
+                        .is_some_and(|c| c.load(Ordering::Relaxed))
+                {
+                    return;
+                }
+                if let Some(next) = state.preloaded.take() {
+                    info!("Gapless transition to src={}", next);
+                    state.current = Some(next.clone());
+                    state.current_duration = state.preloaded_duration.take();
+                    state.current_gain = state.preloaded_gain;
+                    state.preload_cancelled = None;
+                    state.gapless = Some(next);
+                }
+            }
+            Self::send_event(events_tx.clone(), PlayerEvents::Ended);
+        })));
+    }
+
+    /// Sends `StartCrossfade` once the sink reaches `start_at`, unless the src changes before
+    fn watch_crossfade_start(
+        sink: Arc<Sink>,
+        start_at: Duration,
+        generation: u64,
+        state: Arc<Mutex<PlaybackState>>,
+        tx: Sender<RodioCommand>,
+    ) {
+        thread::spawn(move || loop {
+            thread::sleep(Duration::from_millis(50));
+            if state.lock().unwrap().generation != generation || sink.empty() {
+                break;
+            }
+            if sink.get_pos() >= start_at {
+                let _ = tx.send(RodioCommand::StartCrossfade(generation));
+                break;
+            }
+        });
+    }
+
     pub fn get_events_rx(&self) -> Arc<Mutex<Receiver<PlayerEvents>>> {
         self.events_rx.clone()
     }
@@ -150,13 +392,108 @@ impl RodioPlayer {
         events_tx.send(event).unwrap();
     }
 
-    fn initialize(events_tx: Sender<PlayerEvents>, cache_dir: PathBuf) -> Sender<RodioCommand> {
+    /// Opens an output stream which sends `DeviceLost` once if its device goes away
+    fn open_output(
+        device: Option<&str>,
+        stream_id: u64,
+        tx: Sender<RodioCommand>,
+    ) -> Result<OutputStream> {
+        let mut reported = false;
+        output::open_stream(device, move |err| {
+            error!("Output stream error: {:?}", err);
+            if !reported && matches!(err, StreamError::DeviceNotAvailable) {
+                reported = true;
+                let _ = tx.send(RodioCommand::DeviceLost(stream_id));
+            }
+        })
+    }
+
+    /// Opens `device`, falling back to the default device. Without any usable device
+    /// an error is reported and playback stays silent until a device is set
+    fn open_output_or_default(
+        device: Option<&str>,
+        stream_id: u64,
+        tx: Sender<RodioCommand>,
+        events_tx: Sender<PlayerEvents>,
+    ) -> Option<OutputStream> {
+        let res = Self::open_output(device, stream_id, tx.clone()).or_else(|err| {
+            if device.is_none() {
+                return Err(err);
+            }
+            warn!(
+                "Failed to open {:?}, using the default device: {:?}",
+                device, err
+            );
+            Self::open_output(None, stream_id, tx)
+        });
+
+        match res {
+            Ok(stream) => Some(stream),
+            Err(err) => {
+                error!("Failed to open an output device: {:?}", err);
+                Self::send_event(events_tx, PlayerEvents::Error(err));
+                None
+            }
+        }
+    }
+
+    /// Moves playback to a sink on `stream`, reloading the current src at the same position
+    fn switch_stream(
+        sink: &mut Arc<Sink>,
+        fading: &mut Option<Arc<Sink>>,
+        stream: Option<&OutputStream>,
+        volume: f32,
+        state: &Mutex<PlaybackState>,
+        tx: &Sender<RodioCommand>,
+    ) {
+        let resume = {
+            let mut state = state.lock().unwrap();
+            state.preloaded = None;
+            state.gapless = None;
+            state
+                .current
+                .clone()
+                .filter(|_| !sink.empty())
+                .map(|src| (src, state.current_gain, sink.get_pos(), sink.is_paused()))
+        };
+
+        if let Some(fading) = fading.take() {
+            fading.stop();
+        }
+        sink.stop();
+        *sink = Arc::new(output::connect_sink(stream));
+        sink.set_volume(volume);
+
+        if let Some((src, gain, pos, paused)) = resume {
+            let _ = tx.send(RodioCommand::SetSrc(src, gain));
+            let _ = tx.send(RodioCommand::Seek(pos));
+            let _ = tx.send(if paused {
+                RodioCommand::Pause
+            } else {
+                RodioCommand::Play
+            });
+        }
+    }
+
+    fn initialize(
+        events_tx: Sender<PlayerEvents>,
+        cache_dir: PathBuf,
+        dsp: Arc<DspControl>,
+        device: Option<String>,
+    ) -> Sender<RodioCommand> {
         let (tx, rx) = channel::<RodioCommand>();
         let ret = tx.clone();
 
         thread::spawn(move || {
-            let stream_handle = rodio::OutputStreamBuilder::open_default_stream().unwrap();
-            let sink = Arc::new(rodio::Sink::connect_new(stream_handle.mixer()));
+            // Bumped for every opened stream so errors of replaced streams are ignored
+            let mut stream_id = 0u64;
+            let mut stream_handle = Self::open_output_or_default(
+                device.as_deref(),
+                stream_id,
+                tx.clone(),
+                events_tx.clone(),
+            );
+            let mut sink = Arc::new(output::connect_sink(stream_handle.as_ref()));
 
             let runtime = tokio::runtime::Builder::new_multi_thread()
                 .enable_all()
@@ -165,95 +502,389 @@ impl RodioPlayer {
 
             let events_tx = events_tx.clone();
             runtime.block_on(async move {
-                let last_src = Arc::new(Mutex::new(None));
-                while let Ok(command) = rx.recv() {
-                    let sink = sink.clone();
+                let state = Arc::new(Mutex::new(PlaybackState::default()));
+                let volume = Arc::new(Mutex::new(1f32));
+                let mut crossfade: Option<CrossfadeSettings> = None;
+                // Preloaded source waiting for the current one to reach the crossfade
+                let mut pending: Option<(u64, String, f32, RodioSource, CrossfadeSettings)> = None;
+                // Generation and src of the preload which is still being opened
+                let mut preloading: Option<(u64, String)> = None;
+                // Sink of the previous source while it fades out
+                let mut fading: Option<Arc<Sink>> = None;
 
+                while let Ok(command) = rx.recv() {
                     match command {
-                        RodioCommand::SetSrc(src) => {
-                            let last_src = last_src.clone();
-                            {
-                                let mut last_src = last_src.lock().unwrap();
-                                *last_src = Some(src.clone());
+                        RodioCommand::SetSrc(src, gain) => {
+                            let generation = {
+                                let mut state = state.lock().unwrap();
+                                if state.gapless.take().as_ref() == Some(&src) {
+                                    Err(state.current_duration)
+                                } else {
+                                    state.generation += 1;
+                                    state.current = Some(src.clone());
+                                    state.current_duration = None;
+                                    state.current_gain = gain;
+                                    state.preloaded = None;
+                                    Ok(state.generation)
+                                }
+                            };
+
+                            // The preloaded src is already playing
+                            let generation = match generation {
+                                Ok(generation) => generation,
+                                Err(duration) => {
+                                    debug!("Src already playing");
+                                    if let Some(duration) = duration {
+                                        Self::send_event(
+                                            events_tx.clone(),
+                                            PlayerEvents::DurationChange(duration.as_secs_f64()),
+                                        );
+                                    }
+                                    Self::send_event(
+                                        events_tx.clone(),
+                                        PlayerEvents::TimeUpdate(sink.get_pos().as_secs_f64()),
+                                    );
+                                    continue;
+                                }
+                            };
+
+                            pending = None;
+                            preloading = None;
+                            if let Some(fading) = fading.take() {
+                                fading.stop();
                             }
 
                             sink.clear();
                             Self::send_event(events_tx.clone(), PlayerEvents::TimeUpdate(0f64));
                             Self::send_event(events_tx.clone(), PlayerEvents::Loading);
 
-                            if let Err(err) =
-                                Self::set_src(cache_dir.clone(), src.clone(), &sink).await
+                            match Self::open_src_with_gain(cache_dir.clone(), &src, gain).await {
+                                Ok(source) => {
+                                    debug!("Set src");
+                                    let duration = source.total_duration();
+                                    state.lock().unwrap().current_duration = duration;
+                                    if let Some(duration) = duration {
+                                        Self::send_event(
+                                            events_tx.clone(),
+                                            PlayerEvents::DurationChange(duration.as_secs_f64()),
+                                        );
+                                    }
+                                    Self::append_src(
+                                        &sink,
+                                        source,
+                                        generation,
+                                        None,
+                                        state.clone(),
+                                        events_tx.clone(),
+                                        dsp.clone(),
+                                    );
+                                }
+                                Err(err) => {
+                                    error!("Failed to set src: {:?}", err);
+                                    Self::send_event(events_tx.clone(), PlayerEvents::Error(err))
+                                }
+                            }
+                        }
+                        RodioCommand::Preload(src, allow_crossfade, gain) => {
+                            // Only one source can be queued behind the current one
+                            let preload = {
+                                let mut state = state.lock().unwrap();
+                                if sink.empty()
+                                    || state.preloaded.is_some()
+                                    || pending.is_some()
+                                    || preloading.is_some()
+                                {
+                                    None
+                                } else {
+                                    // Crossfading needs to know when the current source ends
+                                    let fade =
+                                        crossfade.filter(|_| allow_crossfade).and_then(|c| {
+                                            let start_at =
+                                                state.current_duration?.checked_sub(c.duration)?;
+                                            Some((c, start_at))
+                                        });
+                                    if fade.is_none() {
+                                        state.preloaded = Some(src.clone());
+                                        state.preloaded_gain = gain;
+                                    }
+                                    Some((state.generation, fade))
+                                }
+                            };
+                            let Some((generation, fade)) = preload else {
+                                debug!("Nothing playing or already preloaded, skipping preload");
+                                continue;
+                            };
+
+                            // Opening a remote src can take a while, commands are handled meanwhile
+                            preloading = Some((generation, src.clone()));
+                            let tx = tx.clone();
+                            let cache_dir = cache_dir.clone();
+                            tokio::spawn(async move {
+                                let source = Self::open_src_with_gain(cache_dir, &src, gain).await;
+                                let _ = tx.send(RodioCommand::PreloadReady(
+                                    generation, src, gain, source, fade,
+                                ));
+                            });
+                        }
+                        RodioCommand::PreloadReady(generation, src, gain, source, fade) => {
+                            // The song changed or the preload was cancelled while opening
+                            if preloading.take() != Some((generation, src.clone()))
+                                || state.lock().unwrap().generation != generation
                             {
-                                error!("Failed to set src: {:?}", err);
-                                Self::send_event(events_tx.clone(), PlayerEvents::Error(err))
-                            } else {
-                                debug!("Set src");
-                                let src_clone = src.clone();
-
-                                let events_tx = events_tx.clone();
-                                let sink = sink.clone();
-
-                                // Send ended event only if song hasn't changed yet
-                                thread::spawn(move || {
-                                    sink.sleep_until_end();
-                                    let last_src = last_src.clone();
-                                    let last_src = last_src.lock().unwrap();
-                                    if let Some(last_src) = last_src.clone() {
-                                        info!("last src={}, current src={}", last_src, src_clone);
-                                        if last_src == src_clone {
-                                            Self::send_event(
-                                                events_tx.clone(),
-                                                PlayerEvents::Ended,
-                                            );
+                                debug!("Dropping outdated preload of src={}", src);
+                                continue;
+                            }
+
+                            match source {
+                                Ok(source) => {
+                                    if let Some((settings, start_at)) = fade {
+                                        debug!("Crossfading into src={} at {:?}", src, start_at);
+                                        pending = Some((generation, src, gain, source, settings));
+                                        Self::watch_crossfade_start(
+                                            sink.clone(),
+                                            start_at,
+                                            generation,
+                                            state.clone(),
+                                            tx.clone(),
+                                        );
+                                    } else {
+                                        debug!("Preloaded src={}", src);
+                                        let cancelled = Arc::new(AtomicBool::new(false));
+                                        {
+                                            let mut state = state.lock().unwrap();
+                                            state.preloaded_duration = source.total_duration();
+                                            state.preload_cancelled = Some(cancelled.clone());
                                         }
+                                        Self::append_src(
+                                            &sink,
+                                            source,
+                                            generation,
+                                            Some(cancelled),
+                                            state.clone(),
+                                            events_tx.clone(),
+                                            dsp.clone(),
+                                        );
                                     }
-                                });
+                                }
+                                Err(err) => {
+                                    // The src will be loaded again once it becomes current
+                                    error!("Failed to preload src: {:?}", err);
+                                    let mut state = state.lock().unwrap();
+                                    if state.preloaded.as_ref() == Some(&src) {
+                                        state.preloaded = None;
+                                    }
+                                }
                             }
                         }
+                        RodioCommand::CancelPreload => {
+                            pending = None;
+                            preloading = None;
+                            let mut state = state.lock().unwrap();
+                            if let Some(src) = state.preloaded.take() {
+                                debug!("Cancelling preload of src={}", src);
+                                state.preloaded_duration = None;
+                                if let Some(cancelled) = state.preload_cancelled.take() {
+                                    cancelled.store(true, Ordering::Relaxed);
+                                }
+                            }
+                        }
+                        RodioCommand::StartCrossfade(generation) => {
+                            if !pending.as_ref().is_some_and(|(g, ..)| *g == generation) {
+                                continue;
+                            }
+                            let Some((_, src, gain, source, settings)) = pending.take() else {
+                                continue;
+                            };
+
+                            let next = Arc::new(output::connect_sink(stream_handle.as_ref()));
+                            next.set_volume(0f32);
+                            if sink.is_paused() {
+                                next.pause();
+                            }
+
+                            let (generation, duration) = {
+                                let mut state = state.lock().unwrap();
+                                if state.generation != generation {
+                                    continue;
+                                }
+                                // The fade can't outlast the current source if it was seeked past the start
+                                let duration = state
+                                    .current_duration
+                                    .map(|d| d.saturating_sub(sink.get_pos()))
+                                    .unwrap_or(settings.duration)
+                                    .min(settings.duration);
+
+                                state.generation += 1;
+                                state.current = Some(src.clone());
+                                state.current_duration = source.total_duration();
+                                state.current_gain = gain;
+                                state.gapless = Some(src.clone());
+                                (state.generation, duration)
+                            };
+
+                            info!("Crossfading to src={} over {:?}", src, duration);
+                            Self::append_src(
+                                &next,
+                                source,
+                                generation,
+                                None,
+                                state.clone(),
+                                events_tx.clone(),
+                                dsp.clone(),
+                            );
+
+                            if let Some(fading) = fading.take() {
+                                fading.stop();
+                            }
+                            let previous = std::mem::replace(&mut sink, next.clone());
+                            fading = Some(previous.clone());
+
+                            let state = state.clone();
+                            crossfade::fade(
+                                previous,
+                                next,
+                                duration,
+                                settings.curve,
+                                volume.clone(),
+                                move || state.lock().unwrap().generation == generation,
+                            );
+
+                            // The next track is audible from here on
+                            Self::send_event(events_tx.clone(), PlayerEvents::Ended);
+                        }
+                        RodioCommand::SetCrossfade(settings) => {
+                            debug!("Setting crossfade {:?}", settings);
+                            crossfade = settings;
+                        }
                         RodioCommand::Play => {
                             if !sink.empty() {
                                 sink.play();
+                                if let Some(fading) = fading.as_ref() {
+                                    fading.play();
+                                }
                                 Self::send_event(events_tx.clone(), PlayerEvents::Play)
                             }
                         }
                         RodioCommand::Pause => {
                             if !sink.empty() {
                                 sink.pause();
+                                if let Some(fading) = fading.as_ref() {
+                                    fading.pause();
+                                }
+                                Self::send_event(
+                                    events_tx.clone(),
+                                    PlayerEvents::TimeUpdate(sink.get_pos().as_secs_f64()),
+                                );
                                 Self::send_event(events_tx.clone(), PlayerEvents::Pause)
                             }
                         }
                         RodioCommand::Stop => {
+                            {
+                                let mut state = state.lock().unwrap();
+                                state.generation += 1;
+                                state.preloaded = None;
+                                state.gapless = None;
+                            }
+                            pending = None;
+                            preloading = None;
+                            if let Some(fading) = fading.take() {
+                                fading.stop();
+                            }
                             if !sink.empty() {
                                 sink.stop();
                                 sink.clear();
                                 Self::send_event(events_tx.clone(), PlayerEvents::Pause)
                             }
                         }
-                        RodioCommand::SetVolume(volume) => {
+                        RodioCommand::SetVolume(new_volume) => {
+                            *volume.lock().unwrap() = new_volume;
                             if !sink.empty() {
-                                sink.set_volume(volume);
+                                sink.set_volume(new_volume);
                             }
                         }
                         RodioCommand::Seek(pos) => {
                             if !sink.empty() {
-                                if let Err(err) = sink.try_seek(Duration::from_secs(pos)) {
+                                if let Err(err) = sink.try_seek(pos) {
                                     error!("Failed to seek: {:?}", err)
                                 } else {
                                     Self::send_event(
                                         events_tx.clone(),
-                                        PlayerEvents::TimeUpdate(pos as f64),
+                                        PlayerEvents::TimeUpdate(pos.as_secs_f64()),
                                     )
                                 }
                             } else {
-                                let last_src = last_src.clone();
-                                let last_src = last_src.lock().unwrap();
-                                if let Some(last_src) = last_src.clone() {
-                                    tx.send(RodioCommand::SetSrc(last_src.clone())).unwrap();
+                                let (last_src, gain) = {
+                                    let state = state.lock().unwrap();
+                                    (state.current.clone(), state.current_gain)
+                                };
+                                if let Some(last_src) = last_src {
+                                    tx.send(RodioCommand::SetSrc(last_src.clone(), gain)).unwrap();
                                     tx.send(RodioCommand::Seek(pos)).unwrap();
                                     tx.send(RodioCommand::Play).unwrap();
                                 }
                             }
                         }
+                        RodioCommand::ReportPosition => {
+                            if !sink.empty() && !sink.is_paused() {
+                                Self::send_event(
+                                    events_tx.clone(),
+                                    PlayerEvents::TimeUpdate(sink.get_pos().as_secs_f64()),
+                                );
+                            }
+                        }
+                        RodioCommand::SetDevice(device) => {
+                            stream_id += 1;
+                            match Self::open_output(device.as_deref(), stream_id, tx.clone()) {
+                                Ok(stream) => {
+                                    info!("Switching output device to {:?}", device);
+                                    pending = None;
+                                    Self::switch_stream(
+                                        &mut sink,
+                                        &mut fading,
+                                        Some(&stream),
+                                        *volume.lock().unwrap(),
+                                        &state,
+                                        &tx,
+                                    );
+                                    stream_handle = Some(stream);
+                                }
+                                Err(err) => {
+                                    error!("Failed to switch output device: {:?}", err);
+                                    Self::send_event(events_tx.clone(), PlayerEvents::Error(err))
+                                }
+                            }
+                        }
+                        RodioCommand::DeviceLost(id) => {
+                            if id != stream_id {
+                                continue;
+                            }
+                            warn!("Output device disconnected, switching to the default device");
+                            Self::send_event(
+                                events_tx.clone(),
+                                PlayerEvents::Error(
+                                    "Output device disconnected, switching to the default device"
+                                        .into(),
+                                ),
+                            );
+
+                            stream_id += 1;
+                            let stream = Self::open_output_or_default(
+                                None,
+                                stream_id,
+                                tx.clone(),
+                                events_tx.clone(),
+                            );
+                            pending = None;
+                            Self::switch_stream(
+                                &mut sink,
+                                &mut fading,
+                                stream.as_ref(),
+                                *volume.lock().unwrap(),
+                                &state,
+                                &tx,
+                            );
+                            stream_handle = stream;
+                        }
                     }
                 }
             });
@@ -262,13 +893,62 @@ impl RodioPlayer {
         ret
     }
 
+    /// Loads a src, played at `gain` times its volume
     #[tracing::instrument(level = "debug", skip(self))]
-    pub async fn rodio_load(&self, src: String) -> Result<()> {
+    pub async fn rodio_load(&self, src: String, gain: f32) -> Result<()> {
         info!("Loading src={}", src);
-        self.tx.send(RodioCommand::SetSrc(src.clone())).unwrap();
+        self.tx.send(RodioCommand::SetSrc(src.clone(), gain)).unwrap();
         Ok(())
     }
 
+    /// Queues the next src behind the current one so it starts without a gap,
+    /// or crossfades into it if crossfading is enabled and allowed for this transition
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn rodio_preload(&self, src: String, crossfade: bool, gain: f32) -> Result<()> {
+        info!("Preloading src={}", src);
+        self.tx
+            .send(RodioCommand::Preload(src, crossfade, gain))
+            .unwrap();
+        Ok(())
+    }
+
+    /// Drops the preloaded src if it hasn't started playing yet, so the current src
+    /// ends normally and the next one has to be loaded or preloaded again
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn rodio_cancel_preload(&self) -> Result<()> {
+        self.tx.send(RodioCommand::CancelPreload).unwrap();
+        Ok(())
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn get_output_devices(&self) -> Result<Vec<OutputDevice>> {
+        output::output_devices()
+    }
+
+    /// Switches to the output device called `name`, or the default device if None,
+    /// keeping the current song playing at the same position
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn set_output_device(&self, name: Option<String>) {
+        self.tx.send(RodioCommand::SetDevice(name)).unwrap();
+    }
+
+    /// Applies to the playing source right away
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn set_dsp_settings(&self, settings: DspSettings) {
+        self.dsp.set(settings);
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn get_dsp_settings(&self) -> DspSettings {
+        self.dsp.get()
+    }
+
+    /// Crossfade used between tracks, None disables crossfading
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn set_crossfade(&self, settings: Option<CrossfadeSettings>) {
+        self.tx.send(RodioCommand::SetCrossfade(settings)).unwrap();
+    }
+
     #[tracing::instrument(level = "debug", skip(self))]
     pub async fn rodio_play(&self) -> Result<()> {
         self.tx.send(RodioCommand::Play).unwrap();
@@ -287,14 +967,21 @@ impl RodioPlayer {
         Ok(())
     }
 
+    /// Seeks to `pos` seconds, with sub-second precision
     #[tracing::instrument(level = "debug", skip(self))]
     pub async fn rodio_seek(&self, pos: f64) -> Result<()> {
-        self.tx
-            .send(RodioCommand::Seek(pos.abs().round() as u64))
-            .unwrap();
+        let pos = Duration::try_from_secs_f64(pos.max(0f64)).unwrap_or_default();
+        self.tx.send(RodioCommand::Seek(pos)).unwrap();
         Ok(())
     }
 
+    /// Interval between position updates while playing
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn set_position_interval(&self, interval: Duration) {
+        self.position_interval
+            .store(interval.as_millis() as u64, Ordering::Relaxed);
+    }
+
     #[tracing::instrument(level = "debug", skip(self))]
     pub async fn rodio_set_volume(&self, volume: f32) -> Result<()> {
         self.tx.send(RodioCommand::SetVolume(volume)).unwrap();
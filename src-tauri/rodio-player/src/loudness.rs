@@ -0,0 +1,213 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{f64::consts::PI, fs, path::Path};
+
+use rodio::Source;
+use types::errors::{error_helpers, Result};
+
+/// Loudness ReplayGain 2.0 normalizes to, in LUFS
+pub const REPLAYGAIN_REFERENCE: f64 = -18f64;
+
+/// Blocks quieter than this are silence and never count towards the loudness
+const ABSOLUTE_GATE: f64 = -70f64;
+
+/// Blocks this many LU below the ungated loudness are left out, so quiet passages
+/// don't drag the loudness of the whole track down
+const RELATIVE_GATE: f64 = -10f64;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Loudness {
+    /// Integrated loudness in LUFS, as defined by ITU-R BS.1770 / EBU R128
+    pub integrated: f64,
+    /// Highest absolute sample value
+    pub peak: f64,
+}
+
+impl Loudness {
+    /// Gain in dB which brings the track to the ReplayGain reference loudness
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn replaygain(&self) -> f64 {
+        REPLAYGAIN_REFERENCE - self.integrated
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    z: [f64; 2],
+}
+
+impl Biquad {
+    #[inline]
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.z[0];
+        self.z[0] = self.b[1] * x - self.a[0] * y + self.z[1];
+        self.z[1] = self.b[2] * x - self.a[1] * y;
+        y
+    }
+}
+
+/// K-weighting filter of BS.1770, a high shelf modelling the head followed by a high pass.
+/// The coefficients are derived for any sample rate instead of only the tabled 48 kHz
+#[tracing::instrument(level = "debug", skip())]
+fn k_weighting(sample_rate: f64) -> [Biquad; 2] {
+    let f0 = 1681.974450955533;
+    let gain = 3.999843853973347;
+    let q = 0.7071752369554196;
+    let k = (PI * f0 / sample_rate).tan();
+    let vh = 10f64.powf(gain / 20f64);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1f64 + k / q + k * k;
+    let shelf = Biquad {
+        b: [
+            (vh + vb * k / q + k * k) / a0,
+            2f64 * (k * k - vh) / a0,
+            (vh - vb * k / q + k * k) / a0,
+        ],
+        a: [2f64 * (k * k - 1f64) / a0, (1f64 - k / q + k * k) / a0],
+        z: [0f64; 2],
+    };
+
+    let f0 = 38.13547087602444;
+    let q = 0.5003270373238773;
+    let k = (PI * f0 / sample_rate).tan();
+    let a0 = 1f64 + k / q + k * k;
+    let high_pass = Biquad {
+        b: [1f64, -2f64, 1f64],
+        a: [2f64 * (k * k - 1f64) / a0, (1f64 - k / q + k * k) / a0],
+        z: [0f64; 2],
+    };
+
+    [shelf, high_pass]
+}
+
+/// Measures the loudness of interleaved samples pushed one frame at a time
+pub struct LoudnessMeter {
+    filters: Vec<[Biquad; 2]>,
+    weights: Vec<f64>,
+    /// Samples per 100ms step, blocks are 4 steps long
+    step_len: usize,
+    step_pos: usize,
+    step_power: f64,
+    steps: Vec<f64>,
+    blocks: Vec<f64>,
+    peak: f64,
+}
+
+impl LoudnessMeter {
+    #[tracing::instrument(level = "debug", skip())]
+    pub fn new(channels: u16, sample_rate: u32) -> Self {
+        let channels = channels.max(1) as usize;
+        // Surround channels are weighted higher and the LFE channel is ignored
+        let weights = (0..channels)
+            .map(|c| match (channels, c) {
+                (6, 3) => 0f64,
+                (6, 4) | (6, 5) => 1.41,
+                _ => 1f64,
+            })
+            .collect();
+
+        Self {
+            filters: vec![k_weighting(sample_rate.max(1) as f64); channels],
+            weights,
+            step_len: (sample_rate as usize / 10).max(1),
+            step_pos: 0,
+            step_power: 0f64,
+            steps: vec![],
+            blocks: vec![],
+            peak: 0f64,
+        }
+    }
+
+    pub fn push(&mut self, frame: &[f32]) {
+        for ((sample, filters), weight) in frame
+            .iter()
+            .zip(self.filters.iter_mut())
+            .zip(self.weights.iter())
+        {
+            let x = *sample as f64;
+            self.peak = self.peak.max(x.abs());
+            let shelved = filters[0].process(x);
+            let y = filters[1].process(shelved);
+            self.step_power += weight * y * y;
+        }
+
+        self.step_pos += 1;
+        if self.step_pos == self.step_len {
+            self.steps.push(self.step_power / self.step_len as f64);
+            self.step_pos = 0;
+            self.step_power = 0f64;
+
+            // 400ms blocks overlapping by 75%
+            if self.steps.len() >= 4 {
+                let power = self.steps[self.steps.len() - 4..].iter().sum::<f64>() / 4f64;
+                self.blocks.push(power);
+            }
+        }
+    }
+
+    /// None if the audio is too short or silent to be measured
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn finish(self) -> Option<Loudness> {
+        let loudness = |power: f64| -0.691 + 10f64 * power.log10();
+        let gated_mean = |threshold: f64| {
+            let gated: Vec<f64> = self
+                .blocks
+                .iter()
+                .copied()
+                .filter(|p| loudness(*p) > threshold)
+                .collect();
+            if gated.is_empty() {
+                None
+            } else {
+                Some(gated.iter().sum::<f64>() / gated.len() as f64)
+            }
+        };
+
+        let ungated = gated_mean(ABSOLUTE_GATE)?;
+        let integrated = gated_mean((loudness(ungated) + RELATIVE_GATE).max(ABSOLUTE_GATE))?;
+        Some(Loudness {
+            integrated: loudness(integrated),
+            peak: self.peak,
+        })
+    }
+}
+
+/// Decodes a local file and measures its loudness. This reads the whole file,
+/// so it should run off the main thread
+#[tracing::instrument(level = "debug", skip())]
+pub fn analyse_loudness(path: &Path) -> Result<Loudness> {
+    let file = fs::File::open(path)?;
+    let mut decoder = rodio::Decoder::try_from(file).map_err(error_helpers::to_playback_error)?;
+
+    let channels = decoder.channels();
+    let mut meter = LoudnessMeter::new(channels, decoder.sample_rate());
+    let mut frame = Vec::with_capacity(channels as usize);
+    loop {
+        frame.clear();
+        frame.extend(decoder.by_ref().take(channels as usize));
+        if frame.len() < channels as usize {
+            break;
+        }
+        meter.push(&frame);
+    }
+
+    meter
+        .finish()
+        .ok_or_else(|| format!("Failed to measure loudness of {:?}", path).into())
+}
@@ -0,0 +1,88 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use rodio::{
+    cpal::{
+        self,
+        traits::{DeviceTrait, HostTrait},
+        StreamError,
+    },
+    OutputStream, OutputStreamBuilder, Sink,
+};
+use tracing::warn;
+use types::{
+    errors::{error_helpers, Result},
+    ui::player_details::OutputDevice,
+};
+
+/// Output devices of the default audio host
+#[tracing::instrument(level = "debug", skip())]
+pub fn output_devices() -> Result<Vec<OutputDevice>> {
+    let host = cpal::default_host();
+    let default = host.default_output_device().and_then(|d| d.name().ok());
+    let devices = host
+        .output_devices()
+        .map_err(error_helpers::to_playback_error)?;
+
+    Ok(devices
+        .filter_map(|d| d.name().ok())
+        .map(|name| OutputDevice {
+            is_default: default.as_ref() == Some(&name),
+            name,
+        })
+        .collect())
+}
+
+/// Opens the output device called `name`, or the default device if None.
+/// `on_error` is called from the audio thread, for example when the device is unplugged
+#[tracing::instrument(level = "debug", skip(on_error))]
+pub(crate) fn open_stream(
+    name: Option<&str>,
+    on_error: impl FnMut(StreamError) + Send + 'static,
+) -> Result<OutputStream> {
+    let builder = match name {
+        Some(name) => {
+            let device = cpal::default_host()
+                .output_devices()
+                .map_err(error_helpers::to_playback_error)?
+                .find(|d| d.name().is_ok_and(|n| n == name))
+                .ok_or(format!("Output device {} not found", name))?;
+            OutputStreamBuilder::from_device(device)
+        }
+        None => OutputStreamBuilder::from_default_device(),
+    }
+    .map_err(error_helpers::to_playback_error)?;
+
+    let mut stream = builder
+        .with_error_callback(on_error)
+        .open_stream_or_fallback()
+        .map_err(error_helpers::to_playback_error)?;
+    stream.log_on_drop(false);
+    Ok(stream)
+}
+
+/// Sink playing to `stream`. Without a stream the sink is detached and stays silent
+/// until a device is available again
+#[tracing::instrument(level = "debug", skip(stream))]
+pub(crate) fn connect_sink(stream: Option<&OutputStream>) -> Sink {
+    match stream {
+        Some(stream) => Sink::connect_new(stream.mixer()),
+        None => {
+            warn!("No output device available");
+            Sink::new().0
+        }
+    }
+}
@@ -0,0 +1,190 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::time::Duration;
+
+use rodio::{source::SeekError, Source};
+
+/// Part of a file to play, like a track of a CUE sheet
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct TimeRange {
+    pub(crate) start: Duration,
+    /// None plays until the end of the file
+    pub(crate) end: Option<Duration>,
+}
+
+/// Splits a media fragment like `#t=12.5,245` off a src. Both times are in seconds
+pub(crate) fn split_time_range(src: &str) -> Option<(&str, TimeRange)> {
+    let (file, times) = src.rsplit_once("#t=")?;
+    let (start, end) = match times.split_once(',') {
+        Some((start, end)) => (start, Some(end)),
+        None => (times, None),
+    };
+
+    let parse = |time: &str| Duration::try_from_secs_f64(time.trim().parse().ok()?).ok();
+    let start = parse(start)?;
+    let end = match end {
+        Some(end) => Some(parse(end)?),
+        None => None,
+    };
+    if end.is_some_and(|end| end <= start) {
+        return None;
+    }
+    Some((file, TimeRange { start, end }))
+}
+
+/// Plays only the given range of a source. Positions, seeking and the duration are
+/// all relative to the start of the range
+pub(crate) struct RangeSource<S> {
+    inner: S,
+    range: TimeRange,
+    /// Samples left until the end of the range
+    remaining: Option<u64>,
+}
+
+impl<S: Source> RangeSource<S> {
+    pub(crate) fn new(mut inner: S, range: TimeRange) -> Result<Self, SeekError> {
+        if !range.start.is_zero() {
+            inner.try_seek(range.start)?;
+        }
+        let mut source = Self {
+            inner,
+            range,
+            remaining: None,
+        };
+        source.set_position(Duration::ZERO);
+        Ok(source)
+    }
+
+    fn set_position(&mut self, pos: Duration) {
+        let channels = self.inner.channels().max(1) as u64;
+        let sample_rate = self.inner.sample_rate() as f64;
+        self.remaining = self.range.end.map(|end| {
+            let left = end.saturating_sub(self.range.start + pos);
+            (left.as_secs_f64() * sample_rate).round() as u64 * channels
+        });
+    }
+}
+
+impl<S: Source> Iterator for RangeSource<S> {
+    type Item = S::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(remaining) = self.remaining.as_mut() {
+            if *remaining == 0 {
+                return None;
+            }
+            *remaining -= 1;
+        }
+        self.inner.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (lower, upper) = self.inner.size_hint();
+        match self.remaining {
+            Some(remaining) => {
+                let remaining = remaining as usize;
+                (
+                    lower.min(remaining),
+                    Some(upper.map_or(remaining, |u| u.min(remaining))),
+                )
+            }
+            None => (lower, upper),
+        }
+    }
+}
+
+impl<S: Source> Source for RangeSource<S> {
+    fn current_span_len(&self) -> Option<usize> {
+        let len = self.inner.current_span_len();
+        match self.remaining {
+            Some(remaining) => Some(len.map_or(remaining as usize, |l| l.min(remaining as usize))),
+            None => len,
+        }
+    }
+
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        match self.range.end {
+            Some(end) => Some(end.saturating_sub(self.range.start)),
+            None => self
+                .inner
+                .total_duration()
+                .map(|d| d.saturating_sub(self.range.start)),
+        }
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        let pos = match self.range.end {
+            Some(end) => pos.min(end.saturating_sub(self.range.start)),
+            None => pos,
+        };
+        self.inner.try_seek(self.range.start + pos)?;
+        self.set_position(pos);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_time_range() {
+        assert_eq!(
+            split_time_range("/music/album.flac#t=12.5,245"),
+            Some((
+                "/music/album.flac",
+                TimeRange {
+                    start: Duration::from_millis(12500),
+                    end: Some(Duration::from_secs(245)),
+                }
+            ))
+        );
+        assert_eq!(
+            split_time_range("/music/album.flac#t=300"),
+            Some((
+                "/music/album.flac",
+                TimeRange {
+                    start: Duration::from_secs(300),
+                    end: None,
+                }
+            ))
+        );
+        // Only the last fragment is the range
+        assert_eq!(
+            split_time_range("/music/#t=1/album.flac#t=0,10").map(|(file, _)| file),
+            Some("/music/#t=1/album.flac")
+        );
+
+        assert_eq!(split_time_range("/music/album.flac"), None);
+        assert_eq!(split_time_range("/music/album.flac#t=abc"), None);
+        assert_eq!(split_time_range("/music/album.flac#t=-5"), None);
+        assert_eq!(split_time_range("/music/album.flac#t=10,"), None);
+        // Ranges which end before they start
+        assert_eq!(split_time_range("/music/album.flac#t=10,10"), None);
+        assert_eq!(split_time_range("/music/album.flac#t=20,10"), None);
+    }
+}
@@ -0,0 +1,68 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayGainMode {
+    #[default]
+    Off,
+    Track,
+    /// Keeps the loudness differences between songs of an album
+    Album,
+}
+
+/// ReplayGain values of a song, gains in dB and peaks as linear sample values
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct TrackGain {
+    pub track_gain: Option<f64>,
+    pub track_peak: Option<f64>,
+    pub album_gain: Option<f64>,
+    pub album_peak: Option<f64>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct ReplayGainSettings {
+    pub mode: ReplayGainMode,
+    /// Extra gain in dB applied to songs with ReplayGain values
+    pub preamp: f64,
+    /// Lowers the gain where it would push the peak of the song above full scale
+    pub prevent_clipping: bool,
+}
+
+impl ReplayGainSettings {
+    /// Linear volume the song should be played at. Album mode falls back to
+    /// the track gain for songs without album gain, songs without any play unchanged
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn volume(&self, gain: &TrackGain) -> f32 {
+        let (db, peak) = match self.mode {
+            ReplayGainMode::Off => return 1f32,
+            ReplayGainMode::Album if gain.album_gain.is_some() => {
+                (gain.album_gain, gain.album_peak.or(gain.track_peak))
+            }
+            _ => (gain.track_gain, gain.track_peak),
+        };
+        let Some(db) = db else {
+            return 1f32;
+        };
+
+        let mut volume = 10f64.powf((db + self.preamp) / 20f64);
+        if self.prevent_clipping {
+            if let Some(peak) = peak.filter(|p| *p > 0f64) {
+                volume = volume.min(1f64 / peak);
+            }
+        }
+        volume as f32
+    }
+}
@@ -15,18 +15,26 @@
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
 use database::{cache::CacheHolder, database::Database};
+use file_scanner::{playlist_format, read_playlist, scan_song, M3u};
 use macros::generate_command;
+use preferences::preferences::PreferenceConfig;
 use serde_json::Value;
-use std::fs;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
 use tauri::{App, AppHandle, Manager, State};
 use tracing::{info, trace};
 use types::errors::Result;
 use types::songs::AllAnalytics;
+use types::stats::{ListeningStats, StatsExportFormat};
+use types::tags::TagJournalEntry;
 use types::{
     entities::{
-        GetEntityOptions, QueryableAlbum, QueryableArtist, QueryablePlaylist, SearchResult,
+        GetEntityOptions, ImportedPlaylist, PlayDetails, PlayHistoryItem, QueryableAlbum,
+        QueryableArtist, QueryablePlaylist, SearchResult,
     },
-    songs::{GetSongOptions, QueryableSong, Song},
+    songs::{GetSongOptions, QueryableSong, SearchableSong, Song},
 };
 
 use crate::window::handler::WindowHandler;
@@ -41,12 +49,100 @@ pub fn export_playlist(
     id: String,
 ) -> Result<()> {
     info!("Exporting playlist");
-    let exported = db.export_playlist(id)?;
+    let (playlist, songs) = db.get_playlist_with_songs(id)?;
     let selected_file = window_handler.open_save_file(app)?;
+
+    // The format follows the extension of the chosen file, M3U if it has none we know
+    let format = playlist_format(&selected_file).unwrap_or(&M3u);
+    let exported = format.write(&playlist.playlist_name, &songs)?;
     trace!("Exported playlist");
     Ok(fs::write(selected_file, exported)?)
 }
 
+/// Reads a playlist file picked in the UI. Songs already in the library are reused and other
+/// local files are scanned for their tags
+#[tracing::instrument(level = "debug", skip(db, preferences))]
+#[tauri_invoke_proc::parse_tauri_command]
+#[tauri::command(async)]
+pub fn import_playlist_file(
+    db: State<Database>,
+    preferences: State<PreferenceConfig>,
+    path: String,
+) -> Result<ImportedPlaylist> {
+    info!("Importing playlist");
+    let thumbnail_dir: String = preferences.load_selective("thumbnail_path".to_string())?;
+    let artist_split: String = preferences
+        .load_selective("artist_splitter".to_string())
+        .unwrap_or(";".to_string());
+
+    let mut imported = read_playlist(Path::new(&path))?;
+    for song in imported.songs.iter_mut() {
+        let Some(song_path) = song.song.path.clone() else {
+            continue;
+        };
+
+        let existing = db.get_songs_by_options(GetSongOptions {
+            song: Some(SearchableSong {
+                path: Some(song_path.clone()),
+                ..Default::default()
+            }),
+            inclusive: Some(true),
+            ..Default::default()
+        })?;
+        if let Some(existing) = existing.into_iter().next() {
+            *song = existing;
+            continue;
+        }
+
+        match scan_song(
+            &PathBuf::from(&song_path),
+            Path::new(&thumbnail_dir),
+            &artist_split,
+        ) {
+            Ok(mut scanned) => {
+                scanned.song._id = song.song._id.clone();
+                *song = scanned;
+            }
+            Err(e) => tracing::warn!("Failed to scan {}: {:?}", song_path, e),
+        }
+    }
+    trace!("Imported playlist");
+    Ok(imported)
+}
+
+#[tracing::instrument(level = "debug", skip(app, db, window_handler))]
+#[tauri_invoke_proc::parse_tauri_command]
+#[tauri::command(async)]
+pub fn export_listening_stats(
+    app: AppHandle,
+    db: State<Database>,
+    window_handler: State<WindowHandler>,
+    from: Option<i64>,
+    to: Option<i64>,
+    utc_offset: i32,
+    format: StatsExportFormat,
+) -> Result<()> {
+    info!("Exporting listening stats");
+    let stats = db.get_listening_stats(from, to, utc_offset)?;
+    let exported = match format {
+        StatsExportFormat::Json => serde_json::to_string_pretty(&stats)?,
+        StatsExportFormat::Csv => stats.to_csv(),
+    };
+    let selected_file = window_handler.open_save_file(app)?;
+    trace!("Exported listening stats");
+    Ok(fs::write(selected_file, exported)?)
+}
+
+// Merged copies are remembered by the database so the next scan doesn't add them back
+#[tracing::instrument(level = "debug", skip(db))]
+#[tauri_invoke_proc::parse_tauri_command]
+#[tauri::command(async)]
+pub fn merge_duplicates(db: State<Database>, keep: String, duplicates: Vec<String>) -> Result<()> {
+    db.merge_duplicates(keep, duplicates)?;
+    info!("Merged duplicate songs");
+    Ok(())
+}
+
 generate_command!(insert_songs, Database, Vec<Song>, songs: Vec<Song>);
 generate_command!(remove_songs, Database, (), songs: Vec<String>);
 generate_command!(update_song, Database, (), a: QueryableSong);
@@ -54,7 +150,9 @@ generate_command!(get_songs_by_options, Database, Vec<Song>, options: GetSongOpt
 generate_command!(get_entity_by_options, Database, Value, options: GetEntityOptions);
 generate_command!(search_all, Database, SearchResult, term: String);
 generate_command!(create_playlist, Database, String, playlist: QueryablePlaylist);
-generate_command!(add_to_playlist, Database, (), id: String, songs: Vec<Song>);
+generate_command!(add_to_playlist, Database, (), id: String, songs: Vec<Song>, position: Option<usize>);
+generate_command!(reorder_playlist, Database, (), id: String, songs: Vec<String>);
+generate_command!(move_in_playlist, Database, (), id: String, song: String, to: usize);
 generate_command!(is_song_in_playlist, Database, bool, playlist_id: String, song_id: String);
 generate_command!(remove_from_playlist, Database, (), id: String, songs: Vec<String>);
 generate_command!(remove_playlist, Database, (), id: String);
@@ -64,8 +162,13 @@ generate_command!(update_playlist, Database, (), playlist: QueryablePlaylist);
 generate_command!(update_songs, Database, (), songs: Vec<Song>);
 generate_command!(update_lyrics, Database, (), id: String, lyrics: String);
 generate_command!(increment_play_count, Database, (), id: String);
-generate_command!(increment_play_time, Database, (), id: String, duration: f64);
+generate_command!(increment_play_time, Database, (), id: String, duration: f64, details: Option<PlayDetails>);
 generate_command!(get_top_listened_songs, Database, AllAnalytics,);
+generate_command!(get_play_history, Database, Vec<PlayHistoryItem>, from: Option<i64>, to: Option<i64>, limit: Option<i64>);
+generate_command!(clear_play_history, Database, (), before: Option<i64>);
+generate_command!(get_listening_stats, Database, ListeningStats, from: Option<i64>, to: Option<i64>, utc_offset: i32);
+generate_command!(get_duplicate_songs, Database, Vec<Vec<Song>>,);
+generate_command!(get_tag_journal, Database, Vec<TagJournalEntry>, limit: Option<i64>);
 
 #[tracing::instrument(level = "debug", skip(app))]
 pub fn get_cache_state(app: &mut App) -> CacheHolder {
@@ -95,7 +95,7 @@ impl ReplyHandler {
         data: Vec<Song>,
     ) -> Result<MainCommandResponse> {
         let database: State<'_, Database> = self.app_handle.state();
-        if let Err(e) = database.add_to_playlist(playlist_id, data) {
+        if let Err(e) = database.add_to_playlist(playlist_id, data, None) {
             tracing::error!("Failed to add songs to playlist {:?}", e);
             Ok(MainCommandResponse::AddToPlaylist(false))
         } else {
@@ -38,8 +38,11 @@ use preference_holder::{
 };
 use providers::handler::get_provider_handler_state;
 use rodio::{
-    get_rodio_state, rodio_get_volume, rodio_load, rodio_pause, rodio_play, rodio_seek,
-    rodio_set_volume, rodio_stop,
+    clear_audio_cache, get_audio_cache_state, get_audio_cache_stats, get_dsp_presets,
+    get_dsp_settings, get_output_devices, get_rodio_state, is_audio_cached, make_available_offline,
+    make_playlist_available_offline, remove_dsp_preset, remove_from_offline, rodio_cancel_preload,
+    rodio_get_volume, rodio_load, rodio_pause, rodio_play, rodio_preload, rodio_seek,
+    rodio_set_volume, rodio_stop, save_dsp_preset, set_dsp_settings, set_output_device,
 };
 use tauri_plugin_deep_link::DeepLinkExt;
 use themes::{
@@ -58,7 +61,10 @@ use providers::handler::{
     get_suggestions, initialize_all_providers, match_url, playlist_from_url, provider_authorize,
     provider_login, provider_search, provider_signout, song_from_url, trigger_context_menu_action,
 };
-use scanner::{get_scanner_state, start_scan, ScanTask};
+use scanner::{
+    cancel_scan, get_scan_progress, get_scanner_state, preview_tag_edit, start_scan,
+    undo_tag_edit, write_tag_edit, LoudnessTask, ScanTask, WatchTask,
+};
 use tauri::{Manager, State};
 use tracing_appender::rolling::{RollingFileAppender, Rotation};
 use tracing_subscriber::{
@@ -76,11 +82,13 @@ use {
     db::{
         get_cache_state,
         {
-            add_to_playlist, create_playlist, export_playlist, get_db_state, get_entity_by_options,
-            get_songs_by_options, get_top_listened_songs, increment_play_count,
-            increment_play_time, insert_songs, remove_from_playlist, remove_playlist, remove_songs,
-            search_all, update_album, update_artist, update_lyrics, update_playlist, update_song,
-            update_songs,
+            add_to_playlist, clear_play_history, create_playlist, export_listening_stats,
+            export_playlist, get_db_state, get_duplicate_songs, get_entity_by_options,
+            get_listening_stats, get_play_history, get_songs_by_options, get_tag_journal,
+            get_top_listened_songs, import_playlist_file, increment_play_count,
+            increment_play_time, insert_songs, merge_duplicates, move_in_playlist,
+            remove_from_playlist, remove_playlist, remove_songs, reorder_playlist, search_all,
+            update_album, update_artist, update_lyrics, update_playlist, update_song, update_songs,
         },
     },
     oauth::handler::{get_oauth_state, OAuthHandler},
@@ -208,11 +216,15 @@ pub fn run() {
             // DB
             insert_songs,
             remove_songs,
+            get_duplicate_songs,
+            merge_duplicates,
             get_songs_by_options,
             get_entity_by_options,
             search_all,
             create_playlist,
             add_to_playlist,
+            reorder_playlist,
+            move_in_playlist,
             is_song_in_playlist,
             remove_from_playlist,
             remove_playlist,
@@ -225,7 +237,13 @@ pub fn run() {
             increment_play_count,
             increment_play_time,
             export_playlist,
+            import_playlist_file,
             get_top_listened_songs,
+            get_play_history,
+            clear_play_history,
+            get_listening_stats,
+            export_listening_stats,
+            get_tag_journal,
             // Window
             is_maximized,
             has_frame,
@@ -244,6 +262,11 @@ pub fn run() {
             open_file_browser,
             // Scanner
             start_scan,
+            cancel_scan,
+            get_scan_progress,
+            preview_tag_edit,
+            write_tag_edit,
+            undo_tag_edit,
             // Librespot
             is_initialized,
             librespot_play,
@@ -303,13 +326,28 @@ pub fn run() {
             get_playlist_context_menu,
             trigger_context_menu_action,
             // Rodio player
+            rodio_cancel_preload,
             rodio_get_volume,
             rodio_load,
             rodio_pause,
             rodio_play,
+            rodio_preload,
             rodio_seek,
             rodio_set_volume,
             rodio_stop,
+            get_dsp_settings,
+            set_dsp_settings,
+            get_dsp_presets,
+            save_dsp_preset,
+            remove_dsp_preset,
+            get_output_devices,
+            set_output_device,
+            make_available_offline,
+            make_playlist_available_offline,
+            remove_from_offline,
+            is_audio_cached,
+            get_audio_cache_stats,
+            clear_audio_cache,
             // Logger
             renderer_write,
             // Mobile player
@@ -372,6 +410,12 @@ pub fn run() {
             let scan_task = ScanTask::default();
             app.manage(scan_task);
 
+            let watch_task = WatchTask::default();
+            app.manage(watch_task);
+
+            let loudness_task = LoudnessTask::default();
+            app.manage(loudness_task);
+
             let librespot_state = get_librespot_state();
             app.manage(librespot_state);
 
@@ -390,6 +434,9 @@ pub fn run() {
             let rodio_state = get_rodio_state(app.app_handle().clone());
             app.manage(rodio_state);
 
+            let audio_cache_state = get_audio_cache_state(app.app_handle().clone());
+            app.manage(audio_cache_state);
+
             let logger = get_logger_state(app.app_handle().clone());
             app.manage(logger);
 
@@ -19,6 +19,7 @@ use librespot::LibrespotHolder;
 use lyrics::LyricsFetcher;
 use tauri::State;
 use types::errors::Result;
+use types::lyrics::Lyrics;
 
 #[tracing::instrument(level = "debug", skip())]
 pub fn get_lyrics_state() -> LyricsFetcher {
@@ -39,7 +40,7 @@ pub async fn get_lyrics(
     url: String,
     artists: Vec<String>,
     title: String,
-) -> Result<String> {
+) -> Result<Lyrics> {
     let cache_string = format!("get_lyrics_{id}_{url}_{artists:?}_{title}");
 
     let cached = cache.get(&cache_string);
@@ -20,7 +20,29 @@ use macros::generate_command;
 use mpris::MprisHolder;
 use serde_json::{json, Value};
 use tauri::{AppHandle, Emitter, State};
-use types::{errors::Result, mpris::MprisPlayerDetails, ui::player_details::PlayerState};
+use types::{
+    errors::Result,
+    mpris::MprisPlayerDetails,
+    ui::player_details::{PlayerState, SleepTimer},
+};
+
+const SLEEP_TIMER_URI: &str = "moosync://sleep-timer/";
+
+/// MPRIS has no notion of a sleep timer, so clients set it by opening uris such as
+/// `moosync://sleep-timer/minutes/30`, `moosync://sleep-timer/tracks/2`,
+/// `moosync://sleep-timer/end-of-track` or `moosync://sleep-timer/cancel`
+#[tracing::instrument(level = "debug", skip(uri))]
+fn parse_sleep_timer_uri(uri: &str) -> Option<Option<SleepTimer>> {
+    let path = uri.strip_prefix(SLEEP_TIMER_URI)?;
+    let mut parts = path.trim_end_matches('/').split('/');
+    match (parts.next()?, parts.next()) {
+        ("minutes", Some(minutes)) => minutes.parse().ok().map(|m| Some(SleepTimer::Minutes(m))),
+        ("tracks", Some(tracks)) => tracks.parse().ok().map(|t| Some(SleepTimer::Tracks(t))),
+        ("end-of-track", None) => Some(Some(SleepTimer::EndOfTrack)),
+        ("cancel", None) => Some(None),
+        _ => None,
+    }
+}
 
 #[tracing::instrument(level = "debug", skip(app))]
 pub fn get_mpris_state(app: AppHandle) -> Result<MprisHolder> {
@@ -50,12 +72,15 @@ pub fn get_mpris_state(app: AppHandle) -> Result<MprisHolder> {
                     //         (12, json!((pos.as_secs() as i128).neg()))
                     //     }
                     // }
-                    (12, json!(pos.as_secs()))
+                    (12, json!(pos.as_secs_f64()))
                 }
-                mpris::MediaControlEvent::SetPosition(pos) => (12, json!(pos.0.as_secs())),
+                mpris::MediaControlEvent::SetPosition(pos) => (12, json!(pos.0.as_secs_f64())),
 
                 mpris::MediaControlEvent::SetVolume(vol) => (15, json!(vol)),
-                mpris::MediaControlEvent::OpenUri(uri) => (16, Value::String(uri)),
+                mpris::MediaControlEvent::OpenUri(uri) => match parse_sleep_timer_uri(&uri) {
+                    Some(timer) => (19, json!(timer)),
+                    None => (16, Value::String(uri)),
+                },
                 mpris::MediaControlEvent::Raise => (17, Value::Null),
                 mpris::MediaControlEvent::Quit => (18, Value::Null),
             };
@@ -69,3 +94,49 @@ pub fn get_mpris_state(app: AppHandle) -> Result<MprisHolder> {
 generate_command!(set_metadata, MprisHolder, (), metadata: MprisPlayerDetails);
 generate_command!(set_playback_state, MprisHolder, (), state: PlayerState);
 generate_command!(set_position, MprisHolder, (), duration: f64);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sleep_timer_uri() {
+        assert_eq!(
+            parse_sleep_timer_uri("moosync://sleep-timer/minutes/30"),
+            Some(Some(SleepTimer::Minutes(30f64)))
+        );
+        assert_eq!(
+            parse_sleep_timer_uri("moosync://sleep-timer/minutes/2.5/"),
+            Some(Some(SleepTimer::Minutes(2.5)))
+        );
+        assert_eq!(
+            parse_sleep_timer_uri("moosync://sleep-timer/tracks/2"),
+            Some(Some(SleepTimer::Tracks(2)))
+        );
+        assert_eq!(
+            parse_sleep_timer_uri("moosync://sleep-timer/end-of-track"),
+            Some(Some(SleepTimer::EndOfTrack))
+        );
+        assert_eq!(
+            parse_sleep_timer_uri("moosync://sleep-timer/cancel"),
+            Some(None)
+        );
+
+        // Other uris are left to the player
+        assert_eq!(parse_sleep_timer_uri("https://moosync.app/song.mp3"), None);
+        assert_eq!(parse_sleep_timer_uri("moosync://sleep-timer/minutes"), None);
+        assert_eq!(
+            parse_sleep_timer_uri("moosync://sleep-timer/minutes/abc"),
+            None
+        );
+        assert_eq!(
+            parse_sleep_timer_uri("moosync://sleep-timer/tracks/-1"),
+            None
+        );
+        assert_eq!(
+            parse_sleep_timer_uri("moosync://sleep-timer/cancel/now"),
+            None
+        );
+        assert_eq!(parse_sleep_timer_uri("moosync://sleep-timer/forever"), None);
+    }
+}
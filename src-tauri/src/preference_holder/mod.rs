@@ -26,7 +26,8 @@ use types::{errors::Result, preferences::CheckboxPreference};
 
 use crate::{
     providers::handler::ProviderHandler,
-    scanner::{start_scan, ScanTask},
+    rodio::{update_audio_cache, update_crossfade, update_position_interval},
+    scanner::{start_scan, LoudnessTask, ScanTask, WatchTask},
 };
 
 const UI_KEYS: &[&str] = &[
@@ -59,6 +60,11 @@ pub fn handle_pref_changes(app: AppHandle) {
             }
 
             if key == "prefs.music_paths" || key == "prefs.exclude_music_paths" {
+                let watch_task: State<WatchTask> = app.state();
+                if let Err(e) = watch_task.start_watching(app.clone()) {
+                    tracing::error!("Failed to watch music paths: {:?}", e);
+                }
+
                 let app = app.clone();
                 thread::spawn(move || {
                     let app = app.clone();
@@ -96,6 +102,23 @@ pub fn handle_pref_changes(app: AppHandle) {
                 }
             }
 
+            if key.starts_with("prefs.crossfade") {
+                update_crossfade(&app);
+            }
+
+            if key.starts_with("prefs.position_interval") {
+                update_position_interval(&app);
+            }
+
+            if key.starts_with("prefs.audio_cache") {
+                update_audio_cache(&app);
+            }
+
+            if key.starts_with("prefs.replaygain_options") {
+                let loudness_task: State<LoudnessTask> = app.state();
+                loudness_task.spawn_analysis(app.clone());
+            }
+
             if key.starts_with("prefs.scan_interval") {
                 let scan_task: State<ScanTask> = app.state();
                 scan_task.spawn_scan_task(app.clone(), value.as_u64().unwrap().max(30));
@@ -155,6 +178,12 @@ pub fn initial(app: &mut App) {
         tracing::warn!("Could not spawn scan task, no / invalid duration found");
     }
 
+    // Watch music paths for changes, falls back to the scan task where unsupported
+    let watch_task: State<WatchTask> = app.state();
+    if let Err(e) = watch_task.start_watching(app.handle().clone()) {
+        tracing::error!("Failed to watch music paths: {:?}", e);
+    }
+
     let handle = app.handle().clone();
     tauri::async_runtime::spawn(async move {
         let extension_handler = handle.state::<ExtensionHandler>();
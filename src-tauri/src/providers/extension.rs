@@ -24,6 +24,7 @@ use tokio::sync::Mutex;
 use types::{
     entities::{QueryableAlbum, QueryableArtist, QueryablePlaylist, SearchResult},
     errors::Result,
+    lyrics::Lyrics,
     providers::generic::{GenericProvider, Pagination, ProviderStatus},
     songs::Song,
     ui::extensions::{
@@ -408,11 +409,11 @@ impl GenericProvider for ExtensionProvider {
         Ok((res.songs, pagination))
     }
 
-    async fn get_lyrics(&self, song: Song) -> Result<String> {
+    async fn get_lyrics(&self, song: Song) -> Result<Lyrics> {
         if !self.provides.contains(&ExtensionProviderScope::Lyrics) {
             return Err("Extension does not have this capability".into());
         }
-        let res = send_extension_event!(self, ExtensionExtraEvent::RequestedLyrics([song]), String);
+        let res = send_extension_event!(self, ExtensionExtraEvent::RequestedLyrics([song]), Lyrics);
 
         Ok(res)
     }
@@ -33,6 +33,7 @@ use types::{
     entities::{QueryableAlbum, QueryableArtist, QueryablePlaylist, SearchResult},
     errors::error_helpers,
     errors::{MoosyncError, Result},
+    lyrics::Lyrics,
     providers::generic::{GenericProvider, Pagination, ProviderStatus},
     songs::Song,
     ui::extensions::ContextMenuReturnType,
@@ -379,7 +380,7 @@ impl ProviderHandler {
             args: {
                 song: Song,
             },
-            result_type: String,
+            result_type: Lyrics,
             method_name: get_lyrics,
         },
         get_song_context_menu  {
@@ -436,7 +437,7 @@ generate_command_async_cached!(match_url, ProviderHandler, bool, key: String, ur
 generate_command_async_cached!(get_suggestions, ProviderHandler, Vec<Song>, key: String);
 generate_command_async_cached!(get_artist_content, ProviderHandler, (Vec<Song>, Pagination), key: String, artist: QueryableArtist, pagination: Pagination);
 generate_command_async_cached!(get_album_content, ProviderHandler, (Vec<Song>, Pagination), key: String, album: QueryableAlbum, pagination: Pagination);
-generate_command_async_cached!(get_provider_lyrics, ProviderHandler, String, key: String, song: Song);
+generate_command_async_cached!(get_provider_lyrics, ProviderHandler, Lyrics, key: String, song: Song);
 generate_command_async!(get_song_context_menu, ProviderHandler, Vec<ContextMenuReturnType>, key: String, songs: Vec<Song>);
 generate_command_async!(get_playlist_context_menu, ProviderHandler, Vec<ContextMenuReturnType>, key: String, playlist: QueryablePlaylist);
 generate_command_async!(trigger_context_menu_action, ProviderHandler, (), key: String, action: String);
@@ -45,6 +45,7 @@ use tokio::sync::{Mutex, RwLock, RwLockReadGuard};
 use types::{
     entities::{EntityInfo, QueryableAlbum, QueryableArtist, QueryablePlaylist, SearchResult},
     errors::{error_helpers, MoosyncError, Result},
+    lyrics::Lyrics,
     oauth::OAuth2Client,
     providers::generic::{GenericProvider, Pagination, ProviderStatus},
     songs::{QueryableSong, Song, SongType},
@@ -285,7 +286,8 @@ impl SpotifyProvider {
                 url: Some(id.clone()),
                 song_cover_path_high: item.album.images.first().map(|i| i.url.clone()),
                 playback_url: Some(id),
-                track_no: Some(item.disc_number as f64),
+                track_no: Some(item.track_number as f64),
+                disc_no: Some(item.disc_number as f64),
                 provider_extension: Some(self.key()),
                 ..Default::default()
             },
@@ -980,7 +982,7 @@ impl GenericProvider for SpotifyProvider {
         Err("API Client not initialized".into())
     }
 
-    async fn get_lyrics(&self, _: Song) -> Result<String> {
+    async fn get_lyrics(&self, _: Song) -> Result<Lyrics> {
         return Err("Not implemented".into());
     }
 
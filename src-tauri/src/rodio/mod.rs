@@ -14,23 +14,262 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::thread;
+use std::{path::PathBuf, thread, time::Duration};
 
+use database::database::Database;
 use futures::executor::block_on;
 use macros::generate_command_async;
-use rodio_player::RodioPlayer;
+use preferences::preferences::PreferenceConfig;
+use rodio_player::{
+    AudioCache, AudioCacheSettings, CrossfadeCurve, CrossfadeSettings, EvictionPolicy,
+    ReplayGainMode, ReplayGainSettings, RodioPlayer, TrackGain,
+};
 use tauri::{AppHandle, Emitter, Manager, State};
-use types::errors::Result;
+use types::{
+    cache::AudioCacheStats,
+    dsp::{DspPreset, DspSettings},
+    entities::QueryablePlaylist,
+    errors::Result,
+    preferences::CheckboxPreference,
+    providers::generic::Pagination,
+    songs::{GetSongOptions, QueryableSong, Song},
+    ui::player_details::{OutputDevice, PlayerEvents},
+};
+
+use crate::providers::handler::ProviderHandler;
+
+/// Longest allowed crossfade. The UI preloads the next song a few seconds before this
+const MAX_CROSSFADE_S: f64 = 12f64;
+
+/// Largest ReplayGain preamp in either direction, in dB
+const MAX_REPLAYGAIN_PREAMP: f64 = 15f64;
+
+/// Bounds of the position update interval, in milliseconds
+const POSITION_INTERVAL_RANGE_MS: (f64, f64) = (50f64, 5000f64);
+
+/// Default size of the audio cache, in MB
+const DEFAULT_AUDIO_CACHE_SIZE_MB: f64 = 1024f64;
+
+/// Songs fetched per request while listing a provider playlist to make it available offline
+const OFFLINE_PAGE_SIZE: u32 = 50;
+
+#[tracing::instrument(level = "debug", skip(preferences))]
+fn get_crossfade_settings(preferences: &PreferenceConfig) -> Option<CrossfadeSettings> {
+    let duration: f64 = preferences
+        .load_selective("crossfade_duration".to_string())
+        .unwrap_or_default();
+    if duration <= 0f64 {
+        return None;
+    }
+
+    let curve = preferences
+        .load_selective::<Vec<CheckboxPreference>>("crossfade_curve".to_string())
+        .ok()
+        .and_then(|items| items.into_iter().find(|i| i.enabled))
+        .map(|item| match item.key.as_str() {
+            "equal_power" => CrossfadeCurve::EqualPower,
+            "s_curve" => CrossfadeCurve::SCurve,
+            _ => CrossfadeCurve::Linear,
+        })
+        .unwrap_or_default();
+
+    Some(CrossfadeSettings {
+        duration: Duration::from_secs_f64(duration.min(MAX_CROSSFADE_S)),
+        curve,
+    })
+}
+
+#[tracing::instrument(level = "debug", skip(preferences))]
+fn get_replaygain_settings(preferences: &PreferenceConfig) -> ReplayGainSettings {
+    let mode = preferences
+        .load_selective::<Vec<CheckboxPreference>>("replaygain_mode".to_string())
+        .ok()
+        .and_then(|items| items.into_iter().find(|i| i.enabled))
+        .map(|item| match item.key.as_str() {
+            "track" => ReplayGainMode::Track,
+            "album" => ReplayGainMode::Album,
+            _ => ReplayGainMode::Off,
+        })
+        .unwrap_or_default();
+
+    let preamp: f64 = preferences
+        .load_selective("replaygain_preamp".to_string())
+        .unwrap_or_default();
+
+    // Clipping is prevented unless turned off explicitly
+    let prevent_clipping = preferences
+        .load_selective_array::<CheckboxPreference>("replaygain_options.prevent_clipping".into())
+        .map(|c| c.enabled)
+        .unwrap_or(true);
+
+    ReplayGainSettings {
+        mode,
+        preamp: preamp.clamp(-MAX_REPLAYGAIN_PREAMP, MAX_REPLAYGAIN_PREAMP),
+        prevent_clipping,
+    }
+}
+
+/// Song in the library playing from `src`, if there is one
+#[tracing::instrument(level = "debug", skip(app))]
+fn get_library_song(app: &AppHandle, src: &str) -> Option<QueryableSong> {
+    let database: State<Database> = app.state();
+    database
+        .get_songs_by_path(PathBuf::from(src))
+        .ok()
+        .and_then(|songs| songs.into_iter().find(|s| s.path.as_deref() == Some(src)))
+}
+
+/// Volume a song should play at. Only songs in the library have ReplayGain values
+#[tracing::instrument(level = "debug", skip(app, song))]
+fn get_song_gain(app: &AppHandle, song: Option<&QueryableSong>) -> f32 {
+    let settings = get_replaygain_settings(&app.state::<PreferenceConfig>());
+    if settings.mode == ReplayGainMode::Off {
+        return 1f32;
+    }
+
+    song.map(|s| {
+        settings.volume(&TrackGain {
+            track_gain: s.track_gain,
+            track_peak: s.track_peak,
+            album_gain: s.album_gain,
+            album_peak: s.album_peak,
+        })
+    })
+    .unwrap_or(1f32)
+}
+
+/// Tracks of CUE sheets play only their part of the file, given as a media fragment
+#[tracing::instrument(level = "debug", skip(song))]
+fn get_track_src(song: &QueryableSong) -> Option<String> {
+    let start = song.start_offset?;
+    let file = song.file_path()?;
+    Some(match song.end_offset {
+        Some(end) => format!("{}#t={},{}", file, start, end),
+        None => format!("{}#t={}", file, start),
+    })
+}
+
+#[tracing::instrument(level = "debug", skip(preferences))]
+fn get_audio_cache_settings(preferences: &PreferenceConfig) -> AudioCacheSettings {
+    let size: f64 = preferences
+        .load_selective("audio_cache_size".to_string())
+        .unwrap_or(DEFAULT_AUDIO_CACHE_SIZE_MB);
+
+    let policy = preferences
+        .load_selective::<Vec<CheckboxPreference>>("audio_cache_policy".to_string())
+        .ok()
+        .and_then(|items| items.into_iter().find(|i| i.enabled))
+        .map(|item| match item.key.as_str() {
+            "lfu" => EvictionPolicy::LeastFrequentlyUsed,
+            "oldest" => EvictionPolicy::OldestFirst,
+            _ => EvictionPolicy::LeastRecentlyUsed,
+        })
+        .unwrap_or_default();
+
+    AudioCacheSettings {
+        max_size: (size.max(0f64) * 1024f64 * 1024f64) as u64,
+        policy,
+    }
+}
+
+#[tracing::instrument(level = "debug", skip(app))]
+pub fn get_audio_cache_state(app: AppHandle) -> AudioCache {
+    let dir = app.path().app_local_data_dir().unwrap().join("audio_cache");
+    let settings = get_audio_cache_settings(&app.state::<PreferenceConfig>());
+    AudioCache::new(dir, settings)
+}
+
+#[tracing::instrument(level = "debug", skip(app))]
+pub fn update_audio_cache(app: &AppHandle) {
+    let preferences: State<PreferenceConfig> = app.state();
+    let cache: State<AudioCache> = app.state();
+    cache.set_settings(get_audio_cache_settings(&preferences));
+}
+
+/// Path of the cached copy of the song `id`, if it has one
+#[tracing::instrument(level = "debug", skip(app))]
+fn get_cached_src(app: &AppHandle, id: Option<&String>) -> Option<String> {
+    let cache: State<AudioCache> = app.state();
+    cache
+        .get(id?)
+        .map(|path| path.to_string_lossy().to_string())
+}
+
+/// Downloads the song which stopped playing into the audio cache in the background,
+/// so it plays from disk next time. `next` is the streamed song which started playing,
+/// it is cached once it stops too
+#[tracing::instrument(level = "debug", skip(app))]
+fn cache_in_background(app: &AppHandle, next: Option<(String, String)>) {
+    let cache: State<AudioCache> = app.state();
+    let next = next.filter(|(_, src)| src.starts_with("http") && cache.is_enabled());
+    let Some((id, src)) = cache.replace_deferred(next) else {
+        return;
+    };
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let cache: State<AudioCache> = app.state();
+        if let Err(e) = cache.store(id.clone(), src, false).await {
+            tracing::warn!("Failed to cache {}: {:?}", id, e);
+        }
+    });
+}
+
+#[tracing::instrument(level = "debug", skip(app))]
+pub fn update_crossfade(app: &AppHandle) {
+    let preferences: State<PreferenceConfig> = app.state();
+    let rodio: State<RodioPlayer> = app.state();
+    rodio.set_crossfade(get_crossfade_settings(&preferences));
+}
+
+#[tracing::instrument(level = "debug", skip(preferences))]
+fn get_position_interval(preferences: &PreferenceConfig) -> Duration {
+    let (min, max) = POSITION_INTERVAL_RANGE_MS;
+    let interval: f64 = preferences
+        .load_selective("position_interval".to_string())
+        .unwrap_or(500f64);
+    Duration::from_millis(interval.clamp(min, max) as u64)
+}
+
+#[tracing::instrument(level = "debug", skip(app))]
+pub fn update_position_interval(app: &AppHandle) {
+    let preferences: State<PreferenceConfig> = app.state();
+    let rodio: State<RodioPlayer> = app.state();
+    rodio.set_position_interval(get_position_interval(&preferences));
+}
+
+/// Saved output device name, None for the system default
+#[tracing::instrument(level = "debug", skip(preferences))]
+fn get_output_device(preferences: &PreferenceConfig) -> Option<String> {
+    preferences
+        .load_selective::<String>("output_device".to_string())
+        .ok()
+        .filter(|d| !d.is_empty())
+}
 
 #[tracing::instrument(level = "debug", skip())]
 pub fn get_rodio_state(app: AppHandle) -> RodioPlayer {
     let cache_dir = app.path().app_cache_dir().unwrap();
-    let rodio_player = RodioPlayer::new(cache_dir);
+    let rodio_player = {
+        let preferences = app.state::<PreferenceConfig>();
+        let rodio_player = RodioPlayer::new(cache_dir, get_output_device(&preferences));
+        rodio_player.set_crossfade(get_crossfade_settings(&preferences));
+        rodio_player.set_position_interval(get_position_interval(&preferences));
+        rodio_player.set_dsp_settings(
+            preferences
+                .load_selective("dsp".to_string())
+                .unwrap_or_default(),
+        );
+        rodio_player
+    };
 
     let events_rx = rodio_player.get_events_rx();
     thread::spawn(move || {
         let events_rx = events_rx.lock().unwrap();
         while let Ok(event) = events_rx.recv() {
+            if matches!(event, PlayerEvents::Ended) {
+                cache_in_background(&app, None);
+            }
             tracing::info!("Sending rodio event {:?}", event);
             let res = app.emit("rodio_event", event);
             if res.is_err() {
@@ -42,14 +281,264 @@ pub fn get_rodio_state(app: AppHandle) -> RodioPlayer {
     rodio_player
 }
 
+#[tracing::instrument(level = "debug", skip(rodio))]
+#[tauri_invoke_proc::parse_tauri_command]
+#[tauri::command(async)]
+pub fn get_output_devices(rodio: State<RodioPlayer>) -> Result<Vec<OutputDevice>> {
+    rodio.get_output_devices()
+}
+
+/// Switches playback to the device called `name`, or the system default if None,
+/// and remembers the choice
+#[tracing::instrument(level = "debug", skip(rodio, preferences))]
+#[tauri_invoke_proc::parse_tauri_command]
+#[tauri::command(async)]
+pub fn set_output_device(
+    rodio: State<RodioPlayer>,
+    preferences: State<PreferenceConfig>,
+    name: Option<String>,
+) -> Result<()> {
+    preferences.save_selective(
+        "output_device".to_string(),
+        Some(name.clone().unwrap_or_default()),
+    )?;
+    rodio.set_output_device(name);
+    Ok(())
+}
+
+#[tracing::instrument(level = "debug", skip(preferences))]
+fn get_saved_presets(preferences: &PreferenceConfig) -> Vec<DspPreset> {
+    preferences
+        .load_selective("dsp_presets".to_string())
+        .unwrap_or_default()
+}
+
+#[tracing::instrument(level = "debug", skip(rodio))]
+#[tauri_invoke_proc::parse_tauri_command]
+#[tauri::command(async)]
+pub fn get_dsp_settings(rodio: State<RodioPlayer>) -> Result<DspSettings> {
+    Ok(rodio.get_dsp_settings())
+}
+
+/// Applies DSP settings to the playing song right away. Sliders can skip persisting
+/// while dragging and only save the final value
+#[tracing::instrument(level = "debug", skip(rodio, preferences, settings))]
+#[tauri_invoke_proc::parse_tauri_command]
+#[tauri::command(async)]
+pub fn set_dsp_settings(
+    rodio: State<RodioPlayer>,
+    preferences: State<PreferenceConfig>,
+    settings: DspSettings,
+    persist: bool,
+) -> Result<()> {
+    rodio.set_dsp_settings(settings.clone());
+    if persist {
+        preferences.save_selective("dsp".to_string(), Some(settings))?;
+    }
+    Ok(())
+}
+
+#[tracing::instrument(level = "debug", skip(preferences))]
+#[tauri_invoke_proc::parse_tauri_command]
+#[tauri::command(async)]
+pub fn get_dsp_presets(preferences: State<PreferenceConfig>) -> Result<Vec<DspPreset>> {
+    let mut presets = DspPreset::builtin();
+    presets.extend(get_saved_presets(&preferences));
+    Ok(presets)
+}
+
+/// Saves a user preset, replacing any saved preset with the same name
+#[tracing::instrument(level = "debug", skip(preferences))]
+#[tauri_invoke_proc::parse_tauri_command]
+#[tauri::command(async)]
+pub fn save_dsp_preset(preferences: State<PreferenceConfig>, preset: DspPreset) -> Result<()> {
+    let mut preset = preset;
+    preset.name = preset.name.trim().to_string();
+    if preset.name.is_empty() {
+        return Err("Preset name can't be empty".into());
+    }
+    if DspPreset::builtin().iter().any(|p| p.name == preset.name) {
+        return Err(format!("{} is a built-in preset", preset.name).into());
+    }
+
+    preset.builtin = false;
+    let mut presets = get_saved_presets(&preferences);
+    presets.retain(|p| p.name != preset.name);
+    presets.push(preset);
+    preferences.save_selective("dsp_presets".to_string(), Some(presets))
+}
+
+#[tracing::instrument(level = "debug", skip(preferences))]
+#[tauri_invoke_proc::parse_tauri_command]
+#[tauri::command(async)]
+pub fn remove_dsp_preset(preferences: State<PreferenceConfig>, name: String) -> Result<()> {
+    let mut presets = get_saved_presets(&preferences);
+    presets.retain(|p| p.name != name);
+    preferences.save_selective("dsp_presets".to_string(), Some(presets))
+}
+
+/// Loads the song `id` from the audio cache if it is there, or from `src` otherwise.
+/// The src is resolved before returning, so commands sent after this one, like play,
+/// always reach the player after the src is set
 #[tracing::instrument(level = "debug", skip(app, src))]
 #[tauri::command(async)]
 #[tauri_invoke_proc::parse_tauri_command]
-pub fn rodio_load(app: AppHandle, src: String) -> Result<()> {
-    tauri::async_runtime::spawn_blocking(move || {
-        let rodio: State<'_, RodioPlayer> = app.state();
-        block_on(rodio.rodio_load(src)).unwrap();
-    });
+pub fn rodio_load(app: AppHandle, src: String, id: Option<String>) -> Result<()> {
+    let song = get_library_song(&app, &src);
+    let gain = get_song_gain(&app, song.as_ref());
+    let src = match get_cached_src(&app, id.as_ref()) {
+        Some(cached) => {
+            cache_in_background(&app, None);
+            cached
+        }
+        None => {
+            cache_in_background(&app, id.map(|id| (id, src.clone())));
+            song.as_ref().and_then(get_track_src).unwrap_or(src)
+        }
+    };
+    let rodio: State<RodioPlayer> = app.state();
+    block_on(rodio.rodio_load(src, gain))
+}
+
+/// Preloads from the audio cache the same way as [rodio_load], but never starts caching.
+/// The src has to stay the same until the song is loaded to keep the transition gapless
+#[tracing::instrument(level = "debug", skip(app, src))]
+#[tauri::command(async)]
+#[tauri_invoke_proc::parse_tauri_command]
+pub fn rodio_preload(
+    app: AppHandle,
+    src: String,
+    id: Option<String>,
+    crossfade: bool,
+) -> Result<()> {
+    let song = get_library_song(&app, &src);
+    let gain = get_song_gain(&app, song.as_ref());
+    let src = get_cached_src(&app, id.as_ref())
+        .or_else(|| song.as_ref().and_then(get_track_src))
+        .unwrap_or(src);
+    let rodio: State<RodioPlayer> = app.state();
+    block_on(rodio.rodio_preload(src, crossfade, gain))
+}
+
+/// Url the song streams from, fetched from its provider if it doesn't have one yet
+#[tracing::instrument(level = "debug", skip(providers))]
+async fn get_offline_src(providers: &ProviderHandler, song: &Song) -> Result<String> {
+    if let Some(url) = song
+        .song
+        .playback_url
+        .clone()
+        .filter(|u| u.starts_with("http"))
+    {
+        return Ok(url);
+    }
+
+    let id = song.song._id.clone().ok_or("Song has no id")?;
+    let key = providers.get_provider_key_by_id(id).await?;
+    providers
+        .fetch_playback_url(key, song.clone(), "rodio".to_string())
+        .await
+}
+
+/// Downloads streamed songs into the audio cache and keeps them there, however full
+/// it gets, until they are removed from offline. Local files are skipped
+#[tracing::instrument(level = "debug", skip(app, songs))]
+#[tauri_invoke_proc::parse_tauri_command]
+#[tauri::command()]
+pub async fn make_available_offline(app: AppHandle, songs: Vec<Song>) -> Result<()> {
+    let cache: State<AudioCache> = app.state();
+    let providers: State<ProviderHandler> = app.state();
+
+    let mut failed = 0;
+    for song in songs {
+        let Some(id) = song.song._id.clone().filter(|_| song.song.path.is_none()) else {
+            continue;
+        };
+
+        let res = match get_offline_src(&providers, &song).await {
+            Ok(src) => cache.store(id.clone(), src, true).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = res {
+            tracing::error!("Failed to make {} available offline: {:?}", id, e);
+            failed += 1;
+        }
+    }
+
+    if failed > 0 {
+        return Err(format!("Failed to make {} songs available offline", failed).into());
+    }
+    Ok(())
+}
+
+/// Makes the songs of a playlist available offline, both those added to it in the
+/// library and those listed by the provider the playlist comes from
+#[tracing::instrument(level = "debug", skip(app))]
+#[tauri_invoke_proc::parse_tauri_command]
+#[tauri::command()]
+pub async fn make_playlist_available_offline(
+    app: AppHandle,
+    playlist: QueryablePlaylist,
+) -> Result<()> {
+    let playlist_id = playlist.playlist_id.clone().ok_or("Playlist has no id")?;
+
+    let database: State<Database> = app.state();
+    let mut songs = database.get_songs_by_options(GetSongOptions {
+        playlist: Some(QueryablePlaylist {
+            playlist_id: Some(playlist_id.clone()),
+            ..Default::default()
+        }),
+        ..Default::default()
+    })?;
+
+    let providers: State<ProviderHandler> = app.state();
+    if let Ok(key) = providers.get_provider_key_by_id(playlist_id).await {
+        let mut pagination = Pagination::new_limit(OFFLINE_PAGE_SIZE, 0);
+        loop {
+            let (page, next) = providers
+                .fetch_playlist_content(key.clone(), playlist.clone(), pagination)
+                .await?;
+            if page.is_empty() {
+                break;
+            }
+            songs.extend(page);
+            if !next.is_valid {
+                break;
+            }
+            pagination = next;
+        }
+    }
+
+    make_available_offline(app, songs).await
+}
+
+/// Lets songs made available offline be evicted from the audio cache again
+#[tracing::instrument(level = "debug", skip(cache))]
+#[tauri_invoke_proc::parse_tauri_command]
+#[tauri::command(async)]
+pub fn remove_from_offline(cache: State<AudioCache>, ids: Vec<String>) -> Result<()> {
+    cache.remove_offline(&ids);
+    Ok(())
+}
+
+#[tracing::instrument(level = "debug", skip(cache))]
+#[tauri_invoke_proc::parse_tauri_command]
+#[tauri::command(async)]
+pub fn is_audio_cached(cache: State<AudioCache>, id: String) -> Result<bool> {
+    Ok(cache.contains(&id))
+}
+
+#[tracing::instrument(level = "debug", skip(cache))]
+#[tauri_invoke_proc::parse_tauri_command]
+#[tauri::command(async)]
+pub fn get_audio_cache_stats(cache: State<AudioCache>) -> Result<AudioCacheStats> {
+    Ok(cache.stats())
+}
+
+#[tracing::instrument(level = "debug", skip(cache))]
+#[tauri_invoke_proc::parse_tauri_command]
+#[tauri::command(async)]
+pub fn clear_audio_cache(cache: State<AudioCache>, include_offline: bool) -> Result<()> {
+    cache.clear(include_offline);
     Ok(())
 }
 
@@ -57,6 +546,7 @@ pub fn rodio_load(app: AppHandle, src: String) -> Result<()> {
 generate_command_async!(rodio_play, RodioPlayer, (),);
 generate_command_async!(rodio_pause, RodioPlayer, (),);
 generate_command_async!(rodio_stop, RodioPlayer, (),);
+generate_command_async!(rodio_cancel_preload, RodioPlayer, (),);
 generate_command_async!(rodio_seek, RodioPlayer, (), pos: f64);
 generate_command_async!(rodio_set_volume, RodioPlayer, (), volume: f32);
 generate_command_async!(rodio_get_volume, RodioPlayer, f32,);
@@ -0,0 +1,86 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    path::Path,
+    sync::atomic::{AtomicBool, Ordering},
+    thread,
+};
+
+use database::database::Database;
+use preferences::preferences::PreferenceConfig;
+use rodio_player::analyse_loudness;
+use tauri::{AppHandle, Manager};
+use types::{errors::Result, preferences::CheckboxPreference};
+
+/// Measures the loudness of songs without ReplayGain tags so they can be normalized too
+#[derive(Default)]
+pub struct LoudnessTask {
+    running: AtomicBool,
+}
+
+impl LoudnessTask {
+    /// Starts analysing in the background if enabled and not already running
+    #[tracing::instrument(level = "debug", skip(self, app))]
+    pub fn spawn_analysis(&self, app: AppHandle) {
+        if !is_enabled(&app) || self.running.swap(true, Ordering::AcqRel) {
+            return;
+        }
+
+        thread::spawn(move || {
+            if let Err(e) = analyse_songs(&app) {
+                tracing::error!("Failed to analyse loudness: {:?}", e);
+            }
+            app.state::<LoudnessTask>()
+                .running
+                .store(false, Ordering::Release);
+        });
+    }
+}
+
+#[tracing::instrument(level = "debug", skip(app))]
+fn is_enabled(app: &AppHandle) -> bool {
+    app.state::<PreferenceConfig>()
+        .load_selective_array::<CheckboxPreference>("replaygain_options.analyse".into())
+        .is_ok_and(|c| c.enabled)
+}
+
+#[tracing::instrument(level = "debug", skip(app))]
+fn analyse_songs(app: &AppHandle) -> Result<()> {
+    let database = app.state::<Database>();
+    let songs = database.get_songs_without_gain()?;
+    tracing::info!("Analysing loudness of {} songs", songs.len());
+
+    for song in songs {
+        // Stop early if analysis was turned off meanwhile
+        if !is_enabled(app) {
+            break;
+        }
+
+        let (Some(id), Some(path)) = (song._id, song.path) else {
+            continue;
+        };
+        match analyse_loudness(Path::new(&path)) {
+            Ok(loudness) => {
+                tracing::debug!("Measured {:?} for {}", loudness, path);
+                database.set_song_gain(id, loudness.replaygain(), loudness.peak)?;
+            }
+            Err(e) => tracing::warn!("Failed to analyse {}: {:?}", path, e),
+        }
+    }
+
+    Ok(())
+}
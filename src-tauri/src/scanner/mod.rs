@@ -15,6 +15,8 @@
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
     sync::{atomic::AtomicBool, mpsc::channel, Arc, Mutex},
     thread::{self},
     time::Duration,
@@ -23,8 +25,19 @@ use std::{
 use database::database::Database;
 use file_scanner::ScannerHolder;
 use preferences::preferences::PreferenceConfig;
-use tauri::{AppHandle, Manager, State};
-use types::{errors::Result, songs::Song};
+use tauri::{AppHandle, Emitter, Manager, State};
+use types::{
+    errors::Result,
+    scanner::{ScanProgress, ScanReport},
+    songs::Song,
+};
+
+mod loudness;
+mod tags;
+mod watcher;
+pub use loudness::LoudnessTask;
+pub use tags::{preview_tag_edit, undo_tag_edit, write_tag_edit};
+pub use watcher::WatchTask;
 
 #[tracing::instrument(level = "debug", skip())]
 pub fn get_scanner_state() -> ScannerHolder {
@@ -32,11 +45,29 @@ pub fn get_scanner_state() -> ScannerHolder {
 }
 
 #[tracing::instrument(level = "debug", skip(preferences))]
-fn get_scan_paths(preferences: &State<PreferenceConfig>) -> Result<Vec<String>> {
+fn get_exclude_paths(preferences: &State<PreferenceConfig>) -> Vec<String> {
+    preferences
+        .load_selective("exclude_music_paths".to_string())
+        .unwrap_or_default()
+}
+
+#[tracing::instrument(level = "debug", skip(preferences, exclude_paths))]
+fn get_scan_paths(
+    preferences: &State<PreferenceConfig>,
+    exclude_paths: &[String],
+) -> Result<Vec<String>> {
     let tmp: Vec<String> = preferences.load_selective("music_paths".to_string())?;
 
-    // TODO: Filter using exclude paths
-    Ok(tmp)
+    // Paths nested inside an excluded directory are skipped entirely,
+    // excludes nested inside a music path are handled while scanning
+    Ok(tmp
+        .into_iter()
+        .filter(|p| {
+            !exclude_paths
+                .iter()
+                .any(|e| Path::new(p).starts_with(Path::new(e)))
+        })
+        .collect())
 }
 
 #[derive(Default)]
@@ -65,6 +96,12 @@ impl ScanTask {
                 break;
             }
 
+            // Watched folders are already kept up to date
+            if app.state::<WatchTask>().is_watching_all() {
+                tracing::info!("All music paths are watched, skipping scan");
+                continue;
+            }
+
             let app = app.clone();
             let res = start_scan(app, None);
             if let Err(e) = res {
@@ -80,15 +117,31 @@ impl ScanTask {
 #[tracing::instrument(level = "debug", skip(app, paths))]
 #[tauri_invoke_proc::parse_tauri_command]
 #[tauri::command(async)]
-pub fn start_scan(app: AppHandle, paths: Option<Vec<String>>) -> Result<()> {
+pub fn start_scan(app: AppHandle, paths: Option<Vec<String>>) -> Result<ScanReport> {
     start_scan_inner(app, paths)
 }
 
+#[tracing::instrument(level = "debug", skip(scanner))]
+#[tauri_invoke_proc::parse_tauri_command]
+#[tauri::command(async)]
+pub fn cancel_scan(scanner: State<ScannerHolder>) -> Result<()> {
+    scanner.cancel_scan();
+    Ok(())
+}
+
+#[tracing::instrument(level = "debug", skip(scanner))]
+#[tauri_invoke_proc::parse_tauri_command]
+#[tauri::command(async)]
+pub fn get_scan_progress(scanner: State<ScannerHolder>) -> Result<ScanProgress> {
+    Ok(scanner.get_progress())
+}
+
 #[cfg(desktop)]
-pub fn start_scan_inner(app: AppHandle, mut paths: Option<Vec<String>>) -> Result<()> {
+pub fn start_scan_inner(app: AppHandle, mut paths: Option<Vec<String>>) -> Result<ScanReport> {
     let preferences = app.state::<PreferenceConfig>();
+    let exclude_paths = get_exclude_paths(&preferences);
     if paths.is_none() {
-        paths = Some(get_scan_paths(&preferences)?);
+        paths = Some(get_scan_paths(&preferences, &exclude_paths)?);
     }
 
     let thumbnail_dir: String = preferences.load_selective("thumbnail_path".to_string())?;
@@ -102,14 +155,34 @@ pub fn start_scan_inner(app: AppHandle, mut paths: Option<Vec<String>>) -> Resul
         .load_selective("scan_threads".to_string())
         .unwrap_or(-1f64);
 
+    let (progress_tx, progress_rx) = channel::<ScanProgress>();
+    let app_clone = app.clone();
+    thread::spawn(move || {
+        for progress in progress_rx {
+            if let Err(e) = app_clone.emit("scan-progress", progress) {
+                tracing::error!("Failed to emit scan progress: {:?}", e);
+            }
+        }
+    });
+
+    let database = app.state::<Database>();
+    let mut report = ScanReport::default();
     for path in paths.unwrap() {
         tracing::info!("Scanning path: {}", path);
 
+        // Songs already in the library are only rescanned if their files changed
+        let canonical_path = dunce::canonicalize(&path).unwrap_or(PathBuf::from(&path));
+        let known_songs = database.get_songs_by_path(canonical_path)?;
+        let known_ids: HashMap<String, String> = known_songs
+            .iter()
+            .filter_map(|s| Some((s.path.clone()?, s._id.clone()?)))
+            .collect();
+
         let (playlist_tx, playlist_rx) = channel();
         let (song_tx, song_rx) = channel::<(Option<String>, Vec<Song>)>();
 
         let app_clone = app.clone();
-        thread::spawn(move || {
+        let insert_thread = thread::spawn(move || {
             let app = app_clone;
             let database = app.state::<Database>();
             for item in playlist_rx {
@@ -134,21 +207,47 @@ pub fn start_scan_inner(app: AppHandle, mut paths: Option<Vec<String>>) -> Resul
         });
 
         let scanner = app.state::<ScannerHolder>();
-        scanner.start_scan(
+        let path_report = scanner.start_scan(
             path,
             thumbnail_dir.clone(),
             artist_split.clone(),
             scan_threads,
+            exclude_paths.clone(),
+            known_songs,
             song_tx,
             playlist_tx,
+            progress_tx.clone(),
         )?;
+
+        // Wait for the scanned songs to be stored before using the library
+        if insert_thread.join().is_err() {
+            tracing::error!("Failed to store scanned songs");
+        }
+
+        let removed_ids: Vec<String> = path_report
+            .removed
+            .iter()
+            .filter_map(|p| known_ids.get(p).cloned())
+            .collect();
+        if !removed_ids.is_empty() {
+            tracing::info!("Removing {} songs which no longer exist", removed_ids.len());
+            database.remove_songs(removed_ids)?;
+        }
+
+        report.extend(path_report);
     }
 
-    Ok(())
+    if let Err(e) = database.refresh_smart_playlists() {
+        tracing::error!("Failed to refresh smart playlists: {:?}", e);
+    }
+
+    app.state::<LoudnessTask>().spawn_analysis(app.clone());
+
+    Ok(report)
 }
 
 #[cfg(mobile)]
-pub fn start_scan_inner(app: AppHandle, mut paths: Option<Vec<String>>) -> Result<()> {
+pub fn start_scan_inner(app: AppHandle, mut paths: Option<Vec<String>>) -> Result<ScanReport> {
     use tauri_plugin_file_scanner::FileScannerExt;
 
     tracing::debug!("calling file scanner");
@@ -157,8 +256,14 @@ pub fn start_scan_inner(app: AppHandle, mut paths: Option<Vec<String>>) -> Resul
 
     tracing::debug!("Got scanned songs {:?}", res);
 
+    let report = ScanReport {
+        added: res.iter().filter_map(|s| s.song.path.clone()).collect(),
+        ..Default::default()
+    };
+
     let database = app.state::<Database>();
     database.insert_songs(res)?;
+    database.refresh_smart_playlists()?;
 
-    Ok(())
+    Ok(report)
 }
@@ -0,0 +1,266 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use database::database::Database;
+use file_scanner::{cover_backups, diff_tags, scan_song, write_tags};
+use preferences::preferences::PreferenceConfig;
+use tauri::State;
+use types::{
+    errors::{MoosyncError, Result},
+    preferences::CheckboxPreference,
+    songs::{GetSongOptions, QueryableSong, SearchableSong, SongType},
+    tags::{TagChange, TagChanges, TagDiff, TagEdit, TagJournalEntry},
+};
+use uuid::Uuid;
+
+#[tracing::instrument(level = "debug", skip(preferences))]
+fn is_enabled(preferences: &PreferenceConfig) -> bool {
+    preferences
+        .load_selective_array::<CheckboxPreference>("tag_options.write_tags".into())
+        .is_ok_and(|c| c.enabled)
+}
+
+#[tracing::instrument(level = "debug", skip(preferences))]
+fn get_artist_split(preferences: &PreferenceConfig) -> String {
+    preferences
+        .load_selective("artist_splitter".to_string())
+        .unwrap_or(";".to_string())
+}
+
+#[tracing::instrument(level = "debug", skip(preferences))]
+fn get_thumbnail_dir(preferences: &PreferenceConfig) -> Result<PathBuf> {
+    let thumbnail_dir: String = preferences.load_selective("thumbnail_path".to_string())?;
+    Ok(PathBuf::from(thumbnail_dir))
+}
+
+/// Replaced cover art is kept next to the generated thumbnails until its edit is undone
+#[tracing::instrument(level = "debug", skip(thumbnail_dir))]
+fn get_backup_dir(thumbnail_dir: &Path) -> PathBuf {
+    thumbnail_dir.join("tag_backups")
+}
+
+/// Local songs among `ids`, since only their files can be tagged
+#[tracing::instrument(level = "debug", skip(db))]
+fn get_local_songs(db: &Database, ids: Vec<String>) -> Result<Vec<QueryableSong>> {
+    let mut songs = vec![];
+    for id in ids {
+        let found = db.get_songs_by_options(GetSongOptions {
+            song: Some(SearchableSong {
+                _id: Some(id),
+                ..Default::default()
+            }),
+            inclusive: Some(true),
+            ..Default::default()
+        })?;
+        songs.extend(
+            found
+                .into_iter()
+                .map(|s| s.song)
+                .filter(|s| s.type_ == SongType::LOCAL && s.path.is_some() && !s.is_cue_track()),
+        );
+    }
+    Ok(songs)
+}
+
+/// Rescans a file after its tags were written so the library matches it
+#[tracing::instrument(level = "debug", skip(db, song))]
+fn refresh_song(
+    db: &Database,
+    song: &QueryableSong,
+    thumbnail_dir: &Path,
+    artist_split: &str,
+) -> Result<()> {
+    let path = PathBuf::from(song.path.clone().unwrap_or_default());
+    let mut scanned = scan_song(&path, thumbnail_dir, artist_split)?;
+    scanned.song._id = song._id.clone();
+    db.refresh_song(scanned)?;
+    Ok(())
+}
+
+#[tracing::instrument(level = "debug", skip())]
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default()
+}
+
+/// Dry run of a tag edit, listing what would change in the file of each song
+#[tracing::instrument(level = "debug", skip(db, preferences))]
+#[tauri_invoke_proc::parse_tauri_command]
+#[tauri::command(async)]
+pub fn preview_tag_edit(
+    db: State<Database>,
+    preferences: State<PreferenceConfig>,
+    song_ids: Vec<String>,
+    edit: TagEdit,
+) -> Result<Vec<TagDiff>> {
+    let artist_split = get_artist_split(&preferences);
+
+    let mut diffs = vec![];
+    for song in get_local_songs(&db, song_ids)? {
+        let path = song.path.clone().unwrap_or_default();
+        let changes = diff_tags(Path::new(&path), &edit, &artist_split)?;
+        if !changes.is_empty() {
+            diffs.push(TagDiff {
+                song_id: song._id.clone().unwrap_or_default(),
+                path,
+                changes,
+            });
+        }
+    }
+    Ok(diffs)
+}
+
+/// Writes a tag edit into the files of all given songs as one batch, which can be
+/// undone with `undo_tag_edit`. Returns the id of the batch, or an error listing the files
+/// which couldn't be written. Files written before the error stay in the batch
+#[tracing::instrument(level = "debug", skip(db, preferences))]
+#[tauri_invoke_proc::parse_tauri_command]
+#[tauri::command(async)]
+pub fn write_tag_edit(
+    db: State<Database>,
+    preferences: State<PreferenceConfig>,
+    song_ids: Vec<String>,
+    edit: TagEdit,
+) -> Result<String> {
+    if !is_enabled(&preferences) {
+        return Err("Writing tags to files is disabled".into());
+    }
+
+    let artist_split = get_artist_split(&preferences);
+    let thumbnail_dir = get_thumbnail_dir(&preferences)?;
+    let backup_dir = get_backup_dir(&thumbnail_dir);
+
+    // Check every file first so an invalid edit doesn't leave the batch half written
+    let mut pending = vec![];
+    for song in get_local_songs(&db, song_ids)? {
+        let path = song.path.clone().unwrap_or_default();
+        let changes = diff_tags(Path::new(&path), &edit, &artist_split)?;
+        if !changes.is_empty() {
+            pending.push((song, changes));
+        }
+    }
+
+    let batch_id = Uuid::new_v4().to_string();
+    let mut entries = vec![];
+    let mut failed = vec![];
+    for (song, changes) in pending {
+        let path = song.path.clone().unwrap_or_default();
+        let written = match write_tags(Path::new(&path), &changes, Some(&backup_dir)) {
+            Ok(written) => written,
+            Err(e) => {
+                tracing::error!("Failed to write tags to {}: {:?}", path, e);
+                failed.push(path);
+                continue;
+            }
+        };
+
+        if let Err(e) = refresh_song(&db, &song, &thumbnail_dir, &artist_split) {
+            tracing::error!("Failed to refresh {}: {:?}", path, e);
+        }
+
+        entries.push(TagJournalEntry {
+            id: None,
+            batch_id: batch_id.clone(),
+            song_id: song._id.clone().unwrap_or_default(),
+            path,
+            changes: TagChanges(written),
+            written_at: now(),
+        });
+    }
+
+    tracing::info!("Wrote tags of {} songs", entries.len());
+    db.add_tag_journal_entries(entries)?;
+    db.refresh_smart_playlists()?;
+
+    if !failed.is_empty() {
+        return Err(MoosyncError::String(format!(
+            "Failed to write tags to {}",
+            failed.join(", ")
+        )));
+    }
+    Ok(batch_id)
+}
+
+/// Restores the tags a batch of edits replaced, and removes it from the journal.
+/// Every file which can be reverted is, files which can't stay in the journal and are
+/// listed in the returned error
+#[tracing::instrument(level = "debug", skip(db, preferences))]
+#[tauri_invoke_proc::parse_tauri_command]
+#[tauri::command(async)]
+pub fn undo_tag_edit(
+    db: State<Database>,
+    preferences: State<PreferenceConfig>,
+    batch_id: String,
+) -> Result<()> {
+    let artist_split = get_artist_split(&preferences);
+    let thumbnail_dir = get_thumbnail_dir(&preferences)?;
+    let backup_dir = get_backup_dir(&thumbnail_dir);
+
+    let entries = db.get_tag_batch(batch_id.clone())?;
+    let mut reverted = vec![];
+    let mut failed = vec![];
+    for entry in entries.iter() {
+        let changes: Vec<TagChange> = entry.changes.0.iter().map(|c| c.inverse()).collect();
+        if let Err(e) = write_tags(Path::new(&entry.path), &changes, None) {
+            tracing::error!("Failed to revert tags of {}: {:?}", entry.path, e);
+            failed.push(entry.path.clone());
+            continue;
+        }
+        reverted.push(entry);
+
+        let refreshed = get_local_songs(&db, vec![entry.song_id.clone()]).and_then(|songs| {
+            match songs.first() {
+                Some(song) => refresh_song(&db, song, &thumbnail_dir, &artist_split),
+                None => Ok(()),
+            }
+        });
+        if let Err(e) = refreshed {
+            tracing::error!("Failed to refresh {}: {:?}", entry.path, e);
+        }
+    }
+
+    for entry in reverted.iter() {
+        for backup in cover_backups(&entry.changes.0, &backup_dir) {
+            if let Err(e) = fs::remove_file(&backup) {
+                tracing::warn!("Failed to remove cover backup {:?}: {:?}", backup, e);
+            }
+        }
+    }
+
+    if failed.is_empty() {
+        db.remove_tag_batch(batch_id)?;
+    } else {
+        db.remove_tag_journal_entries(reverted.iter().filter_map(|e| e.id).collect())?;
+    }
+    db.refresh_smart_playlists()?;
+    tracing::info!("Undid tag edits of {} songs", reverted.len());
+
+    if !failed.is_empty() {
+        return Err(MoosyncError::String(format!(
+            "Failed to revert tags of {}",
+            failed.join(", ")
+        )));
+    }
+    Ok(())
+}
@@ -0,0 +1,245 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use tauri::AppHandle;
+use types::errors::Result;
+
+#[cfg(desktop)]
+use {
+    super::{get_exclude_paths, get_scan_paths, start_scan_inner, LoudnessTask},
+    database::database::Database,
+    file_scanner::{
+        cue_split_files, file_identity, get_files_recursively, is_playlist_or_cue_file, scan_song,
+        MusicWatcher, WatchEvent,
+    },
+    preferences::preferences::PreferenceConfig,
+    std::{
+        collections::{BTreeSet, HashMap, HashSet},
+        fs,
+        path::{Path, PathBuf},
+        sync::{mpsc::channel, Mutex},
+        thread,
+        time::Duration,
+    },
+    tauri::Manager,
+    types::songs::QueryableSong,
+};
+
+#[cfg(desktop)]
+const WATCH_DEBOUNCE: Duration = Duration::from_secs(2);
+
+#[derive(Default)]
+pub struct WatchTask {
+    #[cfg(desktop)]
+    watcher: Mutex<Option<MusicWatcher>>,
+}
+
+impl WatchTask {
+    /// (Re)starts watching all music paths for changes
+    #[cfg(desktop)]
+    #[tracing::instrument(level = "debug", skip(self, app))]
+    pub fn start_watching(&self, app: AppHandle) -> Result<()> {
+        let mut watcher = self.watcher.lock().unwrap();
+        // Dropping the old watcher also stops its event thread
+        *watcher = None;
+
+        let preferences = app.state::<PreferenceConfig>();
+        let exclude_paths = get_exclude_paths(&preferences);
+        let paths = get_scan_paths(&preferences, &exclude_paths)?;
+
+        let (tx, rx) = channel::<Vec<WatchEvent>>();
+        *watcher = Some(MusicWatcher::new(
+            paths.into_iter().map(PathBuf::from).collect(),
+            exclude_paths.into_iter().map(PathBuf::from).collect(),
+            WATCH_DEBOUNCE,
+            tx,
+        )?);
+
+        thread::spawn(move || {
+            for events in rx {
+                tracing::debug!("Got watch events {:?}", events);
+                if let Err(e) = handle_watch_events(&app, events) {
+                    tracing::error!("Failed to handle file changes: {:?}", e);
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    #[cfg(mobile)]
+    pub fn start_watching(&self, _app: AppHandle) -> Result<()> {
+        Ok(())
+    }
+
+    /// Periodic scans are only needed if some music path couldn't be watched
+    #[cfg(desktop)]
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn is_watching_all(&self) -> bool {
+        self.watcher
+            .lock()
+            .unwrap()
+            .as_ref()
+            .is_some_and(|w| w.unwatched_paths().is_empty())
+    }
+
+    #[cfg(mobile)]
+    pub fn is_watching_all(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(desktop)]
+#[tracing::instrument(level = "debug", skip(app, events))]
+fn handle_watch_events(app: &AppHandle, events: Vec<WatchEvent>) -> Result<()> {
+    let database = app.state::<Database>();
+    let preferences = app.state::<PreferenceConfig>();
+
+    let exclude_paths: Vec<PathBuf> = get_exclude_paths(&preferences)
+        .into_iter()
+        .map(PathBuf::from)
+        .collect();
+    let thumbnail_dir: String = preferences.load_selective("thumbnail_path".to_string())?;
+    let artist_split: String = preferences
+        .load_selective("artist_splitter".to_string())
+        .unwrap_or(";".to_string());
+
+    // Songs whose files disappeared, keyed by inode and device number so they
+    // can be matched with files that show up somewhere else in the same batch
+    let mut removed: HashMap<(String, String), QueryableSong> = HashMap::new();
+    let mut removed_ids = vec![];
+    let mut changed = vec![];
+    // Playlists and CUE sheets are imported by rescanning the directories they are in
+    let mut rescan_dirs = BTreeSet::new();
+
+    for event in events {
+        match event {
+            WatchEvent::Renamed(from, to) => {
+                // Files we never knew about are simply new
+                if database.move_songs(from, to.clone())? == 0 {
+                    changed.push(to);
+                }
+            }
+            WatchEvent::Removed(path) => {
+                if is_playlist_or_cue_file(&path) {
+                    rescan_dirs.extend(path.parent().map(Path::to_path_buf));
+                    continue;
+                }
+                for song in database.get_songs_by_path(path)? {
+                    if let (Some(inode), Some(deviceno)) =
+                        (song.inode.clone(), song.deviceno.clone())
+                    {
+                        removed.insert((inode, deviceno), song);
+                    } else {
+                        removed_ids.extend(song._id);
+                    }
+                }
+            }
+            WatchEvent::Created(path) | WatchEvent::Modified(path) => changed.push(path),
+        }
+    }
+
+    let mut files = vec![];
+    for path in changed {
+        if path.is_dir() {
+            let file_list = get_files_recursively(path.clone(), &exclude_paths)?;
+            if !file_list.playlist_list.is_empty() || !file_list.cue_list.is_empty() {
+                rescan_dirs.insert(path);
+            }
+            files.extend(file_list.file_list.into_iter().map(|(p, _)| p));
+        } else if is_playlist_or_cue_file(&path) {
+            rescan_dirs.extend(path.parent().map(Path::to_path_buf));
+        } else {
+            files.push(path);
+        }
+    }
+
+    let mut songs = vec![];
+    // Sheets of each directory are only parsed once per batch
+    let mut cue_split: HashMap<PathBuf, HashSet<PathBuf>> = HashMap::new();
+    for path in files {
+        // Tracks of CUE sheets are read from the sheet
+        if let Some(dir) = path.parent() {
+            let split = cue_split
+                .entry(dir.to_path_buf())
+                .or_insert_with(|| cue_split_files(dir));
+            if split.contains(&path) {
+                rescan_dirs.insert(dir.to_path_buf());
+                continue;
+            }
+        }
+
+        let size = fs::metadata(&path).map(|m| m.len() as f64).ok();
+        if let (Some(inode), Some(deviceno)) = file_identity(&path) {
+            let moved = removed
+                .get(&(inode.clone(), deviceno.clone()))
+                .is_some_and(|s| s.size == size);
+            if moved {
+                let song = removed.remove(&(inode, deviceno)).unwrap();
+                if let Some(old_path) = song.path {
+                    tracing::info!("Song moved from {} to {:?}", old_path, path);
+                    database.move_songs(PathBuf::from(old_path), path)?;
+                }
+                continue;
+            }
+        }
+
+        match scan_song(&path, Path::new(&thumbnail_dir), &artist_split) {
+            Ok(mut song) => {
+                // Keep the id of modified files so playlists and analytics still point to them
+                if let Some(song_path) = song.song.path.clone() {
+                    if let Some(existing) = database
+                        .get_songs_by_path(PathBuf::from(song_path))?
+                        .into_iter()
+                        .next()
+                    {
+                        song.song._id = existing._id;
+                    }
+                }
+                songs.push(song);
+            }
+            Err(e) => tracing::error!("Failed to scan {:?}: {:?}", path, e),
+        }
+    }
+
+    removed_ids.extend(removed.into_values().filter_map(|s| s._id));
+    if !removed_ids.is_empty() {
+        tracing::info!("Removing {} songs", removed_ids.len());
+        database.remove_songs(removed_ids)?;
+    }
+
+    if !songs.is_empty() {
+        tracing::info!("Adding / updating {} songs", songs.len());
+        database.insert_songs(songs)?;
+    }
+
+    // Rescanning also refreshes smart playlists and analyses loudness
+    let rescan_dirs: Vec<String> = rescan_dirs
+        .into_iter()
+        .filter(|d| d.exists())
+        .map(|d| d.to_string_lossy().to_string())
+        .collect();
+    if !rescan_dirs.is_empty() {
+        tracing::info!("Rescanning {:?} for playlists and CUE sheets", rescan_dirs);
+        start_scan_inner(app.clone(), Some(rescan_dirs))?;
+        return Ok(());
+    }
+
+    database.refresh_smart_playlists()?;
+    app.state::<LoudnessTask>().spawn_analysis(app.clone());
+
+    Ok(())
+}
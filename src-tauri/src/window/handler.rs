@@ -264,12 +264,9 @@ impl WindowHandler {
 
     #[tracing::instrument(level = "debug", skip(self))]
     pub fn open_save_file(&self, app: AppHandle) -> Result<PathBuf> {
-        #[cfg(any(target_os = "android", target_os = "ios"))]
-        {
-            let res = app.dialog().file().blocking_save_file();
-            if let Some(FilePath::Path(path)) = res {
-                return Ok(path.clone());
-            }
+        let res = app.dialog().file().blocking_save_file();
+        if let Some(FilePath::Path(path)) = res {
+            return Ok(path.clone());
         }
         Err("No file selected".into())
     }
@@ -292,12 +289,32 @@ pub fn handle_window_close(app: &AppHandle) -> Result<bool> {
     Ok(true)
 }
 
+/// Sleep timer presets offered in the tray menu, in minutes
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+const TRAY_SLEEP_TIMER_MINUTES: [u32; 4] = [15, 30, 45, 60];
+
 #[tracing::instrument(level = "debug", skip(app))]
 pub fn build_tray_menu(app: &App) -> Result<()> {
     #[cfg(not(any(target_os = "android", target_os = "ios")))]
     {
-        use tauri::menu::MenuBuilder;
+        use serde_json::json;
+        use tauri::menu::{MenuBuilder, SubmenuBuilder};
         use tauri::tray::{MouseButton, MouseButtonState, TrayIconEvent};
+        use types::ui::player_details::SleepTimer;
+
+        let mut sleep_timer = SubmenuBuilder::new(app, "Sleep timer");
+        for minutes in TRAY_SLEEP_TIMER_MINUTES {
+            sleep_timer = sleep_timer.text(
+                format!("sleep_timer_{minutes}"),
+                format!("{minutes} minutes"),
+            );
+        }
+        let sleep_timer = sleep_timer
+            .text("sleep_timer_end_of_track", "End of current track")
+            .text("sleep_timer_cancel", "Cancel sleep timer")
+            .build()
+            .map_err(error_helpers::to_plugin_error)?;
+
         let menu = MenuBuilder::new(app)
             .icon(
                 "show",
@@ -312,6 +329,7 @@ pub fn build_tray_menu(app: &App) -> Result<()> {
             )
             .icon("next", "Next", app.default_window_icon().cloned().unwrap())
             .icon("prev", "Prev", app.default_window_icon().cloned().unwrap())
+            .item(&sleep_timer)
             .icon("quit", "Quit", app.default_window_icon().cloned().unwrap())
             .build()
             .map_err(error_helpers::to_plugin_error)?;
@@ -338,7 +356,21 @@ pub fn build_tray_menu(app: &App) -> Result<()> {
                 "quit" => {
                     app.exit(0);
                 }
-                _ => (),
+                "sleep_timer_end_of_track" => {
+                    let _ = app.emit("media_button_press", (19, json!(SleepTimer::EndOfTrack)));
+                }
+                "sleep_timer_cancel" => {
+                    let _ = app.emit("media_button_press", (19, Value::Null));
+                }
+                id => {
+                    if let Some(minutes) = id
+                        .strip_prefix("sleep_timer_")
+                        .and_then(|m| m.parse::<f64>().ok())
+                    {
+                        let timer = SleepTimer::Minutes(minutes);
+                        let _ = app.emit("media_button_press", (19, json!(timer)));
+                    }
+                }
             })
             .on_tray_icon_event(|tray, event| {
                 if let TrayIconEvent::Click {
@@ -32,3 +32,14 @@ pub struct CacheModel {
     pub blob: Vec<u8>,
     pub expires: i64,
 }
+
+/// Usage of the audio cache, in bytes. Tracks made available offline are counted in
+/// both totals, but are never evicted to stay under `max_size`
+#[derive(Deserialize, Serialize, Default, Clone, Debug, PartialEq)]
+pub struct AudioCacheStats {
+    pub size: u64,
+    pub max_size: u64,
+    pub tracks: usize,
+    pub offline_tracks: usize,
+    pub offline_size: u64,
+}
@@ -0,0 +1,183 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use serde::{Deserialize, Serialize};
+
+/// Center frequencies of the default equalizer bands
+pub const EQ_FREQUENCIES: [f32; 10] = [
+    31f32, 62f32, 125f32, 250f32, 500f32, 1000f32, 2000f32, 4000f32, 8000f32, 16000f32,
+];
+
+/// Largest boost or cut of a band or the preamp, in dB
+pub const MAX_EQ_GAIN: f32 = 12f32;
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EqFilterType {
+    #[default]
+    Peak,
+    LowShelf,
+    HighShelf,
+}
+
+#[tracing::instrument(level = "debug", skip())]
+fn default_q() -> f32 {
+    std::f32::consts::SQRT_2
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct EqBand {
+    #[serde(default)]
+    pub filter: EqFilterType,
+    pub frequency: f32,
+    /// Boost or cut in dB
+    #[serde(default)]
+    pub gain: f32,
+    #[serde(default = "default_q")]
+    pub q: f32,
+}
+
+impl EqBand {
+    #[tracing::instrument(level = "debug", skip())]
+    pub fn new(frequency: f32, gain: f32) -> Self {
+        Self {
+            filter: EqFilterType::Peak,
+            frequency,
+            gain,
+            q: default_q(),
+        }
+    }
+}
+
+#[tracing::instrument(level = "debug", skip())]
+fn default_bands() -> Vec<EqBand> {
+    EQ_FREQUENCIES
+        .iter()
+        .map(|f| EqBand::new(*f, 0f32))
+        .collect()
+}
+
+/// Processing applied to local playback, in order: preamp, equalizer,
+/// stereo balance, mono downmix and limiter
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct DspSettings {
+    pub enabled: bool,
+    /// Gain applied before the equalizer in dB, usually negative to leave headroom for boosts
+    pub preamp: f32,
+    pub bands: Vec<EqBand>,
+    /// -1 is fully left, 1 fully right
+    pub balance: f32,
+    pub mono: bool,
+    pub limiter: bool,
+}
+
+impl Default for DspSettings {
+    #[tracing::instrument(level = "debug", skip())]
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            preamp: 0f32,
+            bands: default_bands(),
+            balance: 0f32,
+            mono: false,
+            limiter: true,
+        }
+    }
+}
+
+impl DspSettings {
+    #[tracing::instrument(level = "debug", skip(self, preset))]
+    pub fn apply_preset(&mut self, preset: &DspPreset) {
+        self.preamp = preset.preamp;
+        self.bands = preset.bands.clone();
+    }
+}
+
+/// Named equalizer settings
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DspPreset {
+    pub name: String,
+    #[serde(default)]
+    pub preamp: f32,
+    #[serde(default = "default_bands")]
+    pub bands: Vec<EqBand>,
+    /// Built in presets can't be overwritten or removed
+    #[serde(default)]
+    pub builtin: bool,
+}
+
+impl DspPreset {
+    #[tracing::instrument(level = "debug", skip(gains))]
+    fn from_gains(name: &str, preamp: f32, gains: [f32; 10]) -> Self {
+        Self {
+            name: name.to_string(),
+            preamp,
+            bands: EQ_FREQUENCIES
+                .iter()
+                .zip(gains)
+                .map(|(f, g)| EqBand::new(*f, g))
+                .collect(),
+            builtin: true,
+        }
+    }
+
+    #[tracing::instrument(level = "debug", skip())]
+    pub fn builtin() -> Vec<DspPreset> {
+        vec![
+            Self::from_gains("Flat", 0f32, [0f32; 10]),
+            Self::from_gains(
+                "Bass Boost",
+                -6f32,
+                [6f32, 5f32, 4f32, 2f32, 0f32, 0f32, 0f32, 0f32, 0f32, 0f32],
+            ),
+            Self::from_gains(
+                "Treble Boost",
+                -6f32,
+                [0f32, 0f32, 0f32, 0f32, 0f32, 1f32, 2f32, 4f32, 5f32, 6f32],
+            ),
+            Self::from_gains(
+                "Vocal",
+                -3f32,
+                [
+                    -2f32, -2f32, -1f32, 1f32, 3f32, 3f32, 2f32, 1f32, 0f32, -1f32,
+                ],
+            ),
+            Self::from_gains(
+                "Rock",
+                -4f32,
+                [4f32, 3f32, 2f32, 0f32, -1f32, -1f32, 1f32, 2f32, 3f32, 4f32],
+            ),
+            Self::from_gains(
+                "Electronic",
+                -4f32,
+                [4f32, 3f32, 1f32, 0f32, -2f32, 1f32, 0f32, 1f32, 3f32, 4f32],
+            ),
+            Self::from_gains(
+                "Classical",
+                0f32,
+                [
+                    0f32, 0f32, 0f32, 0f32, 0f32, 0f32, -2f32, -3f32, -3f32, -4f32,
+                ],
+            ),
+            Self::from_gains(
+                "Loudness",
+                -5f32,
+                [5f32, 3f32, 0f32, 0f32, -1f32, 0f32, -1f32, 0f32, 3f32, 4f32],
+            ),
+        ]
+    }
+}
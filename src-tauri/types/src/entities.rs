@@ -29,12 +29,13 @@ use serde::{Deserialize, Serialize};
 
 #[cfg(feature = "core")]
 use crate::schema::{
-    album_bridge, albums, analytics, artist_bridge, artists, genre_bridge, genres, playlist_bridge,
-    playlists,
+    album_bridge, albums, analytics, artist_bridge, artists, genre_bridge, genres, play_history,
+    playlist_bridge, playlists,
 };
 
 use super::{
     common::{deserialize_default, BridgeUtils, SearchByTerm},
+    smart_playlist::SmartPlaylistRules,
     songs::Song,
 };
 
@@ -73,6 +74,9 @@ where
     }
 }
 
+/// Album artist used for compilations
+pub const VARIOUS_ARTISTS: &str = "Various Artists";
+
 #[derive(Deserialize, Serialize, Default, Clone, Debug, Encode, Decode)]
 #[cfg_attr(
     feature = "core",
@@ -92,6 +96,10 @@ pub struct QueryableAlbum {
     #[serde(rename = "album_coverPath_low")]
     pub album_coverpath_low: Option<String>,
     pub album_extra_info: Option<EntityInfo>,
+    pub disc_total: Option<f64>,
+    pub compilation: Option<bool>,
+    /// MusicBrainz release ID
+    pub album_mbid: Option<String>,
 }
 
 impl std::hash::Hash for QueryableAlbum {
@@ -366,6 +374,7 @@ pub struct PlaylistBridge {
     pub id: Option<i32>,
     pub song: Option<String>,
     pub playlist: Option<String>,
+    pub position: Option<i32>,
 }
 
 impl BridgeUtils for PlaylistBridge {
@@ -400,6 +409,7 @@ pub struct QueryablePlaylist {
     pub extension: Option<String>,
     pub icon: Option<String>,
     pub library_item: Option<bool>,
+    pub smart_rules: Option<SmartPlaylistRules>,
 }
 
 impl std::hash::Hash for QueryablePlaylist {
@@ -457,6 +467,13 @@ pub struct SearchResult {
     pub genres: Vec<QueryableGenre>,
 }
 
+/// A playlist read from a playlist file, along with its songs
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
+pub struct ImportedPlaylist {
+    pub playlist: QueryablePlaylist,
+    pub songs: Vec<Song>,
+}
+
 #[derive(Deserialize, Serialize, Default, Clone, Debug)]
 #[cfg_attr(
     feature = "core",
@@ -470,3 +487,39 @@ pub struct Analytics {
     pub play_count: Option<i32>,
     pub play_time: Option<f64>,
 }
+
+/// A single play of a song
+#[derive(Deserialize, Serialize, Default, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "core", derive(Insertable, Queryable, Identifiable))]
+#[cfg_attr(feature = "core", diesel(table_name = play_history))]
+#[cfg_attr(feature = "core", diesel(primary_key(id)))]
+pub struct PlayHistory {
+    pub id: Option<i32>,
+    pub song_id: String,
+    /// Unix timestamp in milliseconds
+    pub started_at: i64,
+    /// Seconds the song was listened for
+    pub duration: f64,
+    /// False if the song was skipped before it ended
+    pub completed: bool,
+    pub provider: Option<String>,
+    pub player: Option<String>,
+}
+
+/// Details about a play which are recorded in the history along with its play time
+#[derive(Deserialize, Serialize, Default, Clone, Debug)]
+pub struct PlayDetails {
+    /// Unix timestamp in milliseconds, defaults to the time the play time was added minus
+    /// the play time
+    #[serde(default)]
+    pub started_at: Option<i64>,
+    pub completed: bool,
+    pub provider: Option<String>,
+    pub player: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Default, Clone, Debug)]
+pub struct PlayHistoryItem {
+    pub play: PlayHistory,
+    pub song: Song,
+}
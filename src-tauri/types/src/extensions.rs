@@ -28,6 +28,7 @@ use crate::{
         GetEntityOptions, QueryableAlbum, QueryableArtist, QueryableGenre, QueryablePlaylist,
     },
     errors::{MoosyncError, Result as MoosyncResult},
+    lyrics::Lyrics,
     preferences::PreferenceUIData,
     songs::{GetSongOptions, Song},
     ui::{
@@ -80,7 +81,7 @@ pub enum ExtensionExtraEventResponse {
     RequestedPlaylistFromURL(PlaylistAndSongsReturnType),
     RequestedSearchResult(SearchReturnType),
     RequestedRecommendations(RecommendationsReturnType),
-    RequestedLyrics(String),
+    RequestedLyrics(Lyrics),
     RequestedArtistSongs(SongsWithPageTokenReturnType),
     RequestedAlbumSongs(SongsWithPageTokenReturnType),
     SongAdded,
@@ -19,15 +19,21 @@ pub mod cache;
 pub mod cache_schema;
 pub mod canvaz;
 pub mod common;
+pub mod dsp;
 pub mod entities;
 pub mod errors;
+pub mod lyrics;
 
 #[cfg(not(feature = "extensions"))]
 pub mod mpris;
 pub mod preferences;
+pub mod scanner;
 #[cfg(feature = "core")]
 pub mod schema;
+pub mod smart_playlist;
 pub mod songs;
+pub mod stats;
+pub mod tags;
 
 pub mod providers;
 
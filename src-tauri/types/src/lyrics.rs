@@ -0,0 +1,277 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::fmt::Write;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LyricsWord {
+    pub start_ms: u64,
+    pub end_ms: Option<u64>,
+    pub text: String,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LyricsLine {
+    pub start_ms: u64,
+    pub end_ms: Option<u64>,
+    pub text: String,
+    #[serde(default)]
+    pub words: Vec<LyricsWord>,
+}
+
+/// Lyrics of a song. `lines` is only populated for time-coded lyrics,
+/// `plain` always holds the complete text so it can be used as a fallback.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "LyricsRepr")]
+pub struct Lyrics {
+    pub lines: Vec<LyricsLine>,
+    pub plain: String,
+}
+
+// Extensions and older caches return lyrics as a bare string
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum LyricsRepr {
+    Text(String),
+    Structured {
+        #[serde(default)]
+        lines: Vec<LyricsLine>,
+        #[serde(default)]
+        plain: String,
+    },
+}
+
+impl From<LyricsRepr> for Lyrics {
+    fn from(value: LyricsRepr) -> Self {
+        match value {
+            LyricsRepr::Text(text) => Lyrics::parse(&text),
+            LyricsRepr::Structured { lines, plain } => {
+                if lines.is_empty() {
+                    Lyrics::plain(plain)
+                } else {
+                    Lyrics::from_lines(lines)
+                }
+            }
+        }
+    }
+}
+
+impl Lyrics {
+    #[tracing::instrument(level = "debug", skip(text))]
+    pub fn plain(text: impl Into<String>) -> Self {
+        Self {
+            lines: vec![],
+            plain: text.into(),
+        }
+    }
+
+    /// Sorts the lines by start time and fills in missing end times
+    /// using the start of the following line.
+    #[tracing::instrument(level = "debug", skip(lines))]
+    pub fn from_lines(mut lines: Vec<LyricsLine>) -> Self {
+        lines.sort_by_key(|l| l.start_ms);
+
+        let starts = lines.iter().skip(1).map(|l| l.start_ms).collect::<Vec<_>>();
+        for (line, next_start) in lines.iter_mut().zip(starts) {
+            if line.end_ms.is_none_or(|end| end <= line.start_ms) {
+                line.end_ms = Some(next_start);
+            }
+        }
+
+        let plain = lines
+            .iter()
+            .map(|l| l.text.as_str())
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        Self { lines, plain }
+    }
+
+    pub fn is_synced(&self) -> bool {
+        !self.lines.is_empty()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty() && self.plain.trim().is_empty()
+    }
+
+    /// Parses LRC and enhanced LRC (word timings as `<mm:ss.xx>`).
+    /// Text without any time tags is treated as plain lyrics.
+    #[tracing::instrument(level = "debug", skip(text))]
+    pub fn parse(text: &str) -> Self {
+        let mut offset = 0i64;
+        let mut lines = vec![];
+
+        for raw_line in text.lines() {
+            let mut rest = raw_line.trim();
+            let mut timestamps = vec![];
+
+            while let Some(stripped) = rest.strip_prefix('[') {
+                let Some(end) = stripped.find(']') else {
+                    break;
+                };
+                let tag = &stripped[..end];
+                if let Some(time) = parse_timestamp(tag) {
+                    timestamps.push(time);
+                } else if let Some(value) = tag.strip_prefix("offset:") {
+                    offset = value.trim().parse().unwrap_or_default();
+                }
+                rest = stripped[end + 1..].trim_start();
+            }
+
+            if timestamps.is_empty() {
+                continue;
+            }
+
+            let (text, words) = parse_words(rest);
+            for start_ms in timestamps {
+                lines.push(LyricsLine {
+                    start_ms: apply_offset(start_ms, offset),
+                    end_ms: None,
+                    text: text.clone(),
+                    words: words
+                        .iter()
+                        .map(|w| LyricsWord {
+                            start_ms: apply_offset(w.start_ms, offset),
+                            end_ms: w.end_ms.map(|e| apply_offset(e, offset)),
+                            text: w.text.clone(),
+                        })
+                        .collect(),
+                });
+            }
+        }
+
+        if lines.is_empty() {
+            return Self::plain(text.trim());
+        }
+
+        Self::from_lines(lines)
+    }
+
+    /// Index of the line being sung at `position_ms`
+    pub fn line_at(&self, position_ms: u64) -> Option<usize> {
+        let index = self.lines.partition_point(|l| l.start_ms <= position_ms);
+        index.checked_sub(1)
+    }
+
+    /// Serializes back to (enhanced) LRC. Unsynced lyrics are returned as is.
+    pub fn to_lrc(&self) -> String {
+        if !self.is_synced() {
+            return self.plain.clone();
+        }
+
+        let mut ret = String::new();
+        for line in &self.lines {
+            let _ = write!(ret, "[{}]", format_timestamp(line.start_ms));
+            if line.words.is_empty() {
+                ret.push_str(&line.text);
+            } else {
+                for word in &line.words {
+                    let _ = write!(ret, "<{}>{}", format_timestamp(word.start_ms), word.text);
+                }
+                if let Some(end) = line.words.last().and_then(|w| w.end_ms) {
+                    let _ = write!(ret, "<{}>", format_timestamp(end));
+                }
+            }
+            ret.push('\n');
+        }
+        ret
+    }
+}
+
+fn apply_offset(time: u64, offset: i64) -> u64 {
+    // A positive offset in LRC shifts lyrics to appear sooner
+    (time as i64 - offset).max(0) as u64
+}
+
+// Accepts mm:ss, mm:ss.xx, mm:ss.xxx and mm:ss:xx
+fn parse_timestamp(tag: &str) -> Option<u64> {
+    let (minutes, rest) = tag.split_once(':')?;
+    let minutes: u64 = minutes.trim().parse().ok()?;
+
+    let (seconds, fraction) = match rest.find(['.', ':']) {
+        Some(i) => (&rest[..i], &rest[i + 1..]),
+        None => (rest, ""),
+    };
+    let seconds: u64 = seconds.trim().parse().ok()?;
+
+    let fraction = fraction.trim();
+    let millis = if fraction.is_empty() {
+        0
+    } else {
+        if !fraction.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        let digits = &fraction[..fraction.len().min(3)];
+        let value: u64 = digits.parse().ok()?;
+        value * 10u64.pow(3 - digits.len() as u32)
+    };
+
+    Some(minutes * 60_000 + seconds * 1000 + millis)
+}
+
+fn format_timestamp(time_ms: u64) -> String {
+    format!(
+        "{:02}:{:02}.{:02}",
+        time_ms / 60_000,
+        (time_ms / 1000) % 60,
+        (time_ms % 1000) / 10
+    )
+}
+
+fn parse_words(text: &str) -> (String, Vec<LyricsWord>) {
+    let mut words: Vec<LyricsWord> = vec![];
+    let mut plain = String::new();
+    let mut rest = text;
+
+    loop {
+        let Some(start) = rest.find('<') else {
+            break;
+        };
+        let Some(end) = rest[start..].find('>').map(|e| e + start) else {
+            break;
+        };
+        let Some(time) = parse_timestamp(&rest[start + 1..end]) else {
+            break;
+        };
+
+        let before = &rest[..start];
+        plain.push_str(before);
+        if let Some(last) = words.last_mut() {
+            last.text.push_str(before);
+            last.end_ms = Some(time);
+        }
+
+        words.push(LyricsWord {
+            start_ms: time,
+            end_ms: None,
+            text: String::new(),
+        });
+        rest = &rest[end + 1..];
+    }
+
+    plain.push_str(rest);
+    if let Some(last) = words.last_mut() {
+        last.text.push_str(rest);
+    }
+
+    // Trailing timestamps only mark the end of the previous word
+    words.retain(|w| !w.text.is_empty());
+
+    (plain.trim().to_string(), words)
+}
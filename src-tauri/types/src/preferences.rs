@@ -46,6 +46,9 @@ pub enum PreferenceTypes {
     TextField,
     InfoField,
     Dropdown,
+    Equalizer,
+    OutputDevice,
+    AudioCache,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -17,6 +17,7 @@
 use crate::{
     entities::{QueryableAlbum, QueryableArtist, QueryablePlaylist, SearchResult},
     errors::Result,
+    lyrics::Lyrics,
     songs::Song,
     ui::extensions::{ContextMenuReturnType, ExtensionProviderScope},
 };
@@ -135,7 +136,7 @@ pub trait GenericProvider: std::fmt::Debug + Send + Sync {
         pagination: Pagination,
     ) -> Result<(Vec<Song>, Pagination)>;
 
-    async fn get_lyrics(&self, song: Song) -> Result<String>;
+    async fn get_lyrics(&self, song: Song) -> Result<Lyrics>;
     async fn get_song_context_menu(&self, songs: Vec<Song>) -> Result<Vec<ContextMenuReturnType>>;
     async fn get_playlist_context_menu(
         &self,
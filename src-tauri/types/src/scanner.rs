@@ -0,0 +1,56 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use serde::{Deserialize, Serialize};
+
+/// Outcome of a scan. Songs are identified by their path.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScanReport {
+    pub added: Vec<String>,
+    pub updated: Vec<String>,
+    pub removed: Vec<String>,
+    pub unchanged: usize,
+}
+
+impl ScanReport {
+    pub fn extend(&mut self, other: ScanReport) {
+        self.added.extend(other.added);
+        self.updated.extend(other.updated);
+        self.removed.extend(other.removed);
+        self.unchanged += other.unchanged;
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScanState {
+    #[default]
+    UNDEFINED,
+    SCANNING,
+    // Scanning, with more scans waiting for the current one to finish
+    QUEUED,
+}
+
+/// Progress of the running scan, emitted to the UI as the `scan-progress` event
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScanProgress {
+    pub state: ScanState,
+    pub queued: usize,
+    pub discovered: usize,
+    pub processed: usize,
+    pub errors: usize,
+    pub current_path: Option<String>,
+    pub eta_ms: Option<u64>,
+}
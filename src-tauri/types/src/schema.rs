@@ -34,6 +34,9 @@ diesel::table! {
         year -> Nullable<Text>,
         album_coverpath_low -> Nullable<Text>,
         album_extra_info -> Nullable<Text>,
+        disc_total -> Nullable<Double>,
+        compilation -> Nullable<Bool>,
+        album_mbid -> Nullable<Text>,
     }
 }
 
@@ -67,6 +70,23 @@ diesel::table! {
         show_in_library -> Nullable<Bool>,
         track_no -> Nullable<Double>,
         library_item -> Nullable<Bool>,
+        mtime -> Nullable<Double>,
+        track_gain -> Nullable<Double>,
+        track_peak -> Nullable<Double>,
+        album_gain -> Nullable<Double>,
+        album_peak -> Nullable<Double>,
+        disc_no -> Nullable<Double>,
+        disc_total -> Nullable<Double>,
+        compilation -> Nullable<Bool>,
+        recording_mbid -> Nullable<Text>,
+        isrc -> Nullable<Text>,
+        original_date -> Nullable<Text>,
+        label -> Nullable<Text>,
+        catalog_number -> Nullable<Text>,
+        composer -> Nullable<Text>,
+        bpm -> Nullable<Double>,
+        start_offset -> Nullable<Double>,
+        end_offset -> Nullable<Double>,
     }
 }
 
@@ -115,11 +135,32 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    merged_duplicates (path) {
+        path -> Text,
+        hash -> Nullable<Text>,
+        kept_id -> Text,
+    }
+}
+
+diesel::table! {
+    play_history (id) {
+        id -> Nullable<Integer>,
+        song_id -> Text,
+        started_at -> BigInt,
+        duration -> Double,
+        completed -> Bool,
+        provider -> Nullable<Text>,
+        player -> Nullable<Text>,
+    }
+}
+
 diesel::table! {
     playlist_bridge (id) {
         id -> Nullable<Integer>,
         song -> Nullable<Text>,
         playlist -> Nullable<Text>,
+        position -> Nullable<Integer>,
     }
 }
 
@@ -133,7 +174,19 @@ diesel::table! {
         playlist_path -> Nullable<Text>,
         extension -> Nullable<Text>,
         icon -> Nullable<Text>,
-        library_item -> Nullable<Bool>
+        library_item -> Nullable<Bool>,
+        smart_rules -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    tag_journal (id) {
+        id -> Nullable<Integer>,
+        batch_id -> Text,
+        song_id -> Text,
+        path -> Text,
+        changes -> Text,
+        written_at -> BigInt,
     }
 }
 
@@ -146,6 +199,9 @@ diesel::allow_tables_to_appear_in_same_query!(
     artists,
     genre_bridge,
     genres,
+    merged_duplicates,
+    play_history,
     playlist_bridge,
     playlists,
+    tag_journal,
 );
@@ -0,0 +1,168 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+#[cfg(feature = "core")]
+use diesel::{
+    backend::Backend,
+    deserialize::{self, FromSql, FromSqlRow},
+    expression::AsExpression,
+    serialize::{IsNull, ToSql},
+    sql_types::Text,
+    sqlite::Sqlite,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SmartPlaylistField {
+    #[default]
+    Title,
+    Artist,
+    Album,
+    Genre,
+    Year,
+    Duration,
+    Bitrate,
+    Codec,
+    Path,
+    DateAdded,
+    PlayCount,
+    PlayTime,
+}
+
+impl SmartPlaylistField {
+    pub const ALL: [SmartPlaylistField; 12] = [
+        Self::Title,
+        Self::Artist,
+        Self::Album,
+        Self::Genre,
+        Self::Year,
+        Self::Duration,
+        Self::Bitrate,
+        Self::Codec,
+        Self::Path,
+        Self::DateAdded,
+        Self::PlayCount,
+        Self::PlayTime,
+    ];
+
+    /// Numeric fields only support equality and greater / less than comparisons
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn is_numeric(&self) -> bool {
+        matches!(
+            self,
+            Self::Duration | Self::Bitrate | Self::DateAdded | Self::PlayCount | Self::PlayTime
+        )
+    }
+
+    /// Artists, albums and genres are matched by name and can't be compared by order
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn supports(&self, operator: SmartPlaylistOperator) -> bool {
+        match operator {
+            SmartPlaylistOperator::Contains | SmartPlaylistOperator::NotContains => {
+                !self.is_numeric()
+            }
+            SmartPlaylistOperator::GreaterThan | SmartPlaylistOperator::LessThan => {
+                !matches!(self, Self::Artist | Self::Album | Self::Genre)
+            }
+            _ => true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SmartPlaylistOperator {
+    #[default]
+    Equals,
+    NotEquals,
+    Contains,
+    NotContains,
+    GreaterThan,
+    LessThan,
+}
+
+impl SmartPlaylistOperator {
+    pub const ALL: [SmartPlaylistOperator; 6] = [
+        Self::Equals,
+        Self::NotEquals,
+        Self::Contains,
+        Self::NotContains,
+        Self::GreaterThan,
+        Self::LessThan,
+    ];
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct SmartPlaylistRule {
+    pub field: SmartPlaylistField,
+    pub operator: SmartPlaylistOperator,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SmartPlaylistMatch {
+    #[default]
+    All,
+    Any,
+}
+
+/// Rules of a smart playlist. Songs matching the rules are fetched each time
+/// the playlist is read instead of being stored in the playlist
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "core", derive(FromSqlRow, AsExpression))]
+#[cfg_attr(feature = "core", diesel(sql_type = diesel::sql_types::Text))]
+pub struct SmartPlaylistRules {
+    #[serde(default)]
+    pub match_type: SmartPlaylistMatch,
+    #[serde(default)]
+    pub rules: Vec<SmartPlaylistRule>,
+    pub sort_by: Option<SmartPlaylistField>,
+    #[serde(default)]
+    pub sort_desc: bool,
+    pub limit: Option<i64>,
+}
+
+#[cfg(feature = "core")]
+impl<DB> FromSql<Text, DB> for SmartPlaylistRules
+where
+    DB: Backend,
+    String: FromSql<Text, DB>,
+{
+    #[tracing::instrument(level = "debug", skip(bytes))]
+    fn from_sql(bytes: DB::RawValue<'_>) -> deserialize::Result<Self> {
+        let t = <String as FromSql<Text, DB>>::from_sql(bytes)?;
+        Ok(serde_json::from_str(&t)?)
+    }
+}
+
+#[cfg(feature = "core")]
+impl ToSql<Text, Sqlite> for SmartPlaylistRules
+where
+    String: ToSql<Text, Sqlite>,
+{
+    #[tracing::instrument(level = "debug", skip(self, out))]
+    fn to_sql<'b>(
+        &'b self,
+        out: &mut diesel::serialize::Output<'b, '_, Sqlite>,
+    ) -> diesel::serialize::Result {
+        let s = serde_json::to_string(self)?;
+
+        out.set_value(s);
+        Ok(IsNull::No)
+    }
+}
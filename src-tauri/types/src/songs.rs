@@ -171,6 +171,54 @@ pub struct QueryableSong {
     pub show_in_library: Option<bool>,
     pub track_no: Option<f64>,
     pub library_item: Option<bool>,
+    pub mtime: Option<f64>,
+    /// ReplayGain adjustments in dB, relative to a -18 LUFS reference
+    pub track_gain: Option<f64>,
+    pub track_peak: Option<f64>,
+    pub album_gain: Option<f64>,
+    pub album_peak: Option<f64>,
+    pub disc_no: Option<f64>,
+    pub disc_total: Option<f64>,
+    /// Whether the song is part of a compilation by various artists
+    pub compilation: Option<bool>,
+    /// MusicBrainz recording ID
+    pub recording_mbid: Option<String>,
+    pub isrc: Option<String>,
+    pub original_date: Option<String>,
+    pub label: Option<String>,
+    pub catalog_number: Option<String>,
+    pub composer: Option<String>,
+    pub bpm: Option<f64>,
+    /// Position in seconds where a track of a CUE sheet starts inside its file
+    pub start_offset: Option<f64>,
+    /// Position in seconds where a track of a CUE sheet ends, None for the last track
+    pub end_offset: Option<f64>,
+}
+
+/// Separates the path of the file from the track number in paths of CUE sheet tracks
+pub const CUE_TRACK_SEPARATOR: char = '#';
+
+impl QueryableSong {
+    /// Whether the song is a track of a CUE sheet, which only plays a part of its file
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn is_cue_track(&self) -> bool {
+        self.start_offset.is_some()
+    }
+
+    /// Path of the audio file of a local song. Tracks of a CUE sheet share it with the
+    /// other tracks of the sheet
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn file_path(&self) -> Option<&str> {
+        let path = self.path.as_deref()?;
+        if !self.is_cue_track() {
+            return Some(path);
+        }
+        Some(
+            path.rsplit_once(CUE_TRACK_SEPARATOR)
+                .map(|(file, _)| file)
+                .unwrap_or(path),
+        )
+    }
 }
 
 impl std::hash::Hash for QueryableSong {
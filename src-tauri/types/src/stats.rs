@@ -0,0 +1,136 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::fmt::Write;
+
+use serde::{Deserialize, Serialize};
+
+/// Plays of a song, artist, album or genre
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StatsEntry {
+    pub id: String,
+    pub name: String,
+    pub play_count: i64,
+    pub play_time: f64,
+}
+
+/// Plays in a day, week or month. Periods are named by the date they start on
+/// (YYYY-MM-DD, weeks start on monday) or by their month (YYYY-MM)
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StatsPeriod {
+    pub period: String,
+    pub play_count: i64,
+    pub play_time: f64,
+}
+
+/// Consecutive days with at least one play
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ListeningStreak {
+    pub start: String,
+    pub end: String,
+    pub days: i64,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ListeningStats {
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    pub play_count: i64,
+    pub play_time: f64,
+    pub skip_count: i64,
+    pub skip_rate: f64,
+    pub top_songs: Vec<StatsEntry>,
+    pub top_artists: Vec<StatsEntry>,
+    pub top_albums: Vec<StatsEntry>,
+    pub top_genres: Vec<StatsEntry>,
+    /// Songs played for the first time in this range
+    pub new_songs: Vec<StatsEntry>,
+    pub days: Vec<StatsPeriod>,
+    pub weeks: Vec<StatsPeriod>,
+    pub months: Vec<StatsPeriod>,
+    pub longest_streak: Option<ListeningStreak>,
+}
+
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StatsExportFormat {
+    #[default]
+    Json,
+    Csv,
+}
+
+#[tracing::instrument(level = "debug", skip(value))]
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+impl ListeningStats {
+    /// Every entry and period as a row of `section,id,name,play_count,play_time`
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn to_csv(&self) -> String {
+        let mut ret = String::from("section,id,name,play_count,play_time\n");
+        let _ = writeln!(ret, "total,,,{},{}", self.play_count, self.play_time);
+        let _ = writeln!(ret, "skipped,,,{},", self.skip_count);
+        if let Some(streak) = &self.longest_streak {
+            let _ = writeln!(
+                ret,
+                "longest_streak,{},{},{},",
+                streak.start, streak.end, streak.days
+            );
+        }
+
+        let sections = [
+            ("top_song", &self.top_songs),
+            ("top_artist", &self.top_artists),
+            ("top_album", &self.top_albums),
+            ("top_genre", &self.top_genres),
+            ("new_song", &self.new_songs),
+        ];
+        for (section, entries) in sections {
+            for entry in entries {
+                let _ = writeln!(
+                    ret,
+                    "{},{},{},{},{}",
+                    section,
+                    csv_field(&entry.id),
+                    csv_field(&entry.name),
+                    entry.play_count,
+                    entry.play_time
+                );
+            }
+        }
+
+        let periods = [
+            ("day", &self.days),
+            ("week", &self.weeks),
+            ("month", &self.months),
+        ];
+        for (section, periods) in periods {
+            for period in periods {
+                let _ = writeln!(
+                    ret,
+                    "{},{},,{},{}",
+                    section, period.period, period.play_count, period.play_time
+                );
+            }
+        }
+        ret
+    }
+}
@@ -0,0 +1,174 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+#[cfg(feature = "core")]
+use diesel::{
+    backend::Backend,
+    deserialize::{self, FromSql, FromSqlRow},
+    expression::AsExpression,
+    serialize::{IsNull, ToSql},
+    sql_types::Text,
+    sqlite::Sqlite,
+    Insertable, Queryable,
+};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "core")]
+use crate::schema::tag_journal;
+
+/// A tag which can be written back to audio files
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum TagField {
+    Title,
+    Artists,
+    Album,
+    AlbumArtist,
+    Year,
+    Genre,
+    TrackNo,
+    DiscNo,
+    Lyrics,
+    Cover,
+}
+
+/// Tag edits applied to one or more songs. Fields left as None are kept as they are
+/// while empty values remove the tag from the file
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct TagEdit {
+    pub title: Option<String>,
+    pub artists: Option<Vec<String>>,
+    pub album: Option<String>,
+    pub album_artist: Option<String>,
+    pub year: Option<String>,
+    pub genre: Option<String>,
+    pub track_no: Option<String>,
+    pub disc_no: Option<String>,
+    pub lyrics: Option<String>,
+    /// Path to an image embedded as the front cover
+    pub cover: Option<String>,
+}
+
+impl TagEdit {
+    /// New values of the edited fields, with multiple artists joined by `artist_split`
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn values(&self, artist_split: &str) -> Vec<(TagField, Option<String>)> {
+        let artists = self.artists.as_ref().map(|artists| {
+            artists
+                .iter()
+                .map(|a| a.trim())
+                .filter(|a| !a.is_empty())
+                .collect::<Vec<_>>()
+                .join(artist_split)
+        });
+
+        [
+            (TagField::Title, self.title.clone()),
+            (TagField::Artists, artists),
+            (TagField::Album, self.album.clone()),
+            (TagField::AlbumArtist, self.album_artist.clone()),
+            (TagField::Year, self.year.clone()),
+            (TagField::Genre, self.genre.clone()),
+            (TagField::TrackNo, self.track_no.clone()),
+            (TagField::DiscNo, self.disc_no.clone()),
+            (TagField::Lyrics, self.lyrics.clone()),
+            (TagField::Cover, self.cover.clone()),
+        ]
+        .into_iter()
+        .filter_map(|(field, value)| {
+            value.map(|v| (field, Some(v.trim().to_string()).filter(|v| !v.is_empty())))
+        })
+        .collect()
+    }
+}
+
+/// Change of a single tag in a file
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TagChange {
+    pub field: TagField,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+impl TagChange {
+    /// The change which reverts this one
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn inverse(&self) -> Self {
+        Self {
+            field: self.field,
+            old: self.new.clone(),
+            new: self.old.clone(),
+        }
+    }
+}
+
+/// Changes a tag edit makes to the file of a song, shown before writing them
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct TagDiff {
+    pub song_id: String,
+    pub path: String,
+    pub changes: Vec<TagChange>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "core", derive(FromSqlRow, AsExpression))]
+#[cfg_attr(feature = "core", diesel(sql_type = diesel::sql_types::Text))]
+pub struct TagChanges(pub Vec<TagChange>);
+
+#[cfg(feature = "core")]
+impl<DB> FromSql<Text, DB> for TagChanges
+where
+    DB: Backend,
+    String: FromSql<Text, DB>,
+{
+    #[tracing::instrument(level = "debug", skip(bytes))]
+    fn from_sql(bytes: DB::RawValue<'_>) -> deserialize::Result<Self> {
+        let t = <String as FromSql<Text, DB>>::from_sql(bytes)?;
+        Ok(serde_json::from_str(&t)?)
+    }
+}
+
+#[cfg(feature = "core")]
+impl ToSql<Text, Sqlite> for TagChanges
+where
+    String: ToSql<Text, Sqlite>,
+{
+    #[tracing::instrument(level = "debug", skip(self, out))]
+    fn to_sql<'b>(
+        &'b self,
+        out: &mut diesel::serialize::Output<'b, '_, Sqlite>,
+    ) -> diesel::serialize::Result {
+        let s = serde_json::to_string(self)?;
+
+        out.set_value(s);
+        Ok(IsNull::No)
+    }
+}
+
+/// Changes written to the file of a song, kept so they can be undone
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "core", derive(Insertable, Queryable))]
+#[cfg_attr(feature = "core", diesel(table_name = tag_journal))]
+pub struct TagJournalEntry {
+    pub id: Option<i32>,
+    /// Entries of songs edited together share a batch, which is undone as a whole
+    pub batch_id: String,
+    pub song_id: String,
+    pub path: String,
+    pub changes: TagChanges,
+    /// Unix timestamp in milliseconds
+    pub written_at: i64,
+}
@@ -36,6 +36,8 @@ pub enum PlayerEvents {
     Ended,
     Loading,
     TimeUpdate(f64),
+    /// Duration in seconds as decoded, for sources whose metadata may lack it
+    DurationChange(f64),
 
     #[serde(
         deserialize_with = "deserialize_moosync_error",
@@ -52,6 +54,7 @@ impl Clone for PlayerEvents {
             PlayerEvents::Ended => PlayerEvents::Ended,
             PlayerEvents::Loading => PlayerEvents::Loading,
             PlayerEvents::TimeUpdate(time) => PlayerEvents::TimeUpdate(*time),
+            PlayerEvents::DurationChange(duration) => PlayerEvents::DurationChange(*duration),
             PlayerEvents::Error(error) => PlayerEvents::Error(error.to_string().clone().into()),
         }
     }
@@ -72,6 +75,12 @@ where
     Ok(MoosyncError::String(error_str))
 }
 
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutputDevice {
+    pub name: String,
+    pub is_default: bool,
+}
+
 #[derive(Debug, Default, Copy, Clone, Encode, Decode)]
 pub enum VolumeMode {
     #[default]
@@ -87,3 +96,15 @@ pub enum RepeatModes {
     Once,
     Loop,
 }
+
+/// When the sleep timer should stop playback
+#[derive(Debug, PartialEq, Copy, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum SleepTimer {
+    /// Stop after the given number of minutes
+    Minutes(f64),
+    /// Stop once the current track ends
+    EndOfTrack,
+    /// Stop once the given number of tracks, including the current one, have ended
+    Tracks(usize),
+}
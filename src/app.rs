@@ -25,7 +25,7 @@ use crate::{
     store::ui_store::UiStore,
     utils::{
         common::{emit, get_locale, listen_event},
-        invoke::{get_css, load_selective, load_theme, toggle_dev_tools},
+        invoke::{get_css, get_scan_progress, load_selective, load_theme, toggle_dev_tools},
         prefs::watch_preferences,
     },
 };
@@ -39,13 +39,16 @@ use leptos_context_menu::provide_context_menu_state;
 use leptos_i18n::t_string;
 use leptos_router::{
     components::{Outlet, ParentRoute, Redirect, Route, Router, Routes},
+    hooks::{use_location, use_navigate},
     path,
 };
 use leptos_use::use_event_listener;
 use serde::Serialize;
 use types::{
-    preferences::CheckboxPreference, ui::extensions::ExtensionUIRequest,
-    ui::player_details::PlayerState,
+    preferences::CheckboxPreference,
+    scanner::ScanProgress,
+    ui::extensions::ExtensionUIRequest,
+    ui::player_details::{PlayerState, SleepTimer},
 };
 use wasm_bindgen::{JsCast, JsValue};
 use wasm_bindgen_futures::spawn_local;
@@ -53,6 +56,7 @@ use web_sys::HtmlElement;
 
 use crate::{
     components::{
+        jukebox::{parse_request_limit, restore_jukebox, JukeboxBar},
         musicbar::MusicBar,
         sidebar::{Sidebar, Tab},
         topbar::TopBar,
@@ -61,10 +65,13 @@ use crate::{
     pages::{
         albums::{AllAlbums, SingleAlbum},
         artists::{AllArtists, SingleArtist},
+        duplicates::Duplicates,
         genres::{AllGenres, SingleGenre},
+        history::History,
         playlists::{AllPlaylists, SinglePlaylist},
         search::Search,
         songs::AllSongs,
+        stats::Stats,
     },
     store::{modal_store::ModalStore, player_store::PlayerStore, provider_store::ProviderStore},
 };
@@ -76,9 +83,29 @@ pub fn RedirectAll() -> impl IntoView {
     view! { <Redirect path="/main/allsongs" /> }
 }
 
+const JUKEBOX_BLOCKED_ROUTES: [&str; 4] =
+    ["/prefs", "/main/stats", "/main/history", "/main/duplicates"];
+
 #[tracing::instrument(level = "debug", skip())]
 #[component]
 fn CommonApp() -> impl IntoView {
+    let player_store = expect_context::<RwSignal<PlayerStore>>();
+    let is_jukebox = create_read_slice(player_store, |p| p.is_jukebox());
+    let location = use_location();
+    let navigate = use_navigate();
+
+    // Guests in jukebox mode may only browse the library
+    Effect::new(move || {
+        let pathname = location.pathname.get();
+        if is_jukebox.get()
+            && JUKEBOX_BLOCKED_ROUTES
+                .iter()
+                .any(|route| pathname.starts_with(route))
+        {
+            navigate("/main/allsongs", Default::default());
+        }
+    });
+
     view! {
         <div>
             <MusicBar />
@@ -139,8 +166,31 @@ pub fn MainApp() -> impl IntoView {
             "Explore",
             "/main/explore",
         ),
+        Tab::new(
+            move || t_string!(use_i18n(), sidebar.tabs.stats),
+            "Stats",
+            "/main/stats",
+        ),
+        Tab::new(
+            move || t_string!(use_i18n(), sidebar.tabs.history),
+            "History",
+            "/main/history",
+        ),
+        Tab::new(
+            move || t_string!(use_i18n(), sidebar.tabs.duplicates),
+            "Duplicates",
+            "/main/duplicates",
+        ),
     ];
 
+    let player_store = expect_context::<RwSignal<PlayerStore>>();
+    let is_jukebox = create_read_slice(player_store, |p| p.is_jukebox());
+    let jukebox_tabs: Vec<Tab> = tabs
+        .iter()
+        .filter(|t| !JUKEBOX_BLOCKED_ROUTES.contains(&t.url.as_str()))
+        .cloned()
+        .collect();
+
     let ui_store = expect_context::<RwSignal<UiStore>>();
     let is_mobile = create_read_slice(ui_store, |u| u.get_is_mobile()).get();
     let sidebar_open = create_read_slice(ui_store, |u| u.get_sidebar_open());
@@ -179,8 +229,14 @@ pub fn MainApp() -> impl IntoView {
     view! {
         <div>
             <TopBar />
-            <Sidebar tabs=tabs />
+            {move || {
+                let tabs = if is_jukebox.get() { jukebox_tabs.clone() } else { tabs.clone() };
+                view! { <Sidebar tabs=tabs /> }
+            }}
             <div class=class>
+                <Show when=move || is_jukebox.get()>
+                    <JukeboxBar />
+                </Show>
                 <Outlet />
             </div>
         </div>
@@ -263,14 +319,6 @@ fn handle_theme(id: String) {
 pub fn App() -> impl IntoView {
     leptos_meta::provide_meta_context();
 
-    let _ = use_event_listener(document().body(), keydown, |ev| {
-        if ev.shift_key() && ev.ctrl_key() && ev.key_code() == 75 {
-            spawn_local(async move {
-                let _ = toggle_dev_tools().await;
-            });
-        }
-    });
-
     provide_context_menu_state();
     provide_context(RwSignal::new(UiStore::new()));
     provide_context(RwSignal::new(ModalStore::default()));
@@ -294,8 +342,19 @@ pub fn App() -> impl IntoView {
         }
     }
 
-    provide_context(PlayerStore::new());
+    let player_store = PlayerStore::new();
+    provide_context(player_store);
     provide_context(Arc::new(ProviderStore::new()));
+    restore_jukebox(player_store);
+
+    let is_jukebox = create_read_slice(player_store, |p| p.is_jukebox());
+    let _ = use_event_listener(document().body(), keydown, move |ev| {
+        if ev.shift_key() && ev.ctrl_key() && ev.key_code() == 75 && !is_jukebox.get_untracked() {
+            spawn_local(async move {
+                let _ = toggle_dev_tools().await;
+            });
+        }
+    });
 
     spawn_local(async move {
         let id = load_selective("themes.active_theme".into()).await.unwrap();
@@ -391,13 +450,19 @@ pub fn App() -> impl IntoView {
                 tracing::debug!("Setting locale to {:?}", enabled.key);
                 i18n.set_locale(get_locale(&enabled.key))
             }
+        } else if key == "prefs.jukebox_request_limit" {
+            let player_store = expect_context::<RwSignal<PlayerStore>>();
+            let limit = parse_request_limit(value);
+            player_store.update(|p| p.set_jukebox_request_limit(limit));
         }
     });
 
     let owner = Owner::new();
     let unlisten_mpris = listen_event("media_button_press", move |data: JsValue| {
         let payload = js_sys::Reflect::get(&data, &JsValue::from_str("payload")).unwrap();
-        let (key, value): (i32, Option<f64>) = serde_wasm_bindgen::from_value(payload).unwrap();
+        let (key, args): (i32, serde_json::Value) =
+            serde_wasm_bindgen::from_value(payload).unwrap();
+        let value = args.as_f64();
 
         owner.with(|| {
             let player_store: RwSignal<PlayerStore> = expect_context();
@@ -406,7 +471,7 @@ pub fn App() -> impl IntoView {
                 0 => player_store.update(|p| p.set_state(PlayerState::Playing)),
                 1 => player_store.update(|p| p.set_state(PlayerState::Paused)),
                 2 => player_store.update(|p| p.set_state(PlayerState::Stopped)),
-                6 => player_store.update(|p| p.next_song()),
+                6 => player_store.update(|p| p.skip_song()),
                 7 => player_store.update(|p| p.prev_song()),
                 12 => player_store.update(|p| p.force_seek(value.unwrap_or_default())),
                 13 => player_store.update(|p| match p.get_player_state() {
@@ -414,12 +479,30 @@ pub fn App() -> impl IntoView {
                     _ => p.set_state(PlayerState::Playing),
                 }),
                 15 => player_store.update(|p| p.set_volume(value.unwrap_or_default())),
+                19 => match serde_json::from_value::<Option<SleepTimer>>(args) {
+                    Ok(timer) => player_store.update(|p| p.set_sleep_timer(timer)),
+                    Err(e) => tracing::error!("Failed to parse sleep timer: {:?}", e),
+                },
 
                 _ => {}
             }
         });
     });
 
+    let ui_store = expect_context::<RwSignal<UiStore>>();
+    spawn_local(async move {
+        if let Ok(progress) = get_scan_progress().await {
+            ui_store.update(|u| u.set_scan_progress(progress));
+        }
+    });
+    let unlisten_scan_progress = listen_event("scan-progress", move |data: JsValue| {
+        let payload = js_sys::Reflect::get(&data, &JsValue::from_str("payload")).unwrap();
+        match serde_wasm_bindgen::from_value::<ScanProgress>(payload) {
+            Ok(progress) => ui_store.update(|u| u.set_scan_progress(progress)),
+            Err(e) => tracing::error!("Failed to parse scan progress: {:?}", e),
+        }
+    });
+
     let window = window();
     if let Err(e) = window.add_event_listener_with_callback("beforeunload", &watch_prefs_unlisten) {
         tracing::error!("Failed to set unmount hook: {:?}", e);
@@ -433,6 +516,11 @@ pub fn App() -> impl IntoView {
         tracing::error!("Failed to set unmount hook: {:?}", e);
     }
 
+    if let Err(e) = window.add_event_listener_with_callback("beforeunload", &unlisten_scan_progress)
+    {
+        tracing::error!("Failed to set unmount hook: {:?}", e);
+    }
+
     check_for_updates();
 
     view! {
@@ -453,6 +541,9 @@ pub fn App() -> impl IntoView {
                                 <Route path=path!("genres/single") view=SingleGenre />
                                 <Route path=path!("search") view=Search />
                                 <Route path=path!("explore") view=Explore />
+                                <Route path=path!("stats") view=Stats />
+                                <Route path=path!("history") view=History />
+                                <Route path=path!("duplicates") view=Duplicates />
                             </ParentRoute>
                             <SettingRoutes />
                         </ParentRoute>
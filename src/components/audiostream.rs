@@ -29,6 +29,7 @@ use std::{
 };
 use tokio::sync::oneshot;
 use types::{
+    entities::PlayDetails,
     errors::{MoosyncError, Result},
     songs::Song,
     ui::{
@@ -51,11 +52,29 @@ use crate::{
     store::{player_store::PlayerStore, provider_store::ProviderStore, ui_store::UiStore},
     utils::{
         extensions::send_extension_event,
-        invoke::{fetch_playback_url, increment_play_count, increment_play_time},
+        invoke::{fetch_playback_url, increment_play_count, increment_play_time, is_audio_cached},
         mpris::set_metadata,
     },
 };
 
+/// Plays which reached this part of the song are recorded as completed instead of skipped
+const COMPLETED_PLAY_RATIO: f64 = 0.9;
+
+/// Seconds before the end of the current song at which the next one is preloaded.
+/// Has to leave enough time to load the song before the longest possible crossfade
+const PRELOAD_BEFORE_END: f64 = 15f64;
+
+#[tracing::instrument(level = "debug", skip(a, b))]
+fn is_same_album(a: &Song, b: &Song) -> bool {
+    match (a.album.as_ref(), b.album.as_ref()) {
+        (Some(a), Some(b)) => match (&a.album_id, &b.album_id) {
+            (Some(a), Some(b)) => a == b,
+            _ => a.album_name.is_some() && a.album_name == b.album_name,
+        },
+        _ => false,
+    }
+}
+
 pub struct PlayerHolder {
     providers: Arc<ProviderStore>,
     players: Rc<Mutex<Vec<Box<dyn GenericPlayer>>>>,
@@ -145,12 +164,40 @@ impl PlayerHolder {
         Ok(())
     }
 
+    /// Position of the rodio player if the song is in its audio cache
+    #[tracing::instrument(level = "debug", skip(self, player_store, song))]
+    async fn get_cached_player(
+        &self,
+        player_store: RwSignal<PlayerStore>,
+        song: &Song,
+    ) -> Option<usize> {
+        if song.song.path.is_some() {
+            return None;
+        }
+        let id = song.song._id.clone()?;
+        if !is_audio_cached(id).await.unwrap_or_default() {
+            return None;
+        }
+
+        let player_blacklist = player_store.with_untracked(|p| p.get_player_blacklist());
+        let players = self.players.lock().await;
+        players
+            .iter()
+            .position(|p| p.key() == "rodio" && !player_blacklist.contains(&p.key()))
+    }
+
     #[tracing::instrument(level = "debug", skip(self, player_store, song))]
     pub async fn get_player(
         &self,
         player_store: RwSignal<PlayerStore>,
         song: &mut Song,
     ) -> Result<usize> {
+        // Cached songs play from disk, so they don't need a playback url
+        if let Some(player) = self.get_cached_player(player_store, song).await {
+            tracing::info!("Playing cached song on player {}", player);
+            return Ok(player);
+        }
+
         // Try to get playback URL from extension if dummy url is provided
         let mut already_fetched = false;
         if song.song.path.is_none()
@@ -219,6 +266,14 @@ impl PlayerHolder {
         Err(MoosyncError::String("Player not found".into()))
     }
 
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn get_active_player_key(&self) -> Option<String> {
+        let players = self.players.lock().await;
+        players
+            .get(self.active_player.load(Ordering::Relaxed))
+            .map(|p| p.key())
+    }
+
     #[tracing::instrument(level = "debug", skip(self, volume))]
     pub async fn set_volume(&self, volume: f64) -> Result<()> {
         let players = self.players.lock().await;
@@ -287,7 +342,7 @@ impl PlayerHolder {
         tracing::debug!("Active player: {}", player.key());
 
         let (resolver_tx, resolver_rx) = oneshot::channel();
-        player.load(src.unwrap(), autoplay, resolver_tx);
+        player.load(src.unwrap(), song.song._id.clone(), autoplay, resolver_tx);
 
         resolver_rx.await.expect("Load failed to resolve");
         tracing::debug!("Setting volume {}", current_volume);
@@ -303,6 +358,52 @@ impl PlayerHolder {
         Ok(())
     }
 
+    /// Preloads the song on the active player if it can also play it, so the
+    /// transition doesn't need to switch players
+    #[tracing::instrument(level = "debug", skip(self, song, crossfade, player_store))]
+    pub async fn preload_audio(
+        &self,
+        song: &Song,
+        crossfade: bool,
+        player_store: RwSignal<PlayerStore>,
+    ) {
+        // Playback urls provided by extensions are only fetched once the song is loaded
+        if song.song.path.is_none()
+            && song
+                .song
+                .playback_url
+                .as_ref()
+                .is_some_and(|u| u.starts_with("extension://"))
+        {
+            return;
+        }
+
+        let Some(src) = song.song.playback_url.clone().or(song.song.path.clone()) else {
+            return;
+        };
+
+        let player_blacklist = player_store.with_untracked(|p| p.get_player_blacklist());
+        let players = self.players.lock().await;
+        if let Some(active) = players.get(self.active_player.load(Ordering::Relaxed)) {
+            if !player_blacklist.contains(&active.key())
+                && active.provides().contains(&song.song.type_)
+                && active.can_play(song)
+            {
+                tracing::debug!("Preloading {:?} on {}", song.song.title, active.key());
+                active.preload(src, song.song._id.clone(), crossfade);
+            }
+        }
+    }
+
+    /// Drops the song preloaded on the active player, if any
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn cancel_preload(&self) {
+        let players = self.players.lock().await;
+        if let Some(active) = players.get(self.active_player.load(Ordering::Relaxed)) {
+            active.cancel_preload();
+        }
+    }
+
     #[tracing::instrument(level = "debug", skip(self, player_store))]
     fn listen_player_state(&self, player_store: RwSignal<PlayerStore>) {
         let player_state_getter = create_read_slice(player_store, move |p| p.get_player_state());
@@ -388,8 +489,14 @@ impl PlayerHolder {
             store.set_state(state);
         });
 
-        let next_song_setter =
-            create_write_slice(player_store, move |store, _| match store.get_repeat() {
+        let next_song_setter = create_write_slice(player_store, move |store, _| {
+            if store.consume_sleep_track() {
+                store.set_state(PlayerState::Stopped);
+                store.next_song();
+                return;
+            }
+
+            match store.get_repeat() {
                 types::ui::player_details::RepeatModes::None => store.next_song(),
                 types::ui::player_details::RepeatModes::Once => {
                     if !store.get_has_repeated() {
@@ -408,12 +515,17 @@ impl PlayerHolder {
                     store.change_index(store.data.queue.current_index, true);
                     store.set_state(PlayerState::Playing);
                 }
-            });
+            }
+        });
 
         let player_time_setter = create_write_slice(player_store, move |store, time| {
             store.update_time(time);
         });
 
+        let player_duration_setter = create_write_slice(player_store, move |store, duration| {
+            store.update_duration(duration);
+        });
+
         let setter = move |player: String, ev: PlayerEvents| {
             match ev {
                 PlayerEvents::Play => player_state_setter.set(PlayerState::Playing),
@@ -425,6 +537,7 @@ impl PlayerHolder {
                     next_song_setter.set(());
                 }
                 PlayerEvents::TimeUpdate(t) => player_time_setter.set(t),
+                PlayerEvents::DurationChange(d) => player_duration_setter.set(d),
                 PlayerEvents::Error(err) => {
                     tracing::error!("Error playing song: {:?}", err);
                     let mut player_blacklist_sender = player_blacklist_sender.clone();
@@ -483,10 +596,25 @@ pub fn AudioStream() -> impl IntoView {
     let last_song_sig = create_read_slice(player_store, |player_store| {
         player_store.data.player_details.last_song.clone()
     });
+    let last_song_played_duration = create_read_slice(player_store, |player_store| {
+        player_store.data.player_details.last_song_played_duration
+    });
+
+    let upcoming_song_sig = create_read_slice(player_store, |player_store| {
+        player_store.get_upcoming_song()
+    });
+    let current_duration_sig = create_read_slice(player_store, |player_store| {
+        player_store.get_current_duration()
+    });
+    let current_time_sig =
+        create_read_slice(player_store, |player_store| player_store.get_current_time());
 
     let force_load_sig =
         create_read_slice(player_store, |player_store| player_store.get_force_load());
-    let current_volume = create_read_slice(player_store, |player_store| player_store.get_volume());
+    // Faded out while the sleep timer is about to stop playback
+    let current_volume = create_read_slice(player_store, |player_store| {
+        player_store.get_volume() * player_store.get_sleep_fade()
+    });
 
     let players_clone = players.clone();
     Effect::new(move || {
@@ -501,7 +629,59 @@ pub fn AudioStream() -> impl IntoView {
         });
     });
 
+    // Id of the upcoming song once it was preloaded for the current one
+    let preloaded = RwSignal::new(None::<Option<String>>);
+    let players_preload = players_clone.clone();
+    Effect::new(move || {
+        let current_time = current_time_sig.get();
+        if preloaded.with_untracked(|p| p.is_some()) {
+            return;
+        }
+
+        let Some(current_song) = current_song_sig.get_untracked() else {
+            return;
+        };
+        let duration = current_duration_sig.get_untracked().unwrap_or_default();
+        if duration <= 0f64 || duration - current_time > PRELOAD_BEFORE_END {
+            return;
+        }
+
+        if let Some(upcoming_song) = upcoming_song_sig.get_untracked() {
+            preloaded.set(Some(upcoming_song.song._id.clone()));
+            // Consecutive tracks of an album are meant to flow into each other
+            let crossfade = !is_same_album(&current_song, &upcoming_song);
+            let players = players_preload.clone();
+            spawn_local(async move {
+                let players = players.lock().await;
+                players
+                    .preload_audio(&upcoming_song, crossfade, player_store)
+                    .await;
+            });
+        }
+    });
+
+    // The preloaded song would still play next after the queue is reordered or cleared
+    let players_cancel = players_clone.clone();
+    Effect::new(move || {
+        let upcoming_id = upcoming_song_sig.with(|s| s.as_ref().map(|s| s.song._id.clone()));
+        let Some(preloaded_id) = preloaded.get_untracked() else {
+            return;
+        };
+        if upcoming_id == Some(preloaded_id) {
+            return;
+        }
+
+        preloaded.set(None);
+        let players = players_cancel.clone();
+        spawn_local(async move {
+            let players = players.lock().await;
+            players.cancel_preload().await;
+        });
+    });
+
     let last_song_time = RwSignal::new(Instant::now());
+    // Id, play details and duration of the loaded song, added to the play history once it changes
+    let current_play = RwSignal::new(None::<(String, PlayDetails, f64)>);
     Effect::new(move || {
         let is_providers_initialized = provider_store.is_initialized.get();
         tracing::info!("providers initialized {}", is_providers_initialized);
@@ -513,11 +693,13 @@ pub fn AudioStream() -> impl IntoView {
         let _ = force_load_sig.get();
 
         send_extension_event(ExtensionExtraEvent::SongChanged([current_song.clone()]));
+        preloaded.set(None);
         if let Some(mut current_song) = current_song {
             tracing::info!("Loading song {:?}", current_song.song.title);
             set_metadata(&current_song);
 
             let last_song_sig = last_song_sig.get_untracked();
+            let last_song_played_duration = last_song_played_duration.get_untracked();
             let players = players_clone.clone();
             spawn_local(async move {
                 let mut players = players.lock().await;
@@ -537,15 +719,36 @@ pub fn AudioStream() -> impl IntoView {
                 //     }
                 // }
                 // } else {
-                if let Err(e) = res {
-                    tracing::error!("Failed to load Song {:?}", e);
+                let last_play = current_play.get_untracked();
+                match res {
+                    Ok(_) => current_play.set(Some((
+                        current_song.song._id.clone().unwrap_or_default(),
+                        PlayDetails {
+                            completed: false,
+                            provider: current_song.song.provider_extension.clone(),
+                            player: players.get_active_player_key().await,
+                            ..Default::default()
+                        },
+                        current_song.song.duration.unwrap_or_default(),
+                    ))),
+                    Err(e) => {
+                        tracing::error!("Failed to load Song {:?}", e);
+                        current_play.set(None);
+                    }
                 }
                 // }
 
                 if let Some(id) = last_song_sig {
                     let time_diff = Instant::now() - last_song_time.get_untracked();
                     last_song_time.set(Instant::now());
-                    let _ = increment_play_time(id.clone(), time_diff.as_secs_f64()).await;
+                    let details = last_play.filter(|(play_id, _, _)| *play_id == id).map(
+                        |(_, mut details, duration)| {
+                            details.completed = duration > 0f64
+                                && last_song_played_duration >= duration * COMPLETED_PLAY_RATIO;
+                            details
+                        },
+                    );
+                    let _ = increment_play_time(id.clone(), time_diff.as_secs_f64(), details).await;
                     let _ = increment_play_count(id).await;
                 }
             });
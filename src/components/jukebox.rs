@@ -0,0 +1,203 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use leptos::{component, prelude::*, task::spawn_local, view, IntoView};
+use leptos_i18n::{t, t_string};
+use wasm_bindgen::JsValue;
+
+use crate::{
+    i18n::use_i18n,
+    modals::jukebox_modal::JukeboxModalState,
+    store::{
+        modal_store::{ModalStore, Modals},
+        player_store::{Jukebox, PlayerStore},
+    },
+    utils::{invoke::load_selective, prefs::save_selective},
+};
+
+pub const JUKEBOX_PIN_KEY: &str = "jukebox_pin";
+const JUKEBOX_ACTIVE_KEY: &str = "jukebox_active";
+const JUKEBOX_REQUEST_LIMIT_KEY: &str = "jukebox_request_limit";
+const DEFAULT_REQUEST_LIMIT: usize = 3;
+
+/// Parses the request limit preference, falling back to the default when unset
+#[tracing::instrument(level = "debug", skip(value))]
+pub fn parse_request_limit(value: JsValue) -> usize {
+    serde_wasm_bindgen::from_value::<Option<f64>>(value)
+        .ok()
+        .flatten()
+        .map(|v| v.max(0f64) as usize)
+        .unwrap_or(DEFAULT_REQUEST_LIMIT)
+}
+
+#[tracing::instrument(level = "debug", skip(player_store))]
+fn activate_jukebox(player_store: RwSignal<PlayerStore>) {
+    player_store.update(|p| p.set_jukebox(Some(Jukebox::new(DEFAULT_REQUEST_LIMIT))));
+    spawn_local(async move {
+        match load_selective(JUKEBOX_REQUEST_LIMIT_KEY.into()).await {
+            Ok(value) => {
+                let limit = parse_request_limit(value);
+                player_store.update(|p| p.set_jukebox_request_limit(limit));
+            }
+            Err(e) => tracing::error!("Failed to load jukebox request limit: {:?}", e),
+        }
+    });
+}
+
+#[tracing::instrument(level = "debug", skip(player_store))]
+pub fn start_jukebox(player_store: RwSignal<PlayerStore>) {
+    activate_jukebox(player_store);
+    save_selective(JUKEBOX_ACTIVE_KEY.into(), true);
+}
+
+#[tracing::instrument(level = "debug", skip(player_store))]
+pub fn stop_jukebox(player_store: RwSignal<PlayerStore>) {
+    player_store.update(|p| p.set_jukebox(None));
+    save_selective(JUKEBOX_ACTIVE_KEY.into(), false);
+}
+
+/// Re-enters jukebox mode if the app was closed while it was active
+#[tracing::instrument(level = "debug", skip(player_store))]
+pub fn restore_jukebox(player_store: RwSignal<PlayerStore>) {
+    spawn_local(async move {
+        let active = load_selective(JUKEBOX_ACTIVE_KEY.into())
+            .await
+            .ok()
+            .and_then(|v| serde_wasm_bindgen::from_value::<Option<bool>>(v).ok())
+            .flatten()
+            .unwrap_or_default();
+        if active {
+            activate_jukebox(player_store);
+        }
+    });
+}
+
+#[tracing::instrument(level = "debug", skip())]
+#[component]
+pub fn JukeboxBar() -> impl IntoView {
+    let player_store = expect_context::<RwSignal<PlayerStore>>();
+    let modal_store = expect_context::<RwSignal<ModalStore>>();
+    let (guest, set_guest) = create_slice(
+        player_store,
+        |p| p.get_jukebox_guest().unwrap_or_default(),
+        |p, val| p.set_jukebox_guest(val),
+    );
+    let request_limit = create_read_slice(player_store, |p| p.get_jukebox_request_limit());
+    let pending = create_read_slice(player_store, |p| {
+        p.get_pending_requests(&p.get_jukebox_guest().unwrap_or_default())
+    });
+    let upcoming = create_read_slice(player_store, |p| p.get_upcoming_requests());
+    // Only becomes the guest once confirmed, changing it afterwards needs the PIN
+    let name = RwSignal::new(String::new());
+
+    let i18n = use_i18n();
+    view! {
+        <div class="container-fluid jukebox-bar">
+            <div class="row no-gutters align-items-center">
+                <div class="col-auto">
+                    {move || {
+                        let guest = guest.get();
+                        if guest.is_empty() {
+                            view! {
+                                <input
+                                    class="form-control jukebox-guest-input"
+                                    type="text"
+                                    placeholder=move || t_string!(i18n, jukebox.guest).to_string()
+                                    prop:value=move || name.get()
+                                    on:input=move |e| name.set(event_target_value(&e))
+                                    on:change=move |_| set_guest.set(name.get_untracked())
+                                />
+                            }
+                                .into_any()
+                        } else {
+                            view! {
+                                <span class="jukebox-guest-name">{guest}</span>
+                                <button
+                                    class="btn btn-secondary ml-3"
+                                    type="button"
+                                    on:click=move |_| {
+                                        name.set(String::new());
+                                        modal_store
+                                            .update(|m| {
+                                                m.set_active_modal(
+                                                    Modals::JukeboxModal(JukeboxModalState::ChangeGuest),
+                                                )
+                                            });
+                                    }
+                                >
+                                    {t!(i18n, jukebox.change_guest)}
+                                </button>
+                            }
+                                .into_any()
+                        }
+                    }}
+                </div>
+                <div class="col-auto ml-3 jukebox-requests-left">
+                    {move || {
+                        let limit = request_limit.get();
+                        if limit == 0 || guest.get().is_empty() {
+                            return ().into_any();
+                        }
+                        let left = limit.saturating_sub(pending.get());
+                        if left == 0 {
+                            t!(i18n, jukebox.limit_reached).into_any()
+                        } else {
+                            t!(i18n, jukebox.requests_left, left = left, limit = limit).into_any()
+                        }
+                    }}
+                </div>
+            </div>
+            <div class="row no-gutters mt-2 jukebox-upcoming">
+                <div class="col-auto jukebox-upcoming-title">{t!(i18n, jukebox.upcoming)}</div>
+                <div class="col jukebox-upcoming-list">
+                    {move || {
+                        let upcoming = upcoming.get();
+                        if upcoming.is_empty() {
+                            return view! {
+                                <span class="jukebox-upcoming-empty">
+                                    {t!(i18n, jukebox.no_upcoming)}
+                                </span>
+                            }
+                                .into_any();
+                        }
+                        upcoming
+                            .into_iter()
+                            .map(|(song, guest)| {
+                                view! {
+                                    <span class="jukebox-upcoming-item">
+                                        <span class="text-truncate">
+                                            {song.song.title.unwrap_or_default()}
+                                        </span>
+                                        {guest
+                                            .map(|guest| {
+                                                view! {
+                                                    <span class="jukebox-requester">
+                                                        {t_string!(i18n, jukebox.requested_by, guest = guest)
+                                                            .to_string()}
+                                                    </span>
+                                                }
+                                            })}
+                                    </span>
+                                }
+                            })
+                            .collect_view()
+                            .into_any()
+                    }}
+                </div>
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,93 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use leptos::{component, html::Div, prelude::*, view, IntoView};
+use types::lyrics::Lyrics;
+use wasm_bindgen::JsCast;
+use web_sys::{HtmlElement, ScrollBehavior, ScrollToOptions};
+
+use crate::store::player_store::PlayerStore;
+
+#[tracing::instrument(level = "trace", skip(lyrics))]
+#[component]
+pub fn LyricsView(#[prop()] lyrics: RwSignal<Option<Lyrics>>) -> impl IntoView {
+    let player_store = expect_context::<RwSignal<PlayerStore>>();
+    let current_time = create_read_slice(player_store, |p| p.get_current_time());
+    let seek = create_write_slice(player_store, |p, time| p.force_seek(time));
+
+    let active_line = Memo::new(move |_| {
+        let position_ms = (current_time.get() * 1000f64).max(0f64) as u64;
+        lyrics.with(|l| l.as_ref().and_then(|l| l.line_at(position_ms)))
+    });
+
+    let container_ref: NodeRef<Div> = NodeRef::new();
+    Effect::new(move || {
+        let Some(active_line) = active_line.get() else {
+            return;
+        };
+        let Some(container) = container_ref.get() else {
+            return;
+        };
+
+        let line = container
+            .query_selector(format!("[data-line='{}']", active_line).as_str())
+            .ok()
+            .flatten()
+            .and_then(|e| e.dyn_into::<HtmlElement>().ok());
+        if let Some(line) = line {
+            // Keep the active line in the middle of the container
+            let top = line.offset_top() - container.client_height() / 2 + line.client_height() / 2;
+            let options = ScrollToOptions::new();
+            options.set_behavior(ScrollBehavior::Smooth);
+            options.set_top(top.max(0) as f64);
+            container.scroll_with_scroll_to_options(&options);
+        }
+    });
+
+    move || {
+        let Some(lyrics) = lyrics.get() else {
+            return ().into_any();
+        };
+
+        if !lyrics.is_synced() {
+            return view! { <pre>{lyrics.plain}</pre> }.into_any();
+        }
+
+        view! {
+            <div class="lyrics-synced" node_ref=container_ref>
+                {lyrics
+                    .lines
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, line)| {
+                        let start = line.start_ms as f64 / 1000f64;
+                        view! {
+                            <div
+                                class="lyrics-line"
+                                class:lyrics-line-active=move || active_line.get() == Some(i)
+                                data-line=i
+                                on:click=move |_| seek.set(start)
+                            >
+                                {line.text}
+                            </div>
+                        }
+                    })
+                    .collect_view()}
+            </div>
+        }
+        .into_any()
+    }
+}
@@ -18,7 +18,9 @@ pub mod artist_list;
 pub mod audiostream;
 pub mod cardview;
 pub mod color_picker;
+pub mod jukebox;
 pub mod low_img;
+pub mod lyrics_view;
 pub mod musicbar;
 pub mod musicbar_components;
 pub mod musicinfo;
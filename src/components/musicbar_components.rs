@@ -21,13 +21,15 @@ use leptos::ev::{pointermove, touchend};
 use leptos::*;
 use leptos::{component, prelude::*, view, IntoView};
 use leptos_dom::helpers::TimeoutHandle;
-use leptos_use::{use_document, use_event_listener};
+use leptos_i18n::{t, t_string};
+use leptos_use::{on_click_outside, use_document, use_event_listener};
 use task::spawn_local;
 use types::entities::{QueryableArtist, QueryablePlaylist};
-use types::ui::player_details::PlayerState;
+use types::ui::player_details::{PlayerState, SleepTimer};
 
 use crate::components::artist_list::ArtistList;
 use crate::components::low_img::LowImg;
+use crate::i18n::use_i18n;
 use crate::icons::expand_icon::ExpandIcon;
 use crate::icons::fav_icon::FavIcon;
 use crate::icons::next_track_icon::NextTrackIcon;
@@ -35,6 +37,7 @@ use crate::icons::play_icon::PlayIcon;
 use crate::icons::prev_track_icon::PrevTrackIcon;
 use crate::icons::repeat_icon::RepeatIcon;
 use crate::icons::shuffle_icon::ShuffleIcon;
+use crate::icons::sleep_timer_icon::SleepTimerIcon;
 use crate::icons::volume_icon::VolumeIcon;
 use crate::store::player_store::PlayerStore;
 use crate::store::ui_store::UiStore;
@@ -117,8 +120,11 @@ pub fn Controls(
 
     let current_song = create_read_slice(player_store, |p| p.get_current_song());
 
-    let prev_track_dis = create_read_slice(player_store, |p| p.get_queue_len() <= 1);
-    let next_track_dis = create_read_slice(player_store, |p| p.get_queue_len() <= 1);
+    let prev_track_dis =
+        create_read_slice(player_store, |p| p.get_queue_len() <= 1 || p.is_jukebox());
+    let next_track_dis = create_read_slice(player_store, |p| {
+        p.get_queue_len() <= 1 || !p.can_skip_current()
+    });
     let is_play = create_read_slice(player_store, |p| {
         p.get_player_state() == PlayerState::Playing
     });
@@ -133,8 +139,8 @@ pub fn Controls(
             format_duration(p.get_current_time(), false)
         });
         let total_duration_sig = create_read_slice(player_store, |p| {
-            if let Some(current_song) = p.get_current_song() {
-                format_duration(current_song.song.duration.unwrap_or(-1f64), false)
+            if p.get_current_song().is_some() {
+                format_duration(p.get_current_duration().unwrap_or(-1f64), false)
             } else {
                 "00:00".to_string()
             }
@@ -146,7 +152,7 @@ pub fn Controls(
     };
 
     let set_current_state = create_write_slice(player_store, |player, n| player.set_state(n));
-    let next_song_setter = create_write_slice(player_store, |p, _| p.next_song());
+    let next_song_setter = create_write_slice(player_store, |p, _| p.skip_song());
     let prev_song_setter = create_write_slice(player_store, |p, _| p.prev_song());
 
     Effect::new(move || {
@@ -190,8 +196,12 @@ pub fn Controls(
                 .await;
 
                 let res = if !is_fav_val {
-                    crate::utils::invoke::add_to_playlist("favorite".into(), vec![current_song])
-                        .await
+                    crate::utils::invoke::add_to_playlist(
+                        "favorite".into(),
+                        vec![current_song],
+                        None,
+                    )
+                    .await
                 } else {
                     crate::utils::invoke::remove_from_playlist(
                         "favorite".into(),
@@ -291,6 +301,106 @@ pub fn Controls(
     }
 }
 
+/// Sleep timer presets offered in the music bar, in minutes
+const SLEEP_TIMER_MINUTES: [u32; 5] = [15, 30, 45, 60, 90];
+/// Sleep timer presets offered in the music bar, in tracks
+const SLEEP_TIMER_TRACKS: [usize; 3] = [2, 3, 5];
+
+#[tracing::instrument(level = "debug", skip())]
+#[component]
+pub fn SleepTimerControl() -> impl IntoView {
+    let player_store = use_context::<RwSignal<PlayerStore>>().unwrap();
+    let (sleep_timer, set_sleep_timer) = create_slice(
+        player_store,
+        |p| p.get_sleep_timer(),
+        |p, timer| p.set_sleep_timer(timer),
+    );
+    let remaining = create_read_slice(player_store, |p| {
+        p.get_sleep_timer_remaining()
+            .map(|r| format_duration(r, false))
+    });
+    let tracks_left = create_read_slice(player_store, |p| p.get_sleep_tracks_left());
+    let is_active = Signal::derive(move || sleep_timer.get().is_some());
+
+    let show_popover = RwSignal::new(false);
+    let target = NodeRef::new();
+    let _ = on_click_outside(target, move |_| {
+        if show_popover.get_untracked() {
+            show_popover.set(false);
+        }
+    });
+
+    let select = move |timer: Option<SleepTimer>| {
+        set_sleep_timer.set(timer);
+        show_popover.set(false);
+    };
+
+    let i18n = use_i18n();
+    view! {
+        <div class="d-flex align-items-center sleep-timer" node_ref=target>
+            <div class="sleep-timer-remaining mr-2">
+                {move || {
+                    match (remaining.get(), tracks_left.get()) {
+                        (Some(remaining), _) => remaining,
+                        (None, Some(count)) => {
+                            t_string!(i18n, sleep_timer.tracks_left, count = count).to_string()
+                        }
+                        _ => String::new(),
+                    }
+                }}
+            </div>
+            <div title=move || t_string!(i18n, sleep_timer.title).to_string()>
+                <SleepTimerIcon
+                    active=is_active
+                    on:click=move |_| show_popover.set(!show_popover.get_untracked())
+                />
+            </div>
+            <Show when=move || show_popover.get()>
+                <div class="sleep-timer-popover">
+                    <div class="sleep-timer-popover-title">{t!(i18n, sleep_timer.title)}</div>
+                    {SLEEP_TIMER_MINUTES
+                        .into_iter()
+                        .map(|minutes| {
+                            view! {
+                                <div
+                                    class="sleep-timer-option"
+                                    on:click=move |_| select(Some(SleepTimer::Minutes(minutes as f64)))
+                                >
+                                    {t!(i18n, sleep_timer.minutes, count = minutes)}
+                                </div>
+                            }
+                        })
+                        .collect_view()}
+                    <div
+                        class="sleep-timer-option"
+                        on:click=move |_| select(Some(SleepTimer::EndOfTrack))
+                    >
+                        {t!(i18n, sleep_timer.end_of_track)}
+                    </div>
+                    {SLEEP_TIMER_TRACKS
+                        .into_iter()
+                        .map(|tracks| {
+                            view! {
+                                <div
+                                    class="sleep-timer-option"
+                                    on:click=move |_| select(Some(SleepTimer::Tracks(tracks)))
+                                >
+                                    {t!(i18n, sleep_timer.tracks, count = tracks)}
+                                </div>
+                            }
+                        })
+                        .collect_view()}
+                    <Show when=move || is_active.get()>
+                        <div class="sleep-timer-option" on:click=move |_| select(None)>
+                            {t!(i18n, sleep_timer.cancel)}
+                        </div>
+                    </Show>
+                </div>
+            </Show>
+        </div>
+    }
+}
+
 #[tracing::instrument(level = "debug", skip())]
 #[component]
 pub fn ExtraControls() -> impl IntoView {
@@ -317,6 +427,9 @@ pub fn ExtraControls() -> impl IntoView {
 
     view! {
         <div class="row no-gutters align-items-center justify-content-end">
+            <div class="col-auto mr-3">
+                <SleepTimerControl />
+            </div>
             <div
                 class="col-auto volume-slider-container d-flex"
                 class:volume-slider-show=move || show_popup_volume.get()
@@ -422,13 +535,17 @@ pub fn Slider() -> impl IntoView {
         player_store,
         |p| p.get_current_time(),
         move |p, val: f64| {
+            // Jukebox guests may only seek within their own requests
+            if !p.can_skip_current() {
+                return;
+            }
             p.force_seek_percent(
                 val / slider_process.get_untracked().unwrap().offset_width() as f64,
             )
         },
     );
 
-    let current_song = create_read_slice(player_store, |p| p.get_current_song());
+    let current_duration = create_read_slice(player_store, |p| p.get_current_duration());
     let total_time = RwSignal::new(1f64);
 
     let is_dragging = RwSignal::new(false);
@@ -471,11 +588,8 @@ pub fn Slider() -> impl IntoView {
     });
 
     Effect::new(move || {
-        let current_song = current_song.get();
-        if let Some(current_song) = current_song {
-            if let Some(duration) = current_song.song.duration {
-                total_time.set(duration);
-            }
+        if let Some(duration) = current_duration.get() {
+            total_time.set(duration);
         }
     });
 
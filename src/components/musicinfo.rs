@@ -18,9 +18,11 @@ use leptos::ev::{mousedown, touchstart};
 use leptos::html::Div;
 use leptos::task::spawn_local;
 use leptos::{component, prelude::*, view, IntoView};
+use leptos_i18n::t_string;
 use leptos_use::use_event_listener;
 use leptos_virtual_scroller::VirtualScroller;
 use std::sync::Arc;
+use types::lyrics::Lyrics;
 use types::songs::{Song, SongType};
 use types::ui::extensions::ExtensionProviderScope;
 use types::ui::player_details::PlayerState;
@@ -29,7 +31,9 @@ use web_sys::{ScrollBehavior, ScrollToOptions};
 
 use crate::components::artist_list::ArtistList;
 use crate::components::audiostream::AudioStream;
+use crate::components::lyrics_view::LyricsView;
 use crate::components::musicbar_components::{Controls, Slider};
+use crate::i18n::use_i18n;
 use crate::icons::lyrics_icon::LyricsIcon;
 use crate::icons::song_default_icon::SongDefaultIcon;
 use crate::modals::new_playlist_modal::PlaylistModalState;
@@ -75,6 +79,12 @@ where
     D: Get<Value = bool> + 'static + Send + Sync,
     P: Set<Value = usize> + 'static + Send + Sync,
 {
+    let player_store = expect_context::<RwSignal<PlayerStore>>();
+    let is_jukebox = create_read_slice(player_store, |p| p.is_jukebox());
+    let song_id = song.song._id.clone().unwrap_or_default();
+    let requester = create_read_slice(player_store, move |p| p.get_requester(&song_id));
+
+    let i18n = use_i18n();
     view! {
         <div class="container-fluid item-container" class:pl-2=is_mobile class:pr-0=is_mobile>
             <div class="row item-row no-gutters">
@@ -101,10 +111,23 @@ where
                     <div class="row no-gutters w-100 flex-nowrap text-truncate">
                         <ArtistList artists=song.artists />
                     </div>
+                    {move || {
+                        requester
+                            .get()
+                            .filter(|_| is_jukebox.get())
+                            .map(|guest| {
+                                view! {
+                                    <div class="row no-gutters w-100 text-truncate jukebox-requester">
+                                        {t_string!(i18n, jukebox.requested_by, guest = guest)
+                                            .to_string()}
+                                    </div>
+                                }
+                            })
+                    }}
 
                 </div>
                 <div class="col-auto text-right ml-auto d-flex align-items-center">
-                    <div class="ml-auto remove-button">
+                    <div class="ml-auto remove-button" class:d-none=move || is_jukebox.get()>
                         <TrashIcon on:click=move |_| remove_from_queue.set(index) />
                     </div>
                 </div>
@@ -167,9 +190,10 @@ pub fn MusicInfoMobile(
     let current_time = create_read_slice(player_store, |p| {
         format_duration(p.get_current_time(), false)
     });
+    let current_duration = create_read_slice(player_store, |p| p.get_current_duration());
     let total_time = Memo::new(move |_| {
-        if let Some(current_song) = current_song.get() {
-            if let Some(duration) = current_song.song.duration {
+        if current_song.with(|s| s.is_some()) {
+            if let Some(duration) = current_duration.get() {
                 return format_duration(duration, false);
             }
         }
@@ -212,7 +236,7 @@ pub fn MusicInfoMobile(
         }
     });
 
-    let selected_lyrics = RwSignal::new(None::<String>);
+    let selected_lyrics = RwSignal::new(None::<Lyrics>);
     let provider_store = expect_context::<Arc<ProviderStore>>();
     Effect::new(move || {
         let song = current_song.get();
@@ -415,7 +439,7 @@ pub fn MusicInfoMobile(
                                             <div class="lyrics-container">
                                                 <div class="lyrics-side-decoration"></div>
                                                 <div class="lyrics-background"></div>
-                                                <pre>{move || selected_lyrics.get()}</pre>
+                                                <LyricsView lyrics=selected_lyrics />
                                             </div>
                                         </div>
                                     </div>
@@ -445,6 +469,7 @@ pub fn MusicInfo(#[prop()] show: Signal<bool>, #[prop()] node_ref: NodeRef<Div>)
     let remove_from_queue = create_write_slice(player_store, |p, val| p.remove_from_queue(val));
 
     let clear_queue = create_write_slice(player_store, |p, _| p.clear_queue_except_current());
+    let is_jukebox = create_read_slice(player_store, |p| p.is_jukebox());
     let canvaz_sig = RwSignal::new(None);
 
     let get_queue = create_read_slice(player_store, |p| {
@@ -547,7 +572,7 @@ pub fn MusicInfo(#[prop()] show: Signal<bool>, #[prop()] node_ref: NodeRef<Div>)
                         </div>
                         <div class="col-7 offset-1 right-container h-100">
                             <div class="h-100">
-                                <div class="row">
+                                <div class="row" class:invisible=move || is_jukebox.get()>
                                     <div class="col-auto d-flex">
                                         <div
                                             class="rounded-btn"
@@ -17,12 +17,15 @@
 use std::{collections::HashMap, sync::Arc};
 
 use leptos::{component, prelude::*, reactive::wrappers::write::SignalSetter, view, IntoView};
-use leptos_i18n::t;
+use leptos_i18n::{t, t_string};
 use leptos_use::use_debounce_fn_with_arg;
 use types::{
+    cache::AudioCacheStats,
+    dsp::{DspPreset, DspSettings, MAX_EQ_GAIN},
     preferences::{CheckboxItems, CheckboxPreference, InputType},
+    scanner::ScanState,
     themes::ThemeDetails,
-    ui::{extensions::ExtensionDetail, themes::ThemeModalState},
+    ui::{extensions::ExtensionDetail, player_details::OutputDevice, themes::ThemeModalState},
     window::DialogFilter,
 };
 use wasm_bindgen_futures::spawn_local;
@@ -38,8 +41,13 @@ use crate::{
         ui_store::UiStore,
     },
     utils::{
+        common::format_duration,
         context_menu::{create_context_menu, ThemesContextMenu},
-        invoke::{get_installed_extensions, load_all_themes, remove_extension},
+        invoke::{
+            clear_audio_cache, get_audio_cache_stats, get_dsp_presets, get_dsp_settings,
+            get_installed_extensions, get_output_devices, load_all_themes, remove_dsp_preset,
+            remove_extension, save_dsp_preset, set_dsp_settings, set_output_device,
+        },
         prefs::{
             load_secure, load_selective, open_file_browser, open_file_browser_single, save_secure,
             save_selective, save_selective_number,
@@ -85,6 +93,13 @@ where
         })
     };
 
+    let cancel_scan = move |_| {
+        spawn_local(async move {
+            let _ = crate::utils::invoke::cancel_scan().await;
+        })
+    };
+    let scan_progress = create_read_slice(ui_store, |u| u.get_scan_progress());
+
     let key_clone = key.clone();
     Effect::new(move || {
         let value = paths.get();
@@ -109,7 +124,46 @@ where
                     </div>
                 </div>
                 <div class="col-auto new-directories ml-auto justify-content-center">
-                    <div on:click=start_scan>{"Refresh"}</div>
+                    {move || {
+                        let progress = scan_progress.get();
+                        if progress.state == ScanState::UNDEFINED {
+                            return view! { <div on:click=start_scan>{"Refresh"}</div> }
+                                .into_any();
+                        }
+                        let mut status = t_string!(
+                            i18n,
+                            settings.paths.scan_progress,
+                            processed = progress.processed,
+                            discovered = progress.discovered
+                        )
+                        .to_string();
+                        if progress.queued > 0 {
+                            status.push_str(
+                                &t_string!(i18n, settings.paths.scan_queued, count = progress.queued)
+                                    .to_string(),
+                            );
+                        }
+                        if let Some(eta) = progress.eta_ms {
+                            let eta = format_duration(eta as f64 / 1000f64, true);
+                            status.push_str(
+                                &t_string!(i18n, settings.paths.scan_eta, eta = eta).to_string(),
+                            );
+                        }
+                        view! {
+                            <div class="d-flex">
+                                <div
+                                    class="text-truncate"
+                                    title=progress.current_path.unwrap_or_default()
+                                >
+                                    {status}
+                                </div>
+                                <div class="ml-3" on:click=cancel_scan>
+                                    {t!(i18n, settings.paths.cancel_scan)}
+                                </div>
+                            </div>
+                        }
+                            .into_any()
+                    }}
                 </div>
                 <div class="col-auto new-directories ml-4">
                     <div
@@ -733,7 +787,10 @@ where
                                                     .into_any()
                                             }
                                             types::preferences::PreferenceTypes::ThemeSelector
-                                            | types::preferences::PreferenceTypes::Extensions => {
+                                            | types::preferences::PreferenceTypes::Extensions
+                                            | types::preferences::PreferenceTypes::Equalizer
+                                            | types::preferences::PreferenceTypes::OutputDevice
+                                            | types::preferences::PreferenceTypes::AudioCache => {
                                                 ().into_any()
                                             }
                                             types::preferences::PreferenceTypes::ButtonGroup
@@ -849,3 +906,532 @@ where
     }
     .into_any()
 }
+
+#[tracing::instrument(level = "debug", skip(id, label, checked, on_change))]
+#[component]
+fn EqualizerCheckbox<F>(
+    #[prop()] id: &'static str,
+    #[prop()] label: AnyView,
+    #[prop()] checked: Signal<bool>,
+    #[prop()] on_change: F,
+) -> impl IntoView
+where
+    F: Fn(bool) + 'static,
+{
+    view! {
+        <div class="row no-gutters item w-100 flex-nowrap">
+            <div class="col-auto align-self-center">
+                <div class="custom-control custom-checkbox">
+                    <input
+                        type="checkbox"
+                        class="custom-control-input"
+                        id=id
+                        prop:checked=move || checked.get()
+                        on:change=move |ev| on_change(event_target_checked(&ev))
+                    />
+                    <label for=id class="custom-control-label"></label>
+                </div>
+            </div>
+            <div class="col align-self-center ml-3 justify-content-start">
+                <div class="item-text text-truncate">{label}</div>
+            </div>
+        </div>
+    }
+}
+
+#[tracing::instrument(level = "debug", skip(label, value, on_input, on_change))]
+#[component]
+fn EqualizerSlider<F, F1>(
+    #[prop()] label: String,
+    #[prop()] value: Signal<f32>,
+    #[prop()] on_input: F,
+    #[prop()] on_change: F1,
+) -> impl IntoView
+where
+    F: Fn(f32) + 'static,
+    F1: Fn() + 'static,
+{
+    view! {
+        <div class="d-flex flex-column align-items-center equalizer-band">
+            <div class="equalizer-value">{move || format!("{:+.1}", value.get())}</div>
+            <input
+                type="range"
+                class="equalizer-slider"
+                min=(-MAX_EQ_GAIN).to_string()
+                max=MAX_EQ_GAIN.to_string()
+                step="0.5"
+                prop:value=move || value.get().to_string()
+                on:input=move |ev| on_input(event_target_value(&ev).parse().unwrap_or_default())
+                on:change=move |_| on_change()
+            />
+            <div class="equalizer-label">{label}</div>
+        </div>
+    }
+}
+
+#[tracing::instrument(level = "debug", skip(title, tooltip, mobile))]
+#[component]
+pub fn OutputDevicePref<K, H, K1, H1>(
+    #[prop()] title: K,
+    #[prop()] tooltip: K1,
+    #[prop()] mobile: bool,
+) -> impl IntoView
+where
+    K: Fn() -> H + Send + Sync + 'static,
+    H: IntoView + 'static,
+    K1: Fn() -> H1 + Send + Sync + 'static,
+    H1: IntoView + 'static,
+{
+    let ui_store = expect_context::<RwSignal<UiStore>>();
+    let is_mobile = create_read_slice(ui_store, |u| u.get_is_mobile()).get();
+    if is_mobile && !mobile {
+        return ().into_any();
+    }
+
+    let devices = RwSignal::new(Vec::<OutputDevice>::new());
+    // Empty for the system default
+    let selected = RwSignal::new(String::new());
+    load_selective("output_device".into(), selected);
+
+    let load_devices = move || {
+        spawn_local(async move {
+            match get_output_devices().await {
+                Ok(res) => devices.set(res),
+                Err(e) => tracing::error!("Failed to get output devices {:?}", e),
+            }
+        })
+    };
+    load_devices();
+
+    let i18n = use_i18n();
+    view! {
+        <div class="container-fluid mt-4">
+            <div class="row no-gutters">
+                <div class="col-auto align-self-center title d-flex preference-title">
+                    {title()}
+                </div>
+                <div class="col-auto ml-2">
+                    <Tooltip>{tooltip()}</Tooltip>
+                </div>
+            </div>
+            <div class="row no-gutters mt-2">
+                <div class="col-auto">
+                    <select
+                        class="dropdown-list"
+                        // Devices may have been plugged in since the page was opened
+                        on:focus=move |_| load_devices()
+                        on:change=move |ev| {
+                            let name = event_target_value(&ev);
+                            selected.set(name.clone());
+                            spawn_local(async move {
+                                let name = if name.is_empty() { None } else { Some(name) };
+                                if let Err(e) = set_output_device(name).await {
+                                    tracing::error!("Failed to set output device {:?}", e);
+                                }
+                            });
+                        }
+                    >
+                        <option value="" prop:selected=move || selected.get().is_empty()>
+                            {t!(i18n, settings.audio.output_device.default)}
+                        </option>
+                        <For
+                            each=move || devices.get()
+                            key=|d| d.name.clone()
+                            children=move |device| {
+                                let name = device.name.clone();
+                                view! {
+                                    <option
+                                        value=device.name.clone()
+                                        prop:selected=move || selected.get() == name
+                                    >
+                                        {device.name}
+                                    </option>
+                                }
+                            }
+                        />
+                    </select>
+                </div>
+            </div>
+        </div>
+    }
+    .into_any()
+}
+
+/// Size in MB, as entered for the cache size preference
+fn format_size(bytes: u64) -> String {
+    format!("{:.1} MB", bytes as f64 / (1024f64 * 1024f64))
+}
+
+#[tracing::instrument(level = "debug", skip(title, tooltip, mobile))]
+#[component]
+pub fn AudioCachePref<K, H, K1, H1>(
+    #[prop()] title: K,
+    #[prop()] tooltip: K1,
+    #[prop()] mobile: bool,
+) -> impl IntoView
+where
+    K: Fn() -> H + Send + Sync + 'static,
+    H: IntoView + 'static,
+    K1: Fn() -> H1 + Send + Sync + 'static,
+    H1: IntoView + 'static,
+{
+    let ui_store = expect_context::<RwSignal<UiStore>>();
+    let is_mobile = create_read_slice(ui_store, |u| u.get_is_mobile()).get();
+    if is_mobile && !mobile {
+        return ().into_any();
+    }
+
+    let stats = RwSignal::new(AudioCacheStats::default());
+    let load_stats = move || {
+        spawn_local(async move {
+            match get_audio_cache_stats().await {
+                Ok(res) => stats.set(res),
+                Err(e) => tracing::error!("Failed to get audio cache stats {:?}", e),
+            }
+        })
+    };
+    load_stats();
+
+    let clear = move |include_offline: bool| {
+        spawn_local(async move {
+            if let Err(e) = clear_audio_cache(include_offline).await {
+                tracing::error!("Failed to clear audio cache {:?}", e);
+            }
+            load_stats();
+        })
+    };
+
+    let i18n = use_i18n();
+    view! {
+        <div class="container-fluid mt-4">
+            <div class="row no-gutters">
+                <div class="col-auto align-self-center title d-flex preference-title">
+                    {title()}
+                </div>
+                <div class="col-auto ml-2">
+                    <Tooltip>{tooltip()}</Tooltip>
+                </div>
+            </div>
+            <div class="row no-gutters mt-2">
+                <div class="col-auto align-self-center">
+                    {move || {
+                        let stats = stats.get();
+                        t_string!(
+                            i18n,
+                            settings.audio.audio_cache.usage,
+                            size = format_size(stats.size),
+                            max_size = format_size(stats.max_size),
+                            tracks = stats.tracks
+                        )
+                        .to_string()
+                    }}
+                </div>
+            </div>
+            <div class="row no-gutters mt-2">
+                <div class="col-auto align-self-center">
+                    {move || {
+                        let stats = stats.get();
+                        t_string!(
+                            i18n,
+                            settings.audio.audio_cache.offline_usage,
+                            tracks = stats.offline_tracks,
+                            size = format_size(stats.offline_size)
+                        )
+                        .to_string()
+                    }}
+                </div>
+            </div>
+            <div class="row no-gutters mt-2">
+                <div class="col-auto">
+                    <button class="btn btn-secondary create-button" on:click=move |_| clear(false)>
+                        {t!(i18n, settings.audio.audio_cache.clear)}
+                    </button>
+                </div>
+                <div class="col-auto ml-2">
+                    <button class="btn btn-secondary create-button" on:click=move |_| clear(true)>
+                        {t!(i18n, settings.audio.audio_cache.clear_offline)}
+                    </button>
+                </div>
+            </div>
+        </div>
+    }
+    .into_any()
+}
+
+#[tracing::instrument(level = "debug", skip(title, tooltip, mobile))]
+#[component]
+pub fn EqualizerPref<K, H, K1, H1>(
+    #[prop()] title: K,
+    #[prop()] tooltip: K1,
+    #[prop()] mobile: bool,
+) -> impl IntoView
+where
+    K: Fn() -> H + Send + Sync + 'static,
+    H: IntoView + 'static,
+    K1: Fn() -> H1 + Send + Sync + 'static,
+    H1: IntoView + 'static,
+{
+    let ui_store = expect_context::<RwSignal<UiStore>>();
+    let is_mobile = create_read_slice(ui_store, |u| u.get_is_mobile()).get();
+    if is_mobile && !mobile {
+        return ().into_any();
+    }
+
+    let settings = RwSignal::new(DspSettings::default());
+    let presets = RwSignal::new(Vec::<DspPreset>::new());
+    let selected_preset = RwSignal::new(String::new());
+    let preset_name = RwSignal::new(String::new());
+
+    spawn_local(async move {
+        match get_dsp_settings().await {
+            Ok(res) => settings.set(res),
+            Err(e) => tracing::error!("Failed to get dsp settings {:?}", e),
+        }
+    });
+
+    let load_presets = move || {
+        spawn_local(async move {
+            match get_dsp_presets().await {
+                Ok(res) => presets.set(res),
+                Err(e) => tracing::error!("Failed to get dsp presets {:?}", e),
+            }
+        })
+    };
+    load_presets();
+
+    // Sliders are applied live while dragging and only saved once released
+    let apply = move |persist: bool| {
+        let value = settings.get_untracked();
+        spawn_local(async move {
+            if let Err(e) = set_dsp_settings(value, persist).await {
+                tracing::error!("Failed to set dsp settings {:?}", e);
+            }
+        });
+    };
+
+    let save_preset = move |_| {
+        let name = preset_name.get_untracked();
+        let value = settings.get_untracked();
+        spawn_local(async move {
+            let preset = DspPreset {
+                name: name.clone(),
+                preamp: value.preamp,
+                bands: value.bands,
+                builtin: false,
+            };
+            match save_dsp_preset(preset).await {
+                Ok(_) => {
+                    selected_preset.set(name.trim().to_string());
+                    preset_name.set(String::new());
+                    load_presets();
+                }
+                Err(e) => tracing::error!("Failed to save dsp preset {:?}", e),
+            }
+        });
+    };
+
+    let remove_preset = move |_| {
+        let name = selected_preset.get_untracked();
+        spawn_local(async move {
+            match remove_dsp_preset(name).await {
+                Ok(_) => {
+                    selected_preset.set(String::new());
+                    load_presets();
+                }
+                Err(e) => tracing::error!("Failed to remove dsp preset {:?}", e),
+            }
+        });
+    };
+
+    // Only rebuild the sliders when bands change, not while a slider is dragged
+    let frequencies = Memo::new(move |_| {
+        settings.with(|s| s.bands.iter().map(|b| b.frequency).collect::<Vec<_>>())
+    });
+
+    let can_remove = move || {
+        let selected = selected_preset.get();
+        presets
+            .get()
+            .iter()
+            .any(|p| p.name == selected && !p.builtin)
+    };
+
+    let i18n = use_i18n();
+    view! {
+        <div class="container-fluid mt-4">
+            <div class="row no-gutters">
+                <div class="col-auto align-self-center title d-flex preference-title">
+                    {title()}
+                </div>
+                <div class="col-auto ml-2">
+                    <Tooltip>{tooltip()}</Tooltip>
+                </div>
+            </div>
+
+            <EqualizerCheckbox
+                id="equalizer-enabled"
+                label=t!(i18n, settings.audio.equalizer.enabled).into_any()
+                checked=Signal::derive(move || settings.with(|s| s.enabled))
+                on_change=move |enabled| {
+                    settings.update(|s| s.enabled = enabled);
+                    apply(true);
+                }
+            />
+
+            <div class="row no-gutters mt-3 align-items-center">
+                <div class="col-auto">
+                    <select
+                        class="dropdown-list"
+                        on:change=move |ev| {
+                            let name = event_target_value(&ev);
+                            if let Some(preset) = presets
+                                .get_untracked()
+                                .into_iter()
+                                .find(|p| p.name == name)
+                            {
+                                settings.update(|s| s.apply_preset(&preset));
+                                apply(true);
+                            }
+                            selected_preset.set(name);
+                        }
+                    >
+                        <option value="" prop:selected=move || selected_preset.get().is_empty()>
+                            {t!(i18n, settings.audio.equalizer.custom)}
+                        </option>
+                        <For
+                            each=move || presets.get()
+                            key=|p| p.name.clone()
+                            children=move |preset| {
+                                let name = preset.name.clone();
+                                view! {
+                                    <option
+                                        value=preset.name.clone()
+                                        prop:selected=move || selected_preset.get() == name
+                                    >
+                                        {preset.name}
+                                    </option>
+                                }
+                            }
+                        />
+                    </select>
+                </div>
+                <div class="col-auto ml-3">
+                    <input
+                        class="ext-input equalizer-preset-input"
+                        placeholder=move || t_string!(i18n, settings.audio.equalizer.preset_name).to_string()
+                        prop:value=move || preset_name.get()
+                        on:input=move |ev| preset_name.set(event_target_value(&ev))
+                    />
+                </div>
+                <div class="col-auto ml-3">
+                    <button
+                        class="btn btn-secondary create-button"
+                        prop:disabled=move || preset_name.get().trim().is_empty()
+                        on:click=save_preset
+                    >
+                        {t!(i18n, settings.audio.equalizer.save_preset)}
+                    </button>
+                </div>
+                <div class="col-auto ml-3">
+                    <Show when=can_remove>
+                        <button class="btn btn-secondary create-button" on:click=remove_preset>
+                            {t!(i18n, settings.audio.equalizer.remove_preset)}
+                        </button>
+                    </Show>
+                </div>
+            </div>
+
+            <div class="d-flex mt-3 equalizer-bands">
+                <EqualizerSlider
+                    label=t_string!(i18n, settings.audio.equalizer.preamp).to_string()
+                    value=Signal::derive(move || settings.with(|s| s.preamp))
+                    on_input=move |gain| {
+                        settings.update(|s| s.preamp = gain);
+                        selected_preset.set(String::new());
+                        apply(false);
+                    }
+                    on_change=move || apply(true)
+                />
+                <div class="equalizer-divider"></div>
+                {move || {
+                    frequencies
+                        .get()
+                        .into_iter()
+                        .enumerate()
+                        .map(|(i, frequency)| {
+                            let label = if frequency >= 1000f32 {
+                                format!("{}k", frequency / 1000f32)
+                            } else {
+                                format!("{}", frequency)
+                            };
+                            view! {
+                                <EqualizerSlider
+                                    label=label
+                                    value=Signal::derive(move || {
+                                        settings
+                                            .with(|s| s.bands.get(i).map(|b| b.gain).unwrap_or_default())
+                                    })
+                                    on_input=move |gain| {
+                                        settings
+                                            .update(|s| {
+                                                if let Some(band) = s.bands.get_mut(i) {
+                                                    band.gain = gain;
+                                                }
+                                            });
+                                        selected_preset.set(String::new());
+                                        apply(false);
+                                    }
+                                    on_change=move || apply(true)
+                                />
+                            }
+                        })
+                        .collect_view()
+                }}
+            </div>
+
+            <div class="row no-gutters mt-3 align-items-center">
+                <div class="col-auto item-text">{t!(i18n, settings.audio.equalizer.balance)}</div>
+                <div class="col-auto ml-3">
+                    <input
+                        type="range"
+                        class="equalizer-balance"
+                        min="-1"
+                        max="1"
+                        step="0.05"
+                        prop:value=move || settings.with(|s| s.balance).to_string()
+                        on:input=move |ev| {
+                            let balance = event_target_value(&ev).parse().unwrap_or_default();
+                            settings.update(|s| s.balance = balance);
+                            apply(false);
+                        }
+                        on:change=move |_| apply(true)
+                        on:dblclick=move |_| {
+                            settings.update(|s| s.balance = 0f32);
+                            apply(true);
+                        }
+                    />
+                </div>
+            </div>
+
+            <EqualizerCheckbox
+                id="equalizer-mono"
+                label=t!(i18n, settings.audio.equalizer.mono).into_any()
+                checked=Signal::derive(move || settings.with(|s| s.mono))
+                on_change=move |mono| {
+                    settings.update(|s| s.mono = mono);
+                    apply(true);
+                }
+            />
+            <EqualizerCheckbox
+                id="equalizer-limiter"
+                label=t!(i18n, settings.audio.equalizer.limiter).into_any()
+                checked=Signal::derive(move || settings.with(|s| s.limiter))
+                on_change=move |limiter| {
+                    settings.update(|s| s.limiter = limiter);
+                    apply(true);
+                }
+            />
+        </div>
+    }
+    .into_any()
+}
@@ -21,14 +21,18 @@ use crate::{
         albums_icon::{AlbumsIcon, AlbumsIconProps},
         allsongs_icon::{AllSongsIcon, AllSongsIconProps},
         artists_icon::{ArtistsIcon, ArtistsIconProps},
+        audio_icon::{AudioIcon, AudioIconProps},
+        duplicates_icon::{DuplicatesIcon, DuplicatesIconProps},
         explore_icon::{ExploreIcon, ExploreIconProps},
         extensions_icon::{ExtensionsIcon, ExtensionsIconProps},
         genres_icon::{GenresIcon, GenresIconProps},
+        history_icon::{HistoryIcon, HistoryIconProps},
         logs_icon::{LogsIcon, LogsIconProps},
         paths_icon::{PathsIcon, PathsIconProps},
         playlists_icon::{PlaylistsIcon, PlaylistsIconProps},
         queue_icon::{QueueIcon, QueueIconProps},
         sidebar_toggle_icon::SidebarToggleIcon,
+        stats_icon::{StatsIcon, StatsIconProps},
         system_icon::{SystemIcon, SystemIconProps},
         themes_icon::{ThemesIcon, ThemesIconProps},
     },
@@ -53,8 +57,12 @@ impl Tab {
             "Albums" => |active| AlbumsIcon(AlbumsIconProps { active }).into_any(),
             "Genres" => |active| GenresIcon(GenresIconProps { active }).into_any(),
             "Explore" => |active| ExploreIcon(ExploreIconProps { active }).into_any(),
+            "Stats" => |active| StatsIcon(StatsIconProps { active }).into_any(),
+            "History" => |active| HistoryIcon(HistoryIconProps { active }).into_any(),
+            "Duplicates" => |active| DuplicatesIcon(DuplicatesIconProps { active }).into_any(),
             "Paths" => |active| PathsIcon(PathsIconProps { active }).into_any(),
             "System" => |active| SystemIcon(SystemIconProps { active }).into_any(),
+            "Audio" => |active| AudioIcon(AudioIconProps { active }).into_any(),
             "Logs" => |active| LogsIcon(LogsIconProps { active }).into_any(),
             "Extensions" => |active| ExtensionsIcon(ExtensionsIconProps { active }).into_any(),
             "Themes" => |active| ThemesIcon(ThemesIconProps { active }).into_any(),
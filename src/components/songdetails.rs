@@ -17,6 +17,7 @@
 use leptos::{component, html::Div, prelude::*, view, IntoView};
 use leptos_use::use_resize_observer;
 use types::{
+    lyrics::Lyrics,
     songs::Song,
     ui::{
         extensions::ExtensionProviderScope,
@@ -26,6 +27,7 @@ use types::{
 use wasm_bindgen_futures::spawn_local;
 
 use crate::{
+    components::lyrics_view::LyricsView,
     icons::{
         add_to_library_icon::AddToLibraryIcon, add_to_queue_icon::AddToQueueIcon,
         fav_playlist_icon::FavPlaylistIcon, pin_icon::PinIcon, plain_play_icon::PlainPlayIcon,
@@ -68,7 +70,7 @@ where
     let selected_duration = RwSignal::new(None::<String>);
     let selected_cover_path = RwSignal::new(default_details.get().icon);
 
-    let selected_lyrics = RwSignal::new(None::<String>);
+    let selected_lyrics = RwSignal::new(None::<Lyrics>);
     let show_default_cover_img = RwSignal::new(true);
     let show_lyrics_div = RwSignal::new(false);
     let show_lyrics_always = RwSignal::new(false);
@@ -214,7 +216,7 @@ where
                                     <div class="lyrics-container">
                                         <div class="lyrics-side-decoration"></div>
                                         <div class="lyrics-background"></div>
-                                        <pre>{move || selected_lyrics.get()}</pre>
+                                        <LyricsView lyrics=selected_lyrics />
                                         <PinIcon
                                             filled=show_lyrics_always
                                             on:click=move |_| {
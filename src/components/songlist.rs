@@ -30,6 +30,7 @@ use leptos::{
     prelude::*,
     view, IntoView,
 };
+use leptos_i18n::t_string;
 use leptos_use::use_event_listener;
 use leptos_virtual_scroller::VirtualScroller;
 use types::{songs::Song, ui::extensions::ExtensionProviderScope};
@@ -37,6 +38,7 @@ use web_sys::{HtmlDivElement, HtmlInputElement, MouseEvent};
 
 use crate::{
     components::{artist_list::ArtistList, low_img::LowImg, provider_icon::ProviderIcon},
+    i18n::use_i18n,
     icons::{
         add_to_queue_icon::AddToQueueIcon, ellipsis_icon::EllipsisIcon, search_icon::SearchIcon,
         sort_icon::SortIcon,
@@ -51,6 +53,7 @@ use crate::{
         common::{format_duration, get_low_img},
         context_menu::{create_context_menu, SongItemContextMenu, SortContextMenu},
         db_utils::get_playlists_local,
+        songs::{get_disc_starts, sort_by_disc},
     },
 };
 
@@ -181,12 +184,15 @@ pub struct ShowProvidersArgs {
         fetch_next_page,
         root_ref,
         scroller_ref,
-        header
+        header,
+        on_reorder
     )
 )]
 #[component()]
 /// filtered_selected is the list of song indices from the **filtered song list** after they have been filtered (search / sort)
 /// selected_songs_sig is the list of song indices from the **original song list**
+/// on_reorder receives the id of a dragged song and its new index in the **original song list**.
+/// Songs are shown unsorted when it is set
 pub fn SongList<I>(
     #[prop()] song_list: impl Get<Value = Vec<Song>> + Copy + 'static + Send + Sync,
     #[prop()] selected_songs_sig: RwSignal<Vec<usize>>,
@@ -200,6 +206,8 @@ pub fn SongList<I>(
     #[prop(optional)] header_height: usize,
     #[prop(optional, default = true)] enable_sort: bool,
     #[prop(optional)] is_loading: RwSignal<HashMap<String, bool>>,
+    #[prop(optional_no_strip)] on_reorder: Option<Arc<Box<dyn Fn(String, usize) + Send + Sync>>>,
+    #[prop(optional)] group_by_disc: bool,
     #[prop()] header: I,
 ) -> impl IntoView
 where
@@ -228,9 +236,15 @@ where
     let player_store = use_context::<RwSignal<PlayerStore>>().unwrap();
     let play_now = create_write_slice(player_store, |store, value| store.play_now(value));
 
+    let enable_sort = enable_sort && on_reorder.is_none() && !group_by_disc;
+    let dragged = RwSignal::new(None::<usize>);
+    let drop_target = RwSignal::new(None::<usize>);
+
     let sorted_songs = Memo::new(move |_| {
         let mut songs = song_list.get();
-        if enable_sort {
+        if group_by_disc {
+            sort_by_disc(&mut songs);
+        } else if enable_sort {
             let sort = songs_sort.get();
             match sort.sort_by {
                 SongSortByColumns::Album => songs.sort_by(|a, b| a.album.cmp(&b.album)),
@@ -275,6 +289,14 @@ where
             .collect()
     });
 
+    let disc_starts = Memo::new(move |_| {
+        if !group_by_disc {
+            return HashMap::new();
+        }
+        filtered_songs.with(|songs| get_disc_starts(songs))
+    });
+    let i18n = use_i18n();
+
     Effect::new(move || {
         let _ = filtered_songs.get();
         filtered_selected.update(|s| s.clear());
@@ -561,42 +583,100 @@ where
                                 let song_cl = song.clone();
                                 let song_cl1 = song.clone();
                                 let song_context_menu = song_context_menu.clone();
+                                let on_reorder = on_reorder.clone();
+                                let reorderable = on_reorder.is_some();
                                 view! {
-                                    <SongListItem
-                                        on_click=move |_| {
-                                            if is_mobile {
-                                                if !is_ctrl_pressed.get_untracked() {
-                                                    play_now.set(song_cl1.clone());
+                                    <div
+                                        draggable=if reorderable { "true" } else { "false" }
+                                        class:position-relative=group_by_disc
+                                        class:song-drop-target=move || {
+                                            drop_target.get() == Some(index)
+                                        }
+                                        on:dragstart=move |_| {
+                                            dragged.set(Some(get_actual_position(index)))
+                                        }
+                                        on:dragover=move |ev| {
+                                            if reorderable {
+                                                ev.prevent_default();
+                                                drop_target.set(Some(index));
+                                            }
+                                        }
+                                        on:dragend=move |_| {
+                                            dragged.set(None);
+                                            drop_target.set(None);
+                                        }
+                                        on:drop=move |ev| {
+                                            ev.prevent_default();
+                                            drop_target.set(None);
+                                            let from = dragged.get_untracked();
+                                            dragged.set(None);
+                                            if let (Some(from), Some(on_reorder)) = (
+                                                from,
+                                                on_reorder.as_ref(),
+                                            ) {
+                                                let to = get_actual_position(index);
+                                                let song_id = song_list
+                                                    .get_untracked()
+                                                    .get(from)
+                                                    .and_then(|s| s.song._id.clone());
+                                                if let Some(song_id) = song_id {
+                                                    if from != to {
+                                                        on_reorder.as_ref()(song_id, to);
+                                                    }
+                                                }
+                                            }
+                                        }
+                                    >
+                                        {move || {
+                                            disc_starts
+                                                .with(|d| d.get(&index).copied())
+                                                .map(|disc| {
+                                                    view! {
+                                                        <div class="disc-header">
+                                                            {t_string!(
+                                                                i18n, song_view.song_list.disc, disc = disc
+                                                            )
+                                                                .to_string()}
+                                                        </div>
+                                                    }
+                                                })
+                                        }}
+                                        <SongListItem
+                                            on_click=move |_| {
+                                                if is_mobile {
+                                                    if !is_ctrl_pressed.get_untracked() {
+                                                        play_now.set(song_cl1.clone());
+                                                    } else {
+                                                        is_ctrl_pressed.set(true);
+                                                        add_to_selected(index);
+                                                    }
                                                 } else {
-                                                    is_ctrl_pressed.set(true);
                                                     add_to_selected(index);
                                                 }
-                                            } else {
-                                                add_to_selected(index);
                                             }
-                                        }
-                                        is_selected=Box::new(move || {
-                                            filtered_selected.get().contains(&index)
-                                        })
-                                        on_context_menu=move |(ev, is_button): (MouseEvent, bool)| {
-                                            ev.prevent_default();
-                                            ev.stop_propagation();
-                                            if is_mobile && !is_button {
-                                                is_ctrl_pressed.set(true);
-                                                add_to_selected(index);
-                                            } else {
-                                                if should_add_to_selected(index) {
+                                            is_selected=Box::new(move || {
+                                                filtered_selected.get().contains(&index)
+                                            })
+                                            on_context_menu=move |(ev, is_button): (MouseEvent, bool)| {
+                                                ev.prevent_default();
+                                                ev.stop_propagation();
+                                                if is_mobile && !is_button {
+                                                    is_ctrl_pressed.set(true);
                                                     add_to_selected(index);
+                                                } else {
+                                                    if should_add_to_selected(index) {
+                                                        add_to_selected(index);
+                                                    }
+                                                    let mut data = song_context_menu.get_data();
+                                                    data.current_song = Some(song_cl.clone());
+                                                    drop(data);
+                                                    song_context_menu.show(ev);
                                                 }
-                                                let mut data = song_context_menu.get_data();
-                                                data.current_song = Some(song_cl.clone());
-                                                drop(data);
-                                                song_context_menu.show(ev);
                                             }
-                                        }
 
-                                        song=song.clone()
-                                    />
+                                            song=song.clone()
+                                        />
+                                    </div>
                                 }
                             }
                         />
@@ -14,7 +14,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::Arc};
 
 use leptos::{component, html::Div, prelude::*, view};
 use leptos_use::on_click_outside;
@@ -38,7 +38,8 @@ use crate::{
         song_update_request,
         default_details,
         refresh_cb,
-        fetch_next_page
+        fetch_next_page,
+        on_reorder
     )
 )]
 #[component()]
@@ -53,6 +54,8 @@ pub fn SongView(
     #[prop(optional)] default_details: RwSignal<DefaultDetails>,
     #[prop(optional, default=ShowProvidersArgs::default())] providers: ShowProvidersArgs,
     #[prop(optional, default = false)] show_mobile_default_details: bool,
+    #[prop(optional_no_strip)] on_reorder: Option<Arc<Box<dyn Fn(String, usize) + Send + Sync>>>,
+    #[prop(optional)] group_by_disc: bool,
 ) -> impl IntoView {
     let last_selected_song = RwSignal::new(None::<Song>);
 
@@ -162,6 +165,8 @@ pub fn SongView(
                             refresh_cb=refresh_cb
                             fetch_next_page=fetch_next_page
                             is_loading=is_loading
+                            on_reorder=on_reorder
+                            group_by_disc=group_by_disc
                             header_height=if show_mobile_default_details { 375 } else { 0 }
                             header=if show_mobile_default_details {
                                 Some(
@@ -18,7 +18,9 @@ use std::sync::Arc;
 
 use crate::components::provider_icon::ProviderIcon;
 use crate::i18n::use_i18n;
+use crate::modals::jukebox_modal::JukeboxModalState;
 use crate::store::ui_store::UiStore;
+use crate::utils::invoke::load_selective;
 use leptos::task::spawn_local;
 use leptos::{
     component, ev::Event, prelude::*, reactive::wrappers::write::SignalSetter, view, IntoView,
@@ -27,6 +29,7 @@ use leptos_i18n::t_string;
 use leptos_router::{hooks::use_navigate, NavigateOptions};
 use leptos_use::on_click_outside;
 use leptos_virtual_scroller::VirtualScroller;
+use types::preferences::CheckboxPreference;
 use types::ui::extensions::ExtensionProviderScope;
 use types::{
     entities::{
@@ -40,8 +43,8 @@ use web_sys::SubmitEvent;
 use crate::{
     components::low_img::LowImg,
     icons::{
-        next_icon::NextIcon, person_icon::PersonIcon, prev_icon::PrevIcon, search_icon::SearchIcon,
-        settings_icon::SettingsIcon,
+        jukebox_icon::JukeboxIcon, next_icon::NextIcon, person_icon::PersonIcon,
+        prev_icon::PrevIcon, search_icon::SearchIcon, settings_icon::SettingsIcon,
     },
     store::{
         modal_store::{ModalStore, Modals},
@@ -108,6 +111,51 @@ pub fn Settings(#[prop(optional)] class: &'static str) -> impl IntoView {
     }
 }
 
+#[tracing::instrument(level = "debug", skip())]
+#[component]
+pub fn JukeboxToggle(#[prop(optional)] class: &'static str) -> impl IntoView {
+    let player_store = expect_context::<RwSignal<PlayerStore>>();
+    let modal_store = expect_context::<RwSignal<ModalStore>>();
+    let is_jukebox = create_read_slice(player_store, |p| p.is_jukebox());
+
+    let enabled = RwSignal::new(false);
+    spawn_local(async move {
+        if let Ok(prefs) = load_selective("system_settings".into()).await {
+            let prefs: Vec<CheckboxPreference> =
+                serde_wasm_bindgen::from_value(prefs).unwrap_or_default();
+            enabled.set(prefs.iter().any(|p| p.key == "jukebox_mode" && p.enabled));
+        }
+    });
+
+    let i18n = use_i18n();
+    view! {
+        <Show when=move || enabled.get() || is_jukebox.get()>
+            <div
+                class=class
+                title=move || {
+                    if is_jukebox.get() {
+                        t_string!(i18n, jukebox.exit).to_string()
+                    } else {
+                        t_string!(i18n, jukebox.enter).to_string()
+                    }
+                }
+            >
+                <JukeboxIcon
+                    active=is_jukebox
+                    on:click=move |_| {
+                        let state = if is_jukebox.get_untracked() {
+                            JukeboxModalState::Exit
+                        } else {
+                            JukeboxModalState::Enter
+                        };
+                        modal_store.update(|m| m.set_active_modal(Modals::JukeboxModal(state)));
+                    }
+                />
+            </div>
+        </Show>
+    }
+}
+
 #[tracing::instrument(level = "debug", skip())]
 #[component]
 pub fn Accounts() -> impl IntoView {
@@ -454,6 +502,7 @@ pub fn TopBar() -> impl IntoView {
 
     let ui_store = expect_context::<RwSignal<UiStore>>();
     let is_mobile = create_read_slice(ui_store, |u| u.get_is_mobile()).get();
+    let is_jukebox = create_read_slice(player_store, |p| p.is_jukebox());
 
     let i18n = use_i18n();
     view! {
@@ -529,8 +578,11 @@ pub fn TopBar() -> impl IntoView {
                     >
                         <div class="row flex-grow-1">
                             <div class="col-auto d-flex">
-                                <Accounts />
-                                <Settings class="ml-2" />
+                                <JukeboxToggle class="mr-2" />
+                                <Show when=move || !is_jukebox.get()>
+                                    <Accounts />
+                                    <Settings class="ml-2" />
+                                </Show>
                             </div>
                         </div>
                     </div>
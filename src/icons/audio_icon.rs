@@ -0,0 +1,44 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use leptos::{component, prelude::*, view, IntoView};
+
+#[tracing::instrument(level = "debug", skip(active))]
+#[component]
+pub fn AudioIcon(#[prop()] active: ReadSignal<bool>) -> impl IntoView {
+    view! {
+        <svg
+            width="18"
+            height="17"
+            viewBox="0 0 18 17"
+            fill="none"
+            xmlns="http://www.w3.org/2000/svg"
+        >
+            <path
+                d="M3 0C3.41421 0 3.75 0.335786 3.75 0.75V8.09451C4.62389 8.40334 5.25 9.23672 5.25 10.2164C5.25 11.196 4.62389 12.0294 3.75 12.3382V16.25C3.75 16.6642 3.41421 17 3 17C2.58579 17 2.25 16.6642 2.25 16.25V12.3382C1.37611 12.0294 0.75 11.196 0.75 10.2164C0.75 9.23672 1.37611 8.40334 2.25 8.09451V0.75C2.25 0.335786 2.58579 0 3 0Z"
+                fill=move || if active.get() { "var(--accent)" } else { "var(--textPrimary)" }
+            />
+            <path
+                d="M9 0C9.41421 0 9.75 0.335786 9.75 0.75V3.66118C10.6239 3.97001 11.25 4.80339 11.25 5.78305C11.25 6.76271 10.6239 7.59609 9.75 7.90492V16.25C9.75 16.6642 9.41421 17 9 17C8.58579 17 8.25 16.6642 8.25 16.25V7.90492C7.37611 7.59609 6.75 6.76271 6.75 5.78305C6.75 4.80339 7.37611 3.97001 8.25 3.66118V0.75C8.25 0.335786 8.58579 0 9 0Z"
+                fill=move || if active.get() { "var(--accent)" } else { "var(--textPrimary)" }
+            />
+            <path
+                d="M15 0C15.4142 0 15.75 0.335786 15.75 0.75V10.3278C16.6239 10.6367 17.25 11.47 17.25 12.4497C17.25 13.4294 16.6239 14.2627 15.75 14.5716V16.25C15.75 16.6642 15.4142 17 15 17C14.5858 17 14.25 16.6642 14.25 16.25V14.5716C13.3761 14.2627 12.75 13.4294 12.75 12.4497C12.75 11.47 13.3761 10.6367 14.25 10.3278V0.75C14.25 0.335786 14.5858 0 15 0Z"
+                fill=move || if active.get() { "var(--accent)" } else { "var(--textPrimary)" }
+            />
+        </svg>
+    }
+}
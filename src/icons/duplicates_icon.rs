@@ -0,0 +1,39 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use leptos::{component, prelude::*, view, IntoView};
+
+#[tracing::instrument(level = "debug", skip(active))]
+#[component]
+pub fn DuplicatesIcon(#[prop()] active: ReadSignal<bool>) -> impl IntoView {
+    view! {
+        <svg
+            width="18"
+            height="18"
+            viewBox="0 0 18 18"
+            fill="none"
+            xmlns="http://www.w3.org/2000/svg"
+        >
+            <title>Duplicates</title>
+            <path
+                fill-rule="evenodd"
+                clip-rule="evenodd"
+                d="M5 0C3.89543 0 3 0.895431 3 2V3H2C0.895431 3 0 3.89543 0 5V16C0 17.1046 0.895431 18 2 18H11C12.1046 18 13 17.1046 13 16V15H14C15.1046 15 16 14.1046 16 13V2C16 0.895431 15.1046 0 14 0H5ZM13 13.5H14C14.2761 13.5 14.5 13.2761 14.5 13V2C14.5 1.72386 14.2761 1.5 14 1.5H5C4.72386 1.5 4.5 1.72386 4.5 2V3H11C12.1046 3 13 3.89543 13 5V13.5Z"
+                fill=move || if active.get() { "var(--accent)" } else { "var(--textPrimary)" }
+            ></path>
+        </svg>
+    }
+}
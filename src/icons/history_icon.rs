@@ -0,0 +1,39 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use leptos::{component, prelude::*, view, IntoView};
+
+#[tracing::instrument(level = "debug", skip(active))]
+#[component]
+pub fn HistoryIcon(#[prop()] active: ReadSignal<bool>) -> impl IntoView {
+    view! {
+        <svg
+            width="18"
+            height="18"
+            viewBox="0 0 18 18"
+            fill="none"
+            xmlns="http://www.w3.org/2000/svg"
+        >
+            <title>History</title>
+            <path
+                fill-rule="evenodd"
+                clip-rule="evenodd"
+                d="M9 1.5C4.85786 1.5 1.5 4.85786 1.5 9C1.5 13.1421 4.85786 16.5 9 16.5C13.1421 16.5 16.5 13.1421 16.5 9C16.5 4.85786 13.1421 1.5 9 1.5ZM0 9C0 4.02944 4.02944 0 9 0C13.9706 0 18 4.02944 18 9C18 13.9706 13.9706 18 9 18C4.02944 18 0 13.9706 0 9ZM9 3.75C9.41421 3.75 9.75 4.08579 9.75 4.5V8.68934L12.5303 11.4697C12.8232 11.7626 12.8232 12.2374 12.5303 12.5303C12.2374 12.8232 11.7626 12.8232 11.4697 12.5303L8.46967 9.53033C8.32902 9.38968 8.25 9.19891 8.25 9V4.5C8.25 4.08579 8.58579 3.75 9 3.75Z"
+                fill=move || if active.get() { "var(--accent)" } else { "var(--textPrimary)" }
+            ></path>
+        </svg>
+    }
+}
@@ -0,0 +1,50 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use leptos::{component, prelude::*, view, IntoView};
+
+#[tracing::instrument(level = "debug", skip(active))]
+#[component]
+pub fn JukeboxIcon<T>(#[prop()] active: T) -> impl IntoView
+where
+    T: Get<Value = bool> + 'static + Send + Sync + Clone,
+{
+    let active_c = active.clone();
+    view! {
+        <svg
+            class="button-grow jukebox-icon"
+            width="18"
+            height="18"
+            viewBox="0 0 18 18"
+            fill="none"
+            xmlns="http://www.w3.org/2000/svg"
+        >
+            <path
+                d="M6.5 13.5V3.2L15.5 1.5V11.8M6.5 13.5C6.5 14.88 5.38 16 4 16C2.62 16 1.5 14.88 1.5 13.5C1.5 12.12 2.62 11 4 11C5.38 11 6.5 12.12 6.5 13.5ZM15.5 11.8C15.5 13.18 14.38 14.3 13 14.3C11.62 14.3 10.5 13.18 10.5 11.8C10.5 10.42 11.62 9.3 13 9.3C14.38 9.3 15.5 10.42 15.5 11.8Z"
+                stroke=move || if active.get() { "var(--accent)" } else { "var(--textPrimary)" }
+                stroke-width="1.5"
+                stroke-linecap="round"
+                stroke-linejoin="round"
+            />
+            <path
+                d="M6.5 6.5L15.5 4.8"
+                stroke=move || if active_c.get() { "var(--accent)" } else { "var(--textPrimary)" }
+                stroke-width="1.5"
+                stroke-linecap="round"
+            />
+        </svg>
+    }
+}
@@ -20,7 +20,9 @@ pub mod albums_icon;
 pub mod allsongs_icon;
 pub mod animated_equalizer_icon;
 pub mod artists_icon;
+pub mod audio_icon;
 pub mod cross_icon;
+pub mod duplicates_icon;
 pub mod ellipsis_icon;
 pub mod expand_icon;
 pub mod explore_icon;
@@ -30,8 +32,10 @@ pub mod fav_playlist_icon;
 pub mod fetch_all_icon;
 pub mod folder_icon;
 pub mod genres_icon;
+pub mod history_icon;
 pub mod import_playlist_icon;
 pub mod import_theme_icon;
+pub mod jukebox_icon;
 pub mod logs_icon;
 pub mod lyrics_icon;
 pub mod new_playlist_icon;
@@ -57,9 +61,11 @@ pub mod search_icon;
 pub mod settings_icon;
 pub mod shuffle_icon;
 pub mod sidebar_toggle_icon;
+pub mod sleep_timer_icon;
 pub mod song_default_icon;
 pub mod sort_icon;
 pub mod spotify_icon;
+pub mod stats_icon;
 pub mod system_icon;
 pub mod theme_view_icon;
 pub mod themes_icon;
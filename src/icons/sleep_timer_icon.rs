@@ -0,0 +1,43 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use leptos::{component, prelude::*, view, IntoView};
+
+#[tracing::instrument(level = "debug", skip(active))]
+#[component]
+pub fn SleepTimerIcon<T>(#[prop()] active: T) -> impl IntoView
+where
+    T: Get<Value = bool> + 'static + Send + Sync,
+{
+    view! {
+        <svg
+            class="button-grow sleep-timer-icon"
+            width="20"
+            height="20"
+            viewBox="0 0 20 20"
+            fill="none"
+            xmlns="http://www.w3.org/2000/svg"
+        >
+            <path
+                d="M17.5 11.9C16.52 12.35 15.43 12.6 14.28 12.6C10.04 12.6 6.6 9.16 6.6 4.92C6.6 3.77 6.85 2.68 7.3 1.7C4.64 2.92 2.8 5.6 2.8 8.72C2.8 12.96 6.24 16.4 10.48 16.4C13.6 16.4 16.28 14.56 17.5 11.9Z"
+                stroke=move || if active.get() { "var(--accent)" } else { "var(--textPrimary)" }
+                stroke-width="1.5"
+                stroke-linecap="round"
+                stroke-linejoin="round"
+            />
+        </svg>
+    }
+}
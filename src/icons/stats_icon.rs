@@ -0,0 +1,39 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use leptos::{component, prelude::*, view, IntoView};
+
+#[tracing::instrument(level = "debug", skip(active))]
+#[component]
+pub fn StatsIcon(#[prop()] active: ReadSignal<bool>) -> impl IntoView {
+    view! {
+        <svg
+            width="18"
+            height="18"
+            viewBox="0 0 18 18"
+            fill="none"
+            xmlns="http://www.w3.org/2000/svg"
+        >
+            <title>Stats</title>
+            <path
+                fill-rule="evenodd"
+                clip-rule="evenodd"
+                d="M9 0C9.41421 0 9.75 0.335786 9.75 0.75V17.25C9.75 17.6642 9.41421 18 9 18C8.58579 18 8.25 17.6642 8.25 17.25V0.75C8.25 0.335786 8.58579 0 9 0ZM15.75 6C16.1642 6 16.5 6.33579 16.5 6.75V17.25C16.5 17.6642 16.1642 18 15.75 18C15.3358 18 15 17.6642 15 17.25V6.75C15 6.33579 15.3358 6 15.75 6ZM3 10.5C3 10.0858 2.66421 9.75 2.25 9.75C1.83579 9.75 1.5 10.0858 1.5 10.5V17.25C1.5 17.6642 1.83579 18 2.25 18C2.66421 18 3 17.6642 3 17.25V10.5Z"
+                fill=move || if active.get() { "var(--accent)" } else { "var(--textPrimary)" }
+            ></path>
+        </svg>
+    }
+}
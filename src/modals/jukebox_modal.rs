@@ -0,0 +1,189 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use leptos::{component, prelude::*, task::spawn_local, view, IntoView};
+use leptos_i18n::{t, t_string};
+
+use crate::{
+    components::jukebox::{start_jukebox, stop_jukebox, JUKEBOX_PIN_KEY},
+    i18n::use_i18n,
+    modals::common::GenericModal,
+    store::{modal_store::ModalStore, player_store::PlayerStore},
+    utils::invoke::{get_secure, set_secure},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JukeboxModalState {
+    Enter,
+    Exit,
+    /// Lets the next guest enter their name
+    ChangeGuest,
+}
+
+#[tracing::instrument(level = "debug", skip(state))]
+#[component]
+pub fn JukeboxModal(#[prop()] state: JukeboxModalState) -> impl IntoView {
+    let modal_store: RwSignal<ModalStore> = expect_context();
+    let player_store: RwSignal<PlayerStore> = expect_context();
+    let close_modal = move |_| modal_store.update(|m| m.clear_active_modal());
+
+    let pin = RwSignal::new(String::new());
+    let confirm_pin = RwSignal::new(String::new());
+    let error = RwSignal::new(None::<String>);
+
+    let i18n = use_i18n();
+    let is_enter = state == JukeboxModalState::Enter;
+    let is_exit = state == JukeboxModalState::Exit;
+
+    let confirm = move |_| {
+        let pin_val = pin.get();
+        if pin_val.is_empty() {
+            error.set(Some(t_string!(i18n, jukebox.pin_empty).to_string()));
+            return;
+        }
+
+        if is_enter {
+            if pin_val != confirm_pin.get() {
+                error.set(Some(t_string!(i18n, jukebox.pin_mismatch).to_string()));
+                return;
+            }
+            // Jukebox mode could never be left without the PIN
+            spawn_local(async move {
+                if let Err(e) = set_secure(JUKEBOX_PIN_KEY.into(), Some(pin_val)).await {
+                    tracing::error!("Failed to save jukebox pin: {:?}", e);
+                    error.set(Some(t_string!(i18n, jukebox.pin_save_failed).to_string()));
+                    return;
+                }
+                start_jukebox(player_store);
+                modal_store.update(|m| m.clear_active_modal());
+            });
+            return;
+        }
+
+        spawn_local(async move {
+            let saved = get_secure(JUKEBOX_PIN_KEY.into())
+                .await
+                .map(|saved| serde_wasm_bindgen::from_value::<Option<String>>(saved));
+            let saved = match saved {
+                Ok(Ok(Some(saved))) => saved,
+                res => {
+                    tracing::error!("Failed to load jukebox pin: {:?}", res);
+                    error.set(Some(t_string!(i18n, jukebox.pin_load_failed).to_string()));
+                    return;
+                }
+            };
+
+            if saved == pin_val {
+                if is_exit {
+                    stop_jukebox(player_store);
+                } else {
+                    player_store.update(|p| p.clear_jukebox_guest());
+                }
+                modal_store.update(|m| m.clear_active_modal());
+            } else {
+                pin.set(String::new());
+                error.set(Some(t_string!(i18n, jukebox.wrong_pin).to_string()));
+            }
+        });
+    };
+
+    view! {
+        <GenericModal size=move || "modal-md".into()>
+            <div class="container-fluid p-0 mt-4">
+                <div class="row no-gutters d-flex">
+                    <div class="col">
+                        <h4>
+                            {match &state {
+                                JukeboxModalState::Enter => t!(i18n, jukebox.enter).into_any(),
+                                JukeboxModalState::Exit => t!(i18n, jukebox.exit).into_any(),
+                                JukeboxModalState::ChangeGuest => {
+                                    t!(i18n, jukebox.change_guest).into_any()
+                                }
+                            }}
+                        </h4>
+                        <h6 class="mt-3">
+                            {match &state {
+                                JukeboxModalState::Enter => t!(i18n, jukebox.set_pin).into_any(),
+                                JukeboxModalState::Exit => t!(i18n, jukebox.enter_pin).into_any(),
+                                JukeboxModalState::ChangeGuest => {
+                                    t!(i18n, jukebox.enter_pin_guest).into_any()
+                                }
+                            }}
+                        </h6>
+                    </div>
+                </div>
+                <div class="row no-gutters mt-3">
+                    <input
+                        class="form-control ext-input"
+                        type="password"
+                        inputmode="numeric"
+                        autocomplete="off"
+                        placeholder=move || t_string!(i18n, jukebox.pin).to_string()
+                        prop:value=move || pin.get()
+                        on:input=move |e| {
+                            error.set(None);
+                            pin.set(event_target_value(&e));
+                        }
+                    />
+                </div>
+                {if is_enter {
+                    view! {
+                        <div class="row no-gutters mt-2">
+                            <input
+                                class="form-control ext-input"
+                                type="password"
+                                inputmode="numeric"
+                                autocomplete="off"
+                                placeholder=move || t_string!(i18n, jukebox.confirm_pin).to_string()
+                                prop:value=move || confirm_pin.get()
+                                on:input=move |e| {
+                                    error.set(None);
+                                    confirm_pin.set(event_target_value(&e));
+                                }
+                            />
+                        </div>
+                    }
+                        .into_any()
+                } else {
+                    ().into_any()
+                }}
+                {move || {
+                    error
+                        .get()
+                        .map(|error| {
+                            view! { <div class="row no-gutters mt-2 jukebox-error">{error}</div> }
+                        })
+                }}
+                <div class="row row-cols-auto mt-3 mr-4">
+                    <button
+                        on:click=close_modal
+                        class="btn btn-secondary cancel-button ml-auto"
+                        type="button"
+                    >
+                        {t!(i18n, jukebox.cancel)}
+                    </button>
+                    <button
+                        on:click=confirm
+                        class="btn btn-secondary confirm-button ml-3"
+                        type="button"
+                    >
+                        {t!(i18n, jukebox.confirm)}
+                    </button>
+                </div>
+            </div>
+        </GenericModal>
+    }
+}
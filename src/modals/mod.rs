@@ -16,10 +16,12 @@
 
 pub mod common;
 pub mod discover_extensions;
+pub mod jukebox_modal;
 pub mod login_modal;
 pub mod modal_manager;
 pub mod new_playlist_modal;
 pub mod new_theme_modal;
 pub mod signout_modal;
 pub mod song_from_url_modal;
+pub mod tag_editor_modal;
 pub mod update_modal;
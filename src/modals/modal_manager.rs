@@ -18,9 +18,10 @@ use leptos::{component, prelude::*, view, IntoView};
 
 use crate::{
     modals::{
-        discover_extensions::DiscoverExtensionsModal, login_modal::LoginModal,
-        new_playlist_modal::NewPlaylistModal, new_theme_modal::NewThemeModal,
-        signout_modal::SignoutModal, song_from_url_modal::SongFromUrlModal,
+        discover_extensions::DiscoverExtensionsModal, jukebox_modal::JukeboxModal,
+        login_modal::LoginModal, new_playlist_modal::NewPlaylistModal,
+        new_theme_modal::NewThemeModal, signout_modal::SignoutModal,
+        song_from_url_modal::SongFromUrlModal, tag_editor_modal::TagEditorModal,
         update_modal::UpdateModal,
     },
     store::modal_store::{ModalStore, Modals},
@@ -59,6 +60,12 @@ pub fn ModalManager() -> impl IntoView {
                     Modals::UpdateModal(metadata) => {
                         view! { <UpdateModal metadata=metadata /> }.into_any()
                     }
+                    Modals::JukeboxModal(state) => {
+                        view! { <JukeboxModal state=state /> }.into_any()
+                    }
+                    Modals::TagEditorModal(songs) => {
+                        view! { <TagEditorModal songs=songs /> }.into_any()
+                    }
                 }
             }}
 
@@ -18,10 +18,15 @@ use std::sync::Arc;
 
 use leptos::task::spawn_local;
 use leptos::{component, prelude::*, view, IntoView};
-use leptos_i18n::t;
+use leptos_i18n::{t, t_string};
 use types::entities::QueryablePlaylist;
+use types::smart_playlist::{
+    SmartPlaylistField, SmartPlaylistMatch, SmartPlaylistOperator, SmartPlaylistRule,
+    SmartPlaylistRules,
+};
 use types::songs::Song;
 use types::ui::extensions::ExtensionProviderScope;
+use types::window::DialogFilter;
 
 use crate::i18n::use_i18n;
 use crate::icons::{
@@ -31,9 +36,12 @@ use crate::icons::{
 use crate::store::modal_store::ModalStore;
 use crate::store::ui_store::UiStore;
 use crate::utils::db_utils::create_playlist_and;
-use crate::utils::invoke::{match_url, playlist_from_url};
+use crate::utils::invoke::{import_playlist_file, match_url, open_file_browser, playlist_from_url};
 use crate::{modals::common::GenericModal, store::provider_store::ProviderStore};
 
+/// Playlist files the backend can import
+const PLAYLIST_EXTENSIONS: [&str; 5] = ["m3u", "m3u8", "pls", "xspf", "wpl"];
+
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum PlaylistModalState {
     None,
@@ -41,6 +49,252 @@ pub enum PlaylistModalState {
     ImportPlaylist,
 }
 
+#[tracing::instrument(level = "debug", skip())]
+fn field_label(field: SmartPlaylistField) -> String {
+    let i18n = use_i18n();
+    match field {
+        SmartPlaylistField::Title => t_string!(i18n, smart_playlist.fields.title),
+        SmartPlaylistField::Artist => t_string!(i18n, smart_playlist.fields.artist),
+        SmartPlaylistField::Album => t_string!(i18n, smart_playlist.fields.album),
+        SmartPlaylistField::Genre => t_string!(i18n, smart_playlist.fields.genre),
+        SmartPlaylistField::Year => t_string!(i18n, smart_playlist.fields.year),
+        SmartPlaylistField::Duration => t_string!(i18n, smart_playlist.fields.duration),
+        SmartPlaylistField::Bitrate => t_string!(i18n, smart_playlist.fields.bitrate),
+        SmartPlaylistField::Codec => t_string!(i18n, smart_playlist.fields.codec),
+        SmartPlaylistField::Path => t_string!(i18n, smart_playlist.fields.path),
+        SmartPlaylistField::DateAdded => t_string!(i18n, smart_playlist.fields.date_added),
+        SmartPlaylistField::PlayCount => t_string!(i18n, smart_playlist.fields.play_count),
+        SmartPlaylistField::PlayTime => t_string!(i18n, smart_playlist.fields.play_time),
+    }
+    .to_string()
+}
+
+#[tracing::instrument(level = "debug", skip())]
+fn operator_label(operator: SmartPlaylistOperator) -> String {
+    let i18n = use_i18n();
+    match operator {
+        SmartPlaylistOperator::Equals => t_string!(i18n, smart_playlist.operators.equals),
+        SmartPlaylistOperator::NotEquals => t_string!(i18n, smart_playlist.operators.not_equals),
+        SmartPlaylistOperator::Contains => t_string!(i18n, smart_playlist.operators.contains),
+        SmartPlaylistOperator::NotContains => {
+            t_string!(i18n, smart_playlist.operators.not_contains)
+        }
+        SmartPlaylistOperator::GreaterThan => {
+            t_string!(i18n, smart_playlist.operators.greater_than)
+        }
+        SmartPlaylistOperator::LessThan => t_string!(i18n, smart_playlist.operators.less_than),
+    }
+    .to_string()
+}
+
+/// Select values are indices into `SmartPlaylistField::ALL` and `SmartPlaylistOperator::ALL`
+#[tracing::instrument(level = "debug", skip(rules))]
+#[component]
+fn SmartRulesEditor(#[prop()] rules: RwSignal<SmartPlaylistRules>) -> impl IntoView {
+    let i18n = use_i18n();
+
+    let update_rule = move |index: usize, f: Box<dyn FnOnce(&mut SmartPlaylistRule)>| {
+        rules.update(|r| {
+            if let Some(rule) = r.rules.get_mut(index) {
+                f(rule);
+                // Keep the operator valid for the selected field
+                if !rule.field.supports(rule.operator) {
+                    rule.operator = SmartPlaylistOperator::Equals;
+                }
+            }
+        })
+    };
+
+    view! {
+        <div class="smart-rules">
+            <div class="d-flex smart-rule">
+                <select
+                    class="dropdown-list"
+                    on:change=move |ev| {
+                        let match_type = if event_target_value(&ev) == "any" {
+                            SmartPlaylistMatch::Any
+                        } else {
+                            SmartPlaylistMatch::All
+                        };
+                        rules.update(|r| r.match_type = match_type);
+                    }
+                >
+                    <option
+                        value="all"
+                        prop:selected=move || rules.get().match_type == SmartPlaylistMatch::All
+                    >
+                        {t!(i18n, smart_playlist.match_all)}
+                    </option>
+                    <option
+                        value="any"
+                        prop:selected=move || rules.get().match_type == SmartPlaylistMatch::Any
+                    >
+                        {t!(i18n, smart_playlist.match_any)}
+                    </option>
+                </select>
+            </div>
+            <For
+                each=move || 0..rules.get().rules.len()
+                key=|index| *index
+                children=move |index| {
+                    let rule = Memo::new(move |_| {
+                        rules.with(|r| r.rules.get(index).cloned().unwrap_or_default())
+                    });
+                    view! {
+                        <div class="d-flex smart-rule">
+                            <select
+                                class="dropdown-list"
+                                on:change=move |ev| {
+                                    if let Some(field) = event_target_value(&ev)
+                                        .parse::<usize>()
+                                        .ok()
+                                        .and_then(|i| SmartPlaylistField::ALL.get(i).copied())
+                                    {
+                                        update_rule(index, Box::new(move |r| r.field = field));
+                                    }
+                                }
+                            >
+                                {SmartPlaylistField::ALL
+                                    .iter()
+                                    .enumerate()
+                                    .map(|(i, field)| {
+                                        let field = *field;
+                                        view! {
+                                            <option
+                                                value=i.to_string()
+                                                prop:selected=move || rule.get().field == field
+                                            >
+                                                {field_label(field)}
+                                            </option>
+                                        }
+                                    })
+                                    .collect_view()}
+                            </select>
+                            <select
+                                class="dropdown-list"
+                                on:change=move |ev| {
+                                    if let Some(operator) = event_target_value(&ev)
+                                        .parse::<usize>()
+                                        .ok()
+                                        .and_then(|i| SmartPlaylistOperator::ALL.get(i).copied())
+                                    {
+                                        update_rule(index, Box::new(move |r| r.operator = operator));
+                                    }
+                                }
+                            >
+                                {move || {
+                                    let rule = rule.get();
+                                    SmartPlaylistOperator::ALL
+                                        .iter()
+                                        .enumerate()
+                                        .filter(|(_, operator)| rule.field.supports(**operator))
+                                        .map(|(i, operator)| {
+                                            view! {
+                                                <option
+                                                    value=i.to_string()
+                                                    selected=rule.operator == *operator
+                                                >
+                                                    {operator_label(*operator)}
+                                                </option>
+                                            }
+                                        })
+                                        .collect_view()
+                                }}
+                            </select>
+                            <input
+                                class="form-control smart-rule-value"
+                                prop:value=move || rule.get().value
+                                placeholder=move || {
+                                    i18n.get_keys().smart_playlist().value_placeholder().build_string()
+                                }
+                                on:input=move |e| {
+                                    let value = event_target_value(&e);
+                                    update_rule(index, Box::new(move |r| r.value = value));
+                                }
+                            />
+                            <button
+                                class="btn btn-secondary close-button"
+                                on:click=move |_| {
+                                    rules
+                                        .update(|r| {
+                                            if index < r.rules.len() {
+                                                r.rules.remove(index);
+                                            }
+                                        })
+                                }
+                            >
+                                {t!(i18n, smart_playlist.remove_rule)}
+                            </button>
+                        </div>
+                    }
+                }
+            />
+            <div class="d-flex smart-rule">
+                <button
+                    class="btn btn-secondary create-button"
+                    on:click=move |_| rules.update(|r| r.rules.push(SmartPlaylistRule::default()))
+                >
+                    {t!(i18n, smart_playlist.add_rule)}
+                </button>
+            </div>
+            <div class="d-flex smart-rule">
+                <select
+                    class="dropdown-list"
+                    on:change=move |ev| {
+                        let sort_by = event_target_value(&ev)
+                            .parse::<usize>()
+                            .ok()
+                            .and_then(|i| SmartPlaylistField::ALL.get(i).copied());
+                        rules.update(|r| r.sort_by = sort_by);
+                    }
+                >
+                    <option value="" prop:selected=move || rules.get().sort_by.is_none()>
+                        {t!(i18n, smart_playlist.unsorted)}
+                    </option>
+                    {SmartPlaylistField::ALL
+                        .iter()
+                        .enumerate()
+                        .map(|(i, field)| {
+                            let field = *field;
+                            view! {
+                                <option
+                                    value=i.to_string()
+                                    prop:selected=move || rules.get().sort_by == Some(field)
+                                >
+                                    {t!(i18n, smart_playlist.sort_by)}
+                                    " "
+                                    {field_label(field)}
+                                </option>
+                            }
+                        })
+                        .collect_view()}
+                </select>
+                <label class="smart-rule-label">
+                    <input
+                        type="checkbox"
+                        prop:checked=move || rules.get().sort_desc
+                        on:change=move |e| rules.update(|r| r.sort_desc = event_target_checked(&e))
+                    />
+                    {t!(i18n, smart_playlist.descending)}
+                </label>
+                <input
+                    class="form-control smart-rule-value"
+                    type="number"
+                    min="1"
+                    prop:value=move || rules.get().limit.map(|l| l.to_string()).unwrap_or_default()
+                    placeholder=move || {
+                        i18n.get_keys().smart_playlist().limit_placeholder().build_string()
+                    }
+                    on:input=move |e| {
+                        let limit = event_target_value(&e).parse::<i64>().ok().filter(|l| *l > 0);
+                        rules.update(|r| r.limit = limit);
+                    }
+                />
+            </div>
+        </div>
+    }
+}
+
 #[tracing::instrument(level = "debug", skip())]
 #[component]
 pub fn NewPlaylistModal(
@@ -51,9 +305,12 @@ pub fn NewPlaylistModal(
 
     let playlist = RwSignal::new(None::<QueryablePlaylist>);
     let import_url = RwSignal::new(String::new());
+    // Songs of a playlist imported from a file, replaced once a URL is entered
+    let imported_songs = RwSignal::new(None::<Vec<Song>>);
     let provider_store: Arc<ProviderStore> = expect_context();
     Effect::new(move || {
         let import_url = import_url.get().clone();
+        imported_songs.set(None);
         if import_url.is_empty() {
             playlist.set(None);
             return;
@@ -79,22 +336,69 @@ pub fn NewPlaylistModal(
     let close_modal = move || modal_store.update(|m| m.clear_active_modal());
     let songs = RwSignal::new(songs);
 
+    let is_smart = RwSignal::new(false);
+    let smart_rules = RwSignal::new(SmartPlaylistRules::default());
+
     let create_new_playlist = move |_| {
         let playlist = playlist.get();
-        let songs = songs.get_untracked();
+        let mut songs = songs.get_untracked();
 
         if playlist.is_none() {
             return;
         }
 
-        let playlist = playlist.unwrap();
+        let mut playlist = playlist.unwrap();
         if playlist.playlist_name.is_empty() {
             return;
         }
 
+        if state.get_untracked() == PlaylistModalState::ImportPlaylist {
+            if let Some(imported) = imported_songs.get_untracked() {
+                songs = Some(imported);
+            }
+        }
+
+        // Smart playlists only contain songs matching their rules
+        if state.get_untracked() == PlaylistModalState::NewPlaylist && is_smart.get_untracked() {
+            playlist.smart_rules = Some(smart_rules.get_untracked());
+            songs = None;
+        }
+
         create_playlist_and(playlist, songs, Arc::new(Box::new(close_modal)));
     };
 
+    let import_from_file = move |_| {
+        spawn_local(async move {
+            let files = open_file_browser(
+                false,
+                false,
+                vec![DialogFilter {
+                    name: "Playlists".into(),
+                    extensions: PLAYLIST_EXTENSIONS.iter().map(|e| e.to_string()).collect(),
+                }],
+            )
+            .await;
+            let path = match files {
+                Ok(files) => match files.first() {
+                    Some(file) => file.path.clone(),
+                    None => return,
+                },
+                Err(e) => {
+                    tracing::error!("Failed to open file browser {:?}", e);
+                    return;
+                }
+            };
+
+            match import_playlist_file(path).await {
+                Ok(imported) => {
+                    playlist.set(Some(imported.playlist));
+                    imported_songs.set(Some(imported.songs));
+                }
+                Err(e) => tracing::error!("Failed to import playlist: {:?}", e),
+            }
+        });
+    };
+
     let i18n = use_i18n();
 
     let ui_store = expect_context::<RwSignal<UiStore>>();
@@ -205,8 +509,19 @@ pub fn NewPlaylistModal(
                                             />
                                         </div>
                                         <p class="songs-count">0 Songs</p>
+                                        <label class="smart-rule-label">
+                                            <input
+                                                type="checkbox"
+                                                prop:checked=is_smart
+                                                on:change=move |e| is_smart.set(event_target_checked(&e))
+                                            />
+                                            {t!(i18n, smart_playlist.toggle)}
+                                        </label>
                                     </div>
                                 </div>
+                                <Show when=move || is_smart.get()>
+                                    <SmartRulesEditor rules=smart_rules />
+                                </Show>
                             </div>
                             <button
                                 class="btn btn-secondary close-button ml-3"
@@ -275,7 +590,18 @@ pub fn NewPlaylistModal(
                                                     </div>
                                                 </div>
                                                 <div class="row w-100">
-                                                    <div class="playlist-subtitle text-truncate deactivated"></div>
+                                                    <div class="playlist-subtitle text-truncate deactivated">
+                                                        {move || {
+                                                            imported_songs
+                                                                .get()
+                                                                .map(|songs| {
+                                                                    t_string!(
+                                                                        i18n, playlists.file.songs, count = songs.len()
+                                                                    )
+                                                                        .to_string()
+                                                                })
+                                                        }}
+                                                    </div>
                                                 </div>
                                             </div>
                                         </div>
@@ -340,6 +666,12 @@ pub fn NewPlaylistModal(
                                     </div>
                                 </div>
                             </div>
+                            <button
+                                class="btn btn-secondary create-button import-file-button"
+                                on:click=import_from_file
+                            >
+                                {t!(i18n, playlists.file.choose)}
+                            </button>
                             <button
                                 class="btn btn-secondary close-button ml-3"
                                 on:click=move |_| close_modal()
@@ -0,0 +1,477 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use chrono::{Local, TimeZone};
+use leptos::{component, prelude::*, task::spawn_local, view, IntoView};
+use leptos_i18n::{t, t_string};
+use types::{
+    songs::Song,
+    tags::{TagDiff, TagEdit, TagField, TagJournalEntry},
+    window::DialogFilter,
+};
+
+use crate::{
+    i18n::use_i18n,
+    modals::common::GenericModal,
+    store::modal_store::ModalStore,
+    utils::invoke::{
+        get_tag_journal, open_file_browser, preview_tag_edit, undo_tag_edit, write_tag_edit,
+    },
+};
+
+const JOURNAL_LIMIT: i64 = 200;
+
+#[derive(Debug, Clone, PartialEq)]
+struct TagBatch {
+    batch_id: String,
+    written_at: i64,
+    songs: usize,
+}
+
+#[tracing::instrument(level = "debug", skip(entries))]
+fn group_batches(entries: Vec<TagJournalEntry>) -> Vec<TagBatch> {
+    let mut ret: Vec<TagBatch> = vec![];
+    for entry in entries {
+        if let Some(batch) = ret.iter_mut().find(|b| b.batch_id == entry.batch_id) {
+            batch.songs += 1;
+        } else {
+            ret.push(TagBatch {
+                batch_id: entry.batch_id,
+                written_at: entry.written_at,
+                songs: 1,
+            });
+        }
+    }
+    ret
+}
+
+#[tracing::instrument(level = "debug", skip(batches))]
+fn fetch_journal(batches: RwSignal<Vec<TagBatch>>) {
+    spawn_local(async move {
+        match get_tag_journal(Some(JOURNAL_LIMIT)).await {
+            Ok(entries) => batches.set(group_batches(entries)),
+            Err(e) => tracing::error!("Failed to fetch tag journal {:?}", e),
+        }
+    });
+}
+
+/// Cleared inputs leave the tag as it is, whitespace removes it
+#[tracing::instrument(level = "debug", skip(value))]
+fn untouched_if_empty(value: String) -> Option<String> {
+    Some(value).filter(|v| !v.is_empty())
+}
+
+#[tracing::instrument(level = "debug", skip(field, value, placeholder))]
+#[component]
+fn TagInput(
+    #[prop()] field: TagField,
+    #[prop()] value: RwSignal<Option<String>>,
+    #[prop(optional)] placeholder: Option<String>,
+) -> impl IntoView {
+    let i18n = use_i18n();
+    let label = move || match field {
+        TagField::Title => t_string!(i18n, tag_editor.fields.title).to_string(),
+        TagField::Artists => t_string!(i18n, tag_editor.fields.artists).to_string(),
+        TagField::Album => t_string!(i18n, tag_editor.fields.album).to_string(),
+        TagField::AlbumArtist => t_string!(i18n, tag_editor.fields.album_artist).to_string(),
+        TagField::Year => t_string!(i18n, tag_editor.fields.year).to_string(),
+        TagField::Genre => t_string!(i18n, tag_editor.fields.genre).to_string(),
+        TagField::TrackNo => t_string!(i18n, tag_editor.fields.track_no).to_string(),
+        TagField::DiscNo => t_string!(i18n, tag_editor.fields.disc_no).to_string(),
+        TagField::Lyrics => t_string!(i18n, tag_editor.fields.lyrics).to_string(),
+        TagField::Cover => t_string!(i18n, tag_editor.fields.cover).to_string(),
+    };
+
+    view! {
+        <div class="row no-gutters mt-2 align-items-center">
+            <div class="col-3 tag-editor-label">{label}</div>
+            <div class="col">
+                <input
+                    class="form-control ext-input"
+                    type="text"
+                    placeholder=placeholder.unwrap_or_default()
+                    prop:value=move || value.get().unwrap_or_default()
+                    on:input=move |e| value.set(untouched_if_empty(event_target_value(&e)))
+                />
+            </div>
+        </div>
+    }
+}
+
+#[tracing::instrument(level = "debug", skip(diff))]
+#[component]
+fn TagDiffItem(#[prop()] diff: TagDiff) -> impl IntoView {
+    let i18n = use_i18n();
+    view! {
+        <div class="tag-diff">
+            <div class="tag-diff-path text-truncate">{diff.path}</div>
+            {if diff.changes.is_empty() {
+                view! { <div class="tag-diff-change">{t!(i18n, tag_editor.no_changes)}</div> }
+                    .into_any()
+            } else {
+                diff.changes
+                    .into_iter()
+                    .map(|change| {
+                        let field = serde_json::to_value(change.field)
+                            .ok()
+                            .and_then(|v| v.as_str().map(|s| s.replace('_', " ")))
+                            .unwrap_or_default();
+                        let old = change
+                            .old
+                            .unwrap_or_else(|| t_string!(i18n, tag_editor.empty).to_string());
+                        let new = change
+                            .new
+                            .unwrap_or_else(|| t_string!(i18n, tag_editor.empty).to_string());
+                        view! {
+                            <div class="tag-diff-change text-truncate">
+                                {format!("{}: {} → {}", field, old, new)}
+                            </div>
+                        }
+                    })
+                    .collect_view()
+                    .into_any()
+            }}
+        </div>
+    }
+}
+
+#[tracing::instrument(level = "debug", skip(songs))]
+#[component]
+pub fn TagEditorModal(#[prop()] songs: Vec<Song>) -> impl IntoView {
+    let modal_store: RwSignal<ModalStore> = expect_context();
+    let close_modal = move |_| modal_store.update(|m| m.clear_active_modal());
+    let i18n = use_i18n();
+
+    // Only local files have tags which can be written
+    let songs: Vec<Song> = songs
+        .into_iter()
+        .filter(|s| s.song.path.is_some() && s.song._id.is_some())
+        .collect();
+    let song_ids: Vec<String> = songs.iter().filter_map(|s| s.song._id.clone()).collect();
+    let single = if songs.len() == 1 {
+        songs.first().cloned()
+    } else {
+        None
+    };
+
+    let title = RwSignal::new(None::<String>);
+    let artists = RwSignal::new(None::<String>);
+    let album = RwSignal::new(None::<String>);
+    let album_artist = RwSignal::new(None::<String>);
+    let year = RwSignal::new(None::<String>);
+    let genre = RwSignal::new(None::<String>);
+    let track_no = RwSignal::new(None::<String>);
+    let disc_no = RwSignal::new(None::<String>);
+    let lyrics = RwSignal::new(None::<String>);
+    let cover = RwSignal::new(None::<String>);
+
+    let diffs = RwSignal::new(None::<Vec<TagDiff>>);
+    let error = RwSignal::new(None::<String>);
+    let batches = RwSignal::new(vec![]);
+    fetch_journal(batches);
+
+    let build_edit = move || TagEdit {
+        title: title.get_untracked(),
+        artists: artists
+            .get_untracked()
+            .map(|a| a.split(';').map(|a| a.to_string()).collect()),
+        album: album.get_untracked(),
+        album_artist: album_artist.get_untracked(),
+        year: year.get_untracked(),
+        genre: genre.get_untracked(),
+        track_no: track_no.get_untracked(),
+        disc_no: disc_no.get_untracked(),
+        lyrics: lyrics.get_untracked(),
+        cover: cover.get_untracked(),
+    };
+
+    let preview_ids = song_ids.clone();
+    let preview = move |_| {
+        let song_ids = preview_ids.clone();
+        let edit = build_edit();
+        spawn_local(async move {
+            match preview_tag_edit(song_ids, edit).await {
+                Ok(res) => {
+                    error.set(None);
+                    diffs.set(Some(res));
+                }
+                Err(e) => error.set(Some(format!("{:?}", e))),
+            }
+        });
+    };
+
+    let write = move |_| {
+        let song_ids = song_ids.clone();
+        let edit = build_edit();
+        spawn_local(async move {
+            match write_tag_edit(song_ids, edit).await {
+                Ok(_) => modal_store.update(|m| m.clear_active_modal()),
+                Err(e) => {
+                    error.set(Some(format!("{:?}", e)));
+                    // Files written before the error can still be undone
+                    fetch_journal(batches);
+                }
+            }
+        });
+    };
+
+    let undo = move |batch_id: String| {
+        spawn_local(async move {
+            if let Err(e) = undo_tag_edit(batch_id).await {
+                error.set(Some(format!("{:?}", e)));
+            }
+            fetch_journal(batches);
+        });
+    };
+
+    let choose_cover = move |_| {
+        spawn_local(async move {
+            match open_file_browser(
+                false,
+                false,
+                vec![DialogFilter {
+                    name: "Images".into(),
+                    extensions: vec!["png".into(), "jpg".into(), "jpeg".into()],
+                }],
+            )
+            .await
+            {
+                Ok(res) => {
+                    if let Some(file) = res.first() {
+                        cover.set(Some(file.path.clone()));
+                    }
+                }
+                Err(e) => tracing::error!("Failed to open file browser {:?}", e),
+            }
+        });
+    };
+
+    let current = single.clone();
+    let artist_names = current.as_ref().map(|s| {
+        s.artists
+            .clone()
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|a| a.artist_name)
+            .collect::<Vec<_>>()
+            .join("; ")
+    });
+    let album_name = current
+        .as_ref()
+        .and_then(|s| s.album.clone().and_then(|a| a.album_name));
+    let album_artist_name = current
+        .as_ref()
+        .and_then(|s| s.album.clone().and_then(|a| a.album_artist));
+    let genre_name = current.as_ref().and_then(|s| {
+        s.genre
+            .clone()
+            .and_then(|g| g.into_iter().find_map(|g| g.genre_name))
+    });
+
+    view! {
+        <GenericModal size=move || "modal-lg".into()>
+            <div class="container-fluid p-0 mt-4 tag-editor">
+                <div class="row no-gutters">
+                    <div class="col">
+                        <h4>{t!(i18n, tag_editor.title)}</h4>
+                        <h6 class="mt-2">
+                            {t!(i18n, tag_editor.songs_selected, count = songs.len())}
+                        </h6>
+                    </div>
+                </div>
+                <div class="mt-3">
+                    {single
+                        .clone()
+                        .map(|song| {
+                            view! {
+                                <TagInput
+                                    field=TagField::Title
+                                    value=title
+                                    placeholder=song.song.title.unwrap_or_default()
+                                />
+                            }
+                        })}
+                    <TagInput
+                        field=TagField::Artists
+                        value=artists
+                        placeholder=artist_names.unwrap_or_default()
+                    />
+                    <TagInput
+                        field=TagField::Album
+                        value=album
+                        placeholder=album_name.unwrap_or_default()
+                    />
+                    <TagInput
+                        field=TagField::AlbumArtist
+                        value=album_artist
+                        placeholder=album_artist_name.unwrap_or_default()
+                    />
+                    <TagInput
+                        field=TagField::Year
+                        value=year
+                        placeholder=current
+                            .as_ref()
+                            .and_then(|s| s.song.year.clone())
+                            .unwrap_or_default()
+                    />
+                    <TagInput
+                        field=TagField::Genre
+                        value=genre
+                        placeholder=genre_name.unwrap_or_default()
+                    />
+                    {single
+                        .clone()
+                        .map(|song| {
+                            view! {
+                                <TagInput
+                                    field=TagField::TrackNo
+                                    value=track_no
+                                    placeholder=song
+                                        .song
+                                        .track_no
+                                        .map(|t| t.to_string())
+                                        .unwrap_or_default()
+                                />
+                            }
+                        })}
+                    <TagInput field=TagField::DiscNo value=disc_no />
+                    {single
+                        .clone()
+                        .map(|_| {
+                            view! {
+                                <div class="row no-gutters mt-2">
+                                    <div class="col-3 tag-editor-label">
+                                        {t!(i18n, tag_editor.fields.lyrics)}
+                                    </div>
+                                    <div class="col">
+                                        <textarea
+                                            class="form-control ext-input tag-editor-lyrics"
+                                            prop:value=move || lyrics.get().unwrap_or_default()
+                                            on:input=move |e| lyrics.set(untouched_if_empty(event_target_value(&e)))
+                                        ></textarea>
+                                    </div>
+                                </div>
+                            }
+                        })}
+                    <div class="row no-gutters mt-2 align-items-center">
+                        <div class="col-3 tag-editor-label">
+                            {t!(i18n, tag_editor.fields.cover)}
+                        </div>
+                        <div class="col text-truncate">
+                            {move || {
+                                cover
+                                    .get()
+                                    .unwrap_or_else(|| {
+                                        t_string!(i18n, tag_editor.keep_cover).to_string()
+                                    })
+                            }}
+                        </div>
+                        <div class="col-auto">
+                            <button
+                                class="btn btn-secondary create-button"
+                                type="button"
+                                on:click=choose_cover
+                            >
+                                {t!(i18n, tag_editor.choose_cover)}
+                            </button>
+                        </div>
+                    </div>
+                </div>
+                <div class="row no-gutters mt-2 tag-editor-hint">{t!(i18n, tag_editor.hint)}</div>
+                {move || {
+                    error
+                        .get()
+                        .map(|error| {
+                            view! { <div class="row no-gutters mt-2 tag-editor-error">{error}</div> }
+                        })
+                }}
+                {move || {
+                    diffs
+                        .get()
+                        .map(|diffs| {
+                            view! {
+                                <div class="mt-3 tag-diff-list">
+                                    <h6>{t!(i18n, tag_editor.preview_title)}</h6>
+                                    {diffs
+                                        .into_iter()
+                                        .map(|diff| view! { <TagDiffItem diff=diff /> })
+                                        .collect_view()}
+                                </div>
+                            }
+                        })
+                }}
+                <div class="row row-cols-auto mt-3 mr-4">
+                    <button
+                        on:click=close_modal
+                        class="btn btn-secondary cancel-button ml-auto"
+                        type="button"
+                    >
+                        {t!(i18n, tag_editor.cancel)}
+                    </button>
+                    <button
+                        on:click=preview
+                        class="btn btn-secondary confirm-button ml-3"
+                        type="button"
+                    >
+                        {t!(i18n, tag_editor.preview)}
+                    </button>
+                    <button
+                        on:click=write
+                        class="btn btn-secondary confirm-button ml-3"
+                        type="button"
+                    >
+                        {t!(i18n, tag_editor.write)}
+                    </button>
+                </div>
+                <div class="mt-4 tag-history">
+                    <h6>{t!(i18n, tag_editor.history)}</h6>
+                    <Show
+                        when=move || !batches.get().is_empty()
+                        fallback=move || view! { <div>{t!(i18n, tag_editor.no_history)}</div> }
+                    >
+                        <For
+                            each=move || batches.get()
+                            key=|b| b.batch_id.clone()
+                            children=move |batch: TagBatch| {
+                                let time = Local
+                                    .timestamp_millis_opt(batch.written_at)
+                                    .single()
+                                    .map(|d| d.format("%Y-%m-%d %H:%M").to_string())
+                                    .unwrap_or_default();
+                                let batch_id = batch.batch_id.clone();
+                                view! {
+                                    <div class="d-flex align-items-center tag-history-item">
+                                        <div>
+                                            {time} " · "
+                                            {t!(i18n, tag_editor.songs_edited, count = batch.songs)}
+                                        </div>
+                                        <button
+                                            class="btn btn-secondary create-button ml-auto"
+                                            type="button"
+                                            on:click=move |_| undo(batch_id.clone())
+                                        >
+                                            {t!(i18n, tag_editor.undo)}
+                                        </button>
+                                    </div>
+                                }
+                            }
+                        />
+                    </Show>
+                </div>
+            </div>
+        </GenericModal>
+    }
+}
@@ -150,6 +150,7 @@ pub fn SingleAlbum() -> impl IntoView {
             fetch_next_page=fetch_next_page
             show_mobile_default_details=is_mobile
             is_loading=is_loading
+            group_by_disc=true
         />
     }
     .into_any()
@@ -0,0 +1,159 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use leptos::{component, prelude::*, view, IntoView};
+use leptos_i18n::t;
+use types::songs::Song;
+use wasm_bindgen_futures::spawn_local;
+
+use crate::{
+    i18n::use_i18n,
+    utils::{
+        common::format_duration,
+        invoke::{get_duplicate_songs, merge_duplicates},
+    },
+};
+
+#[tracing::instrument(level = "debug", skip(song))]
+fn song_quality(song: &Song) -> String {
+    let mut parts = vec![];
+    if let Some(codec) = song.song.codec.clone() {
+        parts.push(codec);
+    }
+    if let Some(bitrate) = song.song.bitrate.filter(|b| *b > 0f64) {
+        parts.push(format!("{} kbps", (bitrate / 1000f64).round()));
+    }
+    if let Some(duration) = song.song.duration {
+        parts.push(format_duration(duration, false));
+    }
+    parts.join(" · ")
+}
+
+#[tracing::instrument(level = "debug", skip(groups))]
+fn fetch_duplicates(groups: RwSignal<Vec<Vec<Song>>>) {
+    spawn_local(async move {
+        match get_duplicate_songs().await {
+            Ok(res) => groups.set(res),
+            Err(e) => tracing::error!("Failed to fetch duplicate songs {:?}", e),
+        }
+    });
+}
+
+#[tracing::instrument(level = "debug", skip(group, groups))]
+#[component]
+fn DuplicateGroup(
+    #[prop()] group: Vec<Song>,
+    #[prop()] groups: RwSignal<Vec<Vec<Song>>>,
+) -> impl IntoView {
+    let i18n = use_i18n();
+    let ids: Vec<String> = group.iter().filter_map(|s| s.song._id.clone()).collect();
+    let keep = RwSignal::new(ids.first().cloned());
+    let title = group
+        .first()
+        .and_then(|s| s.song.title.clone())
+        .unwrap_or_default();
+
+    let merge = move |_| {
+        let Some(keep) = keep.get_untracked() else {
+            return;
+        };
+        let duplicates = ids.iter().filter(|id| **id != keep).cloned().collect();
+        spawn_local(async move {
+            if let Err(e) = merge_duplicates(keep, duplicates).await {
+                tracing::error!("Failed to merge duplicates {:?}", e);
+            }
+            fetch_duplicates(groups);
+        });
+    };
+
+    view! {
+        <div class="row no-gutters duplicate-group">
+            <div class="col">
+                <div class="d-flex align-items-center">
+                    <div class="duplicate-title">{title}</div>
+                    <button class="btn btn-secondary create-button ml-auto" on:click=merge>
+                        {t!(i18n, duplicates.merge)}
+                    </button>
+                </div>
+                {group
+                    .into_iter()
+                    .map(|song| {
+                        let id = song.song._id.clone();
+                        let id_selected = id.clone();
+                        let quality = song_quality(&song);
+                        view! {
+                            <div
+                                class="d-flex align-items-center duplicate-item"
+                                on:click=move |_| keep.set(id.clone())
+                            >
+                                <input
+                                    type="radio"
+                                    prop:checked=move || keep.get() == id_selected
+                                />
+                                <div class="ml-3 text-truncate">
+                                    <div class="duplicate-path">
+                                        {song.song.path.clone().unwrap_or_default()}
+                                    </div>
+                                    <div class="duplicate-details">{quality}</div>
+                                </div>
+                            </div>
+                        }
+                    })
+                    .collect_view()}
+            </div>
+        </div>
+    }
+}
+
+#[tracing::instrument(level = "debug", skip())]
+#[component]
+pub fn Duplicates() -> impl IntoView {
+    let groups = RwSignal::new(vec![]);
+    fetch_duplicates(groups);
+
+    let i18n = use_i18n();
+    view! {
+        <div class="w-100 h-100">
+            <div class="container-fluid song-container h-100 d-flex flex-column">
+                <div class="row page-title no-gutters">
+                    <div class="col-auto">{t!(i18n, pages.duplicates)}</div>
+                    <div class="col align-self-center"></div>
+                </div>
+
+                <div class="duplicates-list">
+                    <Show
+                        when=move || !groups.get().is_empty()
+                        fallback=move || {
+                            view! {
+                                <div class="duplicate-details">{t!(i18n, duplicates.none_found)}</div>
+                            }
+                        }
+                    >
+                        <For
+                            each=move || groups.get()
+                            key=|group| {
+                                group.iter().filter_map(|s| s.song._id.clone()).collect::<Vec<_>>()
+                            }
+                            children=move |group| {
+                                view! { <DuplicateGroup group=group groups=groups /> }
+                            }
+                        />
+                    </Show>
+                </div>
+            </div>
+        </div>
+    }
+}
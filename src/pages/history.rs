@@ -0,0 +1,259 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use chrono::{Datelike, Duration, Local, NaiveDate, TimeZone};
+use leptos::{component, prelude::*, view, IntoView};
+use leptos_i18n::{t, t_string};
+use types::entities::PlayHistoryItem;
+use wasm_bindgen_futures::spawn_local;
+
+use crate::{
+    i18n::use_i18n,
+    store::player_store::PlayerStore,
+    utils::{
+        common::format_duration,
+        entities::get_artist_string,
+        invoke::{clear_play_history, get_play_history},
+    },
+};
+
+/// Most plays shown at once
+const HISTORY_LIMIT: i64 = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ListeningRange {
+    Today,
+    Week,
+    Month,
+    Year,
+    LastYear,
+    All,
+}
+
+impl ListeningRange {
+    const ALL: [ListeningRange; 6] = [
+        Self::Today,
+        Self::Week,
+        Self::Month,
+        Self::Year,
+        Self::LastYear,
+        Self::All,
+    ];
+
+    #[tracing::instrument(level = "debug", skip())]
+    fn start_of_day(day: NaiveDate) -> Option<i64> {
+        Local
+            .from_local_datetime(&day.and_hms_opt(0, 0, 0)?)
+            .earliest()
+            .map(|d| d.timestamp_millis())
+    }
+
+    /// Start of the range as a unix timestamp in milliseconds
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub(crate) fn start(&self) -> Option<i64> {
+        let today = Local::now().date_naive();
+        let start = match self {
+            Self::Today => today,
+            Self::Week => today - Duration::days(today.weekday().num_days_from_monday() as i64),
+            Self::Month => today.with_day(1)?,
+            Self::Year => today.with_ordinal(1)?,
+            Self::LastYear => NaiveDate::from_ymd_opt(today.year() - 1, 1, 1)?,
+            Self::All => return None,
+        };
+        Self::start_of_day(start)
+    }
+
+    /// End of the range (exclusive) as a unix timestamp in milliseconds
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub(crate) fn end(&self) -> Option<i64> {
+        match self {
+            Self::LastYear => Self::start_of_day(Local::now().date_naive().with_ordinal(1)?),
+            _ => None,
+        }
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    fn label(&self) -> String {
+        let i18n = use_i18n();
+        match self {
+            Self::Today => t_string!(i18n, history.range.today),
+            Self::Week => t_string!(i18n, history.range.week),
+            Self::Month => t_string!(i18n, history.range.month),
+            Self::Year => t_string!(i18n, history.range.year),
+            Self::LastYear => t_string!(i18n, history.range.last_year),
+            Self::All => t_string!(i18n, history.range.all),
+        }
+        .to_string()
+    }
+}
+
+#[tracing::instrument(level = "debug", skip(range))]
+#[component]
+pub(crate) fn ListeningRangeSelect(#[prop()] range: RwSignal<ListeningRange>) -> impl IntoView {
+    view! {
+        <select
+            class="dropdown-list"
+            on:change=move |ev| {
+                if let Some(selected) = event_target_value(&ev)
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|i| ListeningRange::ALL.get(i).copied())
+                {
+                    range.set(selected);
+                }
+            }
+        >
+            {ListeningRange::ALL
+                .iter()
+                .enumerate()
+                .map(|(i, r)| {
+                    let r = *r;
+                    view! {
+                        <option value=i.to_string() prop:selected=move || range.get() == r>
+                            {r.label()}
+                        </option>
+                    }
+                })
+                .collect_view()}
+        </select>
+    }
+}
+
+#[tracing::instrument(level = "debug", skip(history))]
+fn fetch_history(range: ListeningRange, history: RwSignal<Vec<PlayHistoryItem>>) {
+    spawn_local(async move {
+        match get_play_history(range.start(), range.end(), Some(HISTORY_LIMIT)).await {
+            Ok(res) => history.set(res),
+            Err(e) => tracing::error!("Failed to fetch play history {:?}", e),
+        }
+    });
+}
+
+/// Plays grouped by the day they were started on
+#[tracing::instrument(level = "debug", skip(history))]
+fn group_by_day(history: Vec<PlayHistoryItem>) -> Vec<(String, Vec<PlayHistoryItem>)> {
+    let mut days: Vec<(String, Vec<PlayHistoryItem>)> = vec![];
+    for item in history {
+        let day = Local
+            .timestamp_millis_opt(item.play.started_at)
+            .single()
+            .map(|d| d.format("%A, %e %B %Y").to_string())
+            .unwrap_or_default();
+        match days.last_mut() {
+            Some((last, items)) if *last == day => items.push(item),
+            _ => days.push((day, vec![item])),
+        }
+    }
+    days
+}
+
+#[tracing::instrument(level = "debug", skip(item))]
+#[component]
+fn HistoryItem(#[prop()] item: PlayHistoryItem) -> impl IntoView {
+    let i18n = use_i18n();
+    let player_store = expect_context::<RwSignal<PlayerStore>>();
+    let play_now = create_write_slice(player_store, |p, song| p.play_now(song));
+
+    let time = Local
+        .timestamp_millis_opt(item.play.started_at)
+        .single()
+        .map(|d| d.format("%H:%M").to_string())
+        .unwrap_or_default();
+    let title = item.song.song.title.clone().unwrap_or_default();
+    let artists = get_artist_string(item.song.artists.clone());
+    let listened = format_duration(item.play.duration, false);
+    let completed = item.play.completed;
+    let song = item.song;
+
+    view! {
+        <div
+            class="d-flex align-items-center history-item"
+            on:dblclick=move |_| play_now.set(song.clone())
+        >
+            <div class="history-time">{time}</div>
+            <div class="ml-3 text-truncate">
+                <div class="history-title text-truncate">{title}</div>
+                <div class="history-details text-truncate">{artists}</div>
+            </div>
+            <div class="ml-auto history-details">
+                {(!completed).then(|| view! { <span class="mr-3">{t!(i18n, history.skipped)}</span> })}
+                {listened}
+            </div>
+        </div>
+    }
+}
+
+#[tracing::instrument(level = "debug", skip())]
+#[component]
+pub fn History() -> impl IntoView {
+    let range = RwSignal::new(ListeningRange::Week);
+    let history = RwSignal::new(vec![]);
+    Effect::new(move || fetch_history(range.get(), history));
+
+    let days = Memo::new(move |_| group_by_day(history.get()));
+
+    let clear = move |_| {
+        spawn_local(async move {
+            if let Err(e) = clear_play_history(None).await {
+                tracing::error!("Failed to clear play history {:?}", e);
+            }
+            fetch_history(range.get_untracked(), history);
+        });
+    };
+
+    let i18n = use_i18n();
+    view! {
+        <div class="w-100 h-100">
+            <div class="container-fluid song-container h-100 d-flex flex-column">
+                <div class="row page-title no-gutters">
+                    <div class="col-auto">{t!(i18n, pages.history)}</div>
+                    <div class="col align-self-center"></div>
+                    <div class="col-auto d-flex align-items-center">
+                        <ListeningRangeSelect range=range />
+                        <button class="btn btn-secondary create-button ml-3" on:click=clear>
+                            {t!(i18n, history.clear)}
+                        </button>
+                    </div>
+                </div>
+
+                <div class="history-list">
+                    <Show
+                        when=move || !days.get().is_empty()
+                        fallback=move || {
+                            view! { <div class="history-details">{t!(i18n, history.empty)}</div> }
+                        }
+                    >
+                        <For
+                            each=move || days.get()
+                            key=|(day, items)| (day.clone(), items.iter().map(|i| i.play.id).collect::<Vec<_>>())
+                            children=move |(day, items)| {
+                                view! {
+                                    <div class="history-day">
+                                        <div class="history-day-title">{day}</div>
+                                        {items
+                                            .into_iter()
+                                            .map(|item| view! { <HistoryItem item=item /> })
+                                            .collect_view()}
+                                    </div>
+                                }
+                            }
+                        />
+                    </Show>
+                </div>
+            </div>
+        </div>
+    }
+}
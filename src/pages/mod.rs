@@ -16,8 +16,11 @@
 
 pub mod albums;
 pub mod artists;
+pub mod duplicates;
 pub mod explore;
 pub mod genres;
+pub mod history;
 pub mod playlists;
 pub mod search;
 pub mod songs;
+pub mod stats;
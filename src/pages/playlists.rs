@@ -27,6 +27,7 @@ use crate::utils::context_menu::{
     create_context_menu, PlaylistContextMenu, PlaylistItemContextMenu,
 };
 use crate::utils::db_utils::get_songs_by_option;
+use crate::utils::invoke::move_in_playlist;
 use crate::utils::songs::get_songs_from_indices;
 use leptos::task::spawn_local;
 use leptos::{component, prelude::*, view, IntoView};
@@ -155,20 +156,62 @@ pub fn SinglePlaylist() -> impl IntoView {
         fetch_selected_providers.as_ref()();
     };
 
+    // Only local songs are stored in the playlist, provider songs are listed after them
+    let on_reorder: Arc<Box<dyn Fn(String, usize) + Send + Sync>> =
+        Arc::new(Box::new(move |song_id: String, to: usize| {
+            let Some(playlist_id) = playlist.get_untracked().and_then(|p| p.playlist_id) else {
+                return;
+            };
+
+            let mut moved = false;
+            songs.update(|songs| {
+                let from = songs
+                    .iter()
+                    .position(|s| s.song._id.as_ref() == Some(&song_id));
+                if let Some(from) = from {
+                    if to < songs.len() {
+                        let song = songs.remove(from);
+                        songs.insert(to, song);
+                        moved = true;
+                    }
+                }
+            });
+
+            if moved {
+                spawn_local(async move {
+                    if let Err(e) = move_in_playlist(playlist_id, song_id, to).await {
+                        tracing::error!("Failed to move song in playlist: {:?}", e);
+                    }
+                });
+            }
+        }));
+
+    // Songs of smart playlists are ordered by their rules
+    let is_smart =
+        Memo::new(move |_| playlist.with(|p| p.as_ref().is_some_and(|p| p.smart_rules.is_some())));
+
     let is_mobile =
         create_read_slice(expect_context::<RwSignal<UiStore>>(), |u| u.get_is_mobile()).get();
 
-    view! {
-        <SongView
-            default_details=default_details
-            songs=filtered_songs
-            icons=icons
-            selected_songs=selected_songs
-            refresh_cb=refresh_songs
-            fetch_next_page=fetch_next_page
-            show_mobile_default_details=is_mobile
-            is_loading=is_loading
-        />
+    move || {
+        let on_reorder = if is_smart.get() {
+            None
+        } else {
+            Some(on_reorder.clone())
+        };
+        view! {
+            <SongView
+                default_details=default_details
+                songs=filtered_songs
+                icons=icons
+                selected_songs=selected_songs
+                refresh_cb=refresh_songs
+                fetch_next_page=fetch_next_page.clone()
+                show_mobile_default_details=is_mobile
+                is_loading=is_loading
+                on_reorder=on_reorder
+            />
+        }
     }
 }
 
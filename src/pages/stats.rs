@@ -0,0 +1,273 @@
+// Moosync
+// Copyright (C) 2024, 2025  Moosync <support@moosync.app>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use chrono::Local;
+use leptos::{component, prelude::*, view, IntoView};
+use leptos_i18n::t;
+use types::stats::{ListeningStats, StatsEntry, StatsExportFormat, StatsPeriod};
+use wasm_bindgen_futures::spawn_local;
+
+use crate::{
+    i18n::use_i18n,
+    pages::history::{ListeningRange, ListeningRangeSelect},
+    utils::{
+        common::format_duration,
+        invoke::{export_listening_stats, get_listening_stats},
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Granularity {
+    Day,
+    Week,
+    Month,
+}
+
+/// Minutes the local timezone is ahead of UTC, used to group plays into local days
+#[tracing::instrument(level = "debug", skip())]
+fn utc_offset() -> i32 {
+    Local::now().offset().local_minus_utc() / 60
+}
+
+#[tracing::instrument(level = "debug", skip(stats))]
+fn fetch_stats(range: ListeningRange, stats: RwSignal<Option<ListeningStats>>) {
+    spawn_local(async move {
+        match get_listening_stats(range.start(), range.end(), utc_offset()).await {
+            Ok(res) => stats.set(Some(res)),
+            Err(e) => tracing::error!("Failed to fetch listening stats {:?}", e),
+        }
+    });
+}
+
+#[tracing::instrument(level = "debug", skip(title, value))]
+#[component]
+fn StatsCard(#[prop()] title: AnyView, #[prop()] value: String) -> impl IntoView {
+    view! {
+        <div class="col-auto stats-card">
+            <div class="stats-card-title">{title}</div>
+            <div class="stats-card-value">{value}</div>
+        </div>
+    }
+}
+
+#[tracing::instrument(level = "debug", skip(title, entries))]
+#[component]
+fn StatsList(#[prop()] title: AnyView, #[prop()] entries: Vec<StatsEntry>) -> impl IntoView {
+    let i18n = use_i18n();
+    view! {
+        <div class="col-lg-4 col-md-6 stats-list">
+            <div class="stats-list-title">{title}</div>
+            {if entries.is_empty() {
+                view! { <div class="history-details">{t!(i18n, stats.no_data)}</div> }.into_any()
+            } else {
+                entries
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, entry)| {
+                        view! {
+                            <div class="d-flex stats-list-item">
+                                <div class="stats-list-rank">{i + 1}</div>
+                                <div class="text-truncate">{entry.name}</div>
+                                <div class="ml-auto history-details">
+                                    {format_duration(entry.play_time, false)}
+                                </div>
+                            </div>
+                        }
+                    })
+                    .collect_view()
+                    .into_any()
+            }}
+        </div>
+    }
+}
+
+#[tracing::instrument(level = "debug", skip(periods))]
+#[component]
+fn StatsChart(#[prop()] periods: Vec<StatsPeriod>) -> impl IntoView {
+    let max = periods.iter().map(|p| p.play_time).fold(0f64, f64::max);
+    view! {
+        <div class="d-flex align-items-end stats-chart">
+            {periods
+                .into_iter()
+                .map(|p| {
+                    let height = if max > 0f64 { p.play_time / max * 100f64 } else { 0f64 };
+                    let title = format!("{}: {}", p.period, format_duration(p.play_time, false));
+                    view! {
+                        <div class="stats-chart-bar" title=title style:height=format!("{}%", height)></div>
+                    }
+                })
+                .collect_view()}
+        </div>
+    }
+}
+
+#[tracing::instrument(level = "debug", skip())]
+#[component]
+pub fn Stats() -> impl IntoView {
+    let range = RwSignal::new(ListeningRange::Year);
+    let granularity = RwSignal::new(Granularity::Day);
+    let stats = RwSignal::new(None);
+    Effect::new(move || fetch_stats(range.get(), stats));
+
+    let export = move |format: StatsExportFormat| {
+        let range = range.get_untracked();
+        spawn_local(async move {
+            if let Err(e) =
+                export_listening_stats(range.start(), range.end(), utc_offset(), format).await
+            {
+                tracing::error!("Failed to export listening stats {:?}", e);
+            }
+        });
+    };
+
+    let i18n = use_i18n();
+    view! {
+        <div class="w-100 h-100">
+            <div class="container-fluid song-container h-100 d-flex flex-column">
+                <div class="row page-title no-gutters">
+                    <div class="col-auto">{t!(i18n, pages.stats)}</div>
+                    <div class="col align-self-center"></div>
+                    <div class="col-auto d-flex align-items-center">
+                        <ListeningRangeSelect range=range />
+                        <button
+                            class="btn btn-secondary create-button ml-3"
+                            on:click=move |_| export(StatsExportFormat::Json)
+                        >
+                            {t!(i18n, stats.export_json)}
+                        </button>
+                        <button
+                            class="btn btn-secondary create-button ml-3"
+                            on:click=move |_| export(StatsExportFormat::Csv)
+                        >
+                            {t!(i18n, stats.export_csv)}
+                        </button>
+                    </div>
+                </div>
+
+                <div class="stats-page">
+                    {move || {
+                        stats
+                            .get()
+                            .map(|stats| {
+                                let streak = stats
+                                    .longest_streak
+                                    .as_ref()
+                                    .map(|s| format!("{} ({} - {})", s.days, s.start, s.end))
+                                    .unwrap_or("-".to_string());
+                                view! {
+                                    <div class="row no-gutters">
+                                        <StatsCard
+                                            title=t!(i18n, stats.play_time).into_any()
+                                            value=format_duration(stats.play_time, true)
+                                        />
+                                        <StatsCard
+                                            title=t!(i18n, stats.play_count).into_any()
+                                            value=stats.play_count.to_string()
+                                        />
+                                        <StatsCard
+                                            title=t!(i18n, stats.skip_rate).into_any()
+                                            value=format!("{:.0}%", stats.skip_rate * 100f64)
+                                        />
+                                        <StatsCard
+                                            title=t!(i18n, stats.longest_streak).into_any()
+                                            value=streak
+                                        />
+                                    </div>
+
+                                    <div class="row no-gutters align-items-center mt-4">
+                                        <div class="col-auto stats-list-title">
+                                            {t!(i18n, stats.listening_time)}
+                                        </div>
+                                        <div class="col"></div>
+                                        <div class="col-auto">
+                                            <select
+                                                class="dropdown-list"
+                                                on:change=move |ev| {
+                                                    granularity
+                                                        .set(
+                                                            match event_target_value(&ev).as_str() {
+                                                                "week" => Granularity::Week,
+                                                                "month" => Granularity::Month,
+                                                                _ => Granularity::Day,
+                                                            },
+                                                        )
+                                                }
+                                            >
+                                                <option
+                                                    value="day"
+                                                    prop:selected=move || granularity.get() == Granularity::Day
+                                                >
+                                                    {t!(i18n, stats.per_day)}
+                                                </option>
+                                                <option
+                                                    value="week"
+                                                    prop:selected=move || granularity.get() == Granularity::Week
+                                                >
+                                                    {t!(i18n, stats.per_week)}
+                                                </option>
+                                                <option
+                                                    value="month"
+                                                    prop:selected=move || granularity.get() == Granularity::Month
+                                                >
+                                                    {t!(i18n, stats.per_month)}
+                                                </option>
+                                            </select>
+                                        </div>
+                                    </div>
+                                    {
+                                        let days = stats.days.clone();
+                                        let weeks = stats.weeks.clone();
+                                        let months = stats.months.clone();
+                                        move || {
+                                            let periods = match granularity.get() {
+                                                Granularity::Day => days.clone(),
+                                                Granularity::Week => weeks.clone(),
+                                                Granularity::Month => months.clone(),
+                                            };
+                                            view! { <StatsChart periods=periods /> }
+                                        }
+                                    }
+
+                                    <div class="row no-gutters mt-4">
+                                        <StatsList
+                                            title=t!(i18n, stats.top_songs).into_any()
+                                            entries=stats.top_songs
+                                        />
+                                        <StatsList
+                                            title=t!(i18n, stats.top_artists).into_any()
+                                            entries=stats.top_artists
+                                        />
+                                        <StatsList
+                                            title=t!(i18n, stats.top_albums).into_any()
+                                            entries=stats.top_albums
+                                        />
+                                        <StatsList
+                                            title=t!(i18n, stats.top_genres).into_any()
+                                            entries=stats.top_genres
+                                        />
+                                        <StatsList
+                                            title=t!(i18n, stats.new_songs).into_any()
+                                            entries=stats.new_songs
+                                        />
+                                    </div>
+                                }
+                            })
+                    }}
+                </div>
+            </div>
+        </div>
+    }
+}
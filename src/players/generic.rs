@@ -30,7 +30,13 @@ pub type PlayerEventsSender = Rc<Box<dyn Fn(String, PlayerEvents)>>;
 pub trait GenericPlayer: std::fmt::Debug + DynClone {
     fn initialize(&self, element: NodeRef<Div>);
     fn key(&self) -> String;
-    fn load(&self, src: String, autoplay: bool, resolver: OneShotSender<()>);
+    /// Loads `src`. `id` is the id of the song it belongs to, for players which cache songs
+    fn load(&self, src: String, id: Option<String>, autoplay: bool, resolver: OneShotSender<()>);
+    /// Prepares the next song so it starts without a gap, or crossfades into it if
+    /// `crossfade` is set. Players which can't do this just load the song when it becomes current
+    fn preload(&self, _src: String, _id: Option<String>, _crossfade: bool) {}
+    /// Drops the preloaded song once another song comes next
+    fn cancel_preload(&self) {}
     fn stop(&mut self) -> Result<()>;
     fn play(&self) -> Result<()>;
     fn pause(&self) -> Result<()>;
@@ -127,7 +127,7 @@ impl GenericPlayer for LocalPlayer {
     }
 
     #[tracing::instrument(level = "debug", skip(self, src, resolver))]
-    fn load(&self, src: String, autoplay: bool, resolver: OneShotSender<()>) {
+    fn load(&self, src: String, _id: Option<String>, autoplay: bool, resolver: OneShotSender<()>) {
         let mut src = convert_file_src(src);
         tracing::debug!("Loading audio {}", src);
 
@@ -207,6 +207,11 @@ impl GenericPlayer for LocalPlayer {
 
     #[tracing::instrument(level = "debug", skip(self, song))]
     fn can_play(&self, song: &types::songs::Song) -> bool {
+        // Tracks of CUE sheets only play a part of their file, which needs rodio
+        if song.song.is_cue_track() {
+            return false;
+        }
+
         let playback_url = song
             .song
             .path
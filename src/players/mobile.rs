@@ -116,7 +116,7 @@ impl GenericPlayer for MobilePlayer {
     }
 
     #[tracing::instrument(level = "debug", skip(self, src, resolver))]
-    fn load(&self, src: String, autoplay: bool, resolver: OneShotSender<()>) {
+    fn load(&self, src: String, _id: Option<String>, autoplay: bool, resolver: OneShotSender<()>) {
         tracing::debug!("Loading audio {}", src);
 
         let key = self.key.clone();
@@ -14,19 +14,16 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::{cell::RefCell, rc::Rc, sync::Mutex, time::Duration};
-
-use leptos::{
-    leptos_dom::helpers::IntervalHandle,
-    prelude::{set_interval_with_handle, NodeRef},
-    task::spawn_local,
-};
+use leptos::{prelude::NodeRef, task::spawn_local};
 use types::{songs::SongType, ui::player_details::PlayerEvents};
 use wasm_bindgen::JsValue;
 
 use crate::utils::{
     common::{convert_file_src, listen_event},
-    invoke::{rodio_load, rodio_pause, rodio_play, rodio_seek, rodio_set_volume, rodio_stop},
+    invoke::{
+        rodio_cancel_preload, rodio_load, rodio_pause, rodio_play, rodio_preload, rodio_seek,
+        rodio_set_volume, rodio_stop,
+    },
 };
 
 use super::generic::{GenericPlayer, PlayerEventsSender};
@@ -34,18 +31,12 @@ use super::generic::{GenericPlayer, PlayerEventsSender};
 #[derive(Debug, Clone)]
 pub struct RodioPlayer {
     unlisten: Option<js_sys::Function>,
-    timer: Rc<Mutex<Option<IntervalHandle>>>,
-    time: Rc<Mutex<f64>>,
 }
 
 impl RodioPlayer {
     #[tracing::instrument(level = "debug", skip())]
     pub fn new() -> Self {
-        Self {
-            unlisten: None,
-            timer: Default::default(),
-            time: Default::default(),
-        }
+        Self { unlisten: None }
     }
 }
 
@@ -59,9 +50,15 @@ impl GenericPlayer for RodioPlayer {
     }
 
     #[tracing::instrument(level = "debug", skip(self, src, resolver))]
-    fn load(&self, src: String, autoplay: bool, resolver: tokio::sync::oneshot::Sender<()>) {
+    fn load(
+        &self,
+        src: String,
+        id: Option<String>,
+        autoplay: bool,
+        resolver: tokio::sync::oneshot::Sender<()>,
+    ) {
         spawn_local(async move {
-            let res = rodio_load(src).await;
+            let res = rodio_load(src, id).await;
             if let Err(err) = res {
                 tracing::error!("Rodio error {:?}", err);
             } else {
@@ -72,6 +69,24 @@ impl GenericPlayer for RodioPlayer {
         });
     }
 
+    #[tracing::instrument(level = "debug", skip(self, src, crossfade))]
+    fn preload(&self, src: String, id: Option<String>, crossfade: bool) {
+        spawn_local(async move {
+            if let Err(err) = rodio_preload(src, id, crossfade).await {
+                tracing::error!("Error preloading {:?}", err);
+            }
+        });
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    fn cancel_preload(&self) {
+        spawn_local(async move {
+            if let Err(err) = rodio_cancel_preload().await {
+                tracing::error!("Error cancelling preload {:?}", err);
+            }
+        });
+    }
+
     #[tracing::instrument(level = "debug", skip(self))]
     fn stop(&mut self) -> types::errors::Result<()> {
         let unlisten = self.unlisten.take();
@@ -89,15 +104,6 @@ impl GenericPlayer for RodioPlayer {
             }
         });
 
-        let mut timer = self.timer.lock().unwrap();
-        if timer.is_some() {
-            let handle = timer.unwrap();
-            handle.clear();
-        }
-        *timer = None;
-
-        *self.time.lock().unwrap() = 0f64;
-
         Ok(())
     }
 
@@ -142,6 +148,7 @@ impl GenericPlayer for RodioPlayer {
             SongType::LOCAL,
             SongType::URL,
             SongType::HLS,
+            SongType::DASH,
             SongType::SPOTIFY,
         ]
     }
@@ -189,82 +196,16 @@ impl GenericPlayer for RodioPlayer {
         }
 
         tracing::debug!("Adding rodio listeners");
-        let start_timer =
-            |timer: Rc<Mutex<Option<IntervalHandle>>>, time: Rc<Mutex<f64>>, tx: Callback| {
-                tracing::debug!("Starting timer");
-                let mut timer = timer.lock().unwrap();
-                if timer.is_some() {
-                    let handle = timer.unwrap();
-                    handle.clear();
-                }
-                let res = set_interval_with_handle(
-                    move || {
-                        let mut time = time.lock().unwrap();
-                        *time += 1f64;
-                        let tx = tx.borrow_mut();
-                        tx("rodio".into(), PlayerEvents::TimeUpdate(*time));
-                    },
-                    Duration::from_secs(1),
-                )
-                .unwrap();
-                *timer = Some(res);
-            };
-
-        type Callback = RefCell<PlayerEventsSender>;
-
-        let stop_timer = |timer: Rc<Mutex<Option<IntervalHandle>>>, _, _| {
-            tracing::debug!("pausing timer");
-            let mut timer = timer.lock().unwrap();
-            if timer.is_some() {
-                let handle = timer.unwrap();
-                handle.clear();
-            }
-            *timer = None;
-        };
-
-        let stop_and_clear_timer =
-            |timer: Rc<Mutex<Option<IntervalHandle>>>, time: Rc<Mutex<f64>>, tx: Callback| {
-                tracing::debug!("Stopping timer");
-                let mut timer = timer.lock().unwrap();
-                if timer.is_some() {
-                    let handle = timer.unwrap();
-                    handle.clear();
-                }
-                *timer = None;
-
-                *time.lock().unwrap() = 0f64;
-                let tx = tx.borrow_mut();
-                tx("rodio".into(), PlayerEvents::TimeUpdate(0f64));
-            };
-
-        let tx = RefCell::new(state_setter);
-        let timer = self.timer.clone();
-        let time = self.time.clone();
-
+        // The backend reports the playback position itself while playing
         let unlisten = listen_event("rodio_event", move |data| {
             tracing::debug!("Got rodio event {:?}", data);
             let payload = js_sys::Reflect::get(&data, &JsValue::from_str("payload")).unwrap();
             let event: PlayerEvents = serde_wasm_bindgen::from_value(payload).unwrap();
 
-            match event {
-                PlayerEvents::Play => start_timer(timer.clone(), time.clone(), tx.clone()),
-                PlayerEvents::Pause => stop_timer(timer.clone(), time.clone(), tx.clone()),
-                PlayerEvents::Ended => {
-                    stop_and_clear_timer(timer.clone(), time.clone(), tx.clone())
-                }
-                PlayerEvents::Loading => stop_timer(timer.clone(), time.clone(), tx.clone()),
-                PlayerEvents::TimeUpdate(pos) => {
-                    let time = time.clone();
-                    *time.lock().unwrap() = pos;
-
-                    let tx = tx.borrow_mut();
-                    tx("rodio".into(), PlayerEvents::TimeUpdate(pos));
-                }
-                PlayerEvents::Error(_) => stop_timer(timer.clone(), time.clone(), tx.clone()),
+            if matches!(event, PlayerEvents::Ended) {
+                state_setter("rodio".into(), PlayerEvents::TimeUpdate(0f64));
             }
-
-            let tx = tx.borrow_mut();
-            tx("rodio".into(), event);
+            state_setter("rodio".into(), event);
         });
         self.unlisten = Some(unlisten);
     }
@@ -114,7 +114,7 @@ fn generate_component(config: &PreferenceUIFile) -> proc_macro2::TokenStream {
 
     quote! {
         use crate::components::{
-            prefs::components::{CheckboxPref, ExtensionPref, InputPref, PathsPref, ThemesPref, DropdownPref},
+            prefs::components::{CheckboxPref, ExtensionPref, InputPref, PathsPref, ThemesPref, DropdownPref, EqualizerPref, OutputDevicePref, AudioCachePref},
             sidebar::{Sidebar, Tab},
         };
         use crate::i18n::*;
@@ -179,6 +179,9 @@ fn generate_children(data: &[PreferenceUIData]) -> Vec<(syn::Ident, proc_macro2:
             types::preferences::PreferenceTypes::ThemeSelector => generate_themes(item),
             types::preferences::PreferenceTypes::Extensions => generate_extensions(item),
             types::preferences::PreferenceTypes::Dropdown => generate_dropdowns(item),
+            types::preferences::PreferenceTypes::Equalizer => generate_equalizer(item),
+            types::preferences::PreferenceTypes::OutputDevice => generate_output_device(item),
+            types::preferences::PreferenceTypes::AudioCache => generate_audio_cache(item),
             types::preferences::PreferenceTypes::ButtonGroup
             | types::preferences::PreferenceTypes::InfoField
             | types::preferences::PreferenceTypes::ProgressBar
@@ -405,3 +408,87 @@ fn generate_dropdowns(data: &PreferenceUIData) -> (syn::Ident, proc_macro2::Toke
 
     (fn_name, stream)
 }
+
+#[tracing::instrument(level = "debug", skip(data))]
+fn generate_equalizer(data: &PreferenceUIData) -> (syn::Ident, proc_macro2::TokenStream) {
+    let name = get_path(data.title.clone());
+    let mobile = data.mobile.unwrap_or(true);
+
+    let tooltip = get_path(data.description.clone());
+
+    let fn_name = syn::Ident::new(
+        format!("Equalizer{}Pref", data.key)
+            .replace(".", "")
+            .as_str(),
+        proc_macro2::Span::call_site(),
+    );
+
+    let stream = quote! {
+        #[component]
+        pub fn #fn_name() -> impl IntoView {
+            let i18n = use_i18n();
+
+            view !{
+                <EqualizerPref title=t!(i18n, #name) tooltip=t!(i18n, #tooltip) mobile=#mobile />
+            }
+        }
+    };
+
+    (fn_name, stream)
+}
+
+#[tracing::instrument(level = "debug", skip(data))]
+fn generate_output_device(data: &PreferenceUIData) -> (syn::Ident, proc_macro2::TokenStream) {
+    let name = get_path(data.title.clone());
+    let mobile = data.mobile.unwrap_or(true);
+
+    let tooltip = get_path(data.description.clone());
+
+    let fn_name = syn::Ident::new(
+        format!("OutputDevice{}Pref", data.key)
+            .replace(".", "")
+            .as_str(),
+        proc_macro2::Span::call_site(),
+    );
+
+    let stream = quote! {
+        #[component]
+        pub fn #fn_name() -> impl IntoView {
+            let i18n = use_i18n();
+
+            view !{
+                <OutputDevicePref title=t!(i18n, #name) tooltip=t!(i18n, #tooltip) mobile=#mobile />
+            }
+        }
+    };
+
+    (fn_name, stream)
+}
+
+#[tracing::instrument(level = "debug", skip(data))]
+fn generate_audio_cache(data: &PreferenceUIData) -> (syn::Ident, proc_macro2::TokenStream) {
+    let name = get_path(data.title.clone());
+    let mobile = data.mobile.unwrap_or(true);
+
+    let tooltip = get_path(data.description.clone());
+
+    let fn_name = syn::Ident::new(
+        format!("AudioCache{}Pref", data.key)
+            .replace(".", "")
+            .as_str(),
+        proc_macro2::Span::call_site(),
+    );
+
+    let stream = quote! {
+        #[component]
+        pub fn #fn_name() -> impl IntoView {
+            let i18n = use_i18n();
+
+            view !{
+                <AudioCachePref title=t!(i18n, #name) tooltip=t!(i18n, #tooltip) mobile=#mobile />
+            }
+        }
+    };
+
+    (fn_name, stream)
+}
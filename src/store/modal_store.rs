@@ -21,7 +21,7 @@ use types::{
     ui::{themes::ThemeModalState, updater::UpdateMetadata},
 };
 
-use crate::modals::new_playlist_modal::PlaylistModalState;
+use crate::modals::{jukebox_modal::JukeboxModalState, new_playlist_modal::PlaylistModalState};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Modals {
@@ -32,6 +32,8 @@ pub enum Modals {
     SongFromUrlModal,
     ThemeModal(Box<ThemeModalState>),
     UpdateModal(UpdateMetadata),
+    JukeboxModal(JukeboxModalState),
+    TagEditorModal(Vec<Song>),
 }
 
 #[derive(Clone, Default)]
@@ -25,7 +25,7 @@ use types::{
     preferences::CheckboxPreference,
     songs::Song,
     ui::extensions::ExtensionExtraEvent,
-    ui::player_details::{PlayerState, RepeatModes, VolumeMode},
+    ui::player_details::{PlayerState, RepeatModes, SleepTimer, VolumeMode},
 };
 use wasm_bindgen_futures::spawn_local;
 
@@ -34,7 +34,7 @@ use crate::{
     utils::{
         db_utils::{read_from_indexed_db, write_to_indexed_db},
         extensions::send_extension_event,
-        mpris::{set_playback_state, set_position},
+        mpris::{set_metadata, set_playback_state, set_position},
     },
 };
 
@@ -70,12 +70,50 @@ pub struct PlayerStoreData {
     pub force_load_song: bool,
 }
 
+/// Seconds over which the volume fades out before the sleep timer stops playback
+const SLEEP_TIMER_FADE: f64 = 15f64;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct SleepTimerState {
+    timer: SleepTimer,
+    /// Unix time in milliseconds at which playback stops, for timers measured in minutes
+    deadline: Option<f64>,
+    /// Tracks left to play including the current one, for timers measured in tracks
+    tracks_left: usize,
+}
+
+/// State of an active jukebox session. Guests may only request songs,
+/// which are appended to the queue and attributed to them.
+#[derive(Debug, Default, Clone)]
+pub struct Jukebox {
+    pub guest: String,
+    /// Maximum number of pending requests per guest. 0 means unlimited
+    pub request_limit: usize,
+    /// Song id -> guest who requested it
+    requests: HashMap<String, String>,
+}
+
+impl Jukebox {
+    pub fn new(request_limit: usize) -> Self {
+        Self {
+            request_limit,
+            ..Default::default()
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct PlayerStore {
     pub data: PlayerStoreData,
     scrobble_time: f64,
     scrobbled: bool,
     is_mobile: bool,
+    /// Duration of the current song as reported by the player
+    reported_duration: Option<f64>,
+    jukebox: Option<Jukebox>,
+    sleep_timer: Option<SleepTimerState>,
+    /// Volume multiplier applied while the sleep timer fades out playback
+    sleep_fade: f64,
 }
 
 #[derive(Debug)]
@@ -100,6 +138,10 @@ impl PlayerStore {
             scrobble_time: 0f64,
             scrobbled: false,
             is_mobile,
+            reported_duration: None,
+            jukebox: None,
+            sleep_timer: None,
+            sleep_fade: 1f64,
         };
 
         tracing::debug!("Created player store {:?}", player_store);
@@ -210,12 +252,17 @@ impl PlayerStore {
 
         self.scrobble_time = 0f64;
         self.scrobbled = false;
+        self.reported_duration = None;
 
         self.dump_store(&[DumpType::CurrentIndex, DumpType::PlayerState]);
     }
 
     #[tracing::instrument(level = "debug", skip(self, songs))]
     pub fn add_to_queue(&mut self, songs: Vec<Song>) {
+        if self.is_jukebox() {
+            self.request_songs(songs);
+            return;
+        }
         self.add_to_queue_at_index(songs, self.data.queue.song_queue.len());
         self.update_current_song(false);
     }
@@ -233,6 +280,9 @@ impl PlayerStore {
 
     #[tracing::instrument(level = "debug", skip(self, index))]
     pub fn remove_from_queue(&mut self, index: usize) {
+        if self.is_jukebox() {
+            return;
+        }
         self.data.queue.song_queue.remove(index);
         if self.data.queue.current_index > index {
             self.data.queue.current_index -= 1;
@@ -259,6 +309,10 @@ impl PlayerStore {
 
     #[tracing::instrument(level = "debug", skip(self, song))]
     pub fn play_now(&mut self, song: Song) {
+        if self.is_jukebox() {
+            self.request_songs(vec![song]);
+            return;
+        }
         self.set_state(PlayerState::Playing);
         self.insert_song_at_index(song, self.data.queue.current_index + 1, true);
         self.data.queue.current_index += 1;
@@ -271,6 +325,11 @@ impl PlayerStore {
             return;
         }
 
+        if self.is_jukebox() {
+            self.request_songs(songs);
+            return;
+        }
+
         let first_song = songs.first();
         if let Some(first_song) = first_song {
             self.play_now(first_song.clone())
@@ -283,6 +342,10 @@ impl PlayerStore {
 
     #[tracing::instrument(level = "debug", skip(self, song))]
     pub fn play_next(&mut self, song: Song) {
+        if self.is_jukebox() {
+            self.request_songs(vec![song]);
+            return;
+        }
         self.insert_song_at_index(song, self.data.queue.current_index + 1, true);
     }
 
@@ -292,6 +355,11 @@ impl PlayerStore {
             return;
         }
 
+        if self.is_jukebox() {
+            self.request_songs(songs);
+            return;
+        }
+
         let first_song = songs.first();
         if let Some(first_song) = first_song {
             self.play_next(first_song.clone())
@@ -304,6 +372,9 @@ impl PlayerStore {
 
     #[tracing::instrument(level = "debug", skip(self, new_index))]
     pub fn change_index(&mut self, new_index: usize, force: bool) {
+        if self.is_jukebox() {
+            return;
+        }
         self.data.queue.current_index = new_index;
         self.update_current_song(force);
     }
@@ -321,6 +392,7 @@ impl PlayerStore {
         }
 
         set_position(new_time);
+        self.tick_sleep_timer();
     }
 
     #[tracing::instrument(level = "debug", skip(self))]
@@ -328,18 +400,38 @@ impl PlayerStore {
         self.data.player_details.current_time
     }
 
+    /// Streams may lack a duration in their metadata, or have a wrong one,
+    /// so the duration reported by the player takes precedence
+    #[tracing::instrument(level = "debug", skip(self, duration))]
+    pub fn update_duration(&mut self, duration: f64) {
+        if duration <= 0f64 || self.reported_duration == Some(duration) {
+            return;
+        }
+        self.reported_duration = Some(duration);
+
+        if let Some(mut current_song) = self.data.current_song.clone() {
+            current_song.song.duration = Some(duration);
+            set_metadata(&current_song);
+        }
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn get_current_duration(&self) -> Option<f64> {
+        self.reported_duration.or(self
+            .data
+            .current_song
+            .as_ref()
+            .and_then(|s| s.song.duration))
+    }
+
     #[tracing::instrument(level = "debug", skip(self, new_time))]
     pub fn force_seek_percent(&mut self, new_time: f64) {
-        let new_time_c = if let Some(current_song) = &self.data.current_song {
-            current_song.song.duration.unwrap_or_default() * new_time
-        } else {
-            0f64
-        };
+        let new_time_c = self.get_current_duration().unwrap_or_default() * new_time;
 
         tracing::debug!(
             "Got seek {}, {:?}, {}",
             new_time,
-            self.data.current_song.clone().map(|c| c.song.duration),
+            self.get_current_duration(),
             new_time_c
         );
         self.data.player_details.force_seek = new_time_c;
@@ -355,6 +447,19 @@ impl PlayerStore {
     #[tracing::instrument(level = "debug", skip(self, state))]
     pub fn set_state(&mut self, state: PlayerState) {
         tracing::debug!("Setting player state {:?}", state);
+        if state == PlayerState::Playing {
+            // A timer which ran out while paused shouldn't stop playback right after resuming
+            if self
+                .sleep_timer
+                .and_then(|t| t.deadline)
+                .is_some_and(|d| d <= js_sys::Date::now())
+            {
+                self.sleep_timer = None;
+            }
+            if self.sleep_timer.is_none() {
+                self.sleep_fade = 1f64;
+            }
+        }
         self.data.player_details.state = state;
         self.dump_store(&[DumpType::PlayerState]);
 
@@ -484,8 +589,34 @@ impl PlayerStore {
         self.update_current_song(true);
     }
 
+    /// Song which will be played once the current one ends, taking repeat modes into account
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn get_upcoming_song(&self) -> Option<Song> {
+        if self.stops_after_current() {
+            return None;
+        }
+
+        let index = match self.data.player_details.repeat {
+            RepeatModes::Loop => self.data.queue.current_index,
+            RepeatModes::Once if !self.data.player_details.has_repeated => {
+                self.data.queue.current_index
+            }
+            _ => self.data.queue.current_index + 1,
+        };
+        let index = if index >= self.data.queue.song_queue.len() {
+            0
+        } else {
+            index
+        };
+        let id = self.data.queue.song_queue.get(index)?;
+        self.data.queue.data.get(id).cloned()
+    }
+
     #[tracing::instrument(level = "debug", skip(self))]
     pub fn prev_song(&mut self) {
+        if self.is_jukebox() {
+            return;
+        }
         if self.data.queue.current_index == 0 {
             self.data.queue.current_index = self.data.queue.song_queue.len() - 1;
         } else {
@@ -496,6 +627,9 @@ impl PlayerStore {
 
     #[tracing::instrument(level = "debug", skip(self))]
     pub fn toggle_repeat(&mut self) {
+        if self.is_jukebox() {
+            return;
+        }
         let new_mode = match self.data.player_details.repeat {
             RepeatModes::None => RepeatModes::Once,
             RepeatModes::Once => RepeatModes::Loop,
@@ -508,6 +642,9 @@ impl PlayerStore {
 
     #[tracing::instrument(level = "debug", skip(self))]
     pub fn shuffle_queue(&mut self) {
+        if self.is_jukebox() {
+            return;
+        }
         let binding = self.data.queue.song_queue.clone();
         let current_song = binding.get(self.data.queue.current_index).unwrap();
         let mut rng = rand::rng();
@@ -526,6 +663,9 @@ impl PlayerStore {
 
     #[tracing::instrument(level = "debug", skip(self))]
     pub fn clear_queue(&mut self) {
+        if self.is_jukebox() {
+            return;
+        }
         self.data.queue.song_queue.clear();
         self.data.queue.current_index = 0;
         self.update_current_song(false);
@@ -533,6 +673,9 @@ impl PlayerStore {
 
     #[tracing::instrument(level = "debug", skip(self))]
     pub fn clear_queue_except_current(&mut self) {
+        if self.is_jukebox() {
+            return;
+        }
         let current_song = self.get_current_song();
 
         let only_one_song = self.get_queue().song_queue.len() == 1;
@@ -549,6 +692,261 @@ impl PlayerStore {
         self.dump_store(&[DumpType::QueueData, DumpType::SongQueue]);
     }
 
+    #[tracing::instrument(level = "debug", skip(self, timer))]
+    pub fn set_sleep_timer(&mut self, timer: Option<SleepTimer>) {
+        self.sleep_timer = timer.map(|timer| {
+            let (deadline, tracks_left) = match timer {
+                SleepTimer::Minutes(minutes) => {
+                    (Some(js_sys::Date::now() + minutes * 60_000f64), 0)
+                }
+                SleepTimer::EndOfTrack => (None, 1),
+                SleepTimer::Tracks(tracks) => (None, tracks.max(1)),
+            };
+            SleepTimerState {
+                timer,
+                deadline,
+                tracks_left,
+            }
+        });
+        self.sleep_fade = 1f64;
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn get_sleep_timer(&self) -> Option<SleepTimer> {
+        self.sleep_timer.map(|t| t.timer)
+    }
+
+    /// Seconds until the sleep timer stops playback, if already known
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn get_sleep_timer_remaining(&self) -> Option<f64> {
+        let timer = self.sleep_timer?;
+        if let Some(deadline) = timer.deadline {
+            return Some(((deadline - js_sys::Date::now()) / 1000f64).max(0f64));
+        }
+
+        if timer.tracks_left == 1 {
+            let duration = self.get_current_duration()?;
+            return Some((duration - self.data.player_details.current_time).max(0f64));
+        }
+        None
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn get_sleep_tracks_left(&self) -> Option<usize> {
+        self.sleep_timer
+            .filter(|t| t.deadline.is_none())
+            .map(|t| t.tracks_left)
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn get_sleep_fade(&self) -> f64 {
+        self.sleep_fade
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    fn stops_after_current(&self) -> bool {
+        self.sleep_timer
+            .is_some_and(|t| t.deadline.is_none() && t.tracks_left <= 1)
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    fn tick_sleep_timer(&mut self) {
+        let Some(remaining) = self.get_sleep_timer_remaining() else {
+            return;
+        };
+
+        let is_timed = self.sleep_timer.is_some_and(|t| t.deadline.is_some());
+        if is_timed && remaining <= 0f64 {
+            tracing::info!("Sleep timer ran out, stopping playback");
+            self.sleep_timer = None;
+            self.set_state(PlayerState::Stopped);
+            return;
+        }
+
+        self.sleep_fade = (remaining / SLEEP_TIMER_FADE).clamp(0f64, 1f64);
+    }
+
+    /// Counts down track based sleep timers once a track ends. Returns true if playback should stop
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn consume_sleep_track(&mut self) -> bool {
+        let Some(timer) = self.sleep_timer.as_mut() else {
+            return false;
+        };
+        if timer.deadline.is_some() {
+            return false;
+        }
+
+        timer.tracks_left = timer.tracks_left.saturating_sub(1);
+        if timer.tracks_left > 0 {
+            return false;
+        }
+
+        tracing::info!("Sleep timer reached its last track, stopping playback");
+        self.sleep_timer = None;
+        true
+    }
+
+    /// Skips the current song, unless a jukebox guest tries to skip a song requested by someone else
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn skip_song(&mut self) {
+        if !self.can_skip_current() {
+            return;
+        }
+        self.next_song();
+    }
+
+    #[tracing::instrument(level = "debug", skip(self, jukebox))]
+    pub fn set_jukebox(&mut self, jukebox: Option<Jukebox>) {
+        self.jukebox = jukebox;
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn is_jukebox(&self) -> bool {
+        self.jukebox.is_some()
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn get_jukebox_guest(&self) -> Option<String> {
+        self.jukebox.as_ref().map(|j| j.guest.clone())
+    }
+
+    /// Only sets the guest if there is none yet, so guests can't rename themselves
+    /// to get around the request limit. The operator clears it with [Self::clear_jukebox_guest]
+    #[tracing::instrument(level = "debug", skip(self, guest))]
+    pub fn set_jukebox_guest(&mut self, guest: String) {
+        if let Some(jukebox) = self.jukebox.as_mut() {
+            if jukebox.guest.is_empty() {
+                jukebox.guest = guest.trim().to_string();
+            }
+        }
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn clear_jukebox_guest(&mut self) {
+        if let Some(jukebox) = self.jukebox.as_mut() {
+            jukebox.guest.clear();
+        }
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn get_jukebox_request_limit(&self) -> usize {
+        self.jukebox
+            .as_ref()
+            .map(|j| j.request_limit)
+            .unwrap_or_default()
+    }
+
+    #[tracing::instrument(level = "debug", skip(self, limit))]
+    pub fn set_jukebox_request_limit(&mut self, limit: usize) {
+        if let Some(jukebox) = self.jukebox.as_mut() {
+            jukebox.request_limit = limit;
+        }
+    }
+
+    /// Songs queued after the current one, along with the guest who requested them
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn get_upcoming_requests(&self) -> Vec<(Song, Option<String>)> {
+        let requests = self.jukebox.as_ref().map(|j| &j.requests);
+        self.data
+            .queue
+            .song_queue
+            .iter()
+            .skip(self.data.queue.current_index + 1)
+            .filter_map(|id| {
+                let song = self.data.queue.data.get(id)?.clone();
+                let guest = requests.and_then(|r| r.get(id)).cloned();
+                Some((song, guest))
+            })
+            .collect()
+    }
+
+    #[tracing::instrument(level = "debug", skip(self, guest))]
+    pub fn get_pending_requests(&self, guest: &str) -> usize {
+        self.get_upcoming_requests()
+            .iter()
+            .filter(|(_, g)| g.as_deref() == Some(guest))
+            .count()
+    }
+
+    /// Guest who requested the given song, if any
+    #[tracing::instrument(level = "debug", skip(self, id))]
+    pub fn get_requester(&self, id: &str) -> Option<String> {
+        self.jukebox
+            .as_ref()
+            .and_then(|j| j.requests.get(id))
+            .cloned()
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn can_skip_current(&self) -> bool {
+        let Some(jukebox) = self.jukebox.as_ref() else {
+            return true;
+        };
+        let current = self
+            .data
+            .current_song
+            .as_ref()
+            .and_then(|s| s.song._id.as_ref());
+        current.is_some_and(|id| {
+            !jukebox.guest.is_empty() && jukebox.requests.get(id) == Some(&jukebox.guest)
+        })
+    }
+
+    #[tracing::instrument(level = "debug", skip(self, songs))]
+    fn request_songs(&mut self, songs: Vec<Song>) {
+        let Some(jukebox) = self.jukebox.as_ref() else {
+            return;
+        };
+        let guest = jukebox.guest.clone();
+        let limit = jukebox.request_limit;
+
+        let upcoming: Vec<String> = self
+            .data
+            .queue
+            .song_queue
+            .iter()
+            .skip(self.data.queue.current_index + 1)
+            .cloned()
+            .collect();
+        let mut pending = self.get_pending_requests(&guest);
+
+        let mut accepted = vec![];
+        for song in songs {
+            if limit > 0 && pending >= limit {
+                tracing::info!("Guest {} reached the request limit", guest);
+                break;
+            }
+            let Some(id) = song.song._id.clone() else {
+                continue;
+            };
+            if upcoming.contains(&id) || accepted.iter().any(|s: &Song| s.song._id == song.song._id)
+            {
+                continue;
+            }
+            pending += 1;
+            accepted.push(song);
+        }
+
+        if accepted.is_empty() {
+            return;
+        }
+
+        if let Some(jukebox) = self.jukebox.as_mut() {
+            for song in accepted.iter() {
+                jukebox
+                    .requests
+                    .insert(song.song._id.clone().unwrap(), guest.clone());
+            }
+        }
+
+        let was_empty = self.data.queue.song_queue.is_empty();
+        self.add_to_queue_at_index(accepted, self.data.queue.song_queue.len());
+        self.update_current_song(false);
+        if was_empty {
+            self.set_state(PlayerState::Playing);
+        }
+    }
+
     #[tracing::instrument(level = "debug", skip(self, key))]
     pub fn blacklist_player(&mut self, key: String) {
         if self.data.player_blacklist.contains(&key) {
@@ -690,3 +1088,116 @@ impl PlayerStore {
         });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use types::songs::QueryableSong;
+
+    use super::*;
+
+    fn store(queue: &[&str], current_index: usize, repeat: RepeatModes) -> PlayerStore {
+        let data = queue
+            .iter()
+            .map(|id| {
+                let song = Song {
+                    song: QueryableSong {
+                        _id: Some(id.to_string()),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                };
+                (id.to_string(), song)
+            })
+            .collect();
+
+        PlayerStore {
+            data: PlayerStoreData {
+                queue: Queue {
+                    song_queue: queue.iter().map(|id| id.to_string()).collect(),
+                    current_index,
+                    data,
+                },
+                player_details: PlayerDetails {
+                    repeat,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            scrobble_time: 0f64,
+            scrobbled: false,
+            is_mobile: false,
+            reported_duration: None,
+            jukebox: None,
+            sleep_timer: None,
+            sleep_fade: 1f64,
+        }
+    }
+
+    fn upcoming_id(store: &PlayerStore) -> Option<String> {
+        store.get_upcoming_song().and_then(|s| s.song._id)
+    }
+
+    #[test]
+    fn test_upcoming_song() {
+        let store_1 = store(&["a", "b", "c"], 0, RepeatModes::None);
+        assert_eq!(upcoming_id(&store_1), Some("b".into()));
+
+        // The queue wraps around after the last song
+        let store_2 = store(&["a", "b", "c"], 2, RepeatModes::None);
+        assert_eq!(upcoming_id(&store_2), Some("a".into()));
+
+        assert_eq!(upcoming_id(&store(&[], 0, RepeatModes::None)), None);
+    }
+
+    #[test]
+    fn test_upcoming_song_repeat() {
+        let store_1 = store(&["a", "b", "c"], 1, RepeatModes::Loop);
+        assert_eq!(upcoming_id(&store_1), Some("b".into()));
+
+        // Repeating once plays the current song again, then moves on
+        let mut store_2 = store(&["a", "b", "c"], 1, RepeatModes::Once);
+        assert_eq!(upcoming_id(&store_2), Some("b".into()));
+        store_2.data.player_details.has_repeated = true;
+        assert_eq!(upcoming_id(&store_2), Some("c".into()));
+    }
+
+    #[test]
+    fn test_upcoming_song_stop_after_current() {
+        let mut store_1 = store(&["a", "b", "c"], 0, RepeatModes::None);
+        store_1.set_sleep_timer(Some(SleepTimer::EndOfTrack));
+        assert_eq!(upcoming_id(&store_1), None);
+
+        // Repeating doesn't keep playing past the sleep timer
+        store_1.data.player_details.repeat = RepeatModes::Loop;
+        assert_eq!(upcoming_id(&store_1), None);
+
+        store_1.set_sleep_timer(Some(SleepTimer::Tracks(1)));
+        assert_eq!(upcoming_id(&store_1), None);
+
+        store_1.data.player_details.repeat = RepeatModes::None;
+        store_1.set_sleep_timer(Some(SleepTimer::Tracks(2)));
+        assert_eq!(upcoming_id(&store_1), Some("b".into()));
+
+        store_1.set_sleep_timer(None);
+        assert_eq!(upcoming_id(&store_1), Some("b".into()));
+    }
+
+    #[test]
+    fn test_jukebox_guest() {
+        let mut store_1 = store(&["a"], 0, RepeatModes::None);
+        store_1.set_jukebox_guest("Alice".into());
+        assert_eq!(store_1.get_jukebox_guest(), None);
+
+        store_1.set_jukebox(Some(Jukebox::new(3)));
+        store_1.set_jukebox_guest(" Alice ".into());
+        assert_eq!(store_1.get_jukebox_guest(), Some("Alice".into()));
+
+        // Guests can't rename themselves
+        store_1.set_jukebox_guest("Bob".into());
+        assert_eq!(store_1.get_jukebox_guest(), Some("Alice".into()));
+
+        store_1.clear_jukebox_guest();
+        store_1.set_jukebox_guest("Bob".into());
+        assert_eq!(store_1.get_jukebox_guest(), Some("Bob".into()));
+    }
+}
@@ -14,6 +14,8 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
+use types::scanner::ScanProgress;
+
 #[derive(PartialEq, Eq, Clone, Copy)]
 pub enum SongSortByColumns {
     Album,
@@ -49,6 +51,7 @@ pub struct UiStore {
     sidebar_open: bool,
     is_mobile: bool,
     is_mobile_player: bool,
+    scan_progress: ScanProgress,
 }
 
 impl UiStore {
@@ -67,6 +70,7 @@ impl UiStore {
             sidebar_open: true,
             is_mobile: false,
             is_mobile_player: false,
+            scan_progress: ScanProgress::default(),
         }
     }
 
@@ -124,4 +128,12 @@ impl UiStore {
     pub fn get_is_mobile_player(&self) -> bool {
         self.is_mobile_player
     }
+
+    pub fn set_scan_progress(&mut self, progress: ScanProgress) {
+        self.scan_progress = progress;
+    }
+
+    pub fn get_scan_progress(&self) -> ScanProgress {
+        self.scan_progress.clone()
+    }
 }
@@ -27,7 +27,7 @@ use leptos_router::{
 };
 use types::{
     entities::{QueryableArtist, QueryablePlaylist},
-    songs::Song,
+    songs::{Song, SongType},
     ui::extensions::ExtensionProviderScope,
 };
 
@@ -49,11 +49,18 @@ use super::{
         remove_playlist, remove_songs_from_library,
     },
     invoke::{
-        get_playlist_context_menu, get_song_context_menu, load_theme, trigger_context_menu_action,
+        get_playlist_context_menu, get_song_context_menu, load_theme, make_available_offline,
+        make_playlist_available_offline, trigger_context_menu_action,
     },
     songs::get_sort_cx_items,
 };
 
+/// Guests in jukebox mode may only request songs
+#[tracing::instrument(level = "debug", skip())]
+fn is_jukebox() -> bool {
+    expect_context::<RwSignal<PlayerStore>>().with_untracked(|p| p.is_jukebox())
+}
+
 #[derive(Clone)]
 pub struct SongItemContextMenu<T>
 where
@@ -129,6 +136,23 @@ where
         add_to_playlist(id, self.current_or_list());
     }
 
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn make_available_offline(&self) {
+        let songs = self.current_or_list();
+        spawn_local(async move {
+            if let Err(e) = make_available_offline(songs).await {
+                tracing::error!("Failed to make songs available offline: {:?}", e);
+            }
+        });
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn edit_tags(&self) {
+        let songs = self.current_or_list();
+        let modal_store: RwSignal<ModalStore> = expect_context();
+        modal_store.update(|m| m.set_active_modal(Modals::TagEditorModal(songs)));
+    }
+
     #[tracing::instrument(level = "debug", skip(self))]
     pub fn goto_album(&self) {
         let navigate = use_navigate();
@@ -203,6 +227,15 @@ where
     fn get_menu_items(&self) -> ReadSignal<ContextMenuItems<Self>> {
         let i18n = use_i18n();
 
+        if is_jukebox() {
+            return RwSignal::new(vec![ContextMenuItemInner::new_with_handler(
+                t_string!(i18n, context_menu.song.add_to_queue).to_string(),
+                |_, cx| cx.add_to_queue(),
+                None,
+            )])
+            .read_only();
+        }
+
         let mut artist_items = vec![];
         if let Some(song) = &self.current_song {
             if let Some(artists) = &song.artists {
@@ -218,7 +251,13 @@ where
         }
 
         let mut playlist_items = vec![];
-        for playlist in self.playlists.get().iter() {
+        // Songs of smart playlists come from their rules
+        for playlist in self
+            .playlists
+            .get()
+            .iter()
+            .filter(|p| p.smart_rules.is_none())
+        {
             let playlist_name = playlist.playlist_name.clone();
             let playlist_id = playlist.playlist_id.clone().unwrap_or_default();
             playlist_items.push(ContextMenuItemInner::<Self>::new_with_handler(
@@ -304,6 +343,33 @@ where
             });
         }
 
+        // Local files are already on disk
+        if self.current_or_list().iter().any(|s| s.song.path.is_none()) {
+            ret.update(|ret| {
+                ret.push(ContextMenuItemInner::new_with_handler(
+                    t_string!(i18n, context_menu.song.make_offline).to_string(),
+                    |_, cx| cx.make_available_offline(),
+                    None,
+                ));
+            });
+        }
+
+        // Tags can only be written to local files, which tracks of CUE sheets share
+        let songs = self.current_or_list();
+        if !songs.is_empty()
+            && songs.iter().all(|s| {
+                s.song.type_ == SongType::LOCAL && s.song.path.is_some() && !s.song.is_cue_track()
+            })
+        {
+            ret.update(|ret| {
+                ret.push(ContextMenuItemInner::new_with_handler(
+                    t_string!(i18n, context_menu.song.edit_tags).to_string(),
+                    |_, cx| cx.edit_tags(),
+                    None,
+                ));
+            });
+        }
+
         let provider_store = expect_context::<Arc<ProviderStore>>();
         let song_list = self.current_or_list();
         spawn_local(async move {
@@ -448,6 +514,10 @@ impl PlaylistContextMenu {
 impl ContextMenuData<Self> for PlaylistContextMenu {
     #[tracing::instrument(level = "debug", skip(self))]
     fn get_menu_items(&self) -> ReadSignal<ContextMenuItems<Self>> {
+        if is_jukebox() {
+            return RwSignal::new(vec![]).read_only();
+        }
+
         let i18n = use_i18n();
         RwSignal::new(vec![
             ContextMenuItemInner::<Self>::new_with_handler(
@@ -491,11 +561,26 @@ impl PlaylistItemContextMenu {
             export_playlist(playlist.clone());
         }
     }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    fn make_available_offline(&self) {
+        if let Some(playlist) = self.playlist.clone() {
+            spawn_local(async move {
+                if let Err(e) = make_playlist_available_offline(playlist).await {
+                    tracing::error!("Failed to make playlist available offline: {:?}", e);
+                }
+            });
+        }
+    }
 }
 
 impl ContextMenuData<Self> for PlaylistItemContextMenu {
     #[tracing::instrument(level = "debug", skip(self))]
     fn get_menu_items(&self) -> ReadSignal<ContextMenuItems<Self>> {
+        if is_jukebox() {
+            return RwSignal::new(vec![]).read_only();
+        }
+
         let i18n = use_i18n();
         let mut ret = RwSignal::new(vec![]);
         if let Some(playlist) = &self.playlist {
@@ -522,6 +607,17 @@ impl ContextMenuData<Self> for PlaylistItemContextMenu {
                 )]);
             }
 
+            // Songs of smart playlists come from their rules
+            if playlist.smart_rules.is_none() {
+                ret.update(|ret| {
+                    ret.push(ContextMenuItemInner::new_with_handler(
+                        t_string!(i18n, context_menu.playlist.make_offline).into(),
+                        |_, cx| cx.make_available_offline(),
+                        None,
+                    ));
+                });
+            }
+
             let provider_store = expect_context::<Arc<ProviderStore>>();
             let playlist = playlist.clone();
             spawn_local(async move {
@@ -596,6 +692,14 @@ impl SongsContextMenu {
 impl ContextMenuData<Self> for SongsContextMenu {
     #[tracing::instrument(level = "debug", skip(self))]
     fn get_menu_items(&self) -> ReadSignal<ContextMenuItems<Self>> {
+        if is_jukebox() {
+            return RwSignal::new(vec![ContextMenuItemInner::new(
+                "Sort by".into(),
+                Some(get_sort_cx_items()),
+            )])
+            .read_only();
+        }
+
         RwSignal::new(vec![
             ContextMenuItemInner::new("Sort by".into(), Some(get_sort_cx_items())),
             ContextMenuItemInner::<Self>::new_with_handler(
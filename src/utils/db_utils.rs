@@ -329,7 +329,7 @@ pub fn remove_songs_from_library(songs: Vec<Song>, refresh_cb: Arc<Box<dyn Fn()
 #[tracing::instrument(level = "debug", skip(id, songs))]
 pub fn add_to_playlist(id: String, songs: Vec<Song>) {
     spawn_local(async move {
-        let res = super::invoke::add_to_playlist(id, songs).await;
+        let res = super::invoke::add_to_playlist(id, songs, None).await;
         if res.is_err() {
             tracing::error!("Error adding to playlist: {:?}", res);
         }
@@ -350,7 +350,7 @@ pub fn create_playlist_and(
             }
             Ok(playlist_id) => {
                 if let Some(songs) = songs {
-                    let res = super::invoke::add_to_playlist(playlist_id, songs).await;
+                    let res = super::invoke::add_to_playlist(playlist_id, songs, None).await;
                     if let Err(e) = res {
                         tracing::error!("Failed to add songs to playlist: {:?}", e);
                     }
@@ -52,22 +52,28 @@ pub fn set_playback_state(state: PlayerState) {
 }
 
 lazy_static! {
-    static ref last_time_update: Mutex<wasm_timer::Instant> =
-        Mutex::new(wasm_timer::Instant::now());
+    static ref last_time_update: Mutex<(wasm_timer::Instant, f64)> =
+        Mutex::new((wasm_timer::Instant::now(), 0f64));
 }
+
+/// Largest difference in seconds between the expected and actual position
+/// before MPRIS is updated right away, like after a seek
+const MAX_POSITION_DRIFT: f64 = 1.5f64;
+
 #[tracing::instrument(level = "debug", skip(duration))]
 pub fn set_position(duration: f64) {
     let should_update = {
-        if duration == 0f64 {
+        let mut last_time_update_lock = last_time_update.lock().unwrap();
+        let (last_update, last_position) = *last_time_update_lock;
+        let expected = last_position + last_update.elapsed().as_secs_f64();
+        if duration == 0f64
+            || last_update.elapsed().as_secs() > 10
+            || (duration - expected).abs() > MAX_POSITION_DRIFT
+        {
+            *last_time_update_lock = (wasm_timer::Instant::now(), duration);
             true
         } else {
-            let mut last_time_update_lock = last_time_update.lock().unwrap();
-            if last_time_update_lock.elapsed().as_secs() > 10 {
-                *last_time_update_lock = wasm_timer::Instant::now();
-                true
-            } else {
-                false
-            }
+            false
         }
     };
     if should_update {
@@ -14,9 +14,11 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
+use std::collections::HashMap;
+
 use leptos::prelude::*;
 use leptos_context_menu::ContextMenuItemInner;
-use types::songs::Song;
+use types::{lyrics::Lyrics, songs::Song};
 
 use crate::{
     store::ui_store::{SongSortBy, SongSortByColumns, UiStore},
@@ -37,6 +39,42 @@ where
         .collect()
 }
 
+/// Orders the songs of an album by disc, then by track number
+#[tracing::instrument(level = "debug", skip(songs))]
+pub fn sort_by_disc(songs: &mut [Song]) {
+    let position = |s: &Song| {
+        (
+            s.song.disc_no.unwrap_or(1f64),
+            s.song.track_no.unwrap_or_default(),
+        )
+    };
+    songs.sort_by(|a, b| {
+        let (a_disc, a_track) = position(a);
+        let (b_disc, b_track) = position(b);
+        a_disc.total_cmp(&b_disc).then(a_track.total_cmp(&b_track))
+    });
+}
+
+/// Maps the index of the first song of each disc to its disc number. Empty when
+/// all songs are on the same disc
+#[tracing::instrument(level = "debug", skip(songs))]
+pub fn get_disc_starts(songs: &[Song]) -> HashMap<usize, f64> {
+    let mut starts = HashMap::new();
+    let mut last_disc = None;
+    for (index, song) in songs.iter().enumerate() {
+        let disc = song.song.disc_no.unwrap_or(1f64);
+        if last_disc != Some(disc) {
+            starts.insert(index, disc);
+            last_disc = Some(disc);
+        }
+    }
+
+    if starts.len() < 2 {
+        starts.clear();
+    }
+    starts
+}
+
 #[tracing::instrument(level = "debug", skip())]
 pub fn sort_by_album() {
     let ui_store: RwSignal<UiStore> = expect_context();
@@ -121,10 +159,10 @@ where
     ]
 }
 
-pub async fn fetch_lyrics(song: &Option<Song>) -> Option<String> {
+pub async fn fetch_lyrics(song: &Option<Song>) -> Option<Lyrics> {
     tracing::debug!("Fetching lyrics");
     if let Some(song) = song {
-        let lyrics = song.song.lyrics.clone();
+        let lyrics = song.song.lyrics.as_deref().map(Lyrics::parse);
         if lyrics.is_none() {
             let res = get_lyrics(
                 song.song._id.clone().unwrap_or_default(),
@@ -139,12 +177,12 @@ pub async fn fetch_lyrics(song: &Option<Song>) -> Option<String> {
             )
             .await;
             if let Ok(lyrics) = res {
-                return Some(lyrics);
+                return Some(lyrics).filter(|l| !l.is_empty());
             } else {
                 tracing::error!("Failed to fetch lyrics: {:?}", res.unwrap_err());
             }
         }
-        return lyrics;
+        return lyrics.filter(|l| !l.is_empty());
     }
 
     None